@@ -0,0 +1,279 @@
+//! Tokenizer for the escape sequences found in rendered text.
+//!
+//! Besides the usual SGR sequences (`ESC [ ... m`), rendered titles may hold OSC 8 hyperlinks
+//! (`ESC ] 8 ;; url ESC \`) and other string sequences (APC, DCS, PM, SOS) that terminals do
+//! not display. All of them, along with titular's internal markers, have no visual width.
+
+use crate::width::{text_width, AmbiguousWidth};
+
+const ESC: char = '\x1b';
+const BEL: char = '\x07';
+/// String terminator (`ESC \`)
+const ST: &str = "\x1b\\";
+
+/// The kind of an escape sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EscapeKind {
+    /// Control sequence (`ESC [`), including SGR
+    Csi,
+    /// Operating system command (`ESC ]`), e.g. OSC 8 hyperlinks
+    Osc,
+    /// Device control string (`ESC P`)
+    Dcs,
+    /// Application program command (`ESC _`)
+    Apc,
+    /// Privacy message (`ESC ^`) or start of string (`ESC X`)
+    Pm,
+    /// Any other escape (`ESC` followed by intermediates and a final byte)
+    Other,
+}
+
+/// Whether the SGR parameters only turn attributes or colors off (`22`, `39`, `4:0`, ...).
+pub(crate) fn is_partial_reset(params: &str) -> bool {
+    params.split(';').all(|p| {
+        matches!(
+            p,
+            "22" | "23" | "24" | "25" | "27" | "28" | "29" | "39" | "49" | "55" | "59" | "4:0"
+        )
+    })
+}
+
+/// Updates the layers of style left open by a text, nested as the `AnsiFormatter` nests them,
+/// with one of its escape sequences: an SGR sequence opens a layer, closed by the next full reset
+/// (`\x1b[0m`), and sequences only turning attributes off amend the innermost layer.
+///
+/// # Returns
+/// `false` if the sequence is not an SGR sequence, leaving the layers untouched.
+pub(crate) fn update_layers(layers: &mut Vec<String>, seq: &str) -> bool {
+    let Some(params) = seq
+        .strip_prefix("\x1b[")
+        .and_then(|rest| rest.strip_suffix('m'))
+    else {
+        return false;
+    };
+    if params.is_empty() || params == "0" {
+        layers.pop();
+    } else if is_partial_reset(params) {
+        if let Some(layer) = layers.last_mut() {
+            layer.push_str(seq);
+        }
+    } else {
+        layers.push(seq.to_string());
+    }
+    true
+}
+
+/// A piece of text : either printable text or a whole escape sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Token<'a> {
+    Text(&'a str),
+    Escape(EscapeKind, &'a str),
+}
+
+impl Token<'_> {
+    /// Whether the token is an SGR sequence (`ESC [ ... m`).
+    #[must_use]
+    pub fn is_sgr(&self) -> bool {
+        matches!(self, Token::Escape(EscapeKind::Csi, seq) if seq.ends_with('m'))
+    }
+}
+
+/// Iterator over the [`Token`]s of a string, see [`tokenize`].
+pub struct Tokens<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Token<'a>> {
+        if self.rest.is_empty() {
+            return None;
+        }
+        let len = match self.rest.find(ESC) {
+            Some(0) => escape_len(self.rest),
+            Some(pos) => pos,
+            None => self.rest.len(),
+        };
+        let (token, rest) = self.rest.split_at(len);
+        self.rest = rest;
+        Some(match escape_kind(token) {
+            Some(kind) => Token::Escape(kind, token),
+            None => Token::Text(token),
+        })
+    }
+}
+
+fn escape_kind(token: &str) -> Option<EscapeKind> {
+    let mut chars = token.chars();
+    if chars.next() != Some(ESC) {
+        return None;
+    }
+    Some(match chars.next() {
+        Some('[') => EscapeKind::Csi,
+        Some(']') => EscapeKind::Osc,
+        Some('P') => EscapeKind::Dcs,
+        Some('_') => EscapeKind::Apc,
+        Some('^' | 'X') => EscapeKind::Pm,
+        _ => EscapeKind::Other,
+    })
+}
+
+/// Length of the escape sequence at the start of `s`. Unterminated string sequences run to the
+/// end of the text.
+fn escape_len(s: &str) -> usize {
+    let body = &s[1..];
+    match body.chars().next() {
+        Some('[') => {
+            // Parameter and intermediate bytes, then a final byte
+            body[1..]
+                .char_indices()
+                .find(|(_, c)| ('\x40'..='\x7e').contains(c))
+                .map_or(s.len(), |(i, c)| 2 + i + c.len_utf8())
+        }
+        Some(']' | 'P' | '_' | '^' | 'X') => {
+            let content = &body[1..];
+            let bel = content.find(BEL).map(|i| (i, 1));
+            let st = content.find(ST).map(|i| (i, ST.len()));
+            match (bel, st) {
+                (Some(b), Some(t)) => Some(b.min(t)),
+                (b, t) => b.or(t),
+            }
+            .map_or(s.len(), |(i, terminator)| 2 + i + terminator)
+        }
+        Some(_) => body
+            .char_indices()
+            .find(|(_, c)| !('\x20'..='\x2f').contains(c))
+            .map_or(s.len(), |(i, c)| 1 + i + c.len_utf8()),
+        None => 1,
+    }
+}
+
+/// Splits the string into printable text and escape sequences.
+///
+/// # Examples
+/// ```
+/// use titular::ansi::{tokenize, EscapeKind, Token};
+///
+/// let tokens: Vec<_> = tokenize("\x1b]8;;https://x.io\x1b\\\x1b[1mx").collect();
+/// assert_eq!(
+///     tokens,
+///     [
+///         Token::Escape(EscapeKind::Osc, "\x1b]8;;https://x.io\x1b\\"),
+///         Token::Escape(EscapeKind::Csi, "\x1b[1m"),
+///         Token::Text("x"),
+///     ]
+/// );
+/// ```
+#[must_use]
+pub fn tokenize(s: &str) -> Tokens<'_> {
+    Tokens { rest: s }
+}
+
+/// Removes every escape sequence from the string, keeping the markers.
+///
+/// # Examples
+/// ```
+/// use titular::ansi::strip_escapes;
+///
+/// assert_eq!(strip_escapes("\x1b]8;;https://x.io\x07\x1b[31mdocs\x1b[0m\x1b]8;;\x07"), "docs");
+/// ```
+#[must_use]
+pub fn strip_escapes(s: &str) -> String {
+    tokenize(s)
+        .filter_map(|token| match token {
+            Token::Text(text) => Some(text),
+            Token::Escape(..) => None,
+        })
+        .collect()
+}
+
+/// Display width of the string, escape sequences and markers being zero-width. See
+/// [`text_width`] for the measure of the remaining text.
+///
+/// # Examples
+/// ```
+/// use titular::ansi::visual_width;
+/// use titular::width::AmbiguousWidth;
+///
+/// let narrow = AmbiguousWidth::Narrow;
+/// assert_eq!(visual_width("\x1b]8;;https://x.io\x1b\\link\x1b]8;;\x1b\\ 🦀", narrow), 7);
+/// assert_eq!(visual_width("\x1b_Gi=1;abc\x1b\\\u{F0000}x\u{F0001}", narrow), 1);
+/// ```
+#[must_use]
+pub fn visual_width(s: &str, ambiguous: AmbiguousWidth) -> usize {
+    text_width(&strip_escapes(s), ambiguous)
+}
+
+/// Wraps the text in an OSC 8 hyperlink.
+///
+/// The control characters (C0, DEL and C1) of the URL are removed, so that it cannot end the
+/// sequence and start others. The text is returned unchanged when no URL remains.
+///
+/// # Examples
+/// ```
+/// use titular::ansi::hyperlink;
+///
+/// assert_eq!(hyperlink("https://x.io", "x"), "\x1b]8;;https://x.io\x1b\\x\x1b]8;;\x1b\\");
+/// assert_eq!(hyperlink("\x07\x1b[2J", "x"), "\x1b]8;;[2J\x1b\\x\x1b]8;;\x1b\\");
+/// ```
+#[must_use]
+pub fn hyperlink(url: &str, text: &str) -> String {
+    let url: String = url.chars().filter(|c| !c.is_control()).collect();
+    if url.is_empty() {
+        return text.to_string();
+    }
+    format!("\x1b]8;;{url}{ST}{text}\x1b]8;;{ST}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hyperlink_strips_controls() {
+        let link = hyperlink("https://x.io/\x1b\\\x1b]0;owned\x07\u{9b}2J\u{9c}\n", "x");
+        assert_eq!(
+            link,
+            "\x1b]8;;https://x.io/\\]0;owned2J\x1b\\x\x1b]8;;\x1b\\"
+        );
+        let tokens: Vec<_> = tokenize(&link).collect();
+        assert_eq!(tokens.len(), 3);
+        assert_eq!(hyperlink("\x1b\x07", "x"), "x");
+    }
+
+    #[test]
+    fn test_tokenize() {
+        let input = "a\x1b[38;2;1;2;3mb\x1b]0;title\x07c\x1bPq#0\x1b\\d\x1b(Be\x1b]8;;u";
+        let tokens: Vec<_> = tokenize(input).collect();
+        assert_eq!(
+            tokens,
+            [
+                Token::Text("a"),
+                Token::Escape(EscapeKind::Csi, "\x1b[38;2;1;2;3m"),
+                Token::Text("b"),
+                Token::Escape(EscapeKind::Osc, "\x1b]0;title\x07"),
+                Token::Text("c"),
+                Token::Escape(EscapeKind::Dcs, "\x1bPq#0\x1b\\"),
+                Token::Text("d"),
+                Token::Escape(EscapeKind::Other, "\x1b(B"),
+                Token::Text("e"),
+                Token::Escape(EscapeKind::Osc, "\x1b]8;;u"),
+            ]
+        );
+        assert!(tokens[1].is_sgr());
+        assert!(!tokens[3].is_sgr());
+    }
+
+    #[test]
+    fn test_visual_width() {
+        let narrow = AmbiguousWidth::Narrow;
+        assert_eq!(visual_width("", narrow), 0);
+        assert_eq!(visual_width("\x1b[31mred\x1b[0m", narrow), 3);
+        assert_eq!(
+            visual_width(&hyperlink("https://example.com/a/b", "ab"), narrow),
+            2
+        );
+        assert_eq!(visual_width("\x1b", narrow), 0);
+    }
+}
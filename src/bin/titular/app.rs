@@ -1,11 +1,12 @@
 use std::io::IsTerminal;
 
-use crate::{bootstrap::BootStrap, clap_app, env_cli};
+use crate::{bootstrap::BootStrap, clap_app, completions, directories::PROJECT_DIRS, env_cli};
 use clap::{parser::ValueSource, ArgMatches};
 use titular::{
     context::Context,
     controller::TemplatesController,
     error::{Error, Result},
+    shell::{InitMode, Shell},
     string_utils::unescape_cli_escapes,
 };
 
@@ -121,6 +122,15 @@ impl App {
                     .as_str(),
             );
         }
+        if let Some(appearance) = self.matches.get_one::<String>("appearance") {
+            context.insert("appearance", appearance.as_str());
+        }
+        if let Some(mode) = self.matches.get_one::<String>("color_mode") {
+            context.insert("color_mode", mode.as_str());
+        }
+        if let Some(url) = self.matches.get_one::<String>("link") {
+            context.insert("link", url.as_str());
+        }
         if self.matches.get_flag("no-newline") {
             context.insert("skip-newline", "true");
         }
@@ -133,6 +143,18 @@ impl App {
         if self.matches.get_flag("clear") {
             context.insert("clear", "true");
         }
+        if let Some(shell) = self.matches.get_one::<String>("prompt_shell") {
+            context.insert("prompt_shell", shell.as_str());
+        }
+        // User FIGlet fonts of the banner filter
+        context.insert(
+            "fonts_dir",
+            PROJECT_DIRS
+                .config_dir()
+                .join("fonts")
+                .to_string_lossy()
+                .as_ref(),
+        );
 
         Ok(context)
     }
@@ -183,6 +205,46 @@ impl App {
         }
     }
 
+    /// Prints the shell integration hooks of the init subcommand.
+    ///
+    /// # Arguments
+    /// * `init_params` - The arguments of the init subcommand.
+    fn print_init_script(init_params: &ArgMatches) -> Result<()> {
+        let shell: Shell = init_params
+            .get_one::<String>("shell")
+            .map_or("", String::as_str)
+            .parse()?;
+        let mode: InitMode = init_params
+            .get_one::<String>("mode")
+            .map_or("separator", String::as_str)
+            .parse()?;
+        // The hooks call back this executable, wherever it is installed
+        let exe = std::env::current_exe().map_or_else(
+            |_| "titular".to_string(),
+            |exe| exe.to_string_lossy().into_owned(),
+        );
+        let template = init_params
+            .get_one::<String>("template")
+            .map(String::as_str);
+        print!("{}", shell.init_script(&exe, template, mode));
+        Ok(())
+    }
+
+    /// Prints the shell completion script of the completions subcommand.
+    ///
+    /// # Arguments
+    /// * `completions_params` - The arguments of the completions subcommand.
+    fn print_completions(completions_params: &ArgMatches) -> Result<()> {
+        let shell = *completions_params
+            .get_one::<clap_complete::Shell>("shell")
+            .ok_or_else(|| Error::ArgsProcessingError("Missing shell".to_string()))?;
+        print!(
+            "{}",
+            completions::completion_script(shell, &mut clap_app::build_app(false))?
+        );
+        Ok(())
+    }
+
     /// Start the application, bootstraps the configuration and forwards the request to the controller.
     ///
     /// When formatting a template, the application will create a context and pass it automatically.
@@ -191,6 +253,15 @@ impl App {
     /// # Returns
     /// A `Result` indicating whether the application started successfully.
     pub fn start(&self) -> Result<bool> {
+        if let Some(("completions", completions_params)) = self.matches.subcommand() {
+            Self::print_completions(completions_params)?;
+            return Ok(true);
+        }
+        if let Some(("init", init_params)) = self.matches.subcommand() {
+            Self::print_init_script(init_params)?;
+            return Ok(true);
+        }
+
         // Parse the default config
         let bootstrap = BootStrap::new()?;
         let controller =
@@ -200,8 +271,15 @@ impl App {
 
         if let Some(("templates", tpl_params)) = self.matches.subcommand() {
             Self::add_params_to_context(&mut context, tpl_params);
-            controller.run_template_subcommand(&context)?;
-            return Ok(true);
+            let succeeded = controller.run_template_subcommand(&context)?;
+            // Only checks report their outcome through the exit code
+            return Ok(succeeded || tpl_params.subcommand_name() != Some("check"));
+        }
+
+        #[cfg(feature = "display")]
+        if let Some(("themes", theme_params)) = self.matches.subcommand() {
+            Self::add_params_to_context(&mut context, theme_params);
+            return controller.run_themes_subcommand(&context);
         }
 
         let default_name = bootstrap.get_config().templates.default.as_str();
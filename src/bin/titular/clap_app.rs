@@ -10,7 +10,8 @@ fn env_no_color() -> bool {
 }
 
 // Builds the application command line interface defining the commands, subcommands
-// and arguments
+// and arguments. The arguments taking the name of an installed template or of an embedded
+// theme use the TEMPLATE or THEME value name, which the completion scripts complete.
 pub fn build_app(interactive_output: bool) -> Command {
     let color_when = if interactive_output && !env_no_color() {
         ColorChoice::Auto
@@ -31,7 +32,7 @@ pub fn build_app(interactive_output: bool) -> Command {
         .color(color_when)
         .allow_hyphen_values(true)
         .arg(
-            arg!(-t --template <VALUE> "Template to use for the title").long_help(
+            arg!(-t --template <TEMPLATE> "Template to use for the title").long_help(
                 "Template to be rendered with the custom message. Must match a name \
                     inside the templates directory ($TITULAR_TEMPLATE_DIR).",
             ),
@@ -43,7 +44,7 @@ pub fn build_app(interactive_output: bool) -> Command {
             Arg::new("theme")
                 .short('T')
                 .long("theme")
-                .value_name("NAME")
+                .value_name("THEME")
                 .help("Syntax highlighting theme for theme_* palette vars and fancy preview."),
         );
     }
@@ -92,6 +93,53 @@ pub fn build_app(interactive_output: bool) -> Command {
         )
         .value_parser(value_parser!(u8).range(0..=100)),
     )
+    .arg(
+        Arg::new("appearance")
+            .long("appearance")
+            .value_name("MODE")
+            .value_parser(PossibleValuesParser::new(["light", "dark"]))
+            .help("Selects the [vars.light] / [vars.dark] set of the template.")
+            .long_help(
+                "Selects which appearance-specific variable table of the template \
+                    ([vars.light] or [vars.dark]) is layered over [vars]. When omitted, the value is \
+                    resolved from defaults.appearance in the main configuration.",
+            ),
+    )
+    .arg(
+        Arg::new("color_mode")
+            .long("color-mode")
+            .value_name("WHEN")
+            .value_parser(PossibleValuesParser::new([
+                "auto",
+                "always",
+                "never",
+                "256",
+                "16",
+                "truecolor",
+                "24bit",
+            ]))
+            .help("When and how to use colors in the rendered title (auto, always, never, 256, 16, truecolor).")
+            .long_help(
+                "Controls the color depth of the rendered title. 'auto' (default) detects the \
+                    terminal capabilities from NO_COLOR, CLICOLOR_FORCE, COLORTERM and TERM, \
+                    'always' keeps colors even when NO_COLOR is set, 'never' strips all styling, \
+                    '256' / '16' downsample RGB colors to the nearest palette entry and \
+                    'truecolor' (or '24bit') keeps RGB colors whatever the terminal. When \
+                    omitted, the value is resolved from defaults.color_mode in the main configuration.",
+            ),
+    )
+    .arg(
+        Arg::new("link")
+            .long("link")
+            .value_name("URL")
+            .help("Makes the message a clickable terminal hyperlink to URL.")
+            .long_help(
+                "Sets the URL of the OSC 8 terminal hyperlinks emitted by the link filter, which \
+                    the stock templates apply to the message (e.g. a CI job page or a changelog). \
+                    Terminals without hyperlink support display the text as is, and no link is \
+                    emitted when colors are disabled.",
+            ),
+    )
     .arg(
         arg!(--"with-time" "Adds a trailing timestamp.")
         .long_help("Adds a timestamp to the end of the pattern using the time format
@@ -116,14 +164,91 @@ pub fn build_app(interactive_output: bool) -> Command {
     ).arg(
         arg!(--clear "Clears the current line and moves the cursor at the beginning.")
         .long_help("Erases the entire line the cursor is currently on then moves the cursor to the beginning of the line.")
+    ).arg(
+        Arg::new("prompt_shell")
+            .long("prompt-shell")
+            .value_name("SHELL")
+            .value_parser(PossibleValuesParser::new(["bash", "zsh", "fish"]))
+            .help("Escapes the title for the prompt of the shell (bash, zsh, fish).")
+            .long_help(
+                "Makes the title usable in a shell prompt: escape sequences are wrapped in \\[ \\] \
+                    for bash or %{ %} for zsh so that the shell does not count them in the width \
+                    of the prompt, and the characters the shell interprets in prompts are escaped. \
+                    Fish prompts are printed as is.",
+            ),
     );
 
     // Add the templates subcommand
     app = app.subcommand(configure_subcommands());
 
+    app = app.subcommand(build_init_command());
+
+    app = app.subcommand(build_completions_command());
+
+    #[cfg(feature = "display")]
+    {
+        app = app.subcommand(configure_themes_subcommands());
+    }
+
     app
 }
 
+/// Builds the init subcommand printing the shell integration hooks
+fn build_init_command() -> Command {
+    Command::new("init")
+        .arg(
+            Arg::new("shell")
+                .required(true)
+                .value_parser(PossibleValuesParser::new(["bash", "zsh", "fish"]))
+                .help("The shell to integrate with"),
+        )
+        .arg(arg!(-t --template <TEMPLATE> "The template rendered by the hooks"))
+        .arg(
+            Arg::new("mode")
+                .long("mode")
+                .value_name("MODE")
+                .value_parser(PossibleValuesParser::new([
+                    "separator",
+                    "after-command",
+                    "prompt",
+                ]))
+                .requires("template")
+                .help(
+                    "When the template is rendered (separator by default, after-command, prompt).",
+                ),
+        )
+        .about("Prints the shell hooks exporting the exit code and duration of commands.")
+        .long_about(
+            "Prints the hooks integrating titular with the shell, to be evaluated by its startup \
+                    file: eval \"$(titular init bash)\" in ~/.bashrc, eval \"$(titular init zsh)\" \
+                    in ~/.zshrc or titular init fish | source in config.fish. Before each prompt, \
+                    they export LAST_EXIT_CODE and LAST_DURATION_MS, read by the \
+                    get_last_exit_code() and get_last_duration() template functions. With a \
+                    template, they also render it before each prompt (separator), after each \
+                    command (after-command) or as the prompt itself (prompt).",
+        )
+}
+
+/// Builds the completions subcommand printing the shell completion scripts
+fn build_completions_command() -> Command {
+    Command::new("completions")
+        .arg(
+            Arg::new("shell")
+                .required(true)
+                .value_parser(value_parser!(clap_complete::Shell))
+                .help("The shell to complete the command line of"),
+        )
+        .about("Prints the shell completion script.")
+        .long_about(
+            "Prints the completion script of the shell: source <(titular completions bash) in \
+                    ~/.bashrc, source <(titular completions zsh) in ~/.zshrc (or save it as _titular \
+                    in a directory of $fpath) or titular completions fish > \
+                    ~/.config/fish/completions/titular.fish. The bash, zsh and fish scripts complete \
+                    template and theme names by calling back titular templates list -o txt, elvish \
+                    and powershell only complete the subcommands and options.",
+        )
+}
+
 /// Configure the templates subcommands
 fn configure_subcommands() -> Command {
     let templates_subcmd = Command::new("templates")
@@ -151,7 +276,7 @@ fn configure_subcommands() -> Command {
     )
     .subcommand(
         Command::new("edit")
-        .arg(arg!(<template> "The name of template to edit"))
+        .arg(arg!(<template> "The name of template to edit").value_name("TEMPLATE"))
         .about("Opens the selected installed template.")
         .long_about(
             "Opens the selected templates from \
@@ -161,12 +286,28 @@ fn configure_subcommands() -> Command {
         ),
     )
     .subcommand(build_show_command())
+    .subcommand(build_check_command())
+    .subcommand(
+        Command::new("trust")
+        .arg(arg!(<template> "The name of template to trust").value_name("TEMPLATE"))
+        .arg(arg!(--revoke "Marks the template as untrusted again"))
+        .about("Lets a downloaded template read any environment variable and run commands.")
+        .long_about(
+            "Templates downloaded with \"templates add\" (or fetched on first use) or imported \
+                    are untrusted: the env() function only reads the variables listed in \
+                    env_allowlist (see the [policy] section of the configuration) while rendering \
+                    them, and exec() runs no command. Trusting a template lifts these restrictions \
+                    (exec() still only runs the commands of exec_allowlist) and lets the [policy] \
+                    section of the template add to exec_allowlist, --revoke restores them.",
+        ),
+    )
     .subcommand(
         Command::new("remove")
         .alias("rm")
         .arg(Arg::new("template")
         .required(true)
         .action(ArgAction::Set)
+        .value_name("TEMPLATE")
         .help("The name of template to remove")
         .index(1))
         .about("Removes the template with the given name.")
@@ -192,6 +333,40 @@ fn configure_subcommands() -> Command {
     templates_subcmd
 }
 
+/// Configure the themes subcommands (`display` feature)
+#[cfg(feature = "display")]
+fn configure_themes_subcommands() -> Command {
+    let output_fmt_arg = Arg::new("output")
+        .short('o')
+        .long("output")
+        .value_name("FORMAT");
+
+    Command::new("themes")
+    .about("Inspect the embedded syntax highlighting themes")
+    .arg_required_else_help(true)
+    .subcommand(
+        Command::new("list")
+        .alias("ls")
+        .about("List the embedded syntax highlighting themes.")
+        .arg(output_fmt_arg.clone()
+            .value_parser(PossibleValuesParser::new(["txt", "json"]))
+            .help("Output format: names only (txt), or JSON (json). Default is the interactive tree.")),
+    )
+    .subcommand(
+        Command::new("show")
+        .arg(arg!(<name> "The name of the theme to inspect").value_name("THEME"))
+        .arg(output_fmt_arg
+            .value_parser(PossibleValuesParser::new(["json"]))
+            .help("Output format: JSON (json). Default is the swatch table plus a sample title."))
+        .about("Shows the palette resolved from the given theme.")
+        .long_about(
+            "Prints each theme_* palette slot resolved from the given theme with a color swatch, \
+                    its RGB(...) value and the theme scope it was taken from, followed by a sample title \
+                    rendered with the default template using that theme.",
+        ),
+    )
+}
+
 /// Builds the list command with optional themes argument when display feature is enabled
 ///
 /// # Returns
@@ -272,13 +447,48 @@ fn build_import_command() -> Command {
         )
 }
 
+/// Builds the check command with the theme selection arguments when display feature is enabled
+///
+/// # Returns
+/// A `Command` object representing the check command.
+fn build_check_command() -> Command {
+    let cmd = Command::new("check")
+        .arg(
+            arg!([template] "The name of the template to check (default: all templates)")
+                .value_name("TEMPLATE"),
+        )
+        .arg(
+            arg!(--contrast "Checks the WCAG contrast ratio of the template colors").long_help(
+                "Computes the WCAG contrast ratio of every foreground / background pair set by \
+                    the color and style filters of the pattern, using the theme background when no \
+                    background is set, and reports the pairs below the minimum ratio.",
+            ),
+        )
+        .arg(arg!(--"min-ratio" <RATIO> "Minimum contrast ratio (default: 4.5, WCAG AA)"))
+        .about("Checks the installed templates.")
+        .long_about(
+            "Loads the selected template (or every installed template) reporting the ones that \
+            cannot be parsed. With --contrast, also reports the colors having a poor contrast.",
+        );
+
+    #[cfg(feature = "display")]
+    let cmd = cmd
+        .arg(
+            arg!(-t --theme <THEME> "Checks the contrast against the given theme")
+                .conflicts_with("all-themes"),
+        )
+        .arg(arg!(--"all-themes" "Checks the contrast against every embedded theme"));
+
+    cmd
+}
+
 /// Builds the show command with optional themes argument when display feature is enabled
 ///
 /// # Returns
 /// A `Command` object representing the show command.
 fn build_show_command() -> Command {
     let show_cmd = Command::new("show")
-    .arg(arg!(<template> "The name of template to display"))
+    .arg(arg!(<template> "The name of template to display").value_name("TEMPLATE"))
     .about("Displays the contents of the selected installed template.")
     .long_about(
         "Displays the selected templates from \
@@ -295,9 +505,30 @@ fn build_show_command() -> Command {
     #[cfg(feature = "display")]
     {
         cmd = cmd.arg(
-            arg!(-t --theme <VALUE> "Sets the syntax highlighting theme")
+            arg!(-t --theme <THEME> "Sets the syntax highlighting theme")
                 .long_help("Explicitly specify the syntax highlighting theme to use."),
         );
     }
     cmd
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_color_modes() {
+        for mode in ["auto", "always", "never", "256", "16", "truecolor", "24bit"] {
+            let matches = build_app(false)
+                .try_get_matches_from(["titular", "--color-mode", mode])
+                .unwrap();
+            assert_eq!(
+                matches.get_one::<String>("color_mode").map(String::as_str),
+                Some(mode)
+            );
+        }
+        assert!(build_app(false)
+            .try_get_matches_from(["titular", "--color-mode", "8"])
+            .is_err());
+    }
+}
@@ -0,0 +1,457 @@
+//! Shell completion scripts printed by the completions subcommand.
+//!
+//! The scripts generated by clap_complete only know the values listed by the command line
+//! definition. For bash, zsh and fish, helpers are added so that the arguments using the
+//! TEMPLATE or THEME value name complete to the installed templates or to the embedded themes,
+//! listed at completion time by calling back `titular templates list [--themes] -o txt`.
+//! The elvish and powershell scripts are printed as generated, without the names.
+//!
+//! The helpers are hooked into the generated scripts by patching them, which is why
+//! clap_complete is pinned to an exact version: a changed script is an error, caught by the tests.
+
+use std::fmt::Write;
+
+use clap::{Arg, Command};
+use clap_complete::Shell;
+use titular::error::{Error, Result};
+
+/// The names an argument completes to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Names {
+    Templates,
+    Themes,
+}
+
+impl Names {
+    fn of(arg: &Arg) -> Option<Self> {
+        match arg.get_value_names()?.first()?.as_str() {
+            "TEMPLATE" => Some(Names::Templates),
+            "THEME" => Some(Names::Themes),
+            _ => None,
+        }
+    }
+
+    fn kind(self) -> &'static str {
+        match self {
+            Names::Templates => "templates",
+            Names::Themes => "themes",
+        }
+    }
+
+    /// The arguments listing the names
+    fn list_args(self) -> &'static str {
+        match self {
+            Names::Templates => "templates list -o txt",
+            Names::Themes => "templates list --themes -o txt",
+        }
+    }
+}
+
+/// A (sub)command of the command line and how its arguments complete.
+struct CommandArgs {
+    /// The subcommands leading to the command, each with its aliases
+    path: Vec<Vec<String>>,
+    /// The options taking a value (with their aliases), and the names they complete to
+    options: Vec<(Vec<String>, Option<Names>)>,
+    /// The names the first positional argument completes to
+    positional: Option<Names>,
+}
+
+impl CommandArgs {
+    /// The subcommands leading to the command, joined by slashes
+    fn key(&self) -> String {
+        self.path
+            .iter()
+            .map(|names| names[0].as_str())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
+
+/// Walks the command and its subcommands.
+fn collect(cmd: &Command, path: Vec<Vec<String>>, commands: &mut Vec<CommandArgs>) {
+    let options = cmd
+        .get_opts()
+        .map(|arg| {
+            let mut spellings: Vec<String> = Vec::new();
+            if let Some(short) = arg.get_short() {
+                spellings.push(format!("-{short}"));
+            }
+            for short in arg.get_all_short_aliases().unwrap_or_default() {
+                spellings.push(format!("-{short}"));
+            }
+            if let Some(long) = arg.get_long() {
+                spellings.push(format!("--{long}"));
+            }
+            for long in arg.get_all_aliases().unwrap_or_default() {
+                spellings.push(format!("--{long}"));
+            }
+            (spellings, Names::of(arg))
+        })
+        .collect();
+    let positional = cmd.get_positionals().next().and_then(Names::of);
+    commands.push(CommandArgs {
+        path: path.clone(),
+        options,
+        positional,
+    });
+
+    // The help subcommands only take the names of the other subcommands
+    for sub in cmd.get_subcommands().filter(|sub| sub.get_name() != "help") {
+        let mut names = vec![sub.get_name().to_string()];
+        names.extend(sub.get_all_aliases().map(str::to_string));
+        let mut sub_path = path.clone();
+        sub_path.push(names);
+        collect(sub, sub_path, commands);
+    }
+}
+
+/// The kinds of names completed by the commands, in a stable order.
+fn used_names(commands: &[CommandArgs]) -> Vec<Names> {
+    [Names::Templates, Names::Themes]
+        .into_iter()
+        .filter(|names| {
+            commands.iter().any(|cmd| {
+                cmd.positional == Some(*names)
+                    || cmd.options.iter().any(|(_, n)| *n == Some(*names))
+            })
+        })
+        .collect()
+}
+
+/// The bash / zsh function printing the kind of names completing the current word, given the
+/// current word followed by the previous ones (the command name excluded).
+fn names_kind_function(bin: &str, commands: &[CommandArgs]) -> String {
+    let mut arms = String::new();
+    for cmd in commands {
+        let key = cmd.key();
+        // Subcommands
+        for sub in commands
+            .iter()
+            .filter(|sub| sub.path.len() == cmd.path.len() + 1 && sub.path.starts_with(&cmd.path))
+        {
+            let patterns: Vec<String> = sub.path[sub.path.len() - 1]
+                .iter()
+                .map(|name| format!("{key},{name}"))
+                .collect();
+            let _ = writeln!(
+                arms,
+                "            {}) cmd_path={}; positionals=0 ;;",
+                patterns.join("|"),
+                sub.key()
+            );
+        }
+        // Options taking a value, whose value is skipped
+        for names in [None, Some(Names::Templates), Some(Names::Themes)] {
+            let patterns: Vec<String> = cmd
+                .options
+                .iter()
+                .filter(|(_, n)| *n == names)
+                .flat_map(|(spellings, _)| spellings.iter().map(|s| format!("{key},{s}")))
+                .collect();
+            if !patterns.is_empty() {
+                let _ = writeln!(
+                    arms,
+                    "            {}) value={} ;;",
+                    patterns.join("|"),
+                    names.map_or("-", Names::kind)
+                );
+            }
+        }
+    }
+
+    let mut positionals = String::new();
+    for names in [Names::Templates, Names::Themes] {
+        let keys: Vec<String> = commands
+            .iter()
+            .filter(|cmd| cmd.positional == Some(names))
+            .map(CommandArgs::key)
+            .collect();
+        if !keys.is_empty() {
+            let _ = writeln!(
+                positionals,
+                "            {}) echo {} ;;",
+                keys.join("|"),
+                names.kind()
+            );
+        }
+    }
+
+    format!(
+        r#"__{bin}_names_kind() {{
+    local cur="$1" cmd_path="" value="" positionals=0 word
+    shift
+    for word in "$@"; do
+        if [[ -n "$value" ]]; then
+            value=""
+            continue
+        fi
+        case "$cmd_path,$word" in
+{arms}            *,-*) ;;
+            *) positionals=$(( positionals + 1 )) ;;
+        esac
+    done
+    if [[ -n "$value" ]]; then
+        [[ "$value" != - ]] && echo "$value"
+    elif [[ "$cur" != -* && $positionals -eq 0 ]]; then
+        case "$cmd_path" in
+{positionals}        esac
+    fi
+}}
+"#
+    )
+}
+
+/// The bash / zsh function printing the names of the given kind.
+fn names_function(bin: &str, used: &[Names]) -> String {
+    let mut arms = String::new();
+    for names in used {
+        let _ = writeln!(
+            arms,
+            "        {}) {bin} {} ;;",
+            names.kind(),
+            names.list_args()
+        );
+    }
+    format!(
+        r#"__{bin}_names() {{
+    case "$1" in
+{arms}    esac 2>/dev/null
+}}
+"#
+    )
+}
+
+/// Makes the completion function of the bash script try the names first.
+fn extend_bash(script: &str, bin: &str, commands: &[CommandArgs]) -> Result<String> {
+    let register = format!("complete -F _{bin} ");
+    if !script.contains(&register) {
+        return Err(Error::Msg("unexpected bash completion script".to_string()));
+    }
+    let mut script = script.replace(&register, &format!("complete -F __{bin}_complete "));
+    script.push('\n');
+    script.push_str(&names_kind_function(bin, commands));
+    script.push('\n');
+    script.push_str(&names_function(bin, &used_names(commands)));
+    let _ = write!(
+        script,
+        r#"
+__{bin}_complete() {{
+    local cur="${{COMP_WORDS[COMP_CWORD]}}" kind
+    kind="$(__{bin}_names_kind "$cur" "${{COMP_WORDS[@]:1:COMP_CWORD-1}}")"
+    if [[ -z "$kind" ]]; then
+        _{bin} "$@"
+        return
+    fi
+    # Escapes the spaces of the theme names
+    compopt -o filenames 2>/dev/null
+    local IFS=$'\n'
+    COMPREPLY=( $(compgen -W "$(__{bin}_names "$kind")" -- "$cur") )
+}}
+"#
+    );
+    Ok(script)
+}
+
+/// Makes the completion function of the zsh script try the names first.
+fn extend_zsh(script: &str, bin: &str, commands: &[CommandArgs]) -> Result<String> {
+    let function = format!("\n_{bin}() {{\n");
+    if !script.contains(&function) {
+        return Err(Error::Msg("unexpected zsh completion script".to_string()));
+    }
+    let helpers = format!(
+        r#"
+{}
+{}
+__{bin}_complete() {{
+    local kind expl
+    kind="$(__{bin}_names_kind "${{words[CURRENT]}}" "${{(@)words[2,CURRENT-1]}}")"
+    [[ -n "$kind" ]] || return 1
+    local -a names
+    names=(${{(f)"$(__{bin}_names "$kind")"}})
+    _wanted "$kind" expl "$kind" compadd -a names
+    return 0
+}}
+{function}    __{bin}_complete && return 0
+"#,
+        names_kind_function(bin, commands),
+        names_function(bin, &used_names(commands)),
+    );
+    Ok(script.replacen(&function, &helpers, 1))
+}
+
+/// Adds the completions of the names to the fish script.
+fn extend_fish(script: &str, bin: &str, commands: &[CommandArgs]) -> Result<String> {
+    if !script.contains(&format!("function __fish_{bin}_using_subcommand")) {
+        return Err(Error::Msg("unexpected fish completion script".to_string()));
+    }
+    let mut script = format!("{script}\nfunction __fish_{bin}_names\n    switch $argv[1]\n");
+    for names in used_names(commands) {
+        let _ = write!(
+            script,
+            "        case {}\n            {bin} {} 2>/dev/null\n",
+            names.kind(),
+            names.list_args()
+        );
+    }
+    script.push_str("    end\nend\n\n");
+
+    for cmd in commands {
+        // Same conditions as the completions generated for the command
+        let condition = match cmd.path.as_slice() {
+            [] => format!("__fish_{bin}_needs_command"),
+            [first] => format!("__fish_{bin}_using_subcommand {}", first.join(" ")),
+            [first, .., last] => format!(
+                "__fish_{bin}_using_subcommand {}; and __fish_seen_subcommand_from {}",
+                first.join(" "),
+                last.join(" ")
+            ),
+        };
+        for (spellings, names) in &cmd.options {
+            let Some(names) = names else { continue };
+            let flags: Vec<String> = spellings
+                .iter()
+                .map(|s| match s.strip_prefix("--") {
+                    Some(long) => format!("-l {long}"),
+                    None => format!("-s {}", &s[1..]),
+                })
+                .collect();
+            let _ = writeln!(
+                script,
+                "complete -c {bin} -n \"{condition}\" {} -x -a \"(__fish_{bin}_names {})\"",
+                flags.join(" "),
+                names.kind()
+            );
+        }
+        if let Some(names) = cmd.positional {
+            let _ = writeln!(
+                script,
+                "complete -c {bin} -n \"{condition}\" -f -a \"(__fish_{bin}_names {})\"",
+                names.kind()
+            );
+        }
+    }
+    Ok(script)
+}
+
+/// Writes the completion script of the shell for the command.
+///
+/// # Arguments
+/// * `shell` - The shell to complete the command line of.
+/// * `cmd` - The command line definition.
+///
+/// # Returns
+/// The completion script, completing template and theme names for bash, zsh and fish.
+///
+/// # Errors
+/// Returns an error if the script generated by clap_complete cannot be extended.
+pub fn completion_script(shell: Shell, cmd: &mut Command) -> Result<String> {
+    // Sets the number of values taken by the arguments, telling the options from the flags
+    cmd.build();
+    let bin = cmd.get_name().to_string();
+    let mut buffer = Vec::new();
+    clap_complete::generate(shell, cmd, bin.as_str(), &mut buffer);
+    let script = String::from_utf8_lossy(&buffer);
+
+    let mut commands = Vec::new();
+    collect(cmd, Vec::new(), &mut commands);
+    match shell {
+        Shell::Bash => extend_bash(&script, &bin, &commands),
+        Shell::Zsh => extend_zsh(&script, &bin, &commands),
+        Shell::Fish => extend_fish(&script, &bin, &commands),
+        _ => Ok(script.into_owned()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clap_app::build_app;
+
+    /// Asserts that the names helpers are added to the script of the shell.
+    fn assert_names_listed(script: &str) {
+        assert!(script.contains("titular templates list -o txt"));
+        assert_eq!(
+            script.contains("titular templates list --themes -o txt"),
+            cfg!(feature = "display")
+        );
+    }
+
+    #[test]
+    fn test_bash_script_extended() {
+        let script = completion_script(Shell::Bash, &mut build_app(false)).unwrap();
+        assert_names_listed(&script);
+        assert!(script.contains("complete -F __titular_complete "));
+        assert!(!script.contains("complete -F _titular "));
+        // The generated completion function is kept as the fallback
+        assert!(script.starts_with("_titular() {\n"));
+    }
+
+    #[test]
+    fn test_zsh_script_extended() {
+        let script = completion_script(Shell::Zsh, &mut build_app(false)).unwrap();
+        assert_names_listed(&script);
+        assert!(script.contains("\n_titular() {\n    __titular_complete && return 0\n"));
+        assert_eq!(script.matches("\n_titular() {\n").count(), 1);
+    }
+
+    #[test]
+    fn test_fish_script_extended() {
+        let script = completion_script(Shell::Fish, &mut build_app(false)).unwrap();
+        assert_names_listed(&script);
+        assert!(script.contains("function __fish_titular_using_subcommand"));
+        assert!(script.contains(
+            "complete -c titular -n \"__fish_titular_using_subcommand templates; \
+             and __fish_seen_subcommand_from remove rm\" -f -a \"(__fish_titular_names templates)\""
+        ));
+        assert!(script.contains(
+            "complete -c titular -n \"__fish_titular_needs_command\" -s t -l template -x"
+        ));
+    }
+
+    #[test]
+    fn test_other_scripts_unchanged() {
+        for shell in [Shell::Elvish, Shell::PowerShell] {
+            let script = completion_script(shell, &mut build_app(false)).unwrap();
+            assert!(!script.contains("templates list"), "{shell}");
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_names_kind() {
+        let mut app = build_app(false);
+        app.build();
+        let mut commands = Vec::new();
+        collect(&app, Vec::new(), &mut commands);
+        let function = names_kind_function("titular", &commands);
+        let kind = |words: &str| {
+            let output = std::process::Command::new("bash")
+                .arg("-c")
+                .arg(format!("{function}\n__titular_names_kind {words}"))
+                .output()
+                .unwrap();
+            String::from_utf8(output.stdout).unwrap().trim().to_string()
+        };
+        assert_eq!(kind("'' -t"), "templates");
+        assert_eq!(kind("'' --template"), "templates");
+        assert_eq!(kind("'' -m"), "");
+        assert_eq!(kind("'' -t sep"), "");
+        assert_eq!(kind("'' -m -t templates edit"), "templates");
+        assert_eq!(kind("'' -t templates edit"), "");
+        assert_eq!(kind("'' templates edit"), "templates");
+        assert_eq!(kind("'' templates rm"), "templates");
+        assert_eq!(kind("-- templates edit"), "");
+        assert_eq!(kind("'' templates edit sep"), "");
+        assert_eq!(kind("'' templates create"), "");
+        assert_eq!(kind("'' init -t"), "templates");
+        assert_eq!(kind("'' -n templates trust"), "templates");
+        #[cfg(feature = "display")]
+        {
+            assert_eq!(kind("'' -T"), "themes");
+            assert_eq!(kind("'' templates show -t"), "themes");
+            assert_eq!(kind("'' templates show -t x"), "templates");
+            assert_eq!(kind("'' themes show"), "themes");
+        }
+    }
+}
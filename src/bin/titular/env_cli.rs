@@ -1,6 +1,6 @@
 //! Inject selected CLI flags from environment variables (bat-style), merged before real argv.
-//! Flags already present in the **global** argv (tokens before `templates`) are not injected,
-//! so explicit CLI wins without duplicate-flag errors.
+//! Flags already present in the **global** argv (tokens before a subcommand such as `templates`)
+//! are not injected, so explicit CLI wins without duplicate-flag errors.
 
 use std::ffi::{OsStr, OsString};
 
@@ -24,10 +24,13 @@ fn push_flag_value(out: &mut Vec<OsString>, flag: &str, key: &str) {
     }
 }
 
+/// Top-level subcommands that end the global argv prefix.
+const SUBCOMMANDS: [&str; 2] = ["templates", "themes"];
+
 fn global_argv_prefix(user_args: &[OsString]) -> &[OsString] {
     let pos = user_args
         .iter()
-        .position(|a| a.as_os_str() == OsStr::new("templates"));
+        .position(|a| SUBCOMMANDS.iter().any(|cmd| a.as_os_str() == OsStr::new(cmd)));
     match pos {
         Some(i) => &user_args[..i],
         None => user_args,
@@ -38,6 +41,7 @@ fn global_argv_prefix(user_args: &[OsString]) -> &[OsString] {
 struct GlobalClaims {
     template: bool,
     width: bool,
+    appearance: bool,
     #[cfg(feature = "display")]
     theme: bool,
     interpret_escapes: bool,
@@ -68,6 +72,7 @@ fn scan_global_claims(global: &[OsString]) -> GlobalClaims {
             match name {
                 "template" => claims.template = true,
                 "width" => claims.width = true,
+                "appearance" => claims.appearance = true,
                 #[cfg(feature = "display")]
                 "theme" => claims.theme = true,
                 "interpret-escapes" => claims.interpret_escapes = true,
@@ -80,7 +85,7 @@ fn scan_global_claims(global: &[OsString]) -> GlobalClaims {
 
             let takes_value = matches!(
                 name,
-                "template" | "width"
+                "template" | "width" | "appearance"
             ) || {
                 #[cfg(feature = "display")]
                 {
@@ -176,6 +181,9 @@ pub(crate) fn get_args_from_env_vars_filtered(user_args: &[OsString]) -> Vec<OsS
     if !claims.width {
         push_flag_value(&mut args, "--width", "TITULAR_WIDTH");
     }
+    if !claims.appearance {
+        push_flag_value(&mut args, "--appearance", "TITULAR_APPEARANCE");
+    }
 
     #[cfg(feature = "display")]
     {
@@ -239,6 +247,21 @@ mod tests {
         );
     }
 
+    #[test]
+    fn appearance_env_injected_unless_passed_on_cli() {
+        temp_env::with_vars(vec![("TITULAR_APPEARANCE", Some("light"))], || {
+            let args = get_args_from_env_vars_filtered(&[]);
+            assert!(args.contains(&OsString::from("--appearance=light")), "{args:?}");
+
+            let user = vec![OsString::from("--appearance"), OsString::from("dark")];
+            let env = get_args_from_env_vars_filtered(&user);
+            assert!(
+                !env.iter().any(|a| a.to_string_lossy().starts_with("--appearance")),
+                "{env:?}"
+            );
+        });
+    }
+
     #[test]
     fn width_env_skipped_when_user_passes_w() {
         temp_env::with_vars(vec![("TITULAR_WIDTH", Some("50"))], || {
@@ -1,6 +1,7 @@
 mod app;
 mod bootstrap;
 mod clap_app;
+mod completions;
 mod directories;
 mod env_cli;
 
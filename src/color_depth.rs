@@ -0,0 +1,234 @@
+//! Terminal color capability detection and color downsampling.
+//!
+//! The depth is taken from the `color_mode` context variable (`--color-mode` on the CLI, or
+//! `defaults.color_mode` in the main configuration) and defaults to `auto`, which inspects the
+//! environment:
+//! - `CLICOLOR_FORCE` (non-empty and not `0`) forces colors even when `NO_COLOR` is set.
+//! - `NO_COLOR` (non-empty) disables styling entirely.
+//! - `COLORTERM=truecolor|24bit` enables 24-bit colors.
+//! - `TERM` selects the depth otherwise (`dumb` disables colors, `*256color*` uses the
+//!   256-color palette, any other value the 16 ANSI colors). Without `TERM` colors are left as is.
+
+use std::str::FromStr;
+
+use nu_ansi_term::Color;
+
+use crate::color_utils::{
+    ansi16_color, ansi16_index, color_to_rgb, nearest_ansi16, nearest_ansi256,
+};
+use crate::context::Context;
+use crate::error::{Error, Result};
+
+/// Number of colors a terminal is able to display, in increasing order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ColorDepth {
+    NoColor,
+    Ansi16,
+    Ansi256,
+    TrueColor,
+}
+
+/// Color mode requested by the user (`--color-mode`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorMode {
+    Auto,
+    Always,
+    Never,
+    Ansi256,
+    Ansi16,
+    TrueColor,
+}
+
+impl FromStr for ColorMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "auto" => Ok(ColorMode::Auto),
+            "always" => Ok(ColorMode::Always),
+            "never" | "none" => Ok(ColorMode::Never),
+            "256" => Ok(ColorMode::Ansi256),
+            "16" => Ok(ColorMode::Ansi16),
+            "truecolor" | "24bit" => Ok(ColorMode::TrueColor),
+            _ => Err(Error::ConfigError(format!(
+                "Invalid color mode: {s} (expected auto, always, never, 256, 16 or truecolor)"
+            ))),
+        }
+    }
+}
+
+fn env_value(key: &str) -> Option<String> {
+    std::env::var(key).ok().filter(|v| !v.is_empty())
+}
+
+impl ColorDepth {
+    /// Resolves the color depth to use for the given context (see module docs).
+    #[must_use]
+    pub fn from_context(ctx: &Context) -> Self {
+        let mode = ["color_mode", "defaults.color_mode"]
+            .iter()
+            .filter_map(|key| ctx.get(key))
+            .find_map(|value| value.parse::<ColorMode>().ok())
+            .unwrap_or(ColorMode::Auto);
+        ColorDepth::from_mode(mode)
+    }
+
+    /// Resolves the color depth for an explicit color mode, inspecting the environment for the
+    /// `auto` and `always` modes.
+    #[must_use]
+    pub fn from_mode(mode: ColorMode) -> Self {
+        match mode {
+            ColorMode::Auto => ColorDepth::detect(),
+            ColorMode::Always => ColorDepth::from_term().max(ColorDepth::Ansi16),
+            ColorMode::Never => ColorDepth::NoColor,
+            ColorMode::Ansi256 => ColorDepth::Ansi256,
+            ColorMode::Ansi16 => ColorDepth::Ansi16,
+            ColorMode::TrueColor => ColorDepth::TrueColor,
+        }
+    }
+
+    /// Detects the color depth from `CLICOLOR_FORCE`, `NO_COLOR`, `COLORTERM` and `TERM`.
+    #[must_use]
+    pub fn detect() -> Self {
+        let forced = env_value("CLICOLOR_FORCE").is_some_and(|v| v != "0");
+        if forced {
+            return ColorDepth::from_term().max(ColorDepth::Ansi16);
+        }
+        if env_value("NO_COLOR").is_some() {
+            return ColorDepth::NoColor;
+        }
+        ColorDepth::from_term()
+    }
+
+    fn from_term() -> Self {
+        if env_value("COLORTERM")
+            .is_some_and(|v| matches!(v.to_lowercase().as_str(), "truecolor" | "24bit"))
+        {
+            return ColorDepth::TrueColor;
+        }
+        match env_value("TERM").map(|t| t.to_lowercase()) {
+            None => ColorDepth::TrueColor,
+            Some(term) if term == "dumb" => ColorDepth::NoColor,
+            Some(term) if term.contains("truecolor") || term.contains("direct") => {
+                ColorDepth::TrueColor
+            }
+            Some(term) if term.contains("256") => ColorDepth::Ansi256,
+            Some(_) => ColorDepth::Ansi16,
+        }
+    }
+
+    /// Converts a color to the nearest one displayable with this depth (`None` when colors
+    /// are disabled).
+    ///
+    /// # Examples
+    /// ```
+    /// use nu_ansi_term::Color;
+    /// use titular::color_depth::ColorDepth;
+    ///
+    /// let orange = Color::Rgb(255, 135, 0);
+    /// assert_eq!(ColorDepth::TrueColor.downsample(orange), Some(orange));
+    /// assert_eq!(ColorDepth::Ansi256.downsample(orange), Some(Color::Fixed(208)));
+    /// assert_eq!(ColorDepth::Ansi16.downsample(orange), Some(Color::Yellow));
+    /// assert_eq!(ColorDepth::NoColor.downsample(orange), None);
+    /// ```
+    #[must_use]
+    pub fn downsample(self, color: Color) -> Option<Color> {
+        match (self, color) {
+            (ColorDepth::NoColor, _) => None,
+            (ColorDepth::TrueColor, c) | (_, c @ Color::Default) => Some(c),
+            (ColorDepth::Ansi256, Color::Rgb(r, g, b)) => {
+                Some(Color::Fixed(nearest_ansi256((r, g, b))))
+            }
+            (ColorDepth::Ansi256, c) => Some(c),
+            (ColorDepth::Ansi16, c) => match ansi16_index(c) {
+                Some(_) => Some(c),
+                None => color_to_rgb(c).map(|rgb| ansi16_color(nearest_ansi16(rgb))),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detect_with(vars: Vec<(&str, Option<&str>)>) -> ColorDepth {
+        let mut all = vec![
+            ("CLICOLOR_FORCE", None),
+            ("NO_COLOR", None),
+            ("COLORTERM", None),
+            ("TERM", None),
+        ];
+        for (k, v) in vars {
+            if let Some(entry) = all.iter_mut().find(|(key, _)| *key == k) {
+                entry.1 = v;
+            }
+        }
+        temp_env::with_vars(all, ColorDepth::detect)
+    }
+
+    #[test]
+    fn test_detect_from_env() {
+        assert_eq!(
+            detect_with(vec![("COLORTERM", Some("truecolor"))]),
+            ColorDepth::TrueColor
+        );
+        assert_eq!(
+            detect_with(vec![("TERM", Some("xterm-256color"))]),
+            ColorDepth::Ansi256
+        );
+        assert_eq!(
+            detect_with(vec![("TERM", Some("xterm"))]),
+            ColorDepth::Ansi16
+        );
+        assert_eq!(
+            detect_with(vec![("TERM", Some("dumb"))]),
+            ColorDepth::NoColor
+        );
+        assert_eq!(
+            detect_with(vec![
+                ("NO_COLOR", Some("1")),
+                ("COLORTERM", Some("truecolor"))
+            ]),
+            ColorDepth::NoColor
+        );
+        assert_eq!(
+            detect_with(vec![
+                ("NO_COLOR", Some("1")),
+                ("CLICOLOR_FORCE", Some("1")),
+                ("TERM", Some("dumb"))
+            ]),
+            ColorDepth::Ansi16
+        );
+    }
+
+    #[test]
+    fn test_mode_from_context() {
+        let mut ctx = Context::new();
+        ctx.insert("color_mode", "never");
+        assert_eq!(ColorDepth::from_context(&ctx), ColorDepth::NoColor);
+        ctx.insert("color_mode", "256");
+        assert_eq!(ColorDepth::from_context(&ctx), ColorDepth::Ansi256);
+        assert!("sometimes".parse::<ColorMode>().is_err());
+    }
+
+    #[test]
+    fn test_downsample_to_16() {
+        assert_eq!(
+            ColorDepth::Ansi16.downsample(Color::Fixed(196)),
+            Some(Color::LightRed)
+        );
+        assert_eq!(
+            ColorDepth::Ansi16.downsample(Color::Fixed(4)),
+            Some(Color::Fixed(4))
+        );
+        assert_eq!(
+            ColorDepth::Ansi16.downsample(Color::Rgb(0, 0, 120)),
+            Some(Color::Blue)
+        );
+        assert_eq!(
+            ColorDepth::Ansi256.downsample(Color::Cyan),
+            Some(Color::Cyan)
+        );
+    }
+}
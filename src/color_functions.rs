@@ -0,0 +1,213 @@
+//! Functions deriving new colors from existing ones (`lighten`, `darken`, `mix`, ...).
+//!
+//! They are available as Tera functions and filters, and as expressions inside any color value
+//! understood by [`ColorManager`](crate::color_manager::ColorManager), e.g.
+//! `border = "darken(accent, 20%)"` in the `[vars]` of a template. Results are rendered as
+//! `RGB(r,g,b)` strings.
+
+use std::str::FromStr;
+
+use crate::color_utils::{adjust_lightness, adjust_saturation, complement, contrast_ratio, mix};
+use crate::error::{Error, Result};
+
+const BLACK: (u8, u8, u8) = (0, 0, 0);
+const WHITE: (u8, u8, u8) = (255, 255, 255);
+
+/// A color manipulation function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorFunction {
+    /// Increases the lightness of a color (`lighten(color, amount)`)
+    Lighten,
+    /// Decreases the lightness of a color (`darken(color, amount)`)
+    Darken,
+    /// Increases the saturation of a color (`saturate(color, amount)`)
+    Saturate,
+    /// Decreases the saturation of a color (`desaturate(color, amount)`)
+    Desaturate,
+    /// Rotates the hue of a color by 180 degrees (`complement(color)`)
+    Complement,
+    /// Mixes two colors (`mix(color, other, amount)`, `amount` being the proportion of `other`)
+    Mix,
+    /// Picks the most readable foreground for a background (`readable_on(bg, candidates...)`),
+    /// choosing between black and white when no candidate is given
+    ReadableOn,
+}
+
+impl ColorFunction {
+    /// All the functions, in the order they are documented.
+    pub const ALL: [ColorFunction; 7] = [
+        ColorFunction::Lighten,
+        ColorFunction::Darken,
+        ColorFunction::Saturate,
+        ColorFunction::Desaturate,
+        ColorFunction::Complement,
+        ColorFunction::Mix,
+        ColorFunction::ReadableOn,
+    ];
+
+    /// The name of the function in templates.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            ColorFunction::Lighten => "lighten",
+            ColorFunction::Darken => "darken",
+            ColorFunction::Saturate => "saturate",
+            ColorFunction::Desaturate => "desaturate",
+            ColorFunction::Complement => "complement",
+            ColorFunction::Mix => "mix",
+            ColorFunction::ReadableOn => "readable_on",
+        }
+    }
+
+    /// Whether the function takes an amount as its last argument.
+    #[must_use]
+    pub fn takes_amount(self) -> bool {
+        !matches!(self, ColorFunction::Complement | ColorFunction::ReadableOn)
+    }
+
+    /// Applies the function to the given colors.
+    ///
+    /// # Arguments
+    /// * `colors` - The colors the function operates on (one color, or two for `mix`, or a
+    ///   background followed by the candidate foregrounds for `readable_on`)
+    /// * `amount` - The amount as a fraction, defaulting to 10% (50% for `mix`)
+    ///
+    /// # Returns
+    /// The resulting color, or `None` if the number of colors does not match the function
+    ///
+    /// # Examples
+    /// ```
+    /// use titular::color_functions::ColorFunction;
+    ///
+    /// assert_eq!(ColorFunction::Darken.apply(&[(255, 0, 0)], Some(0.25)), Some((128, 0, 0)));
+    /// assert_eq!(ColorFunction::ReadableOn.apply(&[(20, 20, 60)], None), Some((255, 255, 255)));
+    /// assert_eq!(ColorFunction::Mix.apply(&[(255, 0, 0)], None), None);
+    /// ```
+    #[must_use]
+    pub fn apply(self, colors: &[(u8, u8, u8)], amount: Option<f64>) -> Option<(u8, u8, u8)> {
+        let amount = amount.unwrap_or(if self == ColorFunction::Mix { 0.5 } else { 0.1 });
+        match (self, colors) {
+            (ColorFunction::Lighten, [c]) => Some(adjust_lightness(*c, amount)),
+            (ColorFunction::Darken, [c]) => Some(adjust_lightness(*c, -amount)),
+            (ColorFunction::Saturate, [c]) => Some(adjust_saturation(*c, amount)),
+            (ColorFunction::Desaturate, [c]) => Some(adjust_saturation(*c, -amount)),
+            (ColorFunction::Complement, [c]) => Some(complement(*c)),
+            (ColorFunction::Mix, [a, b]) => Some(mix(*a, *b, amount)),
+            (ColorFunction::ReadableOn, [bg]) => Some(most_readable(*bg, &[BLACK, WHITE])),
+            (ColorFunction::ReadableOn, [bg, candidates @ ..]) => {
+                Some(most_readable(*bg, candidates))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for ColorFunction {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        ColorFunction::ALL
+            .into_iter()
+            .find(|f| f.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| Error::Msg(format!("unknown color function \"{s}\"")))
+    }
+}
+
+/// Returns the candidate having the highest contrast ratio with the background (the first one
+/// on ties).
+fn most_readable(bg: (u8, u8, u8), candidates: &[(u8, u8, u8)]) -> (u8, u8, u8) {
+    candidates
+        .iter()
+        .copied()
+        .fold(None, |best: Option<((u8, u8, u8), f64)>, c| {
+            let ratio = contrast_ratio(bg, c);
+            match best {
+                Some((_, best_ratio)) if best_ratio >= ratio => best,
+                _ => Some((c, ratio)),
+            }
+        })
+        .map_or(WHITE, |(c, _)| c)
+}
+
+/// Parses an amount given either as a percentage (`"20%"`) or as a fraction (`"0.2"`), in the
+/// `-100%..=100%` range.
+///
+/// # Examples
+/// ```
+/// use titular::color_functions::parse_amount;
+///
+/// assert_eq!(parse_amount("20%"), Some(0.2));
+/// assert_eq!(parse_amount("0.2"), Some(0.2));
+/// assert_eq!(parse_amount("20"), None);
+/// assert_eq!(parse_amount("red"), None);
+/// ```
+#[must_use]
+pub fn parse_amount(amount: &str) -> Option<f64> {
+    let amount = amount.trim();
+    match amount.strip_suffix('%') {
+        Some(percent) => percent.trim().parse::<f64>().ok().map(|p| p / 100.0),
+        None => amount.parse::<f64>().ok(),
+    }
+    .and_then(check_amount)
+}
+
+/// Checks that an amount given as a fraction is in the `-1.0..=1.0` range.
+#[must_use]
+pub fn check_amount(amount: f64) -> Option<f64> {
+    (-1.0..=1.0).contains(&amount).then_some(amount)
+}
+
+/// Formats a color as an `RGB(r,g,b)` string.
+#[must_use]
+pub fn to_rgb_string((r, g, b): (u8, u8, u8)) -> String {
+    format!("RGB({r},{g},{b})")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply() {
+        let red = (255, 0, 0);
+        assert_eq!(
+            ColorFunction::Lighten.apply(&[red], Some(0.25)),
+            Some((255, 128, 128))
+        );
+        assert_eq!(
+            ColorFunction::Desaturate.apply(&[red], Some(1.0)),
+            Some((128, 128, 128))
+        );
+        assert_eq!(
+            ColorFunction::Complement.apply(&[red], None),
+            Some((0, 255, 255))
+        );
+        assert_eq!(
+            ColorFunction::Mix.apply(&[red, (0, 0, 255)], None),
+            Some((128, 0, 128))
+        );
+        assert_eq!(ColorFunction::Lighten.apply(&[red, red], None), None);
+    }
+
+    #[test]
+    fn test_readable_on() {
+        assert_eq!(
+            ColorFunction::ReadableOn.apply(&[(250, 250, 210)], None),
+            Some(BLACK)
+        );
+        let candidates = [(40, 40, 40), (200, 200, 0), (255, 255, 0)];
+        assert_eq!(
+            ColorFunction::ReadableOn.apply(&candidates, None),
+            Some((255, 255, 0))
+        );
+    }
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!(
+            "readable_on".parse::<ColorFunction>().unwrap(),
+            ColorFunction::ReadableOn
+        );
+        assert!("rgb".parse::<ColorFunction>().is_err());
+    }
+}
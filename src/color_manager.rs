@@ -1,13 +1,22 @@
 use crate::utils::safe_parse;
 use nu_ansi_term::{
     Color,
-    Color::{Black, Blue, Cyan, Fixed, Green, Purple, Red, White, Yellow},
-    Style,
+    Color::{
+        Black, Blue, Cyan, DarkGray, Fixed, Green, LightBlue, LightCyan, LightGray, LightGreen,
+        LightPurple, LightRed, LightYellow, Purple, Red, White, Yellow,
+    },
 };
 use regex::Regex;
 use std::collections::HashSet;
+use std::str::FromStr;
 
+use crate::color_depth::ColorDepth;
+use crate::color_functions::{parse_amount, ColorFunction};
+use crate::color_names::{css_color, normalize_name, xterm_color};
+use crate::color_utils::{ansi16_index, color_to_rgb, hsl_to_rgb};
 use crate::context::Context;
+use crate::error::{Error, Result};
+use crate::string_utils::split_top_level;
 
 #[derive(Debug, Clone, Copy)]
 pub enum StyleScope {
@@ -16,11 +25,184 @@ pub enum StyleScope {
     BOTH,
 }
 
+/// The underline variants supported by terminals implementing the extended `SGR 4:x` codes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnderlineStyle {
+    Single,
+    Double,
+    Curly,
+    Dotted,
+    Dashed,
+}
+
+impl UnderlineStyle {
+    fn sgr(self) -> &'static str {
+        match self {
+            UnderlineStyle::Single => "4",
+            UnderlineStyle::Double => "4:2",
+            UnderlineStyle::Curly => "4:3",
+            UnderlineStyle::Dotted => "4:4",
+            UnderlineStyle::Dashed => "4:5",
+        }
+    }
+}
+
+impl FromStr for UnderlineStyle {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "true" | "single" => Ok(UnderlineStyle::Single),
+            "double" => Ok(UnderlineStyle::Double),
+            "curly" | "wavy" => Ok(UnderlineStyle::Curly),
+            "dotted" => Ok(UnderlineStyle::Dotted),
+            "dashed" => Ok(UnderlineStyle::Dashed),
+            other => Err(Error::Msg(format!(
+                "invalid underline style \"{other}\" (expected single, double, curly, dotted or dashed)"
+            ))),
+        }
+    }
+}
+
+/// Text attributes applied along with the colors of a [`StyleFormat`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TextAttributes {
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underline: Option<UnderlineStyle>,
+    pub blink: bool,
+    pub reverse: bool,
+    pub strike: bool,
+}
+
+impl TextAttributes {
+    /// Whether no attribute is set.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        *self == TextAttributes::default()
+    }
+
+    /// Combines both sets of attributes, `other` taking precedence for the underline style.
+    #[must_use]
+    pub fn merge(self, other: TextAttributes) -> TextAttributes {
+        TextAttributes {
+            bold: self.bold || other.bold,
+            dim: self.dim || other.dim,
+            italic: self.italic || other.italic,
+            underline: other.underline.or(self.underline),
+            blink: self.blink || other.blink,
+            reverse: self.reverse || other.reverse,
+            strike: self.strike || other.strike,
+        }
+    }
+
+    /// Sets the attribute named by the given keyword (as used in color specs, e.g.
+    /// `"bold curly_underline red"`), returning `None` if the keyword is not an attribute.
+    #[must_use]
+    pub fn with_keyword(mut self, keyword: &str) -> Option<TextAttributes> {
+        match normalize_name(keyword).as_str() {
+            "bold" => self.bold = true,
+            "dim" | "faint" => self.dim = true,
+            "italic" => self.italic = true,
+            "underline" | "underlined" => self.underline = Some(UnderlineStyle::Single),
+            "doubleunderline" => self.underline = Some(UnderlineStyle::Double),
+            "curlyunderline" => self.underline = Some(UnderlineStyle::Curly),
+            "dottedunderline" => self.underline = Some(UnderlineStyle::Dotted),
+            "dashedunderline" => self.underline = Some(UnderlineStyle::Dashed),
+            "blink" => self.blink = true,
+            "reverse" | "inverse" => self.reverse = true,
+            "strike" | "strikethrough" => self.strike = true,
+            _ => return None,
+        }
+        Some(self)
+    }
+
+    fn sgr_codes(&self) -> Vec<String> {
+        [
+            (self.bold, "1"),
+            (self.dim, "2"),
+            (self.italic, "3"),
+            (
+                self.underline.is_some(),
+                self.underline.map_or("", UnderlineStyle::sgr),
+            ),
+            (self.blink, "5"),
+            (self.reverse, "7"),
+            (self.strike, "9"),
+        ]
+        .into_iter()
+        .filter(|(on, _)| *on)
+        .map(|(_, code)| code.to_string())
+        .collect()
+    }
+}
+
 #[derive(Debug)]
 pub struct StyleFormat {
     pub fg_color: Option<String>,
     pub bg_color: Option<String>,
     pub scope: StyleScope,
+    pub attributes: TextAttributes,
+    /// Color of the underline (`SGR 58`), implying a single underline if none is set
+    pub underline_color: Option<String>,
+}
+
+/// The colors and attributes a color value resolves to. Plain colors only fill `fg`, while
+/// specs such as `"bold red on blue"` may set every field.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct ResolvedStyle {
+    fg: Option<Color>,
+    bg: Option<Color>,
+    attributes: TextAttributes,
+}
+
+impl ResolvedStyle {
+    fn color(color: Color) -> Self {
+        ResolvedStyle {
+            fg: Some(color),
+            ..ResolvedStyle::default()
+        }
+    }
+
+    fn is_plain_color(&self) -> bool {
+        self.bg.is_none() && self.attributes.is_empty()
+    }
+
+    fn merge(&mut self, other: ResolvedStyle) {
+        self.fg = other.fg.or(self.fg);
+        self.bg = other.bg.or(self.bg);
+        self.attributes = self.attributes.merge(other.attributes);
+    }
+}
+
+/// The SGR layer a color code is emitted for.
+#[derive(Clone, Copy)]
+enum Layer {
+    Fg,
+    Bg,
+    Underline,
+}
+
+impl Layer {
+    fn code(self, color: Color) -> String {
+        let (base, bright_base, extended) = match self {
+            Layer::Fg => (30, 90, 38),
+            Layer::Bg => (40, 100, 48),
+            Layer::Underline => (0, 0, 58),
+        };
+        match color {
+            Color::Rgb(r, g, b) => format!("{extended};2;{r};{g};{b}"),
+            Color::Fixed(n) => format!("{extended};5;{n}"),
+            Color::Default => format!("{}", extended + 1),
+            named => match (self, ansi16_index(named)) {
+                (Layer::Underline, Some(n)) => format!("{extended};5;{n}"),
+                (_, Some(n)) if n < 8 => format!("{}", base + n),
+                (_, Some(n)) => format!("{}", bright_base + n - 8),
+                (_, None) => String::new(),
+            },
+        }
+    }
 }
 
 static RGB_REGEX: std::sync::LazyLock<Regex> = std::sync::LazyLock::new(|| {
@@ -29,10 +211,25 @@ static RGB_REGEX: std::sync::LazyLock<Regex> = std::sync::LazyLock::new(|| {
 });
 
 static FNAME_REGEX: std::sync::LazyLock<Regex> = std::sync::LazyLock::new(|| {
-    Regex::new("(?i)^((FIXED)\\([\\s]*([0-9]+)[\\s]*\\)|(NAME)\\([\\s]*([[:alpha:]]+)[\\s]*\\))$")
-        .unwrap()
+    Regex::new(
+        "(?i)^((FIXED)\\([\\s]*([0-9]+)[\\s]*\\)|(NAME)\\([\\s]*([[:alnum:]_\\-]+)[\\s]*\\))$",
+    )
+    .unwrap()
+});
+
+static HEX_REGEX: std::sync::LazyLock<Regex> =
+    std::sync::LazyLock::new(|| Regex::new("^#([[:xdigit:]]{6}|[[:xdigit:]]{3})$").unwrap());
+
+static HSL_REGEX: std::sync::LazyLock<Regex> = std::sync::LazyLock::new(|| {
+    Regex::new(
+        "(?i)^HSL\\([\\s]*(-?[0-9.]+)(?:deg)?[\\s]*,[\\s]*([0-9.]+)%?[\\s]*,[\\s]*([0-9.]+)%?[\\s]*\\)$",
+    )
+    .unwrap()
 });
 
+static FUNCTION_REGEX: std::sync::LazyLock<Regex> =
+    std::sync::LazyLock::new(|| Regex::new("^([[:alpha:]_]+)\\((.*)\\)$").unwrap());
+
 pub struct ColorManager;
 
 impl ColorManager {
@@ -43,36 +240,145 @@ impl ColorManager {
     ///
     /// * `colours` - A reference to the fallback map containing color configurations
     /// * `txt` - The string to format
-    /// * `style` - The style format containing color information and scope
+    /// * `style` - The style format containing color information, scope and text attributes
+    ///
+    /// Colors are downsampled to the [`ColorDepth`] resolved from the context, and no styling
+    /// is applied at all when colors are disabled. A color resolving to a full spec (e.g. a
+    /// variable holding `"bold red on blue"`) contributes all of its colors and attributes.
     ///
     /// # Returns
     ///
     /// A string with the color applied
     #[must_use]
     pub fn format(colours: &Context, txt: &str, style: StyleFormat) -> String {
-        let mut style_obj = Style::new();
+        let codes = ColorManager::sgr_codes(colours, style);
+        if codes.is_empty() {
+            txt.to_string()
+        } else {
+            format!("\x1b[{}m{txt}\x1b[0m", codes.join(";"))
+        }
+    }
+
+    /// Returns the SGR parameters (e.g. `["1", "31"]`) that [`ColorManager::format`] wraps the
+    /// text with, so that callers managing their own escape sequences can combine styles. The
+    /// list is empty when nothing applies or when colors are disabled.
+    #[must_use]
+    pub fn sgr_codes(colours: &Context, style: StyleFormat) -> Vec<String> {
+        let depth = ColorDepth::from_context(colours);
+        if depth == ColorDepth::NoColor {
+            return Vec::new();
+        }
+        let mut resolved = ResolvedStyle {
+            attributes: style.attributes,
+            ..ResolvedStyle::default()
+        };
 
         // Apply foreground color if present
-        if let Some(fg) = style.fg_color {
-            if let Some(c) = ColorManager::get_style(colours, &fg) {
-                style_obj = style_obj.fg(c);
+        if let Some(spec) = style
+            .fg_color
+            .and_then(|fg| ColorManager::get_spec(colours, &fg))
+        {
+            if spec.is_plain_color() {
+                resolved.fg = spec.fg;
+            } else {
+                resolved.merge(spec);
             }
         }
 
         // Apply background color if present
-        if let Some(bg) = style.bg_color {
-            if let Some(c) = ColorManager::get_style(colours, &bg) {
-                style_obj = style_obj.on(c);
+        if let Some(spec) = style
+            .bg_color
+            .and_then(|bg| ColorManager::get_spec(colours, &bg))
+        {
+            if spec.is_plain_color() {
+                resolved.bg = spec.fg;
+            } else {
+                resolved.merge(spec);
+            }
+        }
+
+        let underline_color = style
+            .underline_color
+            .and_then(|ul| ColorManager::get_style(colours, &ul))
+            .and_then(|c| depth.downsample(c));
+        if underline_color.is_some() && resolved.attributes.underline.is_none() {
+            resolved.attributes.underline = Some(UnderlineStyle::Single);
+        }
+
+        let mut codes = resolved.attributes.sgr_codes();
+        let colors = [
+            (Layer::Fg, resolved.fg.and_then(|c| depth.downsample(c))),
+            (Layer::Bg, resolved.bg.and_then(|c| depth.downsample(c))),
+            (Layer::Underline, underline_color),
+        ];
+        for (layer, color) in colors {
+            if let Some(c) = color {
+                codes.push(layer.code(c));
+            }
+        }
+        codes.retain(|c| !c.is_empty());
+        codes
+    }
+
+    /// Same as [`ColorManager::format`] but, when strict color mode is active (see
+    /// [`ColorManager::is_strict`]), fails on colors that cannot be resolved instead of
+    /// silently leaving the text uncolored. Colors resolving to an empty value are never
+    /// considered an error.
+    ///
+    /// # Errors
+    /// Returns [`Error::UnresolvedColor`] in strict mode when a color cannot be resolved.
+    pub fn try_format(colours: &Context, txt: &str, style: StyleFormat) -> Result<String> {
+        ColorManager::check_strict(colours, &style)?;
+        Ok(ColorManager::format(colours, txt, style))
+    }
+
+    /// Same as [`ColorManager::sgr_codes`] but failing on unresolved colors in strict color
+    /// mode, like [`ColorManager::try_format`].
+    ///
+    /// # Errors
+    /// Returns [`Error::UnresolvedColor`] in strict mode when a color cannot be resolved.
+    pub fn try_sgr_codes(colours: &Context, style: StyleFormat) -> Result<Vec<String>> {
+        ColorManager::check_strict(colours, &style)?;
+        Ok(ColorManager::sgr_codes(colours, style))
+    }
+
+    /// Fails on the first color of the style that cannot be resolved in strict color mode.
+    fn check_strict(colours: &Context, style: &StyleFormat) -> Result<()> {
+        if ColorManager::is_strict(colours) {
+            let colors = [&style.fg_color, &style.bg_color, &style.underline_color];
+            for color in colors.into_iter().flatten() {
+                ColorManager::resolve(colours, color)?;
             }
         }
-        style_obj.paint(txt).to_string()
+        Ok(())
+    }
+
+    /// Whether strict color mode is enabled (`strict_colors` in the context or
+    /// `defaults.strict_colors` in the main configuration).
+    #[must_use]
+    pub fn is_strict(colours: &Context) -> bool {
+        colours.is_active("strict_colors") || colours.is_active("defaults.strict_colors")
     }
 
-    /// Process the colour style supplied in one of the following variants supported by the
-    /// `ansi_term` crate :
+    /// Resolves a color name, distinguishing intentionally empty colors (`Ok(None)`) from
+    /// colors that cannot be resolved. For specs carrying attributes, the foreground (or
+    /// else the background) color is returned.
+    ///
+    /// # Errors
+    /// Returns [`Error::UnresolvedColor`] if the color is unknown or its references form a cycle.
+    pub fn resolve(colours: &Context, color_name: &str) -> Result<Option<Color>> {
+        ColorManager::resolve_spec_safely(colours, color_name, &mut HashSet::new())
+            .map(|spec| spec.and_then(|s| s.fg.or(s.bg)))
+            .map_err(|()| Error::UnresolvedColor(color_name.to_string()))
+    }
+
+    /// Process the colour style supplied in one of the following variants :
     /// - RGB(r,g,b) : A colour specified using the RGB notation
-    /// - FIXED(num) : A colour specified in fixed terms
-    /// - NAME(name) : The name of the colour
+    /// - #rrggbb / #rgb : A colour specified in hexadecimal notation
+    /// - HSL(h,s%,l%) : A colour specified using hue, saturation and lightness
+    /// - FIXED(num) : A colour of the 256-colour palette
+    /// - NAME(name) : The name of the colour (ANSI, `bright_*`, CSS or xterm-256 name)
+    /// - A variable holding any of the above (or a bare colour name)
     ///
     /// # Arguments
     ///
@@ -84,7 +390,15 @@ impl ColorManager {
     /// A color object
     ///
     fn get_style(colours: &Context, color_name: &str) -> Option<Color> {
-        ColorManager::resolve_color_safely(colours, color_name, &mut HashSet::new())
+        ColorManager::get_spec(colours, color_name).and_then(|s| s.fg.or(s.bg))
+    }
+
+    /// Resolves a color value that may also be a spec combining attributes and colors, such as
+    /// `"bold RGB(255,0,0) on NAME(Blue)"`.
+    fn get_spec(colours: &Context, color_name: &str) -> Option<ResolvedStyle> {
+        ColorManager::resolve_spec_safely(colours, color_name, &mut HashSet::new())
+            .ok()
+            .flatten()
     }
 
     /// Internal method to process a color with cycle detection
@@ -97,22 +411,34 @@ impl ColorManager {
     ///
     /// # Returns
     ///
-    /// A color object if the color can be resolved, None if a cycle is detected or the color cannot be resolved
-    fn resolve_color_safely(
+    /// `Ok(Some(style))` if the color can be resolved, `Ok(None)` if it resolves to an empty
+    /// value and `Err(())` if a cycle is detected or the color cannot be resolved
+    fn resolve_spec_safely(
         colours: &Context,
         color_name: &str,
         visited: &mut HashSet<String>,
-    ) -> Option<Color> {
+    ) -> std::result::Result<Option<ResolvedStyle>, ()> {
+        if color_name.trim().is_empty() {
+            return Ok(None);
+        }
+
         // Check for cycles
         if !visited.insert(color_name.to_string()) {
-            return None;
+            return Err(());
         }
 
-        ColorManager::process_color(
-            colours,
-            colours.get(color_name).unwrap_or(color_name),
-            visited,
-        )
+        match colours.get(color_name) {
+            Some(value) => ColorManager::process_color(colours, value, visited),
+            None if FUNCTION_REGEX.is_match(color_name.trim()) => {
+                ColorManager::process_color(colours, color_name, visited)
+            }
+            None if ColorManager::is_spec(color_name) => {
+                ColorManager::parse_spec(colours, color_name, visited).map(Some)
+            }
+            None => ColorManager::parse_literal(color_name)
+                .map(|c| Some(ResolvedStyle::color(c)))
+                .ok_or(()),
+        }
     }
 
     /// Process a color string into a Color object
@@ -129,15 +455,127 @@ impl ColorManager {
         colours: &Context,
         color_str: &str,
         visited: &mut HashSet<String>,
-    ) -> Option<Color> {
-        if RGB_REGEX.is_match(color_str) {
-            let groups = RGB_REGEX.captures(color_str).unwrap();
+    ) -> std::result::Result<Option<ResolvedStyle>, ()> {
+        if let Some(c) = ColorManager::parse_syntax(color_str) {
+            return Ok(Some(ResolvedStyle::color(c)));
+        }
+        if let Some(result) = ColorManager::parse_function(colours, color_str, visited) {
+            return result.map(|c| Some(ResolvedStyle::color(c)));
+        }
+        if ColorManager::is_spec(color_str) {
+            ColorManager::parse_spec(colours, color_str, visited).map(Some)
+        } else {
+            ColorManager::resolve_spec_safely(colours, color_str, visited)
+        }
+    }
+
+    /// Evaluates a color function expression such as `darken(accent, 20%)` (see
+    /// [`ColorFunction`]), returning `None` if the value is not one. Its arguments may be any
+    /// color value, including other expressions.
+    fn parse_function(
+        colours: &Context,
+        expr: &str,
+        visited: &HashSet<String>,
+    ) -> Option<std::result::Result<Color, ()>> {
+        let groups = FUNCTION_REGEX.captures(expr.trim())?;
+        let function = groups[1].parse::<ColorFunction>().ok()?;
+        let mut args = split_top_level(groups.get(2).map_or("", |m| m.as_str()), |c| c == ',');
+        let amount = function
+            .takes_amount()
+            .then(|| args.last().and_then(|a| parse_amount(a)))
+            .flatten();
+        if amount.is_some() {
+            args.pop();
+        }
+
+        let colors = args
+            .iter()
+            .map(|arg| {
+                ColorManager::process_color(colours, arg, &mut visited.clone())?
+                    .and_then(|s| s.fg.or(s.bg))
+                    .and_then(color_to_rgb)
+                    .ok_or(())
+            })
+            .collect::<std::result::Result<Vec<_>, ()>>();
+        Some(colors.and_then(|colors| {
+            function
+                .apply(&colors, amount)
+                .map(|(r, g, b)| Color::Rgb(r, g, b))
+                .ok_or(())
+        }))
+    }
+
+    /// Whether the value is a spec rather than a single color : several tokens or a lone
+    /// attribute keyword.
+    fn is_spec(color_str: &str) -> bool {
+        let tokens = split_spec(color_str);
+        tokens.len() > 1
+            || tokens
+                .first()
+                .is_some_and(|t| TextAttributes::default().with_keyword(t).is_some())
+    }
+
+    /// Parses a spec made of attribute keywords, an optional foreground color and an optional
+    /// background color introduced by `on`. Colors may be variables, which are resolved
+    /// independently of each other so that a spec can reference the same variable twice.
+    fn parse_spec(
+        colours: &Context,
+        spec: &str,
+        visited: &HashSet<String>,
+    ) -> std::result::Result<ResolvedStyle, ()> {
+        let mut style = ResolvedStyle::default();
+        let mut tokens = split_spec(spec).into_iter();
+        while let Some(token) = tokens.next() {
+            if token.eq_ignore_ascii_case("on") {
+                let bg = tokens.next().ok_or(())?;
+                let bg = ColorManager::process_color(colours, bg, &mut visited.clone())?;
+                if let Some(bg) = bg {
+                    style.bg = bg.fg.or(bg.bg);
+                    style.attributes = style.attributes.merge(bg.attributes);
+                }
+            } else if let Some(attributes) = style.attributes.with_keyword(token) {
+                style.attributes = attributes;
+            } else if let Some(fg) =
+                ColorManager::process_color(colours, token, &mut visited.clone())?
+            {
+                style.merge(fg);
+            }
+        }
+        Ok(style)
+    }
+
+    /// Parses a color literal that does not need the context : any of the explicit syntaxes or
+    /// a bare colour name.
+    fn parse_literal(color_str: &str) -> Option<Color> {
+        ColorManager::parse_syntax(color_str).or_else(|| ColorManager::to_colour_name(color_str))
+    }
+
+    /// Parses the explicit color syntaxes (`RGB(...)`, `#hex`, `HSL(...)`, `FIXED(...)` and
+    /// `NAME(...)`).
+    fn parse_syntax(color_str: &str) -> Option<Color> {
+        let color_str = color_str.trim();
+        if let Some(groups) = RGB_REGEX.captures(color_str) {
             let r: u8 = safe_parse(groups.get(1).map_or("", |m| m.as_str()));
             let g: u8 = safe_parse(groups.get(2).map_or("", |m| m.as_str()));
             let b: u8 = safe_parse(groups.get(3).map_or("", |m| m.as_str()));
             Some(Color::Rgb(r, g, b))
-        } else if FNAME_REGEX.is_match(color_str) {
-            let groups = FNAME_REGEX.captures(color_str).unwrap();
+        } else if let Some(groups) = HEX_REGEX.captures(color_str) {
+            let hex = groups.get(1).map_or("", |m| m.as_str());
+            let hex = if hex.len() == 3 {
+                hex.chars().flat_map(|c| [c, c]).collect()
+            } else {
+                hex.to_string()
+            };
+            let value = u32::from_str_radix(&hex, 16).ok()?;
+            let [_, r, g, b] = value.to_be_bytes();
+            Some(Color::Rgb(r, g, b))
+        } else if let Some(groups) = HSL_REGEX.captures(color_str) {
+            let h: f64 = safe_parse(groups.get(1).map_or("", |m| m.as_str()));
+            let s: f64 = safe_parse(groups.get(2).map_or("", |m| m.as_str()));
+            let l: f64 = safe_parse(groups.get(3).map_or("", |m| m.as_str()));
+            let (r, g, b) = hsl_to_rgb(h, s / 100.0, l / 100.0);
+            Some(Color::Rgb(r, g, b))
+        } else if let Some(groups) = FNAME_REGEX.captures(color_str) {
             let operator = groups
                 .get(2)
                 .or_else(|| groups.get(4))
@@ -153,11 +591,12 @@ impl ColorManager {
                 None
             }
         } else {
-            ColorManager::resolve_color_safely(colours, color_str, visited)
+            None
         }
     }
 
-    /// List of colours supported by the `ansi_term` crate
+    /// Resolves a colour name, trying in order the ANSI colours supported by the `ansi_term`
+    /// crate, their `bright_*` variants, the CSS named colours and the xterm-256 colour names.
     ///
     /// # Arguments
     ///
@@ -167,17 +606,270 @@ impl ColorManager {
     ///
     /// A color object
     fn to_colour_name(colour_name: &str) -> Option<Color> {
-        let colour = colour_name.to_uppercase();
-        match &*colour {
-            "BLACK" => Some(Black),
-            "RED" => Some(Red),
-            "GREEN" => Some(Green),
-            "YELLOW" => Some(Yellow),
-            "BLUE" => Some(Blue),
-            "PURPLE" => Some(Purple),
-            "CYAN" => Some(Cyan),
-            "WHITE" => Some(White),
+        let colour = normalize_name(colour_name);
+        let ansi = match colour.as_str() {
+            "black" => Some(Black),
+            "red" => Some(Red),
+            "green" => Some(Green),
+            "yellow" => Some(Yellow),
+            "blue" => Some(Blue),
+            "purple" | "magenta" => Some(Purple),
+            "cyan" => Some(Cyan),
+            "white" => Some(White),
+            "brightblack" => Some(DarkGray),
+            "brightred" => Some(LightRed),
+            "brightgreen" => Some(LightGreen),
+            "brightyellow" => Some(LightYellow),
+            "brightblue" => Some(LightBlue),
+            "brightpurple" | "brightmagenta" => Some(LightPurple),
+            "brightcyan" => Some(LightCyan),
+            "brightwhite" => Some(LightGray),
             _ => None,
-        }
+        };
+        ansi.or_else(|| css_color(&colour).map(|(r, g, b)| Color::Rgb(r, g, b)))
+            .or_else(|| xterm_color(&colour).map(Fixed))
+    }
+}
+
+/// Splits a color spec on whitespace, keeping the arguments of `RGB(...)`-like syntaxes together.
+pub(crate) fn split_spec(spec: &str) -> Vec<&str> {
+    split_top_level(spec, char::is_whitespace)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve(ctx: &Context, name: &str) -> Option<Color> {
+        ColorManager::get_style(ctx, name)
+    }
+
+    #[test]
+    fn test_hex_colors() {
+        let ctx = Context::new();
+        assert_eq!(resolve(&ctx, "#ff8000"), Some(Color::Rgb(255, 128, 0)));
+        assert_eq!(resolve(&ctx, "#F80"), Some(Color::Rgb(255, 136, 0)));
+        assert_eq!(resolve(&ctx, "#ff80"), None);
+    }
+
+    #[test]
+    fn test_hsl_colors() {
+        let ctx = Context::new();
+        assert_eq!(
+            resolve(&ctx, "hsl(0, 100%, 50%)"),
+            Some(Color::Rgb(255, 0, 0))
+        );
+        assert_eq!(
+            resolve(&ctx, "HSL(240deg,100%,25%)"),
+            Some(Color::Rgb(0, 0, 128))
+        );
+    }
+
+    #[test]
+    fn test_named_colors() {
+        let ctx = Context::new();
+        assert_eq!(resolve(&ctx, "NAME(Red)"), Some(Red));
+        assert_eq!(resolve(&ctx, "NAME(bright_red)"), Some(LightRed));
+        assert_eq!(resolve(&ctx, "bright-cyan"), Some(LightCyan));
+        assert_eq!(
+            resolve(&ctx, "NAME(rebeccapurple)"),
+            Some(Color::Rgb(0x66, 0x33, 0x99))
+        );
+        assert_eq!(resolve(&ctx, "NAME(DeepSkyBlue4)"), Some(Fixed(23)));
+        assert_eq!(resolve(&ctx, "grey93"), Some(Fixed(255)));
+        assert_eq!(resolve(&ctx, "NAME(NoSuchColor)"), None);
+    }
+
+    #[test]
+    fn test_vars_take_precedence_over_names() {
+        let mut ctx = Context::new();
+        ctx.insert("red", "#800000");
+        ctx.insert("accent", "red");
+        assert_eq!(resolve(&ctx, "accent"), Some(Color::Rgb(128, 0, 0)));
+    }
+
+    #[test]
+    fn test_strict_mode() {
+        let mut ctx = Context::new();
+        ctx.insert("empty", "");
+        let style = |fg: &str| StyleFormat {
+            fg_color: Some(fg.to_string()),
+            bg_color: None,
+            scope: StyleScope::FG,
+            attributes: TextAttributes::default(),
+            underline_color: None,
+        };
+
+        assert!(ColorManager::try_format(&ctx, "x", style("no_such_color")).is_ok());
+
+        ctx.insert("strict_colors", "true");
+        assert!(matches!(
+            ColorManager::try_format(&ctx, "x", style("no_such_color")),
+            Err(Error::UnresolvedColor(_))
+        ));
+        assert!(ColorManager::try_format(&ctx, "x", style("empty")).is_ok());
+        assert!(ColorManager::try_format(&ctx, "x", style("#00ff00")).is_ok());
+    }
+
+    #[test]
+    fn test_format_downsamples_to_color_depth() {
+        let mut ctx = Context::new();
+        let style = || StyleFormat {
+            fg_color: Some("#ff8700".to_string()),
+            bg_color: None,
+            scope: StyleScope::FG,
+            attributes: TextAttributes::default(),
+            underline_color: None,
+        };
+
+        ctx.insert("color_mode", "truecolor");
+        assert_eq!(
+            ColorManager::format(&ctx, "x", style()),
+            "\x1b[38;2;255;135;0mx\x1b[0m"
+        );
+        ctx.insert("color_mode", "256");
+        assert_eq!(
+            ColorManager::format(&ctx, "x", style()),
+            "\x1b[38;5;208mx\x1b[0m"
+        );
+        ctx.insert("color_mode", "16");
+        assert_eq!(ColorManager::format(&ctx, "x", style()), "\x1b[33mx\x1b[0m");
+        ctx.insert("color_mode", "never");
+        assert_eq!(ColorManager::format(&ctx, "x", style()), "x");
+    }
+
+    #[test]
+    fn test_cycles_are_unresolved() {
+        let mut ctx = Context::new();
+        ctx.insert("a", "b");
+        ctx.insert("b", "a");
+        assert!(ColorManager::resolve(&ctx, "a").is_err());
+    }
+
+    #[test]
+    fn test_split_spec() {
+        assert_eq!(
+            split_spec(" bold RGB(255, 0, 0)  on NAME(Blue) "),
+            vec!["bold", "RGB(255, 0, 0)", "on", "NAME(Blue)"]
+        );
+    }
+
+    #[test]
+    fn test_format_attributes() {
+        let mut ctx = Context::new();
+        ctx.insert("color_mode", "truecolor");
+        let style = |attributes, underline_color: Option<&str>| StyleFormat {
+            fg_color: Some("red".to_string()),
+            bg_color: None,
+            scope: StyleScope::FG,
+            attributes,
+            underline_color: underline_color.map(str::to_string),
+        };
+
+        let bold_italic = TextAttributes {
+            bold: true,
+            italic: true,
+            ..TextAttributes::default()
+        };
+        assert_eq!(
+            ColorManager::format(&ctx, "x", style(bold_italic, None)),
+            "\x1b[1;3;31mx\x1b[0m"
+        );
+
+        let curly = TextAttributes {
+            underline: Some(UnderlineStyle::Curly),
+            ..TextAttributes::default()
+        };
+        assert_eq!(
+            ColorManager::format(&ctx, "x", style(curly, Some("#00ff00"))),
+            "\x1b[4:3;31;58;2;0;255;0mx\x1b[0m"
+        );
+        assert_eq!(
+            ColorManager::format(&ctx, "x", style(TextAttributes::default(), Some("blue"))),
+            "\x1b[4;31;58;5;4mx\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_spec_vars() {
+        let mut ctx = Context::new();
+        ctx.insert("color_mode", "truecolor");
+        ctx.insert("accent", "RGB(255,0,0)");
+        ctx.insert("alert", "bold accent on NAME(Blue)");
+        ctx.insert("loud", "alert strike");
+        let style = |fg: &str| StyleFormat {
+            fg_color: Some(fg.to_string()),
+            bg_color: None,
+            scope: StyleScope::FG,
+            attributes: TextAttributes::default(),
+            underline_color: None,
+        };
+
+        assert_eq!(
+            ColorManager::format(&ctx, "x", style("alert")),
+            "\x1b[1;38;2;255;0;0;44mx\x1b[0m"
+        );
+        assert_eq!(
+            ColorManager::format(&ctx, "x", style("loud")),
+            "\x1b[1;9;38;2;255;0;0;44mx\x1b[0m"
+        );
+        assert_eq!(
+            ColorManager::format(&ctx, "x", style("accent on accent")),
+            "\x1b[38;2;255;0;0;48;2;255;0;0mx\x1b[0m"
+        );
+        assert_eq!(
+            ColorManager::format(&ctx, "x", style("dim")),
+            "\x1b[2mx\x1b[0m"
+        );
+        assert_eq!(
+            ColorManager::resolve(&ctx, "alert").unwrap(),
+            Some(Color::Rgb(255, 0, 0))
+        );
+
+        ctx.insert("strict_colors", "true");
+        assert!(ColorManager::try_format(&ctx, "x", style("bold nosuchcolor")).is_err());
+        assert!(ColorManager::try_format(&ctx, "x", style("bold on")).is_err());
+    }
+
+    #[test]
+    fn test_color_function_expressions() {
+        let mut ctx = Context::new();
+        ctx.insert("accent", "#ff0000");
+        ctx.insert("border", "darken(accent, 25%)");
+        ctx.insert("text", "readable_on(border)");
+
+        assert_eq!(resolve(&ctx, "border"), Some(Color::Rgb(128, 0, 0)));
+        assert_eq!(resolve(&ctx, "text"), Some(Color::Rgb(255, 255, 255)));
+        assert_eq!(
+            resolve(&ctx, "mix(accent, NAME(Blue), 0.5)"),
+            Some(Color::Rgb(128, 0, 64))
+        );
+        assert_eq!(
+            resolve(&ctx, "lighten(RGB(255, 0, 0), 0.25)"),
+            Some(Color::Rgb(255, 128, 128))
+        );
+        assert_eq!(
+            resolve(&ctx, "complement(hsl(0, 100%, 50%))"),
+            Some(Color::Rgb(0, 255, 255))
+        );
+        assert_eq!(resolve(&ctx, "darken(nosuchcolor, 10%)"), None);
+        assert_eq!(resolve(&ctx, "lighten(accent, 25)"), None);
+        assert_eq!(resolve(&ctx, "mix(accent)"), None);
+
+        ctx.insert("loop", "lighten(loop, 10%)");
+        assert!(ColorManager::resolve(&ctx, "loop").is_err());
+    }
+
+    #[test]
+    fn test_underline_style_from_str() {
+        assert_eq!(
+            "true".parse::<UnderlineStyle>().unwrap(),
+            UnderlineStyle::Single
+        );
+        assert_eq!(
+            "Curly".parse::<UnderlineStyle>().unwrap(),
+            UnderlineStyle::Curly
+        );
+        assert!("zigzag".parse::<UnderlineStyle>().is_err());
     }
 }
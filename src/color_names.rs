@@ -0,0 +1,467 @@
+//! Named color tables used by [`ColorManager`](crate::color_manager::ColorManager): the CSS
+//! named colors and the xterm-256 palette names.
+//!
+//! Lookups are case-insensitive and ignore `_`, `-` and spaces, so `RebeccaPurple`,
+//! `rebecca_purple` and `rebeccapurple` all resolve to the same entry.
+
+/// CSS Color Module Level 4 named colors as `0xRRGGBB`, sorted by name.
+static CSS_COLORS: [(&str, u32); 148] = [
+    ("aliceblue", 0xF0F8FF),
+    ("antiquewhite", 0xFAEBD7),
+    ("aqua", 0x00FFFF),
+    ("aquamarine", 0x7FFFD4),
+    ("azure", 0xF0FFFF),
+    ("beige", 0xF5F5DC),
+    ("bisque", 0xFFE4C4),
+    ("black", 0x000000),
+    ("blanchedalmond", 0xFFEBCD),
+    ("blue", 0x0000FF),
+    ("blueviolet", 0x8A2BE2),
+    ("brown", 0xA52A2A),
+    ("burlywood", 0xDEB887),
+    ("cadetblue", 0x5F9EA0),
+    ("chartreuse", 0x7FFF00),
+    ("chocolate", 0xD2691E),
+    ("coral", 0xFF7F50),
+    ("cornflowerblue", 0x6495ED),
+    ("cornsilk", 0xFFF8DC),
+    ("crimson", 0xDC143C),
+    ("cyan", 0x00FFFF),
+    ("darkblue", 0x00008B),
+    ("darkcyan", 0x008B8B),
+    ("darkgoldenrod", 0xB8860B),
+    ("darkgray", 0xA9A9A9),
+    ("darkgreen", 0x006400),
+    ("darkgrey", 0xA9A9A9),
+    ("darkkhaki", 0xBDB76B),
+    ("darkmagenta", 0x8B008B),
+    ("darkolivegreen", 0x556B2F),
+    ("darkorange", 0xFF8C00),
+    ("darkorchid", 0x9932CC),
+    ("darkred", 0x8B0000),
+    ("darksalmon", 0xE9967A),
+    ("darkseagreen", 0x8FBC8F),
+    ("darkslateblue", 0x483D8B),
+    ("darkslategray", 0x2F4F4F),
+    ("darkslategrey", 0x2F4F4F),
+    ("darkturquoise", 0x00CED1),
+    ("darkviolet", 0x9400D3),
+    ("deeppink", 0xFF1493),
+    ("deepskyblue", 0x00BFFF),
+    ("dimgray", 0x696969),
+    ("dimgrey", 0x696969),
+    ("dodgerblue", 0x1E90FF),
+    ("firebrick", 0xB22222),
+    ("floralwhite", 0xFFFAF0),
+    ("forestgreen", 0x228B22),
+    ("fuchsia", 0xFF00FF),
+    ("gainsboro", 0xDCDCDC),
+    ("ghostwhite", 0xF8F8FF),
+    ("gold", 0xFFD700),
+    ("goldenrod", 0xDAA520),
+    ("gray", 0x808080),
+    ("green", 0x008000),
+    ("greenyellow", 0xADFF2F),
+    ("grey", 0x808080),
+    ("honeydew", 0xF0FFF0),
+    ("hotpink", 0xFF69B4),
+    ("indianred", 0xCD5C5C),
+    ("indigo", 0x4B0082),
+    ("ivory", 0xFFFFF0),
+    ("khaki", 0xF0E68C),
+    ("lavender", 0xE6E6FA),
+    ("lavenderblush", 0xFFF0F5),
+    ("lawngreen", 0x7CFC00),
+    ("lemonchiffon", 0xFFFACD),
+    ("lightblue", 0xADD8E6),
+    ("lightcoral", 0xF08080),
+    ("lightcyan", 0xE0FFFF),
+    ("lightgoldenrodyellow", 0xFAFAD2),
+    ("lightgray", 0xD3D3D3),
+    ("lightgreen", 0x90EE90),
+    ("lightgrey", 0xD3D3D3),
+    ("lightpink", 0xFFB6C1),
+    ("lightsalmon", 0xFFA07A),
+    ("lightseagreen", 0x20B2AA),
+    ("lightskyblue", 0x87CEFA),
+    ("lightslategray", 0x778899),
+    ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xB0C4DE),
+    ("lightyellow", 0xFFFFE0),
+    ("lime", 0x00FF00),
+    ("limegreen", 0x32CD32),
+    ("linen", 0xFAF0E6),
+    ("magenta", 0xFF00FF),
+    ("maroon", 0x800000),
+    ("mediumaquamarine", 0x66CDAA),
+    ("mediumblue", 0x0000CD),
+    ("mediumorchid", 0xBA55D3),
+    ("mediumpurple", 0x9370DB),
+    ("mediumseagreen", 0x3CB371),
+    ("mediumslateblue", 0x7B68EE),
+    ("mediumspringgreen", 0x00FA9A),
+    ("mediumturquoise", 0x48D1CC),
+    ("mediumvioletred", 0xC71585),
+    ("midnightblue", 0x191970),
+    ("mintcream", 0xF5FFFA),
+    ("mistyrose", 0xFFE4E1),
+    ("moccasin", 0xFFE4B5),
+    ("navajowhite", 0xFFDEAD),
+    ("navy", 0x000080),
+    ("oldlace", 0xFDF5E6),
+    ("olive", 0x808000),
+    ("olivedrab", 0x6B8E23),
+    ("orange", 0xFFA500),
+    ("orangered", 0xFF4500),
+    ("orchid", 0xDA70D6),
+    ("palegoldenrod", 0xEEE8AA),
+    ("palegreen", 0x98FB98),
+    ("paleturquoise", 0xAFEEEE),
+    ("palevioletred", 0xDB7093),
+    ("papayawhip", 0xFFEFD5),
+    ("peachpuff", 0xFFDAB9),
+    ("peru", 0xCD853F),
+    ("pink", 0xFFC0CB),
+    ("plum", 0xDDA0DD),
+    ("powderblue", 0xB0E0E6),
+    ("purple", 0x800080),
+    ("rebeccapurple", 0x663399),
+    ("red", 0xFF0000),
+    ("rosybrown", 0xBC8F8F),
+    ("royalblue", 0x4169E1),
+    ("saddlebrown", 0x8B4513),
+    ("salmon", 0xFA8072),
+    ("sandybrown", 0xF4A460),
+    ("seagreen", 0x2E8B57),
+    ("seashell", 0xFFF5EE),
+    ("sienna", 0xA0522D),
+    ("silver", 0xC0C0C0),
+    ("skyblue", 0x87CEEB),
+    ("slateblue", 0x6A5ACD),
+    ("slategray", 0x708090),
+    ("slategrey", 0x708090),
+    ("snow", 0xFFFAFA),
+    ("springgreen", 0x00FF7F),
+    ("steelblue", 0x4682B4),
+    ("tan", 0xD2B48C),
+    ("teal", 0x008080),
+    ("thistle", 0xD8BFD8),
+    ("tomato", 0xFF6347),
+    ("turquoise", 0x40E0D0),
+    ("violet", 0xEE82EE),
+    ("wheat", 0xF5DEB3),
+    ("white", 0xFFFFFF),
+    ("whitesmoke", 0xF5F5F5),
+    ("yellow", 0xFFFF00),
+    ("yellowgreen", 0x9ACD32),
+];
+
+/// xterm-256 color names indexed by palette number (duplicated names resolve to the lowest index).
+static XTERM_NAMES: [&str; 256] = [
+    "Black",
+    "Maroon",
+    "Green",
+    "Olive",
+    "Navy",
+    "Purple",
+    "Teal",
+    "Silver",
+    "Grey",
+    "Red",
+    "Lime",
+    "Yellow",
+    "Blue",
+    "Fuchsia",
+    "Aqua",
+    "White",
+    "Grey0",
+    "NavyBlue",
+    "DarkBlue",
+    "Blue3",
+    "Blue3",
+    "Blue1",
+    "DarkGreen",
+    "DeepSkyBlue4",
+    "DeepSkyBlue4",
+    "DeepSkyBlue4",
+    "DodgerBlue3",
+    "DodgerBlue2",
+    "Green4",
+    "SpringGreen4",
+    "Turquoise4",
+    "DeepSkyBlue3",
+    "DeepSkyBlue3",
+    "DodgerBlue1",
+    "Green3",
+    "SpringGreen3",
+    "DarkCyan",
+    "LightSeaGreen",
+    "DeepSkyBlue2",
+    "DeepSkyBlue1",
+    "Green3",
+    "SpringGreen3",
+    "SpringGreen2",
+    "Cyan3",
+    "DarkTurquoise",
+    "Turquoise2",
+    "Green1",
+    "SpringGreen2",
+    "SpringGreen1",
+    "MediumSpringGreen",
+    "Cyan2",
+    "Cyan1",
+    "DarkRed",
+    "DeepPink4",
+    "Purple4",
+    "Purple4",
+    "Purple3",
+    "BlueViolet",
+    "Orange4",
+    "Grey37",
+    "MediumPurple4",
+    "SlateBlue3",
+    "SlateBlue3",
+    "RoyalBlue1",
+    "Chartreuse4",
+    "DarkSeaGreen4",
+    "PaleTurquoise4",
+    "SteelBlue",
+    "SteelBlue3",
+    "CornflowerBlue",
+    "Chartreuse3",
+    "DarkSeaGreen4",
+    "CadetBlue",
+    "CadetBlue",
+    "SkyBlue3",
+    "SteelBlue1",
+    "Chartreuse3",
+    "PaleGreen3",
+    "SeaGreen3",
+    "Aquamarine3",
+    "MediumTurquoise",
+    "SteelBlue1",
+    "Chartreuse2",
+    "SeaGreen2",
+    "SeaGreen1",
+    "SeaGreen1",
+    "Aquamarine1",
+    "DarkSlateGray2",
+    "DarkRed",
+    "DeepPink4",
+    "DarkMagenta",
+    "DarkMagenta",
+    "DarkViolet",
+    "Purple",
+    "Orange4",
+    "LightPink4",
+    "Plum4",
+    "MediumPurple3",
+    "MediumPurple3",
+    "SlateBlue1",
+    "Yellow4",
+    "Wheat4",
+    "Grey53",
+    "LightSlateGrey",
+    "MediumPurple",
+    "LightSlateBlue",
+    "Yellow4",
+    "DarkOliveGreen3",
+    "DarkSeaGreen",
+    "LightSkyBlue3",
+    "LightSkyBlue3",
+    "SkyBlue2",
+    "Chartreuse2",
+    "DarkOliveGreen3",
+    "PaleGreen3",
+    "DarkSeaGreen3",
+    "DarkSlateGray3",
+    "SkyBlue1",
+    "Chartreuse1",
+    "LightGreen",
+    "LightGreen",
+    "PaleGreen1",
+    "Aquamarine1",
+    "DarkSlateGray1",
+    "Red3",
+    "DeepPink4",
+    "MediumVioletRed",
+    "Magenta3",
+    "DarkViolet",
+    "Purple",
+    "DarkOrange3",
+    "IndianRed",
+    "HotPink3",
+    "MediumOrchid3",
+    "MediumOrchid",
+    "MediumPurple2",
+    "DarkGoldenrod",
+    "LightSalmon3",
+    "RosyBrown",
+    "Grey63",
+    "MediumPurple2",
+    "MediumPurple1",
+    "Gold3",
+    "DarkKhaki",
+    "NavajoWhite3",
+    "Grey69",
+    "LightSteelBlue3",
+    "LightSteelBlue",
+    "Yellow3",
+    "DarkOliveGreen3",
+    "DarkSeaGreen3",
+    "DarkSeaGreen2",
+    "LightCyan3",
+    "LightSkyBlue1",
+    "GreenYellow",
+    "DarkOliveGreen2",
+    "PaleGreen1",
+    "DarkSeaGreen2",
+    "DarkSeaGreen1",
+    "PaleTurquoise1",
+    "Red3",
+    "DeepPink3",
+    "DeepPink3",
+    "Magenta3",
+    "Magenta3",
+    "Magenta2",
+    "DarkOrange3",
+    "IndianRed",
+    "HotPink3",
+    "HotPink2",
+    "Orchid",
+    "MediumOrchid1",
+    "Orange3",
+    "LightSalmon3",
+    "LightPink3",
+    "Pink3",
+    "Plum3",
+    "Violet",
+    "Gold3",
+    "LightGoldenrod3",
+    "Tan",
+    "MistyRose3",
+    "Thistle3",
+    "Plum2",
+    "Yellow3",
+    "Khaki3",
+    "LightGoldenrod2",
+    "LightYellow3",
+    "Grey84",
+    "LightSteelBlue1",
+    "Yellow2",
+    "DarkOliveGreen1",
+    "DarkOliveGreen1",
+    "DarkSeaGreen1",
+    "Honeydew2",
+    "LightCyan1",
+    "Red1",
+    "DeepPink2",
+    "DeepPink1",
+    "DeepPink1",
+    "Magenta2",
+    "Magenta1",
+    "OrangeRed1",
+    "IndianRed1",
+    "IndianRed1",
+    "HotPink",
+    "HotPink",
+    "MediumOrchid1",
+    "DarkOrange",
+    "Salmon1",
+    "LightCoral",
+    "PaleVioletRed1",
+    "Orchid2",
+    "Orchid1",
+    "Orange1",
+    "SandyBrown",
+    "LightSalmon1",
+    "LightPink1",
+    "Pink1",
+    "Plum1",
+    "Gold1",
+    "LightGoldenrod2",
+    "LightGoldenrod2",
+    "NavajoWhite1",
+    "MistyRose1",
+    "Thistle1",
+    "Yellow1",
+    "LightGoldenrod1",
+    "Khaki1",
+    "Wheat1",
+    "Cornsilk1",
+    "Grey100",
+    "Grey3",
+    "Grey7",
+    "Grey11",
+    "Grey15",
+    "Grey19",
+    "Grey23",
+    "Grey27",
+    "Grey30",
+    "Grey35",
+    "Grey39",
+    "Grey42",
+    "Grey46",
+    "Grey50",
+    "Grey54",
+    "Grey58",
+    "Grey62",
+    "Grey66",
+    "Grey70",
+    "Grey74",
+    "Grey78",
+    "Grey82",
+    "Grey85",
+    "Grey89",
+    "Grey93",
+];
+
+/// Normalizes a color name for table lookups (lowercase, without `_`, `-` or spaces).
+#[must_use]
+pub fn normalize_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| !matches!(c, '_' | '-' | ' '))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Looks up a CSS named color, returning its RGB components.
+///
+/// # Examples
+/// ```
+/// use titular::color_names::css_color;
+///
+/// assert_eq!(css_color("RebeccaPurple"), Some((0x66, 0x33, 0x99)));
+/// assert_eq!(css_color("not_a_color"), None);
+/// ```
+#[must_use]
+pub fn css_color(name: &str) -> Option<(u8, u8, u8)> {
+    let key = normalize_name(name);
+    CSS_COLORS
+        .binary_search_by(|(n, _)| (*n).cmp(key.as_str()))
+        .ok()
+        .map(|idx| {
+            let [_, r, g, b] = CSS_COLORS[idx].1.to_be_bytes();
+            (r, g, b)
+        })
+}
+
+/// Looks up an xterm-256 color name, returning its palette index.
+///
+/// Both `Grey` and `Gray` spellings are accepted.
+///
+/// # Examples
+/// ```
+/// use titular::color_names::xterm_color;
+///
+/// assert_eq!(xterm_color("DarkOrange3"), Some(130));
+/// assert_eq!(xterm_color("gray50"), Some(244));
+/// ```
+#[must_use]
+pub fn xterm_color(name: &str) -> Option<u8> {
+    let key = normalize_name(name).replace("gray", "grey");
+    XTERM_NAMES
+        .iter()
+        .position(|n| normalize_name(n) == key)
+        .and_then(|idx| u8::try_from(idx).ok())
+}
@@ -0,0 +1,439 @@
+//! Color space helpers shared by the color syntaxes understood by
+//! [`ColorManager`](crate::color_manager::ColorManager) and by color depth downsampling.
+
+use nu_ansi_term::Color;
+
+use crate::color_names::css_color;
+
+/// RGB values of the 16 system colors of the xterm palette (`FIXED(0)` to `FIXED(15)`).
+pub const ANSI16_RGB: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (128, 0, 0),
+    (0, 128, 0),
+    (128, 128, 0),
+    (0, 0, 128),
+    (128, 0, 128),
+    (0, 128, 128),
+    (192, 192, 192),
+    (128, 128, 128),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (0, 0, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+/// Channel levels of the 6x6x6 color cube of the 256-color palette.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Converts an HSL color to RGB.
+///
+/// # Arguments
+/// * `h` - Hue in degrees (any value, wrapped to `0..360`)
+/// * `s` - Saturation in the `0.0..=1.0` range
+/// * `l` - Lightness in the `0.0..=1.0` range
+///
+/// # Examples
+/// ```
+/// use titular::color_utils::hsl_to_rgb;
+///
+/// assert_eq!(hsl_to_rgb(0.0, 1.0, 0.5), (255, 0, 0));
+/// assert_eq!(hsl_to_rgb(120.0, 1.0, 0.25), (0, 128, 0));
+/// ```
+#[must_use]
+pub fn hsl_to_rgb(h: f64, s: f64, l: f64) -> (u8, u8, u8) {
+    let h = h.rem_euclid(360.0) / 360.0;
+    let s = s.clamp(0.0, 1.0);
+    let l = l.clamp(0.0, 1.0);
+
+    if s == 0.0 {
+        let v = to_channel(l);
+        return (v, v, v);
+    }
+
+    let q = if l < 0.5 {
+        l * (1.0 + s)
+    } else {
+        l + s - l * s
+    };
+    let p = 2.0 * l - q;
+    (
+        to_channel(hue_to_channel(p, q, h + 1.0 / 3.0)),
+        to_channel(hue_to_channel(p, q, h)),
+        to_channel(hue_to_channel(p, q, h - 1.0 / 3.0)),
+    )
+}
+
+fn hue_to_channel(p: f64, q: f64, t: f64) -> f64 {
+    let t = t.rem_euclid(1.0);
+    if t < 1.0 / 6.0 {
+        p + (q - p) * 6.0 * t
+    } else if t < 0.5 {
+        q
+    } else if t < 2.0 / 3.0 {
+        p + (q - p) * (2.0 / 3.0 - t) * 6.0
+    } else {
+        p
+    }
+}
+
+/// Converts an RGB color to HSL, returning the hue in degrees (`0..360`) and the saturation and
+/// lightness in the `0.0..=1.0` range.
+///
+/// # Examples
+/// ```
+/// use titular::color_utils::rgb_to_hsl;
+///
+/// assert_eq!(rgb_to_hsl((255, 0, 0)), (0.0, 1.0, 0.5));
+/// assert_eq!(rgb_to_hsl((255, 255, 255)), (0.0, 0.0, 1.0));
+/// ```
+#[must_use]
+pub fn rgb_to_hsl((r, g, b): (u8, u8, u8)) -> (f64, f64, f64) {
+    let [r, g, b] = [r, g, b].map(|c| f64::from(c) / 255.0);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let l = (max + min) / 2.0;
+    let delta = max - min;
+    if delta == 0.0 {
+        return (0.0, 0.0, l);
+    }
+
+    let s = delta / (1.0 - (2.0 * l - 1.0).abs());
+    let h = if max == r {
+        ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        (b - r) / delta + 2.0
+    } else {
+        (r - g) / delta + 4.0
+    };
+    (h * 60.0, s, l)
+}
+
+/// Returns the WCAG relative luminance of an sRGB color, in the `0.0..=1.0` range.
+#[must_use]
+pub fn relative_luminance((r, g, b): (u8, u8, u8)) -> f64 {
+    let linear = |c: u8| {
+        let c = f64::from(c) / 255.0;
+        if c <= 0.039_28 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * linear(r) + 0.7152 * linear(g) + 0.0722 * linear(b)
+}
+
+/// Returns the WCAG contrast ratio between two colors, from `1.0` (no contrast) to `21.0`
+/// (black on white).
+///
+/// # Examples
+/// ```
+/// use titular::color_utils::contrast_ratio;
+///
+/// assert_eq!(contrast_ratio((0, 0, 0), (255, 255, 255)), 21.0);
+/// assert_eq!(contrast_ratio((255, 255, 255), (0, 0, 0)), 21.0);
+/// assert!((contrast_ratio((119, 119, 119), (255, 255, 255)) - 4.48).abs() < 0.01);
+/// ```
+#[must_use]
+pub fn contrast_ratio(a: (u8, u8, u8), b: (u8, u8, u8)) -> f64 {
+    let (la, lb) = (relative_luminance(a), relative_luminance(b));
+    (la.max(lb) + 0.05) / (la.min(lb) + 0.05)
+}
+
+/// Adds `amount` (in the `-1.0..=1.0` range) to the HSL lightness of the color.
+///
+/// # Examples
+/// ```
+/// use titular::color_utils::adjust_lightness;
+///
+/// assert_eq!(adjust_lightness((255, 0, 0), 0.25), (255, 128, 128));
+/// assert_eq!(adjust_lightness((255, 0, 0), -0.25), (128, 0, 0));
+/// ```
+#[must_use]
+pub fn adjust_lightness(rgb: (u8, u8, u8), amount: f64) -> (u8, u8, u8) {
+    let (h, s, l) = rgb_to_hsl(rgb);
+    hsl_to_rgb(h, s, l + amount)
+}
+
+/// Adds `amount` (in the `-1.0..=1.0` range) to the HSL saturation of the color.
+#[must_use]
+pub fn adjust_saturation(rgb: (u8, u8, u8), amount: f64) -> (u8, u8, u8) {
+    let (h, s, l) = rgb_to_hsl(rgb);
+    hsl_to_rgb(h, s + amount, l)
+}
+
+/// Returns the complementary color (hue rotated by 180 degrees).
+///
+/// # Examples
+/// ```
+/// use titular::color_utils::complement;
+///
+/// assert_eq!(complement((255, 0, 0)), (0, 255, 255));
+/// ```
+#[must_use]
+pub fn complement(rgb: (u8, u8, u8)) -> (u8, u8, u8) {
+    let (h, s, l) = rgb_to_hsl(rgb);
+    hsl_to_rgb(h + 180.0, s, l)
+}
+
+/// Mixes two colors channel by channel, `weight` being the proportion of `b` in the result.
+///
+/// # Examples
+/// ```
+/// use titular::color_utils::mix;
+///
+/// assert_eq!(mix((0, 0, 0), (255, 255, 255), 0.5), (128, 128, 128));
+/// assert_eq!(mix((255, 0, 0), (0, 0, 255), 0.0), (255, 0, 0));
+/// ```
+#[must_use]
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+pub fn mix(a: (u8, u8, u8), b: (u8, u8, u8), weight: f64) -> (u8, u8, u8) {
+    let weight = weight.clamp(0.0, 1.0);
+    let channel = |x: u8, y: u8| {
+        (f64::from(x) * (1.0 - weight) + f64::from(y) * weight)
+            .round()
+            .clamp(0.0, 255.0) as u8
+    };
+    (channel(a.0, b.0), channel(a.1, b.1), channel(a.2, b.2))
+}
+
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn to_channel(v: f64) -> u8 {
+    (v * 255.0).round().clamp(0.0, 255.0) as u8
+}
+
+/// Returns the RGB value of an entry of the 256-color palette.
+///
+/// # Examples
+/// ```
+/// use titular::color_utils::fixed_to_rgb;
+///
+/// assert_eq!(fixed_to_rgb(9), (255, 0, 0));
+/// assert_eq!(fixed_to_rgb(208), (255, 135, 0));
+/// assert_eq!(fixed_to_rgb(244), (128, 128, 128));
+/// ```
+#[must_use]
+pub fn fixed_to_rgb(n: u8) -> (u8, u8, u8) {
+    match n {
+        0..=15 => ANSI16_RGB[usize::from(n)],
+        16..=231 => {
+            let idx = n - 16;
+            (
+                CUBE_LEVELS[usize::from(idx / 36)],
+                CUBE_LEVELS[usize::from((idx / 6) % 6)],
+                CUBE_LEVELS[usize::from(idx % 6)],
+            )
+        }
+        _ => {
+            let v = 8 + (n - 232) * 10;
+            (v, v, v)
+        }
+    }
+}
+
+/// Index (0-15) of a named ANSI color in the 16-color palette.
+#[must_use]
+pub fn ansi16_index(color: Color) -> Option<u8> {
+    match color {
+        Color::Black => Some(0),
+        Color::Red => Some(1),
+        Color::Green => Some(2),
+        Color::Yellow => Some(3),
+        Color::Blue => Some(4),
+        Color::Purple | Color::Magenta => Some(5),
+        Color::Cyan => Some(6),
+        Color::White => Some(7),
+        Color::DarkGray => Some(8),
+        Color::LightRed => Some(9),
+        Color::LightGreen => Some(10),
+        Color::LightYellow => Some(11),
+        Color::LightBlue => Some(12),
+        Color::LightPurple | Color::LightMagenta => Some(13),
+        Color::LightCyan => Some(14),
+        Color::LightGray => Some(15),
+        Color::Fixed(n) if n < 16 => Some(n),
+        _ => None,
+    }
+}
+
+/// Named ANSI color for an index of the 16-color palette.
+#[must_use]
+pub fn ansi16_color(index: u8) -> Color {
+    match index {
+        0 => Color::Black,
+        1 => Color::Red,
+        2 => Color::Green,
+        3 => Color::Yellow,
+        4 => Color::Blue,
+        5 => Color::Purple,
+        6 => Color::Cyan,
+        7 => Color::White,
+        8 => Color::DarkGray,
+        9 => Color::LightRed,
+        10 => Color::LightGreen,
+        11 => Color::LightYellow,
+        12 => Color::LightBlue,
+        13 => Color::LightPurple,
+        14 => Color::LightCyan,
+        _ => Color::LightGray,
+    }
+}
+
+/// Approximate RGB value of any terminal color (`None` for the terminal default color).
+#[must_use]
+pub fn color_to_rgb(color: Color) -> Option<(u8, u8, u8)> {
+    match color {
+        Color::Rgb(r, g, b) => Some((r, g, b)),
+        Color::Fixed(n) => Some(fixed_to_rgb(n)),
+        Color::Default => None,
+        named => ansi16_index(named).map(fixed_to_rgb),
+    }
+}
+
+/// True-colour RGB value of any terminal color (`None` for the terminal default color): the
+/// eight basic ANSI colors take the value of the CSS color of the same name (`red` being
+/// `#ff0000` rather than the `#800000` of the xterm palette), the other colors are the same as
+/// with [`color_to_rgb`].
+///
+/// # Examples
+/// ```
+/// use nu_ansi_term::Color;
+/// use titular::color_utils::color_to_true_rgb;
+///
+/// assert_eq!(color_to_true_rgb(Color::Red), Some((255, 0, 0)));
+/// assert_eq!(color_to_true_rgb(Color::Fixed(1)), Some((128, 0, 0)));
+/// ```
+#[must_use]
+pub fn color_to_true_rgb(color: Color) -> Option<(u8, u8, u8)> {
+    let name = match color {
+        Color::Black => "black",
+        Color::Red => "red",
+        Color::Green => "green",
+        Color::Yellow => "yellow",
+        Color::Blue => "blue",
+        Color::Purple => "purple",
+        Color::Magenta => "magenta",
+        Color::Cyan => "cyan",
+        Color::White => "white",
+        other => return color_to_rgb(other),
+    };
+    css_color(name)
+}
+
+/// Perceptually weighted squared distance between two RGB colors ("redmean" approximation).
+fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> i64 {
+    let rmean = (i64::from(r1) + i64::from(r2)) / 2;
+    let dr = i64::from(r1) - i64::from(r2);
+    let dg = i64::from(g1) - i64::from(g2);
+    let db = i64::from(b1) - i64::from(b2);
+    (((512 + rmean) * dr * dr) >> 8) + 4 * dg * dg + (((767 - rmean) * db * db) >> 8)
+}
+
+/// Nearest entry of the 256-color palette, excluding the 16 system colors whose actual values
+/// depend on the terminal configuration.
+///
+/// # Examples
+/// ```
+/// use titular::color_utils::nearest_ansi256;
+///
+/// assert_eq!(nearest_ansi256((255, 135, 0)), 208);
+/// assert_eq!(nearest_ansi256((128, 128, 128)), 244);
+/// ```
+#[must_use]
+pub fn nearest_ansi256(rgb: (u8, u8, u8)) -> u8 {
+    let level = |v: u8| {
+        CUBE_LEVELS
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, l)| (i16::from(**l) - i16::from(v)).abs())
+            .map_or(0, |(i, _)| i)
+    };
+    let (r, g, b) = (level(rgb.0), level(rgb.1), level(rgb.2));
+    let cube = u8::try_from(16 + 36 * r + 6 * g + b).unwrap_or(16);
+
+    let avg = (u16::from(rgb.0) + u16::from(rgb.1) + u16::from(rgb.2)) / 3;
+    let gray_idx = u8::try_from(avg.saturating_sub(3) / 10)
+        .unwrap_or(23)
+        .min(23);
+    let gray = 232 + gray_idx;
+
+    if distance(rgb, fixed_to_rgb(gray)) < distance(rgb, fixed_to_rgb(cube)) {
+        gray
+    } else {
+        cube
+    }
+}
+
+/// Nearest entry (0-15) of the 16-color palette.
+///
+/// # Examples
+/// ```
+/// use titular::color_utils::nearest_ansi16;
+///
+/// assert_eq!(nearest_ansi16((250, 10, 10)), 9);
+/// assert_eq!(nearest_ansi16((20, 20, 20)), 0);
+/// ```
+#[must_use]
+pub fn nearest_ansi16(rgb: (u8, u8, u8)) -> u8 {
+    (0u8..16)
+        .min_by_key(|i| distance(rgb, ANSI16_RGB[usize::from(*i)]))
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hsl_to_rgb() {
+        assert_eq!(hsl_to_rgb(0.0, 0.0, 0.0), (0, 0, 0));
+        assert_eq!(hsl_to_rgb(0.0, 0.0, 1.0), (255, 255, 255));
+        assert_eq!(hsl_to_rgb(240.0, 1.0, 0.5), (0, 0, 255));
+        assert_eq!(hsl_to_rgb(-120.0, 1.0, 0.5), (0, 0, 255));
+        assert_eq!(hsl_to_rgb(270.0, 0.5, 0.4), (102, 51, 153));
+    }
+
+    #[test]
+    fn test_rgb_to_hsl_roundtrip() {
+        for rgb in [(102, 51, 153), (0, 128, 0), (70, 130, 180), (250, 128, 114)] {
+            let (h, s, l) = rgb_to_hsl(rgb);
+            assert_eq!(hsl_to_rgb(h, s, l), rgb);
+        }
+    }
+
+    #[test]
+    fn test_color_to_true_rgb() {
+        assert_eq!(color_to_true_rgb(Color::Blue), Some((0, 0, 255)));
+        assert_eq!(color_to_true_rgb(Color::Green), Some((0, 128, 0)));
+        assert_eq!(color_to_true_rgb(Color::LightRed), Some((255, 0, 0)));
+        assert_eq!(color_to_true_rgb(Color::Rgb(1, 2, 3)), Some((1, 2, 3)));
+        assert_eq!(color_to_true_rgb(Color::Default), None);
+    }
+
+    #[test]
+    fn test_relative_luminance() {
+        assert_eq!(relative_luminance((0, 0, 0)), 0.0);
+        assert!((relative_luminance((255, 255, 255)) - 1.0).abs() < 1e-9);
+        assert!(relative_luminance((0, 255, 0)) > relative_luminance((255, 0, 0)));
+    }
+
+    #[test]
+    fn test_fixed_palette_roundtrip() {
+        for n in 16..=255u8 {
+            assert_eq!(nearest_ansi256(fixed_to_rgb(n)), n, "index {n}");
+        }
+        for n in 0..16u8 {
+            assert_eq!(nearest_ansi16(fixed_to_rgb(n)), n, "index {n}");
+        }
+    }
+
+    #[test]
+    fn test_color_to_rgb() {
+        assert_eq!(color_to_rgb(Color::Rgb(1, 2, 3)), Some((1, 2, 3)));
+        assert_eq!(color_to_rgb(Color::LightRed), Some((255, 0, 0)));
+        assert_eq!(color_to_rgb(Color::Fixed(232)), Some((8, 8, 8)));
+        assert_eq!(color_to_rgb(Color::Default), None);
+    }
+}
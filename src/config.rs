@@ -14,6 +14,7 @@ use crate::constants::template::{DEFAULT_TEMPLATE_NAME, DEFAULT_TIME_FORMAT};
 #[cfg(feature = "fetcher")]
 use crate::constants::template::DEFAULT_REMOTE_REPO;
 use crate::error::{Error, Result};
+use crate::policy::{DEFAULT_ENV_ALLOWLIST, DEFAULT_EXEC_TIMEOUT_MAX_MS};
 use crate::utils::safe_time_format;
 
 #[derive(Deserialize, Debug, Serialize)]
@@ -27,12 +28,24 @@ pub enum Display {
     Fancy,
 }
 
+/// Terminal background appearance, used to select `[vars.dark]` / `[vars.light]` in templates.
+#[derive(Deserialize, Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Appearance {
+    Dark,
+    Light,
+}
+
 #[derive(Deserialize, Debug, Default)]
 pub struct MainConfig {
     pub defaults: Defaults,
     #[serde(default)]
     pub vars: BTreeMap<String, String>,
     pub templates: Templates,
+    #[serde(default)]
+    pub icons: Icons,
+    #[serde(default)]
+    pub policy: Policy,
 }
 
 #[derive(Deserialize, Debug, Serialize)]
@@ -48,6 +61,15 @@ pub struct Defaults {
     pub time_format: String,
     pub time_pattern: String,
     pub display: Option<Display>,
+    pub appearance: Option<Appearance>,
+    pub strict_colors: bool,
+    pub color_mode: Option<String>,
+    pub ambiguous_width: Option<u8>,
+    pub pattern_mode: Option<String>,
+    /// Deprecated alias of `enabled` in the `[icons]` section, used when it is left to `auto`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub glyphs: Option<String>,
+    pub segment_separator: Option<String>,
     #[cfg(feature = "display")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub display_theme: Option<String>,
@@ -69,8 +91,58 @@ pub struct Templates {
 pub struct TemplateConfig {
     pub details: Details,
     #[serde(default)]
-    pub vars: BTreeMap<String, String>,
+    pub vars: TemplateVars,
     pub pattern: Pattern,
+    #[serde(default)]
+    pub policy: TemplatePolicyConfig,
+}
+
+/// The `[policy]` section of a template: the commands it runs with `exec()`, once trusted with
+/// `templates trust`.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct TemplatePolicyConfig {
+    pub exec_allowlist: Vec<String>,
+}
+
+/// Template variables: the flat `[vars]` table plus optional `[vars.dark]` / `[vars.light]`
+/// tables layered on top of it for the active [`Appearance`]. A plain string `dark` or `light`
+/// is an ordinary variable.
+#[derive(Deserialize, Debug, Default)]
+#[serde(try_from = "BTreeMap<String, VarValue>")]
+pub struct TemplateVars {
+    pub dark: BTreeMap<String, String>,
+    pub light: BTreeMap<String, String>,
+    pub common: BTreeMap<String, String>,
+}
+
+/// A value of the `[vars]` table: a variable, or the table of an appearance.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum VarValue {
+    Var(String),
+    Table(BTreeMap<String, String>),
+}
+
+impl TryFrom<BTreeMap<String, VarValue>> for TemplateVars {
+    type Error = String;
+
+    fn try_from(values: BTreeMap<String, VarValue>) -> std::result::Result<Self, String> {
+        let mut vars = TemplateVars::default();
+        for (key, value) in values {
+            match (key.as_str(), value) {
+                (_, VarValue::Var(value)) => {
+                    vars.common.insert(key, value);
+                }
+                ("dark", VarValue::Table(table)) => vars.dark = table,
+                ("light", VarValue::Table(table)) => vars.light = table,
+                (_, VarValue::Table(_)) => return Err(format!(
+                    "invalid table [vars.{key}], expected a string or [vars.dark] / [vars.light]"
+                )),
+            }
+        }
+        Ok(vars)
+    }
 }
 
 #[derive(Deserialize, Debug, Default)]
@@ -103,12 +175,58 @@ impl Default for Defaults {
             time_format: DEFAULT_TIME_FORMAT.to_string(),
             time_pattern: "${space}%{time:fg[tc]}".to_string(),
             display: Some(Display::Raw),
+            appearance: None,
+            strict_colors: false,
+            color_mode: None,
+            ambiguous_width: None,
+            pattern_mode: None,
+            glyphs: None,
+            segment_separator: None,
             #[cfg(feature = "display")]
             display_theme: None,
         }
     }
 }
 
+/// The `[icons]` section: whether Nerd Font glyphs are drawn (`auto`, `true` or `false`).
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct Icons {
+    pub enabled: String,
+}
+
+impl Default for Icons {
+    fn default() -> Icons {
+        Icons {
+            enabled: "auto".to_string(),
+        }
+    }
+}
+
+/// The `[policy]` section: the environment variables untrusted (downloaded) templates read, the
+/// commands trusted templates run with `exec()` (none by default, any argument being allowed)
+/// and the longest time these commands are given.
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct Policy {
+    pub env_allowlist: Vec<String>,
+    pub exec_allowlist: Vec<String>,
+    pub exec_timeout_max_ms: u64,
+}
+
+impl Default for Policy {
+    fn default() -> Policy {
+        Policy {
+            env_allowlist: DEFAULT_ENV_ALLOWLIST
+                .iter()
+                .map(|s| (*s).to_string())
+                .collect(),
+            exec_allowlist: Vec::new(),
+            exec_timeout_max_ms: DEFAULT_EXEC_TIMEOUT_MAX_MS,
+        }
+    }
+}
+
 impl Default for Templates {
     fn default() -> Templates {
         Templates {
@@ -138,6 +256,12 @@ impl MainConfig {
         self.defaults.to_map().iter().for_each(|(k, v)| {
             self.vars.insert(format!("defaults.{k}"), v.clone());
         });
+        let icons_enabled = match &self.defaults.glyphs {
+            Some(glyphs) if self.icons.enabled.trim().eq_ignore_ascii_case("auto") => glyphs,
+            _ => &self.icons.enabled,
+        };
+        self.vars
+            .insert("icons.enabled".to_string(), icons_enabled.clone());
         // Add misc vars
         self.vars.insert(
             "time".to_owned(),
@@ -169,6 +293,35 @@ impl FromStr for Display {
     }
 }
 
+impl FromStr for Appearance {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "dark" => Ok(Appearance::Dark),
+            "light" => Ok(Appearance::Light),
+            _ => Err(Error::ConfigError(format!(
+                "Invalid appearance: {s} (expected light or dark)"
+            ))),
+        }
+    }
+}
+
+impl TemplateVars {
+    /// Returns the `[vars]` table with the table for the given appearance (if any) layered on top.
+    #[must_use]
+    pub fn resolve(&self, appearance: Option<Appearance>) -> BTreeMap<String, String> {
+        let mut vars = self.common.clone();
+        let overrides = match appearance {
+            Some(Appearance::Dark) => &self.dark,
+            Some(Appearance::Light) => &self.light,
+            None => return vars,
+        };
+        vars.extend(overrides.iter().map(|(k, v)| (k.clone(), v.clone())));
+        vars
+    }
+}
+
 /// Reads the entire contents of a configuration file into a string.
 ///
 /// # Errors
@@ -206,3 +359,97 @@ impl Defaults {
         map
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEMPLATE: &str = r#"
+[details]
+name = "test"
+
+[vars]
+c = "NAME(Blue)"
+f = "*"
+
+[vars.dark]
+c = "RGB(200, 200, 255)"
+
+[vars.light]
+c = "RGB(0, 0, 120)"
+
+[pattern]
+data = "{{ m }}"
+"#;
+
+    #[test]
+    fn test_template_vars_layering() {
+        let config: TemplateConfig = toml::from_str(TEMPLATE).unwrap();
+        assert_eq!(config.vars.common.get("c").unwrap(), "NAME(Blue)");
+        assert!(!config.vars.common.contains_key("dark"));
+
+        let dark = config.vars.resolve(Some(Appearance::Dark));
+        assert_eq!(dark.get("c").unwrap(), "RGB(200, 200, 255)");
+        assert_eq!(dark.get("f").unwrap(), "*");
+
+        let light = config.vars.resolve(Some(Appearance::Light));
+        assert_eq!(light.get("c").unwrap(), "RGB(0, 0, 120)");
+
+        let none = config.vars.resolve(None);
+        assert_eq!(none.get("c").unwrap(), "NAME(Blue)");
+    }
+
+    #[test]
+    fn test_template_vars_named_like_appearances() {
+        let config: TemplateConfig = toml::from_str(
+            "[details]\nname = \"x\"\n[vars]\ndark = \"#000000\"\nlight = \"#ffffff\"\n\
+             [pattern]\ndata = \"\"",
+        )
+        .unwrap();
+        let vars = config.vars.resolve(Some(Appearance::Dark));
+        assert_eq!(vars.get("dark").unwrap(), "#000000");
+        assert_eq!(vars.get("light").unwrap(), "#ffffff");
+
+        let invalid = toml::from_str::<TemplateConfig>(
+            "[details]\nname = \"x\"\n[vars.dim]\nc = \"red\"\n[pattern]\ndata = \"\"",
+        );
+        assert!(invalid.is_err());
+    }
+
+    #[test]
+    fn test_template_vars_flat_only() {
+        let config: TemplateConfig =
+            toml::from_str("[details]\nname = \"x\"\n[vars]\nc = \"red\"\n[pattern]\ndata = \"\"")
+                .unwrap();
+        assert_eq!(
+            config
+                .vars
+                .resolve(Some(Appearance::Dark))
+                .get("c")
+                .unwrap(),
+            "red"
+        );
+    }
+
+    #[test]
+    fn test_glyphs_alias() {
+        let mut config: MainConfig =
+            toml::from_str("[defaults]\nglyphs = \"false\"\n[templates]\n").unwrap();
+        config.init();
+        assert_eq!(config.vars.get("icons.enabled").unwrap(), "false");
+
+        let mut config: MainConfig = toml::from_str(
+            "[defaults]\nglyphs = \"false\"\n[templates]\n[icons]\nenabled = \"true\"\n",
+        )
+        .unwrap();
+        config.init();
+        assert_eq!(config.vars.get("icons.enabled").unwrap(), "true");
+    }
+
+    #[test]
+    fn test_appearance_from_str() {
+        assert_eq!("Dark".parse::<Appearance>().unwrap(), Appearance::Dark);
+        assert_eq!(" light ".parse::<Appearance>().unwrap(), Appearance::Light);
+        assert!("dim".parse::<Appearance>().is_err());
+    }
+}
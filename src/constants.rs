@@ -8,6 +8,88 @@ pub mod padding {
     pub const START: char = '\u{F0000}';
     /// End marker for a padding group
     pub const END: char = '\u{F0001}';
+    /// Separator between the encoded pattern mode (see [`encode`](super::encode)) and the
+    /// content of the group, only present when the filler has a pattern mode
+    pub const SEP: char = '\u{F0005}';
+}
+
+/// Unicode Private Use Area markers delimiting a block of several lines (e.g. a banner), kept
+/// on a single line until the text processor lays every line of the block out on its own line.
+pub mod block {
+    /// Start marker for a block
+    pub const START: char = '\u{F0006}';
+    /// Separator between two lines of the block
+    pub const ROW: char = '\u{F0007}';
+    /// End marker for a block
+    pub const END: char = '\u{F0008}';
+}
+
+/// Unicode Private Use Area markers delimiting a frame. The encoded frame spec (see
+/// [`encode`]), the SGR sequence of the border and the title come first, each followed by `SEP`,
+/// then the framed content, which may span several lines and hold nested frames.
+pub mod frame {
+    /// Start marker for a frame
+    pub const START: char = '\u{F0009}';
+    /// Separator between the settings of the frame and its content
+    pub const SEP: char = '\u{F000A}';
+    /// End marker for a frame
+    pub const END: char = '\u{F000B}';
+}
+
+/// Unicode Private Use Area markers delimiting a segment. The encoded segment spec (see
+/// [`encode`]) comes first, followed by `SEP` and the content, until the segment processor
+/// draws the segment and its separator.
+pub mod segment {
+    /// Start marker for a segment
+    pub const START: char = '\u{F000C}';
+    /// Separator between the encoded spec and the content of the segment
+    pub const SEP: char = '\u{F000D}';
+    /// End marker for a segment
+    pub const END: char = '\u{F000E}';
+}
+
+/// First code point used to encode the bytes of the specs stored in the text
+const DATA_BASE: u32 = 0xF0100;
+
+/// Encodes an ASCII spec into private use characters, so that it stays zero-width until the
+/// transform it is meant for consumes it.
+#[must_use]
+pub fn encode(spec: &str) -> String {
+    spec.bytes()
+        .filter_map(|b| char::from_u32(DATA_BASE + u32::from(b)))
+        .collect()
+}
+
+/// Decodes a spec produced by [`encode`], ignoring any foreign character.
+#[must_use]
+pub fn decode(data: &str) -> String {
+    data.chars()
+        .filter_map(|c| u32::from(c).checked_sub(DATA_BASE))
+        .filter_map(|b| u8::try_from(b).ok())
+        .map(char::from)
+        .collect()
+}
+
+/// Unicode Private Use Area markers delimiting a gradient group. The gradient spec is stored
+/// between `START` and `SEP` with every byte mapped to a private use character (see
+/// [`encode`](gradient::encode)), so that the whole group stays zero-width until the gradient
+/// processor colors its content.
+pub mod gradient {
+    pub use super::{decode, encode};
+
+    /// Start marker for a gradient group
+    pub const START: char = '\u{F0002}';
+    /// Separator between the encoded spec and the content of the group
+    pub const SEP: char = '\u{F0003}';
+    /// End marker for a gradient group
+    pub const END: char = '\u{F0004}';
+}
+
+/// Whether the character is one of titular's internal markers (padding, gradients, ...), all of
+/// them living in the `U+F0000..=U+F01FF` private use range and having no visual width.
+#[must_use]
+pub fn is_marker(c: char) -> bool {
+    ('\u{F0000}'..='\u{F01FF}').contains(&c)
 }
 
 pub mod template {
@@ -30,7 +112,7 @@ pub mod template {
                                 c2=\"$message_color\"\n\
                                 c3=\"$main_color\"\n\n\
                                 [pattern]\n\
-                                data = \"{{ fb | color(name=c) | pad }}{{ m | color(name=c2) }}{{ fe | color(name=c3) | pad }}\"\n";
+                                data = \"{{ fb | color(name=c) | pad }}{{ m | link | color(name=c2) }}{{ fe | color(name=c3) | pad }}\"\n";
 
     /// Default template file extension
     pub const DEFAULT_TEMPLATE_EXT: &str = ".tl";
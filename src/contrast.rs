@@ -0,0 +1,321 @@
+//! WCAG contrast checks of the colors used by template patterns.
+//!
+//! The foreground / background pairs are taken from the `color(...)` and `style(...)` filters
+//! of each `{{ ... }}` expression of a pattern, and resolved against a context holding the same
+//! variables as a real render (CLI values, config vars, theme palette and template vars). When
+//! an expression sets no background, the `theme_background` of the palette is used instead.
+
+use serde::Serialize;
+
+use crate::color_manager::ColorManager;
+use crate::color_utils::{adjust_lightness, color_to_rgb, contrast_ratio};
+use crate::context::Context;
+use crate::string_utils::split_top_level;
+
+/// Minimum contrast ratio required by WCAG 2 level AA for normal text.
+pub const WCAG_AA: f64 = 4.5;
+
+/// A foreground / background pair found in a pattern, as written in the filter arguments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColorUsage {
+    /// The `{{ ... }}` expression the colors were found in
+    pub expression: String,
+    pub fg: Option<ColorArg>,
+    pub bg: Option<ColorArg>,
+}
+
+/// A color argument of a filter : either a quoted literal or a variable holding the color.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ColorArg {
+    Literal(String),
+    Variable(String),
+}
+
+impl ColorArg {
+    fn parse(value: &str) -> Self {
+        let value = value.trim();
+        let unquoted = value
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')));
+        match unquoted {
+            Some(literal) => ColorArg::Literal(literal.to_string()),
+            None => ColorArg::Variable(value.to_string()),
+        }
+    }
+
+    /// The color value the argument evaluates to in the given context, `None` for variables
+    /// unknown to the context (e.g. set inside the template).
+    fn value<'a>(&'a self, ctx: &'a Context) -> Option<&'a str> {
+        match self {
+            ColorArg::Literal(literal) => Some(literal),
+            ColorArg::Variable(var) => ctx.get(var),
+        }
+    }
+
+    fn label(&self) -> &str {
+        match self {
+            ColorArg::Literal(s) | ColorArg::Variable(s) => s,
+        }
+    }
+}
+
+/// The contrast of a resolved foreground / background pair.
+#[derive(Debug, Clone, Serialize)]
+pub struct ContrastFinding {
+    pub expression: String,
+    pub fg: String,
+    pub bg: String,
+    pub fg_rgb: (u8, u8, u8),
+    pub bg_rgb: (u8, u8, u8),
+    pub ratio: f64,
+    pub passes: bool,
+}
+
+/// Extracts the foreground / background pairs set by the `color` and `style` filters of every
+/// `{{ ... }}` expression of the pattern. Within an expression, the first filter setting a
+/// layer wins, as its escape codes are the innermost ones.
+///
+/// # Examples
+/// ```
+/// use titular::contrast::{color_usages, ColorArg};
+///
+/// let usages = color_usages("{{ m | color(name=c2) | style(bg_color='RGB(0,0,0)') }}");
+/// assert_eq!(usages[0].fg, Some(ColorArg::Variable("c2".to_string())));
+/// assert_eq!(usages[0].bg, Some(ColorArg::Literal("RGB(0,0,0)".to_string())));
+/// ```
+#[must_use]
+pub fn color_usages(pattern: &str) -> Vec<ColorUsage> {
+    let mut usages = Vec::new();
+    let mut rest = pattern;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start..].find("}}") else {
+            break;
+        };
+        let expression = &rest[start + 2..start + len];
+        rest = &rest[start + len + 2..];
+
+        let mut usage = ColorUsage {
+            expression: expression.trim().to_string(),
+            fg: None,
+            bg: None,
+        };
+        for (filter, args) in filter_calls(expression) {
+            match filter {
+                "color" => {
+                    let Some(name) = args.iter().find(|(k, _)| *k == "name") else {
+                        continue;
+                    };
+                    let is_bg = args
+                        .iter()
+                        .any(|(k, v)| *k == "is_bg" && v.trim() == "true");
+                    let slot = if is_bg { &mut usage.bg } else { &mut usage.fg };
+                    slot.get_or_insert_with(|| ColorArg::parse(name.1));
+                }
+                "style" => {
+                    for (key, value) in args {
+                        let slot = match key {
+                            "fg_color" => &mut usage.fg,
+                            "bg_color" => &mut usage.bg,
+                            _ => continue,
+                        };
+                        slot.get_or_insert_with(|| ColorArg::parse(value));
+                    }
+                }
+                _ => {}
+            }
+        }
+        if usage.fg.is_some() || usage.bg.is_some() {
+            usages.push(usage);
+        }
+    }
+    usages
+}
+
+/// Returns the filters called with arguments in a filter chain, along with their `key=value`
+/// arguments. Quotes and nested parentheses (e.g. `'RGB(1,2,3)'`) are honored.
+fn filter_calls(expression: &str) -> Vec<(&str, Vec<(&str, &str)>)> {
+    let mut calls = Vec::new();
+    for segment in split_top_level(expression, |c| c == '|')
+        .into_iter()
+        .skip(1)
+    {
+        let Some((name, args)) = segment.split_once('(') else {
+            continue;
+        };
+        let args = args.trim_end().strip_suffix(')').unwrap_or(args);
+        let args = split_top_level(args, |c| c == ',')
+            .into_iter()
+            .filter_map(|arg| arg.split_once('='))
+            .map(|(k, v)| (k.trim(), v.trim()))
+            .collect();
+        calls.push((name.trim(), args));
+    }
+    calls
+}
+
+/// Resolves the color usages of the pattern and computes their contrast ratio.
+///
+/// Pairs whose colors cannot be resolved (unknown variables, empty colors or no background at
+/// all) are skipped, see [`missing_background`] to tell the latter apart.
+///
+/// # Arguments
+/// * `ctx` - The context holding the variables of the render, including the theme palette
+/// * `pattern` - The template pattern
+/// * `min_ratio` - The minimum contrast ratio for a pair to pass
+#[must_use]
+pub fn check_pattern(ctx: &Context, pattern: &str, min_ratio: f64) -> Vec<ContrastFinding> {
+    let theme_background = ColorArg::Variable("theme_background".to_string());
+    color_usages(pattern)
+        .into_iter()
+        .filter_map(|usage| {
+            let fg = usage.fg.as_ref()?;
+            let bg = usage.bg.as_ref().unwrap_or(&theme_background);
+            let fg_rgb = resolve_rgb(ctx, fg)?;
+            let bg_rgb = resolve_rgb(ctx, bg)?;
+            let ratio = contrast_ratio(fg_rgb, bg_rgb);
+            Some(ContrastFinding {
+                expression: usage.expression,
+                fg: fg.label().to_string(),
+                bg: bg.label().to_string(),
+                fg_rgb,
+                bg_rgb,
+                ratio,
+                passes: ratio >= min_ratio,
+            })
+        })
+        .collect()
+}
+
+/// Counts the foreground colors of the pattern that set no background while the context has no
+/// `theme_background` to check them against (no theme selected, or no `display` feature).
+#[must_use]
+pub fn missing_background(ctx: &Context, pattern: &str) -> usize {
+    let theme_background = ColorArg::Variable("theme_background".to_string());
+    if resolve_rgb(ctx, &theme_background).is_some() {
+        return 0;
+    }
+    color_usages(pattern)
+        .iter()
+        .filter(|usage| usage.fg.is_some() && usage.bg.is_none())
+        .count()
+}
+
+fn resolve_rgb(ctx: &Context, arg: &ColorArg) -> Option<(u8, u8, u8)> {
+    let value = arg.value(ctx)?;
+    ColorManager::resolve(ctx, value)
+        .ok()
+        .flatten()
+        .and_then(color_to_rgb)
+}
+
+/// Adjusts the lightness of the foreground, darker or lighter, until the contrast ratio reaches
+/// `min_ratio`. Both directions are tried: the color is the one meeting the ratio with the
+/// smallest change, or black or white (whichever contrasts most) if no lightness does. The color
+/// is returned unchanged if it already meets the ratio.
+///
+/// # Examples
+/// ```
+/// use titular::color_utils::contrast_ratio;
+/// use titular::contrast::nudge_to_contrast;
+///
+/// let bg = (255, 255, 255);
+/// let fg = nudge_to_contrast((255, 200, 0), bg, 4.5);
+/// assert!(contrast_ratio(fg, bg) >= 4.5);
+/// assert_eq!(nudge_to_contrast((0, 0, 0), bg, 4.5), (0, 0, 0));
+/// ```
+#[must_use]
+pub fn nudge_to_contrast(fg: (u8, u8, u8), bg: (u8, u8, u8), min_ratio: f64) -> (u8, u8, u8) {
+    if contrast_ratio(fg, bg) >= min_ratio {
+        return fg;
+    }
+    // The first step of each direction meeting the ratio
+    let nudge = |step: f64| {
+        (1..=100)
+            .map(|i| (i, adjust_lightness(fg, step * f64::from(i))))
+            .find(|(_, c)| contrast_ratio(*c, bg) >= min_ratio)
+    };
+    match (nudge(-0.01), nudge(0.01)) {
+        (Some((darker_steps, darker)), Some((lighter_steps, lighter))) => {
+            if darker_steps <= lighter_steps {
+                darker
+            } else {
+                lighter
+            }
+        }
+        (Some((_, color)), None) | (None, Some((_, color))) => color,
+        (None, None) => [(0, 0, 0), (255, 255, 255)]
+            .into_iter()
+            .max_by(|a, b| contrast_ratio(*a, bg).total_cmp(&contrast_ratio(*b, bg)))
+            .unwrap_or(fg),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color_utils::relative_luminance;
+
+    #[test]
+    fn test_color_usages() {
+        let pattern =
+            "{{ fb | color(name=c) | pad }}{{ m | style(fg_color=\"a, b\", bg_color=x) }}\
+                       {{ m }}{{ t | color(name='red', is_bg=true) }}";
+        let usages = color_usages(pattern);
+        assert_eq!(usages.len(), 3);
+        assert_eq!(usages[0].fg, Some(ColorArg::Variable("c".to_string())));
+        assert_eq!(usages[0].bg, None);
+        assert_eq!(usages[1].fg, Some(ColorArg::Literal("a, b".to_string())));
+        assert_eq!(usages[1].bg, Some(ColorArg::Variable("x".to_string())));
+        assert_eq!(usages[2].fg, None);
+        assert_eq!(usages[2].bg, Some(ColorArg::Literal("red".to_string())));
+    }
+
+    #[test]
+    fn test_check_pattern_uses_theme_background() {
+        let mut ctx = Context::new();
+        ctx.insert("theme_background", "RGB(255,255,255)");
+        ctx.insert("c", "RGB(250,250,210)");
+        ctx.insert("c2", "RGB(0,0,0)");
+
+        let findings = check_pattern(
+            &ctx,
+            "{{ m | color(name=c) }}{{ m | color(name=c2) }}{{ m | color(name=unknown) }}",
+            WCAG_AA,
+        );
+        assert_eq!(findings.len(), 2);
+        assert!(!findings[0].passes);
+        assert_eq!(findings[0].bg, "theme_background");
+        assert!(findings[1].passes);
+        assert_eq!(findings[1].ratio, 21.0);
+    }
+
+    #[test]
+    fn test_missing_background() {
+        let pattern = "{{ m | color(name=c) }}{{ m | style(fg_color=c, bg_color=\"black\") }}";
+        let mut ctx = Context::new();
+        ctx.insert("c", "RGB(0,0,0)");
+        assert!(check_pattern(&ctx, pattern, WCAG_AA).len() == 1);
+        assert_eq!(missing_background(&ctx, pattern), 1);
+
+        ctx.insert("theme_background", "RGB(255,255,255)");
+        assert_eq!(missing_background(&ctx, pattern), 0);
+    }
+
+    #[test]
+    fn test_nudge_to_contrast_on_mid_gray() {
+        // Lighter only reaches 3.95 against this gray, darker reaches the ratio
+        let bg = (128, 128, 128);
+        let fg = nudge_to_contrast((140, 140, 140), bg, WCAG_AA);
+        assert!(contrast_ratio(fg, bg) >= WCAG_AA);
+        assert!(relative_luminance(fg) < relative_luminance(bg));
+    }
+
+    #[test]
+    fn test_nudge_to_contrast_on_dark_background() {
+        let bg = (30, 30, 46);
+        let fg = nudge_to_contrast((88, 91, 112), bg, WCAG_AA);
+        assert!(contrast_ratio(fg, bg) >= WCAG_AA);
+        assert!(relative_luminance(fg) > relative_luminance((88, 91, 112)));
+    }
+}
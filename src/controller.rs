@@ -8,9 +8,12 @@ use crate::{
     config::MainConfig,
     constants::template::DEFAULT_TEMPLATE_EXT,
     context::Context,
+    contrast::{check_pattern, missing_background, WCAG_AA},
     display,
     error::{Error, Result},
     formatter::TemplateFormatter,
+    policy,
+    reader::TemplateReader,
     writer::TemplateWriter,
 };
 
@@ -21,11 +24,26 @@ use serde_json::json;
 use crate::fetcher::TemplateFetcher;
 
 #[cfg(feature = "display")]
-use crate::theme::ThemeManager;
+use crate::{
+    ansi::visual_width,
+    color_manager::{ColorManager, StyleFormat, StyleScope, TextAttributes},
+    term::TERM_SIZE,
+    theme::ThemeManager,
+    theme_palette::palette_slots_from_theme,
+    width::AmbiguousWidth,
+};
 
 use glob::glob;
 use nu_ansi_term::Color::{Green, Red, Yellow};
 
+/// Columns between the palette and the sample title of `themes show`.
+#[cfg(feature = "display")]
+const SAMPLE_GAP: usize = 4;
+
+/// Width, in percent of the terminal, below which the sample is printed under the palette.
+#[cfg(feature = "display")]
+const MIN_SAMPLE_WIDTH: usize = 25;
+
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 enum ListOutputFormat {
     Tree,
@@ -69,6 +87,8 @@ impl<'a> TemplatesController<'a> {
     /// - edit : opens or creates if not existing the given template in the default system editor (see "edit" crate for more information)
     /// - create : creates a new template from sratch with a default template pattern
     /// - remove : deletes the given template from the templates repository
+    /// - check : validates the given template (or all of them), optionally checking the contrast of its colors
+    /// - trust : lets a downloaded template read any environment variable (or revokes it)
     /// - add (only when feature "fetcher" is enabled) : downloads and installs a template from the default templates remote repository
     ///   or a custom URL
     ///
@@ -105,7 +125,8 @@ impl<'a> TemplatesController<'a> {
                     )?;
                     Ok(true)
                 }
-                "create" | "edit" | "remove" | "show" => {
+                "check" => self.check(context),
+                "create" | "edit" | "remove" | "show" | "trust" => {
                     let template_name = context
                         .get("template")
                         .ok_or_else(|| Error::CommandError("Missing template name".to_string()))?;
@@ -122,6 +143,8 @@ impl<'a> TemplatesController<'a> {
                         self.remove(template_name)
                     } else if cmd == "show" {
                         self.display(template_name, context)
+                    } else if cmd == "trust" {
+                        self.trust(template_name, !context.is_active("revoke"))
                     } else {
                         Err(Error::ArgsProcessingError(
                             "Invalid subcommand provided".to_string(),
@@ -145,6 +168,135 @@ impl<'a> TemplatesController<'a> {
         }
     }
 
+    /// Runs any of the themes subcommands (`display` feature). Currently supported :
+    /// - list : shows the embedded syntax highlighting themes
+    /// - show : prints the `theme_*` palette resolved from the given theme
+    ///
+    /// # Errors
+    /// Returns an error if the subcommand is missing or invalid, or the themes cannot be loaded.
+    #[cfg(feature = "display")]
+    pub fn run_themes_subcommand(&self, context: &Context) -> Result<bool> {
+        match context.get("subcommand") {
+            Some("list") => self.list_themes(ListOutputFormat::from_context(context)),
+            Some("show") => {
+                let name = context
+                    .get("name")
+                    .ok_or_else(|| Error::CommandError("Missing theme name".to_string()))?;
+                self.show_theme(name, context)
+            }
+            Some(_) => Err(Error::ArgsProcessingError(
+                "Invalid subcommand provided".to_string(),
+            )),
+            None => Err(Error::ArgsProcessingError(
+                "Command not found in context".to_string(),
+            )),
+        }
+    }
+
+    /// Prints every palette slot of the given theme with a color swatch, its `RGB(...)` value and
+    /// the scope it was resolved from, next to a sample title rendered with that theme (below it
+    /// when the terminal is too narrow).
+    ///
+    /// With `-o json` the palette is printed as JSON instead (no sample).
+    ///
+    /// # Errors
+    /// Returns an error if themes cannot be loaded or the sample title cannot be rendered.
+    #[cfg(feature = "display")]
+    fn show_theme(&self, name: &str, context: &Context) -> Result<bool> {
+        let mgr = ThemeManager::init()?;
+        let Some(theme) = mgr.resolve_theme(name) else {
+            println!("{}", Yellow.paint(format!("Theme \"{name}\" not found")));
+            let hints = mgr.suggest_theme_names(name, 3);
+            if !hints.is_empty() {
+                println!(
+                    "{}",
+                    Yellow.paint(format!("Did you mean: {}?", hints.join(", ")))
+                );
+            }
+            return Ok(false);
+        };
+        let slots = palette_slots_from_theme(theme);
+
+        if ListOutputFormat::from_context(context) == ListOutputFormat::Json {
+            println!("{}", json!({ "theme": name, "palette": slots }));
+            return Ok(true);
+        }
+
+        let rows: Vec<String> = slots
+            .iter()
+            .map(|slot| {
+                let swatch = ColorManager::format(
+                    &Context::new(),
+                    "    ",
+                    StyleFormat {
+                        fg_color: None,
+                        bg_color: Some(slot.rgb.clone()),
+                        scope: StyleScope::BG,
+                        attributes: TextAttributes::default(),
+                        underline_color: None,
+                    },
+                );
+                format!(
+                    "  {swatch}  {:<18} {:<20} {}",
+                    format!("theme_{}", slot.slot.name()),
+                    slot.rgb,
+                    slot.scope
+                )
+            })
+            .collect();
+        let header = format!("Palette for theme \"{name}\"");
+
+        // The sample takes the columns left by the palette, as a percentage of the terminal
+        let ambiguous = AmbiguousWidth::from_context(context);
+        let left_width = rows
+            .iter()
+            .map(|row| visual_width(row, ambiguous))
+            .chain(std::iter::once(header.len()))
+            .max()
+            .unwrap_or_default()
+            + SAMPLE_GAP;
+        let term_width = TERM_SIZE.get_term_width();
+        let sample_width = term_width.saturating_sub(left_width) * 100 / term_width.max(1);
+
+        let mut sample_ctx = Context::new();
+        sample_ctx.append_from(context);
+        sample_ctx.insert("theme", name);
+        sample_ctx.insert("m", name);
+        let side_by_side = sample_width >= MIN_SAMPLE_WIDTH;
+        if side_by_side {
+            sample_ctx.insert("width", &sample_width.to_string());
+        }
+        let sample = TemplateFormatter::new(&self.input_dir, self.config)
+            .render(&sample_ctx, self.config.templates.default.as_str())?;
+
+        if !side_by_side {
+            println!("{}\n", Yellow.paint(header));
+            rows.iter().for_each(|row| println!("{row}"));
+            println!("\n{}", Yellow.paint("Sample"));
+            println!("{sample}");
+            return Ok(true);
+        }
+
+        let pad = |text: &str| " ".repeat(left_width.saturating_sub(visual_width(text, ambiguous)));
+        println!(
+            "{}{}{}\n",
+            Yellow.paint(&header),
+            pad(&header),
+            Yellow.paint("Sample")
+        );
+        let sample_lines: Vec<&str> = sample.lines().collect();
+        for i in 0..rows.len().max(sample_lines.len()) {
+            let row = rows.get(i).map_or("", String::as_str);
+            let line = sample_lines.get(i).copied().unwrap_or_default();
+            if line.is_empty() {
+                println!("{row}");
+            } else {
+                println!("{row}{}{line}", pad(row));
+            }
+        }
+        Ok(true)
+    }
+
     /// Lists installed templates (default), or embedded themes when `--themes` is set (`display`).
     ///
     /// Honors `-o txt|json` for plain lines or JSON (`templates`, `themes` arrays respectively).
@@ -215,25 +367,7 @@ impl<'a> TemplatesController<'a> {
     /// Returns an error if the glob pattern is invalid or a matched path cannot be read.
     fn list_templates(&self, fmt: ListOutputFormat) -> Result<bool> {
         if self.input_dir.exists() {
-            let pattern = format!(
-                "{}{}{}",
-                self.input_dir.to_string_lossy(),
-                "/**/*",
-                DEFAULT_TEMPLATE_EXT
-            );
-            let mut files = Vec::new();
-            for entry in
-                glob(&pattern).map_err(|e| Error::Msg(format!("Invalid glob pattern: {e}")))?
-            {
-                let path = entry.map_err(|e| Error::Msg(format!("Glob iteration error: {e}")))?;
-                let name = path.file_name().and_then(|n| n.to_str()).ok_or_else(|| {
-                    Error::Msg("Non-UTF-8 or missing file name in template path".to_string())
-                })?;
-                files.push(name.to_string());
-            }
-
-            files.sort_by_key(|a| a.to_ascii_lowercase());
-
+            let files = self.template_files()?;
             let root = self.input_dir.to_string_lossy().to_string();
             match fmt {
                 ListOutputFormat::Tree => {
@@ -263,6 +397,142 @@ impl<'a> TemplatesController<'a> {
         }
     }
 
+    /// Returns the file names of the installed templates, sorted case-insensitively.
+    ///
+    /// # Errors
+    /// Returns an error if the glob pattern is invalid or a matched path cannot be read.
+    fn template_files(&self) -> Result<Vec<String>> {
+        let pattern = format!(
+            "{}{}{}",
+            self.input_dir.to_string_lossy(),
+            "/**/*",
+            DEFAULT_TEMPLATE_EXT
+        );
+        let mut files = Vec::new();
+        for entry in glob(&pattern).map_err(|e| Error::Msg(format!("Invalid glob pattern: {e}")))? {
+            let path = entry.map_err(|e| Error::Msg(format!("Glob iteration error: {e}")))?;
+            let name = path.file_name().and_then(|n| n.to_str()).ok_or_else(|| {
+                Error::Msg("Non-UTF-8 or missing file name in template path".to_string())
+            })?;
+            files.push(name.to_string());
+        }
+
+        files.sort_by_key(|a| a.to_ascii_lowercase());
+        Ok(files)
+    }
+
+    /// Checks that the given template (or every installed template) can be loaded.
+    ///
+    /// With `--contrast`, the WCAG contrast ratio of the colors set by the `color` and `style`
+    /// filters is also computed (against the theme background when no background is set) and the
+    /// pairs below `--min-ratio` (4.5 by default) are reported. The contrast is checked against
+    /// the theme given by `--theme` (or the configured one), or against every embedded theme with
+    /// `--all-themes` (`display` feature). Colors without a background fail the check when there
+    /// is no theme background to check them against.
+    ///
+    /// # Returns
+    /// `Ok(false)` if any template cannot be loaded or has a poor contrast.
+    ///
+    /// # Errors
+    /// Returns an error if the minimum ratio is invalid or the templates cannot be listed.
+    pub fn check(&self, context: &Context) -> Result<bool> {
+        let min_ratio = match context.get("min-ratio") {
+            Some(ratio) => ratio.parse::<f64>().map_err(|_| {
+                Error::ArgsProcessingError(format!("Invalid minimum contrast ratio \"{ratio}\""))
+            })?,
+            None => WCAG_AA,
+        };
+        let names = match context.get("template").filter(|name| !name.is_empty()) {
+            Some(name) => vec![name.to_string()],
+            None => self
+                .template_files()?
+                .iter()
+                .map(|f| template_stem(f))
+                .collect(),
+        };
+        let themes = Self::themes_to_check(context)?;
+        let formatter = TemplateFormatter::new(&self.input_dir, self.config);
+
+        let mut passed = true;
+        for name in &names {
+            let template = match TemplateReader::read(&self.input_dir, name) {
+                Ok(template) => template,
+                Err(e) => {
+                    println!("{} {name}: {e}", Red.paint("✗"));
+                    passed = false;
+                    continue;
+                }
+            };
+            if !context.is_active("contrast") {
+                println!("{} {name}", Green.paint("✓"));
+                continue;
+            }
+
+            for theme in &themes {
+                let mut ctx = Context::new();
+                ctx.append_from(context);
+                if let Some(theme) = theme {
+                    ctx.insert("theme", theme);
+                }
+                formatter.append_render_vars(&mut ctx, &template);
+
+                let label = theme
+                    .as_ref()
+                    .map_or_else(|| name.clone(), |theme| format!("{name} [{theme}]"));
+                let findings = check_pattern(&ctx, &template.pattern.data, min_ratio);
+                let failures: Vec<_> = findings.iter().filter(|f| !f.passes).collect();
+                let unchecked = missing_background(&ctx, &template.pattern.data);
+                if unchecked == 0 && failures.is_empty() {
+                    println!(
+                        "{} {label}: {} color pair(s) checked",
+                        Green.paint("✓"),
+                        findings.len()
+                    );
+                    continue;
+                }
+
+                passed = false;
+                println!("{} {label}", Red.paint("✗"));
+                if unchecked > 0 {
+                    println!(
+                        "    no background found for {unchecked} color(s): select a theme with \
+                         --theme or set a background color"
+                    );
+                }
+                for f in failures {
+                    println!(
+                        "    {} {} on {} (RGB{:?} on RGB{:?}) in {{{{ {} }}}}",
+                        Yellow.paint(format!("{:.2}:1", f.ratio)),
+                        f.fg,
+                        f.bg,
+                        f.fg_rgb,
+                        f.bg_rgb,
+                        f.expression
+                    );
+                }
+            }
+        }
+        Ok(passed)
+    }
+
+    /// The themes the contrast is checked against : every embedded theme with `--all-themes`,
+    /// or only the selected one (`None` meaning the configured theme, if any).
+    ///
+    /// # Errors
+    /// Returns an error if the embedded themes cannot be loaded.
+    #[allow(clippy::unnecessary_wraps)] // Fallible only with the `display` feature
+    fn themes_to_check(context: &Context) -> Result<Vec<Option<String>>> {
+        #[cfg(feature = "display")]
+        if context.is_active("all-themes") {
+            return Ok(ThemeManager::init()?
+                .theme_names_sorted()
+                .into_iter()
+                .map(Some)
+                .collect());
+        }
+        Ok(vec![context.get("theme").map(str::to_string)])
+    }
+
     /// Creates a new template from stratch using the default template contents.
     ///
     /// # Arguments
@@ -330,7 +600,10 @@ impl<'a> TemplatesController<'a> {
 
         if template.exists() {
             match std::fs::remove_file(template) {
-                Ok(()) => println!("Template \"{}\" removed", Green.paint(name)),
+                Ok(()) => {
+                    policy::forget(&self.input_dir, name)?;
+                    println!("Template \"{}\" removed", Green.paint(name));
+                }
                 Err(e) => {
                     return Err(Error::TemplateReadError {
                         file: path,
@@ -345,6 +618,39 @@ impl<'a> TemplatesController<'a> {
         Ok(true)
     }
 
+    /// Marks the given template as trusted, or untrusted when `trusted` is false. Untrusted
+    /// templates (any template not created by titular nor trusted this way) only read the environment variables of the allowlist
+    /// of the `[policy]` section of the configuration, and the `[policy]` section of a template
+    /// only counts once it is trusted this way.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the template to trust.
+    /// * `trusted` - Whether the template is trusted.
+    ///
+    /// # Returns
+    /// Returns `Ok(false)` if the template does not exist.
+    ///
+    /// # Errors
+    /// Returns an error if the lists of trusted and untrusted templates cannot be written.
+    pub fn trust(&self, name: &str, trusted: bool) -> Result<bool> {
+        let template = self.input_dir.join(TemplateWriter::get_template_file(name));
+        if !template.exists() {
+            println!("{}", Yellow.paint(format!("Template \"{name}\" not found")));
+            return Ok(false);
+        }
+
+        let state = if trusted { "trusted" } else { "untrusted" };
+        if policy::set_trusted(&self.input_dir, name, trusted)? {
+            println!("Template \"{}\" {state}", Green.paint(name));
+        } else {
+            println!(
+                "{}",
+                Yellow.paint(format!("Template \"{name}\" is already {state}"))
+            );
+        }
+        Ok(true)
+    }
+
     /// Displays the contents of the given template.
     ///
     /// # Arguments
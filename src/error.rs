@@ -65,6 +65,8 @@ pub enum Error {
     TemplateRenderError(String),
     #[error("error writing to template. Cause : {0}")]
     TemplateWriteError(String),
+    #[error("unable to resolve color \"{0}\"")]
+    UnresolvedColor(String),
     #[error("{0}")]
     Msg(String),
 }
@@ -9,6 +9,7 @@ use std::{
 use crate::{
     constants::template::DEFAULT_TEMPLATE_EXT,
     dispatcher::{Dispatcher, URLDispatcher},
+    policy,
     reader::TemplateReader,
     utils::{self, create_backup, remove_backup, restore_backup},
 };
@@ -219,6 +220,9 @@ impl TemplateFetcher {
             target_info.total_size
         };
 
+        // Downloaded templates are untrusted until `templates trust` is run, even if the
+        // download fails once the file is created
+        policy::set_trusted(path, &target_info.filename, false)?;
         let mut file = std::fs::File::create(&target_info.path)?;
         let body = response.body_mut();
 
@@ -260,10 +264,16 @@ impl TemplateFetcher {
 
             if target_info.created && target_info.filename != template_name {
                 if force {
+                    policy::set_trusted(
+                        target_info.path.parent().unwrap_or(&target_info.path),
+                        &template_name,
+                        false,
+                    )?;
                     std::fs::rename(
                         &target_info.path,
                         target_info.path.with_file_name(&template_name),
                     )?;
+                    target_info.filename = template_name;
                 } else {
                     println!(
                         "{}",
@@ -492,3 +502,43 @@ impl TemplateFetcher {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read as _;
+    use std::net::TcpListener;
+
+    /// Serves a truncated template, so that the fetch fails once the template file is written.
+    fn truncated_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().take(2) {
+                let mut stream = stream.unwrap();
+                let mut request = [0; 1024];
+                let read = stream.read(&mut request).unwrap_or(0);
+                let head = String::from_utf8_lossy(&request[..read]).starts_with("HEAD");
+                let _ = stream.write_all(
+                    b"HTTP/1.1 200 OK\r\nContent-Length: 1000\r\nConnection: close\r\n\r\n",
+                );
+                if !head {
+                    let _ = stream.write_all(b"[pattern]\ndata = \"");
+                }
+            }
+        });
+        format!("http://{addr}/evil.tl")
+    }
+
+    #[test]
+    fn test_failed_fetch_leaves_template_untrusted() {
+        let dir = tempfile::tempdir().unwrap();
+        let templates_dir = dir.path().to_path_buf();
+        std::fs::write(templates_dir.join("evil.tl"), "[pattern]\ndata = \"x\"\n").unwrap();
+        policy::set_trusted(&templates_dir, "evil", true).unwrap();
+
+        let url = truncated_server();
+        assert!(TemplateFetcher::fetch_single(&url, &templates_dir, true).is_err());
+        assert!(!policy::is_trusted(&templates_dir, "evil"));
+    }
+}
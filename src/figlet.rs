@@ -0,0 +1,462 @@
+//! FIGlet fonts (`.flf`) used by the `banner` filter to draw big text.
+//!
+//! The `standard` and `block` fonts are embedded, other fonts are read from `<name>.flf` files
+//! in the fonts directory (`<config_dir>/fonts`), and nowhere else: templates cannot make
+//! titular read other files. Characters are laid out following the
+//! horizontal layout of the font (full width, fitting or smushing with the rules it enables), as
+//! described in the FIGlet font specification.
+
+use std::collections::HashMap;
+use std::path::{Component, Path};
+
+use crate::error::{Error, Result};
+
+/// Fonts shipped with titular
+const EMBEDDED_FONTS: [(&str, &str); 2] = [
+    ("standard", include_str!("../assets/fonts/standard.flf")),
+    ("block", include_str!("../assets/fonts/block.flf")),
+];
+
+/// Characters every font defines, in order, before the code-tagged ones
+const REQUIRED_CHARS: [u32; 7] = [196, 214, 220, 228, 246, 252, 223];
+
+/// Smushing rules of the horizontal layout
+const SMUSH_EQUAL: u32 = 1;
+const SMUSH_LOWLINE: u32 = 2;
+const SMUSH_HIERARCHY: u32 = 4;
+const SMUSH_PAIR: u32 = 8;
+const SMUSH_BIGX: u32 = 16;
+const SMUSH_HARDBLANK: u32 = 32;
+const LAYOUT_KERN: u32 = 64;
+const LAYOUT_SMUSH: u32 = 128;
+
+/// A FIGlet font.
+#[derive(Debug, Clone)]
+pub struct FigFont {
+    hardblank: char,
+    height: usize,
+    layout: u32,
+    glyphs: HashMap<char, Vec<Vec<char>>>,
+}
+
+impl FigFont {
+    /// Parses the content of a `.flf` file.
+    ///
+    /// # Errors
+    /// Returns an error if the header is not a FIGlet header or if a character is truncated.
+    pub fn parse(data: &str) -> Result<Self> {
+        let mut lines = data.lines();
+        let header = lines.next().unwrap_or_default();
+        let invalid = || Error::Msg("invalid FIGlet font header".to_string());
+        let params = header.strip_prefix("flf2a").ok_or_else(invalid)?;
+        let mut chars = params.chars();
+        let hardblank = chars.next().ok_or_else(invalid)?;
+        let params: Vec<i64> = chars
+            .as_str()
+            .split_whitespace()
+            .map(|p| p.parse().map_err(|_| invalid()))
+            .collect::<Result<_>>()?;
+        if params.len() < 4 {
+            return Err(invalid());
+        }
+        let height = usize::try_from(params[0])
+            .ok()
+            .filter(|h| *h > 0)
+            .ok_or_else(invalid)?;
+        let layout = match params.get(6) {
+            Some(full_layout) => u32::try_from(*full_layout).map_err(|_| invalid())? & 0xff,
+            None => match params[3] {
+                layout if layout < 0 => 0,
+                0 => LAYOUT_KERN,
+                layout => (u32::try_from(layout).map_err(|_| invalid())? & 0x3f) | LAYOUT_SMUSH,
+            },
+        };
+        let comment_lines = params
+            .get(4)
+            .and_then(|n| usize::try_from(*n).ok())
+            .unwrap_or(0);
+        let mut lines = lines.skip(comment_lines);
+
+        let mut glyphs = HashMap::new();
+        let read_glyph = |lines: &mut dyn Iterator<Item = &str>| -> Option<Vec<Vec<char>>> {
+            let mut glyph: Vec<Vec<char>> = (0..height)
+                .map(|_| {
+                    lines
+                        .next()
+                        .map(|line| strip_endmark(line).chars().collect())
+                })
+                .collect::<Option<_>>()?;
+            // Rows of a glyph share the same width
+            let width = glyph.iter().map(Vec::len).max().unwrap_or(0);
+            glyph.iter_mut().for_each(|row| row.resize(width, ' '));
+            Some(glyph)
+        };
+        for code in (32..127).chain(REQUIRED_CHARS) {
+            let glyph = read_glyph(&mut lines)
+                .ok_or_else(|| Error::Msg(format!("FIGlet font truncated at character {code}")))?;
+            if let Some(c) = char::from_u32(code) {
+                glyphs.insert(c, glyph);
+            }
+        }
+        // Code-tagged characters, until the end of the file
+        while let Some(tag) = lines.next() {
+            let Some(glyph) = read_glyph(&mut lines) else {
+                break;
+            };
+            if let Some(c) = tag
+                .split_whitespace()
+                .next()
+                .and_then(parse_code)
+                .and_then(char::from_u32)
+            {
+                glyphs.insert(c, glyph);
+            }
+        }
+
+        Ok(FigFont {
+            hardblank,
+            height,
+            layout,
+            glyphs,
+        })
+    }
+
+    /// Looks a font up by name: a `<name>.flf` file of the fonts directory or an embedded font.
+    /// The name is a file name, with or without its `.flf` extension: paths are rejected.
+    ///
+    /// # Errors
+    /// Returns an error if the name is a path, or if the font cannot be found or parsed.
+    pub fn load(name: &str, fonts_dir: Option<&Path>) -> Result<Self> {
+        let name = name.trim();
+        let name = name.strip_suffix(".flf").unwrap_or(name);
+        let mut components = Path::new(name).components();
+        if !matches!(
+            (components.next(), components.next()),
+            (Some(Component::Normal(_)), None)
+        ) {
+            return Err(Error::Msg(format!(
+                "invalid font name \"{name}\": fonts are read from the fonts directory only"
+            )));
+        }
+        if let Some(path) = fonts_dir
+            .map(|dir| dir.join(format!("{name}.flf")))
+            .filter(|path| path.is_file())
+        {
+            return Self::parse(&std::fs::read_to_string(path)?)
+                .map_err(|e| Error::Msg(format!("font \"{name}\": {e}")));
+        }
+        EMBEDDED_FONTS
+            .iter()
+            .find(|(font, _)| font.eq_ignore_ascii_case(name))
+            .map_or_else(
+                || {
+                    Err(Error::Msg(format!(
+                        "unknown font \"{name}\" (embedded fonts: {})",
+                        Self::embedded_names().join(", ")
+                    )))
+                },
+                |(_, data)| Self::parse(data),
+            )
+    }
+
+    /// Names of the embedded fonts.
+    #[must_use]
+    pub fn embedded_names() -> Vec<&'static str> {
+        EMBEDDED_FONTS.iter().map(|(name, _)| *name).collect()
+    }
+
+    /// Height of the characters, in lines.
+    #[must_use]
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Renders the text, one row of `height` lines per line of text. Characters missing from the
+    /// font are skipped, and the lines of a row all have the same width.
+    ///
+    /// # Examples
+    /// ```
+    /// use titular::figlet::FigFont;
+    ///
+    /// let font = FigFont::load("standard", None).unwrap();
+    /// assert_eq!(
+    ///     font.render("Hi"),
+    ///     [
+    ///         " _   _ _ ",
+    ///         "| | | (_)",
+    ///         "| |_| | |",
+    ///         "|  _  | |",
+    ///         "|_| |_|_|",
+    ///         "         ",
+    ///     ]
+    /// );
+    /// ```
+    #[must_use]
+    pub fn render(&self, text: &str) -> Vec<String> {
+        text.lines()
+            .flat_map(|line| self.render_line(line))
+            .collect()
+    }
+
+    fn render_line(&self, text: &str) -> Vec<String> {
+        let mut rows: Vec<Vec<char>> = vec![Vec::new(); self.height];
+        let mut previous_width = 0;
+        for glyph in text.chars().filter_map(|c| self.glyphs.get(&c)) {
+            let width = glyph.first().map_or(0, Vec::len);
+            let overlap = self.overlap(&rows, glyph, previous_width, width);
+            for (row, glyph_row) in rows.iter_mut().zip(glyph) {
+                let start = row.len() - overlap;
+                for (k, c) in glyph_row.iter().enumerate() {
+                    if k < overlap {
+                        let left = row[start + k];
+                        row[start + k] = self.smush(left, *c, previous_width, width).unwrap_or(*c);
+                    } else {
+                        row.push(*c);
+                    }
+                }
+            }
+            previous_width = width;
+        }
+
+        rows.into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .map(|c| if c == self.hardblank { ' ' } else { c })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Number of columns the glyph can overlap the rows with.
+    fn overlap(
+        &self,
+        rows: &[Vec<char>],
+        glyph: &[Vec<char>],
+        previous_width: usize,
+        width: usize,
+    ) -> usize {
+        let line_width = rows.first().map_or(0, Vec::len);
+        if self.layout & (LAYOUT_KERN | LAYOUT_SMUSH) == 0 || line_width == 0 {
+            return 0;
+        }
+        rows.iter()
+            .zip(glyph)
+            .map(|(row, glyph_row)| {
+                let trailing = row.iter().rev().take_while(|c| **c == ' ').count();
+                let leading = glyph_row.iter().take_while(|c| **c == ' ').count();
+                let mut amount = trailing + leading;
+                if let (Some(left), Some(right)) = (
+                    row.iter().rev().find(|c| **c != ' '),
+                    glyph_row.get(leading),
+                ) {
+                    if self.smush(*left, *right, previous_width, width).is_some() {
+                        amount += 1;
+                    }
+                }
+                amount
+            })
+            .min()
+            .unwrap_or(0)
+            .min(width)
+            .min(line_width)
+    }
+
+    /// Character resulting from the smushing of two characters, if the layout allows it.
+    fn smush(
+        &self,
+        left: char,
+        right: char,
+        left_width: usize,
+        right_width: usize,
+    ) -> Option<char> {
+        if left == ' ' {
+            return Some(right);
+        }
+        if right == ' ' {
+            return Some(left);
+        }
+        if left_width < 2 || right_width < 2 || self.layout & LAYOUT_SMUSH == 0 {
+            return None;
+        }
+        let rules = self.layout & 0x3f;
+        let hardblank = self.hardblank;
+        if rules == 0 {
+            // Universal smushing
+            return Some(if right == hardblank { left } else { right });
+        }
+        if left == hardblank || right == hardblank {
+            return (rules & SMUSH_HARDBLANK != 0 && left == right).then_some(left);
+        }
+        if rules & SMUSH_EQUAL != 0 && left == right {
+            return Some(left);
+        }
+        const LOWLINE_REPLACERS: &str = "|/\\[]{}()<>";
+        if rules & SMUSH_LOWLINE != 0 {
+            if left == '_' && LOWLINE_REPLACERS.contains(right) {
+                return Some(right);
+            }
+            if right == '_' && LOWLINE_REPLACERS.contains(left) {
+                return Some(left);
+            }
+        }
+        if rules & SMUSH_HIERARCHY != 0 {
+            const CLASSES: [&str; 6] = ["|", "/\\", "[]", "{}", "()", "<>"];
+            let class = |c: char| CLASSES.iter().position(|class| class.contains(c));
+            match (class(left), class(right)) {
+                (Some(l), Some(r)) if l < r => return Some(right),
+                (Some(l), Some(r)) if l > r => return Some(left),
+                _ => {}
+            }
+        }
+        if rules & SMUSH_PAIR != 0
+            && matches!(
+                (left, right),
+                ('[', ']') | (']', '[') | ('{', '}') | ('}', '{') | ('(', ')') | (')', '(')
+            )
+        {
+            return Some('|');
+        }
+        if rules & SMUSH_BIGX != 0 {
+            match (left, right) {
+                ('/', '\\') => return Some('|'),
+                ('\\', '/') => return Some('Y'),
+                ('>', '<') => return Some('X'),
+                _ => {}
+            }
+        }
+        None
+    }
+}
+
+/// Removes the endmark (the last character, repeated at the end of the last line of a glyph).
+fn strip_endmark(line: &str) -> &str {
+    let line = line.trim_end_matches(['\r', '\n']);
+    match line.chars().last() {
+        Some(endmark) => line.trim_end_matches(endmark),
+        None => line,
+    }
+}
+
+/// Parses the code of a code-tagged character (decimal, `0x` hexadecimal or `0` octal).
+fn parse_code(code: &str) -> Option<u32> {
+    if let Some(hex) = code.strip_prefix("0x").or_else(|| code.strip_prefix("0X")) {
+        u32::from_str_radix(hex, 16).ok()
+    } else if code.len() > 1 && code.starts_with('0') {
+        u32::from_str_radix(&code[1..], 8).ok()
+    } else {
+        code.parse().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A two-line font defining `a` and `b`, plus a code-tagged euro sign
+    fn tiny_font(layout: &str) -> String {
+        let mut data = format!("flf2a$ 2 2 4 {layout} 1\ncomment\n");
+        for code in (32..127).chain(REQUIRED_CHARS) {
+            let glyph = match char::from_u32(code) {
+                Some('a') => "/\\@\n\\/@@\n",
+                Some('b') => "_|@\n_|@@\n",
+                Some(' ') => "$@\n$@@\n",
+                _ => "@\n@@\n",
+            };
+            data.push_str(glyph);
+        }
+        data.push_str("0x20AC EURO SIGN\nE@\nE@@\n");
+        data
+    }
+
+    #[test]
+    fn test_parse() {
+        let font = FigFont::parse(&tiny_font("0")).unwrap();
+        assert_eq!(font.height(), 2);
+        assert_eq!(font.layout, LAYOUT_KERN);
+        assert_eq!(font.glyphs[&'a'], [vec!['/', '\\'], vec!['\\', '/']]);
+        assert_eq!(font.glyphs[&'€'], [vec!['E'], vec!['E']]);
+
+        assert!(FigFont::parse("tlf2a$ 2 2 4 0 0").is_err());
+        assert!(FigFont::parse("flf2a$ 2 2 4 0 0\n@\n@@\n").is_err());
+    }
+
+    #[test]
+    fn test_layouts() {
+        let full = FigFont::parse(&tiny_font("-1")).unwrap();
+        assert_eq!(full.render("ab a"), ["/\\_| /\\", "\\/_| \\/"]);
+
+        let kern = FigFont::parse(&tiny_font("0")).unwrap();
+        assert_eq!(kern.render("ab€"), ["/\\_|E", "\\/_|E"]);
+
+        // Hierarchy rule: `|` is replaced by `/` and `\`
+        let smush = FigFont::parse(&tiny_font("4")).unwrap();
+        assert_eq!(smush.render("ba"), ["_/\\", "_\\/"]);
+        assert_eq!(smush.render("a\nb"), ["/\\", "\\/", "_|", "_|"]);
+    }
+
+    #[test]
+    fn test_embedded_fonts() {
+        for name in FigFont::embedded_names() {
+            let font = FigFont::load(name, None).unwrap();
+            let rows = font.render("Deploy PROD 1.2!");
+            assert_eq!(rows.len(), font.height());
+            let width = rows[0].chars().count();
+            assert!(width > 0);
+            assert!(rows.iter().all(|row| row.chars().count() == width));
+        }
+        assert_eq!(
+            FigFont::load("block", None).unwrap().render("Hi"),
+            [
+                "█   █ ███ ",
+                "█   █  █  ",
+                "█████  █  ",
+                "█   █  █  ",
+                "█   █ ███ ",
+            ]
+        );
+        assert!(FigFont::load("nope", None).is_err());
+    }
+
+    #[test]
+    fn test_user_fonts() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("tiny.flf"), tiny_font("0")).unwrap();
+        let font = FigFont::load("tiny", Some(dir.path())).unwrap();
+        assert_eq!(font.render("a"), ["/\\", "\\/"]);
+
+        assert!(FigFont::load("tiny.flf", Some(dir.path())).is_ok());
+        // Embedded fonts are still found
+        assert!(FigFont::load("standard", Some(dir.path())).is_ok());
+    }
+
+    #[test]
+    fn test_font_paths_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let fonts = dir.path().join("fonts");
+        std::fs::create_dir(&fonts).unwrap();
+        std::fs::write(dir.path().join("outside.flf"), tiny_font("0")).unwrap();
+        let outside = dir.path().join("outside.flf");
+        for name in [
+            outside.to_str().unwrap(),
+            "../outside",
+            "../outside.flf",
+            "",
+            ".",
+        ] {
+            assert!(FigFont::load(name, Some(&fonts)).is_err(), "{name}");
+        }
+
+        // The content of a file that is not a font is not echoed
+        std::fs::write(fonts.join("secret.flf"), "TOKEN=abc\n").unwrap();
+        let error = FigFont::load("secret", Some(&fonts)).unwrap_err();
+        assert!(!error.to_string().contains("TOKEN"), "{error}");
+    }
+
+    #[test]
+    fn test_parse_code() {
+        assert_eq!(parse_code("228"), Some(228));
+        assert_eq!(parse_code("0xE4"), Some(228));
+        assert_eq!(parse_code("0344"), Some(228));
+        assert_eq!(parse_code("-1"), None);
+    }
+}
@@ -0,0 +1,310 @@
+//! Layout of the fillers repeated by padding groups.
+//!
+//! By default a filler is repeated from the start of its group and cut wherever the available
+//! width ends. A pattern mode, set with `pad(pattern_mode="...")` or the `pattern_mode` context
+//! value, changes that layout:
+//! - `edge` anchors the pattern at the message, so a group before the message ends on a whole
+//!   pattern and a group after it starts on one
+//! - `mirror` reverses the pattern of the groups after the message, swapping paired characters
+//!   (`<` and `>`, `(` and `)`, `←` and `→`, ...), so that `>>>> msg >>>>` becomes
+//!   `>>>> msg <<<<`
+//! - `whole` only lays out whole repetitions of the pattern, filling the remainder with spaces
+//!
+//! The filter stores the mode in the rendered text, the layout happens once the text processor
+//! knows the width of each group.
+
+use std::str::FromStr;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::error::{Error, Result};
+use crate::string_utils::expand_to_visual_width;
+use crate::width::{grapheme_width, text_width, AmbiguousWidth};
+
+/// Characters swapped when a pattern is mirrored
+const MIRRORED_PAIRS: [(char, char); 16] = [
+    ('<', '>'),
+    ('(', ')'),
+    ('[', ']'),
+    ('{', '}'),
+    ('/', '\\'),
+    ('«', '»'),
+    ('‹', '›'),
+    ('←', '→'),
+    ('⇐', '⇒'),
+    ('⟵', '⟶'),
+    ('◀', '▶'),
+    ('◁', '▷'),
+    ('◂', '▸'),
+    ('⟨', '⟩'),
+    ('❮', '❯'),
+    ('╱', '╲'),
+];
+
+/// How the filler of a padding group is laid out, see the [module documentation](self).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PatternMode {
+    pub edge: bool,
+    pub mirror: bool,
+    pub whole: bool,
+}
+
+impl FromStr for PatternMode {
+    type Err = Error;
+
+    /// Parses a list of options separated by commas or spaces, e.g. `"edge,mirror"`. An empty
+    /// list or `none` gives the default layout.
+    fn from_str(s: &str) -> Result<Self> {
+        let mut mode = PatternMode::default();
+        for option in s
+            .split([',', ' ', '+'])
+            .map(str::trim)
+            .filter(|o| !o.is_empty())
+        {
+            match option.to_lowercase().as_str() {
+                "edge" => mode.edge = true,
+                "mirror" => mode.mirror = true,
+                "whole" => mode.whole = true,
+                "none" => {}
+                other => {
+                    return Err(Error::Msg(format!(
+                        "invalid pattern mode \"{other}\" (expected edge, mirror or whole)"
+                    )))
+                }
+            }
+        }
+        Ok(mode)
+    }
+}
+
+impl PatternMode {
+    /// Whether the filler is laid out as usual.
+    #[must_use]
+    pub fn is_default(&self) -> bool {
+        *self == PatternMode::default()
+    }
+
+    /// Serializes the mode into the list of its options.
+    ///
+    /// # Examples
+    /// ```
+    /// use titular::filler::PatternMode;
+    ///
+    /// let mode: PatternMode = "mirror, edge".parse().unwrap();
+    /// assert_eq!(mode.to_spec(), "edge,mirror");
+    /// assert_eq!(mode.to_spec().parse::<PatternMode>().unwrap(), mode);
+    /// ```
+    #[must_use]
+    pub fn to_spec(&self) -> String {
+        [
+            (self.edge, "edge"),
+            (self.mirror, "mirror"),
+            (self.whole, "whole"),
+        ]
+        .iter()
+        .filter(|(set, _)| *set)
+        .map(|(_, name)| *name)
+        .collect::<Vec<_>>()
+        .join(",")
+    }
+}
+
+/// Side of the message a padding group is on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    /// The group comes before some text of the line
+    Leading,
+    /// The group ends the line
+    Trailing,
+}
+
+/// Reverses the pattern, swapping the characters that come in pairs.
+///
+/// # Examples
+/// ```
+/// use titular::filler::mirror;
+///
+/// assert_eq!(mirror("=>"), "<=");
+/// assert_eq!(mirror("-«🦀"), "🦀»-");
+/// ```
+#[must_use]
+pub fn mirror(pattern: &str) -> String {
+    pattern
+        .graphemes(true)
+        .rev()
+        .map(|g| {
+            let mut chars = g.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => MIRRORED_PAIRS
+                    .iter()
+                    .find_map(|&(a, b)| {
+                        if c == a {
+                            Some(b)
+                        } else if c == b {
+                            Some(a)
+                        } else {
+                            None
+                        }
+                    })
+                    .map_or_else(|| g.to_string(), String::from),
+                _ => g.to_string(),
+            }
+        })
+        .collect()
+}
+
+/// Lays the pattern out over `width` columns, according to the mode and to the side of the
+/// message the group is on. Columns that the pattern cannot fill are filled with spaces, on the
+/// side away from the message for anchored patterns. The pattern is measured with the given
+/// width of East Asian ambiguous characters.
+///
+/// # Examples
+/// ```
+/// use titular::filler::{fill, PatternMode, Side};
+/// use titular::width::AmbiguousWidth::{Narrow, Wide};
+///
+/// let mode: PatternMode = "edge,mirror".parse().unwrap();
+/// assert_eq!(fill("->", 5, mode, Side::Leading, Narrow), ">->->");
+/// assert_eq!(fill("->", 5, mode, Side::Trailing, Narrow), "<-<-<");
+///
+/// let mode: PatternMode = "edge,whole".parse().unwrap();
+/// assert_eq!(fill("-=", 5, mode, Side::Leading, Narrow), " -=-=");
+/// assert_eq!(fill("-=", 5, mode, Side::Trailing, Narrow), "-=-= ");
+/// assert_eq!(fill("─", 5, mode, Side::Trailing, Wide), "── ");
+/// ```
+#[must_use]
+pub fn fill(
+    pattern: &str,
+    width: usize,
+    mode: PatternMode,
+    side: Side,
+    ambiguous: AmbiguousWidth,
+) -> String {
+    let pattern = if mode.mirror && side == Side::Trailing {
+        mirror(pattern)
+    } else {
+        pattern.to_string()
+    };
+    let pattern_width = text_width(&pattern, ambiguous);
+    if width == 0 || pattern_width == 0 {
+        return expand_to_visual_width(&pattern, width, ambiguous);
+    }
+
+    let anchored_end = mode.edge && side == Side::Leading;
+    let body = if mode.whole {
+        pattern.repeat(width / pattern_width)
+    } else if anchored_end {
+        take_end(
+            &pattern.repeat(width.div_ceil(pattern_width)),
+            width,
+            ambiguous,
+        )
+    } else {
+        expand_to_visual_width(&pattern, width, ambiguous)
+    };
+
+    let spaces = " ".repeat(width.saturating_sub(text_width(&body, ambiguous)));
+    if anchored_end {
+        spaces + &body
+    } else {
+        body + &spaces
+    }
+}
+
+/// Keeps the last graphemes of the text fitting in `width` columns.
+fn take_end(text: &str, width: usize, ambiguous: AmbiguousWidth) -> String {
+    let mut current_width = 0;
+    let mut start = text.len();
+    for (index, grapheme) in text.grapheme_indices(true).rev() {
+        current_width += grapheme_width(grapheme, ambiguous);
+        if current_width > width {
+            break;
+        }
+        start = index;
+    }
+    text[start..].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NARROW: AmbiguousWidth = AmbiguousWidth::Narrow;
+
+    fn mode(spec: &str) -> PatternMode {
+        spec.parse().unwrap()
+    }
+
+    #[test]
+    fn test_pattern_mode_parse() {
+        assert!(mode("").is_default());
+        assert!(mode("none").is_default());
+        assert_eq!(
+            mode("Edge + whole"),
+            PatternMode {
+                edge: true,
+                mirror: false,
+                whole: true
+            }
+        );
+        assert!("edge,center".parse::<PatternMode>().is_err());
+    }
+
+    #[test]
+    fn test_mirror() {
+        assert_eq!(mirror(""), "");
+        assert_eq!(mirror(">>"), "<<");
+        assert_eq!(mirror("(-_-)"), "(-_-)");
+        assert_eq!(mirror("/¯\\_"), "_/¯\\");
+        assert_eq!(mirror("→·"), "·←");
+        assert_eq!(mirror("👨\u{200D}👩\u{200D}👧<"), ">👨\u{200D}👩\u{200D}👧");
+    }
+
+    #[test]
+    fn test_fill_default() {
+        let default = PatternMode::default();
+        assert_eq!(fill("-=", 5, default, Side::Leading, NARROW), "-=-=-");
+        assert_eq!(fill("-=", 5, default, Side::Trailing, NARROW), "-=-=-");
+        assert_eq!(fill("🦀", 5, default, Side::Trailing, NARROW), "🦀🦀 ");
+        assert_eq!(fill("-=", 0, default, Side::Trailing, NARROW), "-=");
+    }
+
+    #[test]
+    fn test_fill_edge() {
+        assert_eq!(fill("-=", 5, mode("edge"), Side::Leading, NARROW), "=-=-=");
+        assert_eq!(fill("-=", 5, mode("edge"), Side::Trailing, NARROW), "-=-=-");
+        assert_eq!(
+            fill("🦀.", 7, mode("edge"), Side::Leading, NARROW),
+            ".🦀.🦀."
+        );
+        assert_eq!(fill(".🦀", 4, mode("edge"), Side::Leading, NARROW), " .🦀");
+    }
+
+    #[test]
+    fn test_fill_mirror() {
+        assert_eq!(fill(">", 3, mode("mirror"), Side::Leading, NARROW), ">>>");
+        assert_eq!(fill(">", 3, mode("mirror"), Side::Trailing, NARROW), "<<<");
+        assert_eq!(
+            fill("=>", 5, mode("mirror"), Side::Trailing, NARROW),
+            "<=<=<"
+        );
+        assert_eq!(
+            fill("=>", 5, mode("mirror,edge"), Side::Leading, NARROW),
+            ">=>=>"
+        );
+    }
+
+    #[test]
+    fn test_fill_whole() {
+        assert_eq!(fill("<>", 5, mode("whole"), Side::Leading, NARROW), "<><> ");
+        assert_eq!(
+            fill("<>", 5, mode("whole,edge"), Side::Leading, NARROW),
+            " <><>"
+        );
+        assert_eq!(fill("<->", 2, mode("whole"), Side::Trailing, NARROW), "  ");
+        assert_eq!(
+            fill("日本", 9, mode("whole"), Side::Trailing, NARROW),
+            "日本日本 "
+        );
+    }
+}
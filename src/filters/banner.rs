@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+use std::path::Path;
+use tera::{Error as TeraError, Value};
+
+use crate::ansi::strip_escapes;
+use crate::constants::{block, is_marker};
+use crate::context_manager::ContextManager;
+use crate::figlet::FigFont;
+
+/// Create a banner filter closure for Tera
+///
+/// Draws the text as big multi-line text with a FIGlet font: `font` is one of the embedded
+/// fonts (`standard`, the default, or `block`) or the name of a `<name>.flf` file of the fonts
+/// directory (the `fonts_dir` value of the context, i.e. `<config_dir>/fonts`). Paths are
+/// rejected, so that a template cannot read files out of the fonts directory. The lines are wrapped in block markers, so that the text processor lays each of
+/// them out on its own line with the text around the banner, padding included.
+///
+/// # Example
+/// ```tera
+/// {{ fb | pad }}{{ m | banner(font="block") | color(name=c2) }}{{ fe | pad }}
+/// ```
+///
+/// # Returns
+/// A closure that can be used with Tera's `register_filter`
+pub fn create_banner_filter() -> impl Fn(&Value, &HashMap<String, Value>) -> Result<Value, TeraError>
+{
+    move |value: &Value, args: &HashMap<String, Value>| {
+        let text = tera::try_get_value!("banner", "value", String, value);
+
+        let font = match args.get("font") {
+            Some(font) => font
+                .as_str()
+                .ok_or_else(|| TeraError::msg("font must be a string"))?,
+            None => "standard",
+        };
+        let fonts_dir = ContextManager::get()
+            .read()
+            .map_err(|e| TeraError::msg(e.to_string()))?
+            .get("fonts_dir")
+            .map(String::from);
+        let font = FigFont::load(font, fonts_dir.as_deref().map(Path::new))
+            .map_err(|e| TeraError::msg(e.to_string()))?;
+
+        // Draw the visible text only, colors being applied to the whole banner afterwards
+        let visible: String = strip_escapes(&text)
+            .chars()
+            .filter(|c| !is_marker(*c))
+            .collect();
+        let rows = font.render(&visible);
+        if rows.iter().all(|row| row.trim().is_empty()) {
+            return Ok(Value::String(String::new()));
+        }
+        Ok(Value::String(format!(
+            "{}{}{}",
+            block::START,
+            rows.join(&block::ROW.to_string()),
+            block::END
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_banner_filter() {
+        let filter = create_banner_filter();
+        let mut args = HashMap::new();
+        args.insert("font".to_string(), Value::String("block".to_string()));
+
+        let result = filter(&Value::String("\x1b[31mI\x1b[0m".to_string()), &args).unwrap();
+        let rows = ["███ ", " █  ", " █  ", " █  ", "███ "];
+        assert_eq!(
+            result.as_str().unwrap(),
+            format!(
+                "{}{}{}",
+                block::START,
+                rows.join(&block::ROW.to_string()),
+                block::END
+            )
+        );
+
+        let result = filter(&Value::String(String::new()), &args).unwrap();
+        assert_eq!(result.as_str().unwrap(), "");
+
+        args.insert("font".to_string(), Value::String("missing".to_string()));
+        assert!(filter(&Value::String("x".to_string()), &args).is_err());
+        args.insert("font".to_string(), Value::String("/etc/passwd".to_string()));
+        assert!(filter(&Value::String("x".to_string()), &args).is_err());
+    }
+}
@@ -1,11 +1,19 @@
 use std::collections::HashMap;
 use tera::{Error as TeraError, Value};
 
-use crate::color_manager::{ColorManager, StyleFormat, StyleScope};
+use crate::color_functions::to_rgb_string;
+use crate::color_manager::{ColorManager, StyleFormat, StyleScope, TextAttributes};
+use crate::color_utils::color_to_rgb;
+use crate::context::Context;
 use crate::context_manager::ContextManager;
+use crate::contrast::nudge_to_contrast;
 
 /// Create a color filter closure for Tera
 ///
+/// With `min_contrast` (e.g. `4.5`), the lightness of a foreground color is adjusted until its
+/// WCAG contrast ratio against the `against` color (the theme background by default) reaches
+/// the given value.
+///
 /// # Returns
 /// A closure that can be used with Tera's `register_filter`
 pub fn create_color_filter() -> impl Fn(&Value, &HashMap<String, Value>) -> Result<Value, TeraError>
@@ -29,6 +37,18 @@ pub fn create_color_filter() -> impl Fn(&Value, &HashMap<String, Value>) -> Resu
             .and_then(serde_json::Value::as_bool)
             .unwrap_or(false);
 
+        let ctx = ContextManager::get()
+            .read()
+            .map_err(|e| TeraError::msg(e.to_string()))?;
+
+        let color_value = match min_contrast_arg(args)? {
+            Some(min_ratio) if !is_bg => {
+                let against = args.get("against").and_then(Value::as_str);
+                with_min_contrast(&ctx, color_value, against, min_ratio)
+            }
+            _ => color_value,
+        };
+
         let style = StyleFormat {
             fg_color: (!is_bg).then_some(color_value.clone()),
             bg_color: is_bg.then_some(color_value),
@@ -37,12 +57,63 @@ pub fn create_color_filter() -> impl Fn(&Value, &HashMap<String, Value>) -> Resu
             } else {
                 StyleScope::FG
             },
+            attributes: TextAttributes::default(),
+            underline_color: None,
         };
 
-        let ctx = ContextManager::get()
-            .read()
-            .map_err(|e| TeraError::msg(e.to_string()))?;
+        ColorManager::try_format(&ctx, &text, style)
+            .map(Value::String)
+            .map_err(|e| TeraError::msg(e.to_string()))
+    }
+}
+
+/// Reads the `min_contrast` argument, given as a number or a string (`4.5` or `"4.5"`).
+fn min_contrast_arg(args: &HashMap<String, Value>) -> Result<Option<f64>, TeraError> {
+    args.get("min_contrast")
+        .map(|ratio| {
+            ratio
+                .as_f64()
+                .or_else(|| ratio.as_str().and_then(|s| s.trim().parse().ok()))
+                .ok_or_else(|| TeraError::msg("min_contrast must be a number"))
+        })
+        .transpose()
+}
+
+/// Nudges the color to reach the minimum contrast ratio against the given background (the
+/// theme background by default), leaving it as is when either color cannot be resolved.
+fn with_min_contrast(
+    ctx: &Context,
+    color: String,
+    against: Option<&str>,
+    min_ratio: f64,
+) -> String {
+    let rgb = |name: &str| {
+        ColorManager::resolve(ctx, name)
+            .ok()
+            .flatten()
+            .and_then(color_to_rgb)
+    };
+    match (rgb(&color), rgb(against.unwrap_or("theme_background"))) {
+        (Some(fg), Some(bg)) => to_rgb_string(nudge_to_contrast(fg, bg, min_ratio)),
+        _ => color,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        Ok(Value::String(ColorManager::format(&ctx, &text, style)))
+    #[test]
+    fn test_min_contrast_arg() {
+        let arg = |value: Value| HashMap::from([("min_contrast".to_string(), value)]);
+        assert_eq!(min_contrast_arg(&HashMap::new()).unwrap(), None);
+        assert_eq!(min_contrast_arg(&arg(Value::from(4.5))).unwrap(), Some(4.5));
+        assert_eq!(min_contrast_arg(&arg(Value::from(7))).unwrap(), Some(7.0));
+        assert_eq!(
+            min_contrast_arg(&arg(Value::from(" 4.5"))).unwrap(),
+            Some(4.5)
+        );
+        assert!(min_contrast_arg(&arg(Value::from("high"))).is_err());
+        assert!(min_contrast_arg(&arg(Value::Bool(true))).is_err());
     }
 }
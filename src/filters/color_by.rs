@@ -0,0 +1,251 @@
+use std::collections::HashMap;
+use tera::{Error as TeraError, Value};
+
+use crate::color_manager::{ColorManager, StyleFormat, StyleScope, TextAttributes};
+use crate::context_manager::ContextManager;
+use crate::string_utils::split_top_level;
+
+/// A key of a thresholds map : an exact value, a numeric range or the fallback.
+#[derive(Debug, Clone, PartialEq)]
+enum Threshold {
+    /// Matches the value itself (numerically when both sides are numbers)
+    Exact(String),
+    /// Matches numbers within the range (`a..b`, `a..=b`, `a..`, `..b` or `..=b`)
+    Range {
+        start: Option<f64>,
+        end: Option<f64>,
+        inclusive: bool,
+    },
+    /// Matches any value (`_` or `*`)
+    Fallback,
+}
+
+impl Threshold {
+    fn parse(key: &str) -> Self {
+        let key = key.trim();
+        if key == "_" || key == "*" {
+            return Threshold::Fallback;
+        }
+        let Some((start, end)) = key.split_once("..") else {
+            return Threshold::Exact(key.to_string());
+        };
+        let (end, inclusive) = match end.strip_prefix('=') {
+            Some(end) => (end, true),
+            None => (end, false),
+        };
+        let bound = |b: &str| b.trim().parse::<f64>().ok();
+        match (bound(start), bound(end)) {
+            (None, None) => Threshold::Exact(key.to_string()),
+            (start, end) => Threshold::Range {
+                start,
+                end,
+                inclusive,
+            },
+        }
+    }
+
+    fn matches(&self, value: &str, number: Option<f64>) -> bool {
+        match self {
+            Threshold::Exact(key) => match (key.parse::<f64>(), number) {
+                (Ok(k), Some(n)) => (k - n).abs() < f64::EPSILON,
+                _ => key == value,
+            },
+            Threshold::Range {
+                start,
+                end,
+                inclusive,
+            } => number.is_some_and(|n| {
+                start.is_none_or(|s| n >= s)
+                    && end.is_none_or(|e| if *inclusive { n <= e } else { n < e })
+            }),
+            Threshold::Fallback => true,
+        }
+    }
+
+    /// Width of the range, used to prefer the narrowest of several matching ranges.
+    fn span(&self) -> f64 {
+        match self {
+            Threshold::Range {
+                start: Some(s),
+                end: Some(e),
+                ..
+            } => e - s,
+            _ => f64::INFINITY,
+        }
+    }
+}
+
+/// Parses the thresholds, given either as an object (`{"0": "green", "1..": "red"}`) or as a
+/// string spec (`"0:green, 1..:red"`).
+fn parse_thresholds(value: &Value) -> Result<Vec<(Threshold, String)>, TeraError> {
+    match value {
+        Value::Object(map) => map
+            .iter()
+            .map(|(key, color)| {
+                color
+                    .as_str()
+                    .map(|c| (Threshold::parse(key), c.to_string()))
+                    .ok_or_else(|| TeraError::msg(format!("Color for \"{key}\" must be a string")))
+            })
+            .collect(),
+        Value::String(spec) => split_top_level(spec, |c| c == ',')
+            .into_iter()
+            .map(|entry| {
+                entry
+                    .split_once(':')
+                    .map(|(key, color)| (Threshold::parse(key), color.trim().to_string()))
+                    .ok_or_else(|| {
+                        TeraError::msg(format!(
+                            "Invalid threshold \"{entry}\" (expected key:color)"
+                        ))
+                    })
+            })
+            .collect(),
+        _ => Err(TeraError::msg(
+            "thresholds must be an object or a \"key:color, ...\" string",
+        )),
+    }
+}
+
+/// Picks the color of the first exact match, else of the narrowest matching range, else of the
+/// fallback.
+fn pick<'a>(thresholds: &'a [(Threshold, String)], value: &str) -> Option<&'a str> {
+    let number = value.trim().parse::<f64>().ok();
+    let matching = |t: &&(Threshold, String)| t.0.matches(value, number);
+
+    let exact = thresholds
+        .iter()
+        .filter(|t| matches!(t.0, Threshold::Exact(_)))
+        .find(matching);
+    let range = || {
+        thresholds
+            .iter()
+            .filter(|t| matches!(t.0, Threshold::Range { .. }))
+            .filter(matching)
+            .min_by(|a, b| a.0.span().total_cmp(&b.0.span()))
+    };
+    let fallback = || {
+        thresholds
+            .iter()
+            .find(|t| matches!(t.0, Threshold::Fallback))
+    };
+    exact
+        .or_else(range)
+        .or_else(fallback)
+        .map(|(_, color)| color.as_str())
+}
+
+/// Create a `color_by` filter closure for Tera
+///
+/// Colors the text with the color mapped to `value` (the text itself by default) in
+/// `thresholds`. Keys are exact values (`"0"`, `"main"`), numeric ranges (`"1..10"`,
+/// `"1..=9"`, `"10.."`, `"..0"`) or `"_"` for any other value. Exact keys win over ranges, and
+/// the narrowest of several matching ranges wins. The text is left unchanged when nothing
+/// matches.
+///
+/// # Example
+/// ```tera
+/// {{ m | color_by(value=get_last_exit_code(), thresholds="0:green, 1..:red") }}
+/// ```
+///
+/// # Returns
+/// A closure that can be used with Tera's `register_filter`
+pub fn create_color_by_filter(
+) -> impl Fn(&Value, &HashMap<String, Value>) -> Result<Value, TeraError> {
+    move |value: &Value, args: &HashMap<String, Value>| {
+        let text = tera::try_get_value!("color_by", "value", String, value);
+
+        let thresholds = parse_thresholds(
+            args.get("thresholds")
+                .ok_or_else(|| TeraError::msg("Missing thresholds argument"))?,
+        )?;
+        let key = match args.get("value") {
+            None => text.clone(),
+            Some(Value::String(s)) => s.clone(),
+            Some(other) => other.to_string(),
+        };
+        let Some(color) = pick(&thresholds, &key) else {
+            return Ok(Value::String(text));
+        };
+
+        let is_bg = args.get("is_bg").and_then(Value::as_bool).unwrap_or(false);
+        paint(&text, color, is_bg)
+    }
+}
+
+/// Colors the text through the [`ColorManager`], as the foreground or the background.
+pub(crate) fn paint(text: &str, color: &str, is_bg: bool) -> Result<Value, TeraError> {
+    let style = StyleFormat {
+        fg_color: (!is_bg).then(|| color.to_string()),
+        bg_color: is_bg.then(|| color.to_string()),
+        scope: if is_bg {
+            StyleScope::BG
+        } else {
+            StyleScope::FG
+        },
+        attributes: TextAttributes::default(),
+        underline_color: None,
+    };
+
+    let ctx = ContextManager::get()
+        .read()
+        .map_err(|e| TeraError::msg(e.to_string()))?;
+
+    ColorManager::try_format(&ctx, text, style)
+        .map(Value::String)
+        .map_err(|e| TeraError::msg(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn thresholds(spec: &str) -> Vec<(Threshold, String)> {
+        parse_thresholds(&Value::String(spec.to_string())).unwrap()
+    }
+
+    #[test]
+    fn test_threshold_parse() {
+        assert_eq!(Threshold::parse("0"), Threshold::Exact("0".to_string()));
+        assert_eq!(Threshold::parse("_"), Threshold::Fallback);
+        assert_eq!(
+            Threshold::parse("1..=9"),
+            Threshold::Range {
+                start: Some(1.0),
+                end: Some(9.0),
+                inclusive: true
+            }
+        );
+        assert_eq!(
+            Threshold::parse("..0"),
+            Threshold::Range {
+                start: None,
+                end: Some(0.0),
+                inclusive: false
+            }
+        );
+    }
+
+    #[test]
+    fn test_pick() {
+        let t = thresholds("0:green, 1..:red, 126..=127:yellow, main:RGB(0, 0, 255), _:white");
+        assert_eq!(pick(&t, "0"), Some("green"));
+        assert_eq!(pick(&t, "0.0"), Some("green"));
+        assert_eq!(pick(&t, "1"), Some("red"));
+        assert_eq!(pick(&t, "127"), Some("yellow"));
+        assert_eq!(pick(&t, "128"), Some("red"));
+        assert_eq!(pick(&t, "main"), Some("RGB(0, 0, 255)"));
+        assert_eq!(pick(&t, "-1"), Some("white"));
+
+        let t = thresholds("..0:blue, 0..10:green");
+        assert_eq!(pick(&t, "10"), None);
+    }
+
+    #[test]
+    fn test_object_thresholds() {
+        let t = parse_thresholds(&serde_json::json!({ "0": "green", "1..": "red" })).unwrap();
+        assert_eq!(pick(&t, "2"), Some("red"));
+        assert!(parse_thresholds(&serde_json::json!({ "0": 1 })).is_err());
+        assert!(parse_thresholds(&Value::String("0=green".to_string())).is_err());
+    }
+}
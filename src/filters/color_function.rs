@@ -0,0 +1,25 @@
+use std::collections::HashMap;
+use tera::{Error as TeraError, Value};
+
+use crate::color_functions::ColorFunction;
+use crate::functions::color::evaluate;
+
+/// Create a filter closure for Tera applying the given color manipulation to the value, which
+/// holds the color (the background for `readable_on`). The other arguments are the same as the
+/// ones of the matching function (see [`create_color_function`](crate::functions::create_color_function)).
+///
+/// # Example
+/// ```tera
+/// {{ m | color(name=accent | lighten(amount=0.2)) }}
+/// ```
+///
+/// # Returns
+/// A closure that can be used with Tera's `register_filter`
+pub fn create_color_function_filter(
+    function: ColorFunction,
+) -> impl Fn(&Value, &HashMap<String, Value>) -> Result<Value, TeraError> {
+    move |value: &Value, args: &HashMap<String, Value>| {
+        let color = tera::try_get_value!(function.name(), "value", String, value);
+        evaluate(function, &color, args)
+    }
+}
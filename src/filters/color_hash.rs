@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+use tera::{Error as TeraError, Value};
+
+use crate::filters::color_by::paint;
+
+/// Colors picked from when no palette is given, distinguishable on dark and light backgrounds.
+const DEFAULT_PALETTE: [&str; 10] = [
+    "#e06c75", "#98c379", "#e5c07b", "#61afef", "#c678dd", "#56b6c2", "#d19a66", "#f78c6c",
+    "#82aaff", "#c3e88d",
+];
+
+/// 64-bit FNV-1a hash, stable across runs and platforms unlike the std `Hasher`s.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+    bytes.iter().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(PRIME)
+    })
+}
+
+/// Picks the color of the palette matching the hash of the key.
+fn pick<'a>(palette: &[&'a str], key: &str) -> Option<&'a str> {
+    let len = u64::try_from(palette.len()).ok().filter(|len| *len > 0)?;
+    let index = usize::try_from(fnv1a(key.as_bytes()) % len).ok()?;
+    palette.get(index).copied()
+}
+
+/// Create a `color_hash` filter closure for Tera
+///
+/// Colors the text with a color of `palette` picked from the hash of `value` (the text itself
+/// by default), so the same value always gets the same color, e.g. a hostname or a branch name.
+///
+/// # Example
+/// ```tera
+/// {{ hostname | color_hash(palette=["red", "green", "blue"]) }}
+/// ```
+///
+/// # Returns
+/// A closure that can be used with Tera's `register_filter`
+pub fn create_color_hash_filter(
+) -> impl Fn(&Value, &HashMap<String, Value>) -> Result<Value, TeraError> {
+    move |value: &Value, args: &HashMap<String, Value>| {
+        let text = tera::try_get_value!("color_hash", "value", String, value);
+
+        let palette = match args.get("palette") {
+            None => DEFAULT_PALETTE.to_vec(),
+            Some(Value::Array(colors)) => colors
+                .iter()
+                .map(|c| {
+                    c.as_str()
+                        .ok_or_else(|| TeraError::msg("palette colors must be strings"))
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            Some(_) => return Err(TeraError::msg("palette must be a list of colors")),
+        };
+        let key = match args.get("value") {
+            None => text.clone(),
+            Some(Value::String(s)) => s.clone(),
+            Some(other) => other.to_string(),
+        };
+        let Some(color) = pick(&palette, &key) else {
+            return Ok(Value::String(text));
+        };
+
+        let is_bg = args.get("is_bg").and_then(Value::as_bool).unwrap_or(false);
+        paint(&text, color, is_bg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fnv1a() {
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(fnv1a(b"foobar"), 0x8594_4171_f739_67e8);
+    }
+
+    #[test]
+    fn test_pick() {
+        let palette = ["red", "green", "blue"];
+        assert_eq!(pick(&palette, "server-1"), pick(&palette, "server-1"));
+        assert_eq!(
+            pick(&palette, "a"),
+            Some(palette[(0xaf63_dc4c_8601_ec8c_u64 % 3) as usize])
+        );
+        assert_eq!(pick(&[], "a"), None);
+    }
+}
@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+use tera::{Error as TeraError, Value};
+
+use crate::color_manager::{ColorManager, StyleFormat, StyleScope, TextAttributes};
+use crate::constants::{encode, frame};
+use crate::context::Context;
+use crate::context_manager::ContextManager;
+use crate::frame::{Align, Frame, FrameStyle};
+
+/// Create a frame filter closure for Tera
+///
+/// Wraps the text, which may span several lines (e.g. a `-e` message or a banner), in a frame
+/// drawn with box-drawing characters once the width is resolved:
+/// - `style`: `single` (the default), `double`, `rounded`, `heavy` or `ascii`
+/// - `title`: text set in the top border
+/// - `align`: `left` (the default), `center` or `right`, for the title and the content lines
+/// - `padding`: columns of spaces between the border and the content (1 by default)
+/// - `margin`: columns of spaces around the border (0 by default)
+/// - `border_color`: color of the border, the colors of the content being left untouched
+///
+/// # Example
+/// ```tera
+/// {{ m | color(name=c2) | frame(style="rounded", title="Build", border_color=c) }}
+/// ```
+///
+/// # Returns
+/// A closure that can be used with Tera's `register_filter`
+pub fn create_frame_filter() -> impl Fn(&Value, &HashMap<String, Value>) -> Result<Value, TeraError>
+{
+    move |value: &Value, args: &HashMap<String, Value>| {
+        let text = tera::try_get_value!("frame", "value", String, value);
+
+        let str_arg = |name: &str| match args.get(name) {
+            Some(Value::String(s)) => Ok(Some(s.as_str())),
+            Some(_) => Err(TeraError::msg(format!("{name} must be a string"))),
+            None => Ok(None),
+        };
+        let count_arg = |name: &str, default: usize| match args.get(name) {
+            Some(Value::Number(n)) => n
+                .as_u64()
+                .and_then(|n| usize::try_from(n).ok())
+                .ok_or_else(|| TeraError::msg(format!("{name} must be a positive number"))),
+            Some(Value::String(s)) => s
+                .trim()
+                .parse()
+                .map_err(|_| TeraError::msg(format!("{name} must be a positive number"))),
+            Some(_) => Err(TeraError::msg(format!("{name} must be a positive number"))),
+            None => Ok(default),
+        };
+        let parse_err = |e: crate::error::Error| TeraError::msg(e.to_string());
+
+        let settings = Frame {
+            style: str_arg("style")?
+                .map(str::parse::<FrameStyle>)
+                .transpose()
+                .map_err(parse_err)?
+                .unwrap_or_default(),
+            align: str_arg("align")?
+                .map(str::parse::<Align>)
+                .transpose()
+                .map_err(parse_err)?
+                .unwrap_or_default(),
+            padding: count_arg("padding", Frame::default().padding)?,
+            margin: count_arg("margin", Frame::default().margin)?,
+        };
+        let title = str_arg("title")?.unwrap_or_default();
+
+        let ctx = ContextManager::get()
+            .read()
+            .map_err(|e| TeraError::msg(e.to_string()))?;
+        let border = match str_arg("border_color")? {
+            Some(color) => border_sgr(&ctx, color)?,
+            None => String::new(),
+        };
+
+        // The frame is drawn by the text processor, which knows the width, so only its settings
+        // are stored (zero-width) before the title and the content
+        Ok(Value::String(format!(
+            "{}{}{sep}{border}{sep}{title}{sep}{text}{}",
+            frame::START,
+            encode(&settings.to_spec()),
+            frame::END,
+            sep = frame::SEP
+        )))
+    }
+}
+
+/// SGR sequence drawing the border in the given color, empty if nothing applies.
+fn border_sgr(ctx: &Context, color: &str) -> Result<String, TeraError> {
+    let style = StyleFormat {
+        fg_color: Some(
+            color
+                .trim_start_matches("raw:")
+                .trim_matches('"')
+                .to_string(),
+        ),
+        bg_color: None,
+        scope: StyleScope::FG,
+        attributes: TextAttributes::default(),
+        underline_color: None,
+    };
+    let codes =
+        ColorManager::try_sgr_codes(ctx, style).map_err(|e| TeraError::msg(e.to_string()))?;
+    Ok(if codes.is_empty() {
+        String::new()
+    } else {
+        format!("\x1b[{}m", codes.join(";"))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::decode;
+
+    #[test]
+    fn test_frame_filter() {
+        let filter = create_frame_filter();
+        let mut args = HashMap::new();
+        args.insert("style".to_string(), Value::String("rounded".to_string()));
+        args.insert("title".to_string(), Value::String("Build".to_string()));
+        args.insert("align".to_string(), Value::String("center".to_string()));
+        args.insert("padding".to_string(), Value::from(2));
+        args.insert("margin".to_string(), Value::String("1".to_string()));
+
+        let result = filter(&Value::String("a\nb".to_string()), &args).unwrap();
+        let result = result.as_str().unwrap();
+        let body = result
+            .strip_prefix(frame::START)
+            .and_then(|r| r.strip_suffix(frame::END))
+            .unwrap();
+        let sections: Vec<_> = body.split(frame::SEP).collect();
+        assert_eq!(decode(sections[0]), "rounded center 2 1");
+        assert_eq!(sections[1..], ["", "Build", "a\nb"]);
+
+        args.insert("style".to_string(), Value::String("dotted".to_string()));
+        assert!(filter(&Value::String("x".to_string()), &args).is_err());
+        args.remove("style");
+        args.insert("padding".to_string(), Value::from(-1));
+        assert!(filter(&Value::String("x".to_string()), &args).is_err());
+    }
+}
@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+use tera::{Error as TeraError, Value};
+
+use crate::color_manager::ColorManager;
+use crate::color_utils::color_to_true_rgb;
+use crate::constants::gradient;
+use crate::context::Context;
+use crate::context_manager::ContextManager;
+use crate::gradient::{Gradient, GradientMode};
+
+/// Create a gradient filter closure for Tera
+///
+/// The gradient filter fades the text from the `from` color to the `to` color, optionally going
+/// through the `via` colors, interpolating in the `rgb` (default) or `hsl` color space. Named
+/// colors use their CSS values (`red` is `#ff0000`).
+/// The colors are resolved right away but only applied after padding has been expanded, so
+/// that `"─" | pad | gradient(from="red", to="blue")` fades across the whole filler.
+///
+/// # Returns
+/// A closure that can be used with Tera's `register_filter`
+pub fn create_gradient_filter(
+) -> impl Fn(&Value, &HashMap<String, Value>) -> Result<Value, TeraError> {
+    move |value: &Value, args: &HashMap<String, Value>| {
+        let text = tera::try_get_value!("gradient", "value", String, value);
+
+        let color_arg = |name: &str| -> Result<String, TeraError> {
+            args.get(name)
+                .and_then(Value::as_str)
+                .map(|s| s.trim_start_matches("raw:").trim_matches('"').to_string())
+                .ok_or_else(|| TeraError::msg(format!("Missing {name} argument")))
+        };
+        let from = color_arg("from")?;
+        let to = color_arg("to")?;
+        let via = match args.get("via") {
+            None => Vec::new(),
+            Some(Value::String(s)) => vec![s.clone()],
+            Some(Value::Array(values)) => values
+                .iter()
+                .map(|v| {
+                    v.as_str()
+                        .map(str::to_string)
+                        .ok_or_else(|| TeraError::msg("via colors must be strings"))
+                })
+                .collect::<Result<_, _>>()?,
+            Some(_) => return Err(TeraError::msg("via must be a color or a list of colors")),
+        };
+        let mode = args
+            .get("mode")
+            .and_then(Value::as_str)
+            .map(str::parse::<GradientMode>)
+            .transpose()
+            .map_err(|e| TeraError::msg(e.to_string()))?
+            .unwrap_or_default();
+
+        let ctx = ContextManager::get()
+            .read()
+            .map_err(|e| TeraError::msg(e.to_string()))?;
+
+        let names = std::iter::once(from).chain(via).chain(std::iter::once(to));
+        let mut stops = Vec::new();
+        for name in names {
+            match resolve_stop(&ctx, &name)? {
+                Some(rgb) => stops.push(rgb),
+                // Unresolved colors leave the text unchanged, as the color filter does
+                None => return Ok(Value::String(text)),
+            }
+        }
+
+        let spec = Gradient { mode, stops }.to_spec();
+        Ok(Value::String(format!(
+            "{}{}{}{}{}",
+            gradient::START,
+            gradient::encode(&spec),
+            gradient::SEP,
+            text,
+            gradient::END
+        )))
+    }
+}
+
+/// Resolves a gradient stop to its RGB value, failing only in strict color mode. Basic ANSI
+/// names are taken as CSS colors, the terminal palette value of `red` being a dark red.
+fn resolve_stop(ctx: &Context, name: &str) -> Result<Option<(u8, u8, u8)>, TeraError> {
+    match ColorManager::resolve(ctx, name) {
+        Ok(color) => Ok(color.and_then(color_to_true_rgb)),
+        Err(e) if ColorManager::is_strict(ctx) => Err(TeraError::msg(e.to_string())),
+        Err(_) => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_named_stops() {
+        let mut ctx = Context::new();
+        ctx.insert("accent", "blue");
+        assert_eq!(resolve_stop(&ctx, "red").unwrap(), Some((255, 0, 0)));
+        assert_eq!(resolve_stop(&ctx, "accent").unwrap(), Some((0, 0, 255)));
+        assert_eq!(resolve_stop(&ctx, "FIXED(1)").unwrap(), Some((128, 0, 0)));
+        assert_eq!(resolve_stop(&ctx, "not_a_color").unwrap(), None);
+    }
+}
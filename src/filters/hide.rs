@@ -1,7 +1,9 @@
-use console::{measure_text_width, strip_ansi_codes};
 use std::collections::HashMap;
 use tera::{Error as TeraError, Value};
 
+use crate::ansi::visual_width;
+use crate::width::AmbiguousWidth;
+
 /// Type alias for a function that provides an optional string value
 pub type ValueProviderFn = fn(&str) -> std::result::Result<Option<String>, TeraError>;
 
@@ -41,10 +43,15 @@ pub fn create_hide_filter_with(
             .is_some_and(|v| matches!(v.trim().to_lowercase().as_str(), "true" | "1"));
 
         if is_active {
-            // Strip ANSI codes and measure the visual width
-            let stripped_text = strip_ansi_codes(&text);
-            let visual_width = measure_text_width(&stripped_text);
-            let spaces = " ".repeat(visual_width);
+            // Measure the visual width, ignoring escape sequences such as colors and hyperlinks
+            let ambiguous = match get_value("ambiguous_width")? {
+                Some(value) => Some(value),
+                None => get_value("defaults.ambiguous_width")?,
+            }
+            .map_or_else(AmbiguousWidth::default, |value| {
+                AmbiguousWidth::parse(&value)
+            });
+            let spaces = " ".repeat(visual_width(&text, ambiguous));
             Ok(Value::String(spaces))
         } else {
             Ok(Value::String(text))
@@ -97,6 +104,20 @@ mod tests {
         assert_eq!(result.as_str().unwrap(), "        "); // 8 spaces (5 for "Hello" + 2 for 🦀 + 1 for space)
     }
 
+    #[test]
+    fn test_hide_filter_with_ambiguous_width() {
+        let filter = create_hide_filter_with(Some(|key| {
+            Ok(match key {
+                "hide" => Some("true".to_string()),
+                "defaults.ambiguous_width" => Some("2".to_string()),
+                _ => None,
+            })
+        }));
+        let value = Value::String("±1…".to_string());
+        let result = filter(&value, &HashMap::new()).unwrap();
+        assert_eq!(result.as_str().unwrap(), "     ");
+    }
+
     #[test]
     fn test_hide_filter_with_ansi() {
         let filter = create_hide_filter_with(Some(|key| {
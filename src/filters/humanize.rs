@@ -0,0 +1,603 @@
+//! Filters turning raw values into human-friendly text: sizes, durations, counts, percentages
+//! and dates. Context values set with `-s` are strings, so numbers are accepted in both forms.
+
+use crate::utils::{self, ByteUnits};
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use std::collections::HashMap;
+use tera::{Error as TeraError, Value};
+
+/// Reads the value of a filter as a number, parsing strings.
+fn number_value(filter: &str, value: &Value) -> Result<f64, TeraError> {
+    let number = match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse::<f64>().ok(),
+        _ => None,
+    };
+    number
+        .filter(|n| n.is_finite())
+        .ok_or_else(|| TeraError::msg(format!("Filter `{filter}` expected a number, got {value}")))
+}
+
+/// Reads an optional argument as a non-negative integer, accepting numbers or strings.
+fn count_arg(args: &HashMap<String, Value>, name: &str) -> Result<Option<usize>, TeraError> {
+    args.get(name)
+        .map(|v| match v {
+            Value::Number(n) => n.as_u64().and_then(|n| usize::try_from(n).ok()),
+            Value::String(s) => s.trim().parse::<usize>().ok(),
+            _ => None,
+        })
+        .map(|n| n.ok_or_else(|| TeraError::msg(format!("{name} must be a positive integer"))))
+        .transpose()
+}
+
+/// Reads an optional boolean argument, accepting booleans or strings.
+fn flag_arg(args: &HashMap<String, Value>, name: &str, default: bool) -> bool {
+    match args.get(name) {
+        Some(Value::Bool(b)) => *b,
+        Some(Value::String(s)) => matches!(s.trim(), "true" | "1" | "yes" | "on"),
+        _ => default,
+    }
+}
+
+/// Reads an optional string argument.
+fn str_arg<'a>(args: &'a HashMap<String, Value>, name: &str) -> Result<Option<&'a str>, TeraError> {
+    args.get(name)
+        .map(|v| {
+            v.as_str()
+                .ok_or_else(|| TeraError::msg(format!("{name} must be a string")))
+        })
+        .transpose()
+}
+
+/// Formats a duration in milliseconds with its `parts` largest units, e.g. `3m 12s`.
+/// Durations under a second are shown in milliseconds.
+fn human_duration(ms: f64, parts: usize) -> String {
+    const UNITS: [(&str, u128); 4] = [("d", 86_400), ("h", 3_600), ("m", 60), ("s", 1)];
+
+    let sign = if ms <= -1.0 { "-" } else { "" };
+    // Saturating cast, durations being finite
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let ms = ms.abs() as u128;
+    if ms < 1000 {
+        return format!("{sign}{ms}ms");
+    }
+
+    let mut seconds = ms / 1000;
+    let amounts: Vec<(&str, u128)> = UNITS
+        .iter()
+        .map(|(unit, size)| {
+            let amount = seconds / size;
+            seconds %= size;
+            (*unit, amount)
+        })
+        .collect();
+    let first = amounts.iter().position(|(_, n)| *n > 0).unwrap_or(0);
+    let text = amounts[first..]
+        .iter()
+        .take(parts.max(1))
+        .filter(|(_, n)| *n > 0)
+        .map(|(unit, n)| format!("{n}{unit}"))
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!("{sign}{text}")
+}
+
+/// Inserts a separator between the groups of three digits of the integer part of a number.
+fn group_digits(number: &str, separator: &str) -> String {
+    let (sign, digits) = match number.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", number),
+    };
+    let (int, frac) = match digits.split_once('.') {
+        Some((int, frac)) => (int, Some(frac)),
+        None => (digits, None),
+    };
+    let mut grouped = String::with_capacity(int.len() * 2);
+    for (i, c) in int.chars().enumerate() {
+        if i > 0 && (int.len() - i) % 3 == 0 {
+            grouped.push_str(separator);
+        }
+        grouped.push(c);
+    }
+    match frac {
+        Some(frac) => format!("{sign}{grouped}.{frac}"),
+        None => format!("{sign}{grouped}"),
+    }
+}
+
+/// Describes a time difference in seconds relative to now, e.g. `5 minutes ago` or
+/// `in 2 days`.
+fn relative_time(seconds: i64) -> String {
+    const UNITS: [(&str, u64); 6] = [
+        ("year", 365 * 86_400),
+        ("month", 30 * 86_400),
+        ("day", 86_400),
+        ("hour", 3_600),
+        ("minute", 60),
+        ("second", 1),
+    ];
+
+    let elapsed = seconds.unsigned_abs();
+    if elapsed < 10 {
+        return "just now".to_string();
+    }
+    let (unit, size) = UNITS
+        .iter()
+        .find(|(_, size)| elapsed >= *size)
+        .copied()
+        .unwrap_or(("second", 1));
+    let amount = elapsed / size;
+    let plural = if amount == 1 { "" } else { "s" };
+    if seconds >= 0 {
+        format!("{amount} {unit}{plural} ago")
+    } else {
+        format!("in {amount} {unit}{plural}")
+    }
+}
+
+/// Reads a point in time: a Unix timestamp in seconds, `now`, an RFC 3339 date or a local
+/// `YYYY-MM-DD[ HH:MM[:SS]]` date.
+fn timestamp_value(filter: &str, value: &Value) -> Result<DateTime<Utc>, TeraError> {
+    let invalid = || {
+        TeraError::msg(format!(
+            "Filter `{filter}` expected a Unix timestamp or a date, got {value}"
+        ))
+    };
+    let from_seconds = |seconds: f64| {
+        // Sub-nanosecond precision is irrelevant here
+        #[allow(clippy::cast_possible_truncation)]
+        let nanos = (seconds * 1e9).round() as i64;
+        DateTime::from_timestamp_nanos(nanos)
+    };
+    let local = |naive: NaiveDateTime| {
+        Local
+            .from_local_datetime(&naive)
+            .earliest()
+            .map(|dt| dt.with_timezone(&Utc))
+    };
+
+    match value {
+        Value::Number(n) => n.as_f64().map(from_seconds).ok_or_else(invalid),
+        Value::String(s) => {
+            let s = s.trim();
+            if s.eq_ignore_ascii_case("now") {
+                return Ok(Utc::now());
+            }
+            if let Ok(seconds) = s.parse::<f64>() {
+                return Ok(from_seconds(seconds));
+            }
+            if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+                return Ok(dt.with_timezone(&Utc));
+            }
+            ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M"]
+                .iter()
+                .find_map(|format| NaiveDateTime::parse_from_str(s, format).ok())
+                .or_else(|| {
+                    NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                        .ok()
+                        .and_then(|date| date.and_hms_opt(0, 0, 0))
+                })
+                .and_then(local)
+                .ok_or_else(invalid)
+        }
+        _ => Err(invalid()),
+    }
+}
+
+/// A timezone dates are shown in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Timezone {
+    Local,
+    Fixed(FixedOffset),
+}
+
+/// Parses `local`, `utc` (or `z` / `gmt`) and fixed offsets like `+02:00`, `-0530`, `+2` or
+/// `UTC+1`.
+pub(crate) fn parse_timezone(tz: &str) -> Result<Timezone, TeraError> {
+    let invalid = || {
+        TeraError::msg(format!(
+            "invalid timezone \"{tz}\" (expected local, utc or an offset like +02:00)"
+        ))
+    };
+    let name = tz.trim().to_lowercase();
+    match name.as_str() {
+        "local" => return Ok(Timezone::Local),
+        "utc" | "z" | "gmt" => return Ok(Timezone::Fixed(FixedOffset::east_opt(0).unwrap())),
+        _ => {}
+    }
+
+    let offset = name
+        .strip_prefix("utc")
+        .or_else(|| name.strip_prefix("gmt"))
+        .unwrap_or(&name);
+    let (sign, rest) = match offset.chars().next() {
+        Some('+') => (1, &offset[1..]),
+        Some('-') => (-1, &offset[1..]),
+        _ => return Err(invalid()),
+    };
+    let (hours, minutes) = match rest.split_once(':') {
+        Some((hours, minutes)) => (hours, minutes),
+        None if rest.len() == 4 => rest.split_at(2),
+        None => (rest, "0"),
+    };
+    let hours = hours.parse::<i32>().map_err(|_| invalid())?;
+    let minutes = minutes.parse::<i32>().map_err(|_| invalid())?;
+    if hours > 14 || minutes > 59 {
+        return Err(invalid());
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+        .map(Timezone::Fixed)
+        .ok_or_else(invalid)
+}
+
+/// Formats a date with a strftime format, rejecting invalid formats.
+pub(crate) fn format_time(
+    dt: &DateTime<Utc>,
+    format: &str,
+    tz: Timezone,
+) -> Result<String, TeraError> {
+    match tz {
+        Timezone::Local => utils::try_time_format(&dt.with_timezone(&Local), format),
+        Timezone::Fixed(offset) => utils::try_time_format(&dt.with_timezone(&offset), format),
+    }
+    .ok_or_else(|| TeraError::msg(format!("invalid time format \"{format}\"")))
+}
+
+/// Create a bytes filter closure for Tera
+///
+/// Formats a number of bytes with the largest unit it reaches, e.g. `1.4 MB`. Units are powers
+/// of 1024, or of 1000 with `si=true`, and `precision` (default `1`) sets the number of
+/// decimals.
+///
+/// # Example
+/// ```tera
+/// {{ size | bytes(precision=2) }}
+/// ```
+///
+/// # Returns
+/// A closure that can be used with Tera's `register_filter`
+pub fn create_bytes_filter() -> impl Fn(&Value, &HashMap<String, Value>) -> Result<Value, TeraError>
+{
+    move |value: &Value, args: &HashMap<String, Value>| {
+        let bytes = number_value("bytes", value)?;
+        let precision = count_arg(args, "precision")?.unwrap_or(1);
+        let units = if flag_arg(args, "si", false) {
+            ByteUnits::Si
+        } else {
+            ByteUnits::Binary
+        };
+        Ok(Value::String(utils::format_bytes_with(
+            bytes,
+            units,
+            Some(precision),
+        )))
+    }
+}
+
+/// Create a duration filter closure for Tera
+///
+/// Formats a duration in seconds (or in milliseconds with `unit="ms"`) with its largest units,
+/// e.g. `3m 12s` or `1d 4h`. `parts` (default `2`) sets the number of units shown; durations
+/// under a second are shown in milliseconds.
+///
+/// # Example
+/// ```tera
+/// took {{ elapsed_ms | duration(unit="ms", parts=3) }}
+/// ```
+///
+/// # Returns
+/// A closure that can be used with Tera's `register_filter`
+pub fn create_duration_filter(
+) -> impl Fn(&Value, &HashMap<String, Value>) -> Result<Value, TeraError> {
+    move |value: &Value, args: &HashMap<String, Value>| {
+        let amount = number_value("duration", value)?;
+        let ms = match str_arg(args, "unit")?.unwrap_or("s") {
+            "s" | "sec" | "seconds" => amount * 1000.0,
+            "ms" | "milliseconds" => amount,
+            unit => {
+                return Err(TeraError::msg(format!(
+                    "invalid duration unit \"{unit}\" (expected s or ms)"
+                )))
+            }
+        };
+        let parts = count_arg(args, "parts")?.unwrap_or(2);
+        Ok(Value::String(human_duration(ms, parts)))
+    }
+}
+
+/// Create a number filter closure for Tera
+///
+/// Formats a number with its thousands grouped (`grouping`, default `true`) by `separator`
+/// (default `,`), and `decimals` digits after the decimal point when given.
+///
+/// # Example
+/// ```tera
+/// {{ count | number }} tests, {{ ratio | number(decimals=2) }}
+/// ```
+///
+/// # Returns
+/// A closure that can be used with Tera's `register_filter`
+pub fn create_number_filter() -> impl Fn(&Value, &HashMap<String, Value>) -> Result<Value, TeraError>
+{
+    move |value: &Value, args: &HashMap<String, Value>| {
+        let number = number_value("number", value)?;
+        let text = match count_arg(args, "decimals")? {
+            Some(decimals) => format!("{number:.decimals$}"),
+            None => match value {
+                Value::Number(n) if !n.is_f64() => n.to_string(),
+                Value::String(s) if s.trim().parse::<i128>().is_ok() => {
+                    s.trim().trim_start_matches('+').to_string()
+                }
+                _ => number.to_string(),
+            },
+        };
+        if !flag_arg(args, "grouping", true) {
+            return Ok(Value::String(text));
+        }
+        let separator = str_arg(args, "separator")?.unwrap_or(",");
+        Ok(Value::String(group_digits(&text, separator)))
+    }
+}
+
+/// Create a percent filter closure for Tera
+///
+/// Formats a ratio as a percentage with `decimals` digits (default `0`), e.g. `0.423` as
+/// `42%`. With `total`, the value is a part of the total instead.
+///
+/// # Example
+/// ```tera
+/// {{ passed | percent(total=count, decimals=1) }} passed
+/// ```
+///
+/// # Returns
+/// A closure that can be used with Tera's `register_filter`
+pub fn create_percent_filter(
+) -> impl Fn(&Value, &HashMap<String, Value>) -> Result<Value, TeraError> {
+    move |value: &Value, args: &HashMap<String, Value>| {
+        let mut ratio = number_value("percent", value)?;
+        if let Some(total) = args.get("total") {
+            let total = number_value("percent", total)?;
+            if total == 0.0 {
+                return Err(TeraError::msg("total must not be zero"));
+            }
+            ratio /= total;
+        }
+        let decimals = count_arg(args, "decimals")?.unwrap_or(0);
+        let percent = format!("{:.decimals$}", ratio * 100.0);
+        // Avoid printing "-0%" for tiny negative values
+        let percent = match percent.parse::<f64>() {
+            Ok(0.0) => percent.trim_start_matches('-'),
+            _ => &percent,
+        };
+        Ok(Value::String(format!("{percent}%")))
+    }
+}
+
+/// Create an ago filter closure for Tera
+///
+/// Describes a date (a Unix timestamp in seconds, an RFC 3339 date or a local
+/// `YYYY-MM-DD HH:MM:SS` date) relative to now, e.g. `5 minutes ago` or `in 2 days`.
+///
+/// # Example
+/// ```tera
+/// deployed {{ deployed_at | ago }}
+/// ```
+///
+/// # Returns
+/// A closure that can be used with Tera's `register_filter`
+pub fn create_ago_filter() -> impl Fn(&Value, &HashMap<String, Value>) -> Result<Value, TeraError> {
+    move |value: &Value, _: &HashMap<String, Value>| {
+        let dt = timestamp_value("ago", value)?;
+        let seconds = Utc::now().signed_duration_since(dt).num_seconds();
+        Ok(Value::String(relative_time(seconds)))
+    }
+}
+
+/// Create a strftime filter closure for Tera
+///
+/// Formats a date (see the `ago` filter, `now` included) with a strftime `format` (defaults to
+/// `%Y-%m-%d %H:%M:%S`) in the timezone `tz`: `local` (the default), `utc` or a fixed offset
+/// like `+02:00`.
+///
+/// # Example
+/// ```tera
+/// {{ "now" | strftime(format="%H:%M", tz="utc") }}
+/// ```
+///
+/// # Returns
+/// A closure that can be used with Tera's `register_filter`
+pub fn create_strftime_filter(
+) -> impl Fn(&Value, &HashMap<String, Value>) -> Result<Value, TeraError> {
+    move |value: &Value, args: &HashMap<String, Value>| {
+        let dt = timestamp_value("strftime", value)?;
+        let format = str_arg(args, "format")?.unwrap_or("%Y-%m-%d %H:%M:%S");
+        let tz = parse_timezone(str_arg(args, "tz")?.unwrap_or("local"))?;
+        format_time(&dt, format, tz).map(Value::String)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(pairs: &[(&str, Value)]) -> HashMap<String, Value> {
+        pairs
+            .iter()
+            .map(|(k, v)| ((*k).to_string(), v.clone()))
+            .collect()
+    }
+
+    fn apply(
+        filter: impl Fn(&Value, &HashMap<String, Value>) -> Result<Value, TeraError>,
+        value: Value,
+        pairs: &[(&str, Value)],
+    ) -> String {
+        filter(&value, &args(pairs))
+            .unwrap()
+            .as_str()
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn test_bytes_filter() {
+        let bytes = create_bytes_filter;
+        assert_eq!(apply(bytes(), Value::from(0), &[]), "0 B");
+        assert_eq!(apply(bytes(), Value::from(1023), &[]), "1023 B");
+        assert_eq!(apply(bytes(), Value::from(1_468_006), &[]), "1.4 MB");
+        assert_eq!(
+            apply(bytes(), Value::from("1500"), &[("si", Value::from(true))]),
+            "1.5 kB"
+        );
+        assert_eq!(
+            apply(
+                bytes(),
+                Value::from(-2048),
+                &[("precision", Value::from(0))]
+            ),
+            "-2 KB"
+        );
+        assert_eq!(apply(bytes(), Value::from(1u64 << 62), &[]), "4096.0 PB");
+        assert!(bytes()(&Value::from("big"), &HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_duration_filter() {
+        let duration = create_duration_filter;
+        assert_eq!(apply(duration(), Value::from(192), &[]), "3m 12s");
+        assert_eq!(apply(duration(), Value::from(0), &[]), "0ms");
+        assert_eq!(apply(duration(), Value::from(0.45), &[]), "450ms");
+        assert_eq!(apply(duration(), Value::from(3600), &[]), "1h");
+        assert_eq!(apply(duration(), Value::from(3605), &[]), "1h");
+        assert_eq!(
+            apply(duration(), Value::from(3605), &[("parts", Value::from(3))]),
+            "1h 5s"
+        );
+        assert_eq!(
+            apply(
+                duration(),
+                Value::from("90061000"),
+                &[("unit", Value::from("ms"))]
+            ),
+            "1d 1h"
+        );
+        assert_eq!(apply(duration(), Value::from(-61), &[]), "-1m 1s");
+        assert!(duration()(&Value::from(1), &args(&[("unit", Value::from("h"))])).is_err());
+    }
+
+    #[test]
+    fn test_number_filter() {
+        let number = create_number_filter;
+        assert_eq!(apply(number(), Value::from(1_234_567), &[]), "1,234,567");
+        assert_eq!(apply(number(), Value::from(999), &[]), "999");
+        assert_eq!(apply(number(), Value::from("-1000"), &[]), "-1,000");
+        assert_eq!(apply(number(), Value::from(1234.5), &[]), "1,234.5");
+        assert_eq!(
+            apply(
+                number(),
+                Value::from(1234.567),
+                &[
+                    ("decimals", Value::from(2)),
+                    ("separator", Value::from(" "))
+                ]
+            ),
+            "1 234.57"
+        );
+        assert_eq!(
+            apply(
+                number(),
+                Value::from(1234),
+                &[("grouping", Value::from(false))]
+            ),
+            "1234"
+        );
+    }
+
+    #[test]
+    fn test_percent_filter() {
+        let percent = create_percent_filter;
+        assert_eq!(apply(percent(), Value::from(0.423), &[]), "42%");
+        assert_eq!(
+            apply(
+                percent(),
+                Value::from("0.4236"),
+                &[("decimals", Value::from(1))]
+            ),
+            "42.4%"
+        );
+        assert_eq!(
+            apply(percent(), Value::from(3), &[("total", Value::from(4))]),
+            "75%"
+        );
+        assert_eq!(apply(percent(), Value::from(-0.001), &[]), "0%");
+        assert!(percent()(&Value::from(1), &args(&[("total", Value::from(0))])).is_err());
+    }
+
+    #[test]
+    fn test_relative_time() {
+        assert_eq!(relative_time(0), "just now");
+        assert_eq!(relative_time(-5), "just now");
+        assert_eq!(relative_time(42), "42 seconds ago");
+        assert_eq!(relative_time(60), "1 minute ago");
+        assert_eq!(relative_time(300), "5 minutes ago");
+        assert_eq!(relative_time(2 * 86_400 + 10), "2 days ago");
+        assert_eq!(relative_time(-3 * 3600), "in 3 hours");
+        assert_eq!(relative_time(400 * 86_400), "1 year ago");
+    }
+
+    #[test]
+    fn test_ago_filter() {
+        let ago = create_ago_filter();
+        let five_minutes_ago = Utc::now().timestamp() - 300;
+        let result = ago(&Value::from(five_minutes_ago), &HashMap::new()).unwrap();
+        assert_eq!(result.as_str().unwrap(), "5 minutes ago");
+        let result = ago(&Value::from("now"), &HashMap::new()).unwrap();
+        assert_eq!(result.as_str().unwrap(), "just now");
+        assert!(ago(&Value::from("yesterday"), &HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_strftime_filter() {
+        let strftime = create_strftime_filter;
+        let format = ("format", Value::from("%Y-%m-%d %H:%M %z"));
+        assert_eq!(
+            apply(
+                strftime(),
+                Value::from(0),
+                &[format.clone(), ("tz", Value::from("utc"))]
+            ),
+            "1970-01-01 00:00 +0000"
+        );
+        assert_eq!(
+            apply(
+                strftime(),
+                Value::from("2024-03-01T12:30:00Z"),
+                &[format.clone(), ("tz", Value::from("UTC+05:30"))]
+            ),
+            "2024-03-01 18:00 +0530"
+        );
+        assert_eq!(
+            apply(
+                strftime(),
+                Value::from("2024-03-01T12:30:00+01:00"),
+                &[format.clone(), ("tz", Value::from("-0800"))]
+            ),
+            "2024-03-01 03:30 -0800"
+        );
+        let invalid_tz = args(&[("tz", Value::from("Mars/Olympus"))]);
+        assert!(strftime()(&Value::from(0), &invalid_tz).is_err());
+        let invalid_format = args(&[("format", Value::from("%Q"))]);
+        assert!(strftime()(&Value::from(0), &invalid_format).is_err());
+    }
+
+    #[test]
+    fn test_parse_timezone() {
+        assert_eq!(parse_timezone("local").unwrap(), Timezone::Local);
+        let offset = |seconds| Timezone::Fixed(FixedOffset::east_opt(seconds).unwrap());
+        assert_eq!(parse_timezone("Z").unwrap(), offset(0));
+        assert_eq!(parse_timezone("+2").unwrap(), offset(7200));
+        assert_eq!(parse_timezone("gmt-03:30").unwrap(), offset(-12_600));
+        assert!(parse_timezone("+15").is_err());
+        assert!(parse_timezone("2").is_err());
+    }
+}
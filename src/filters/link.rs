@@ -0,0 +1,46 @@
+use std::collections::HashMap;
+use tera::{Error as TeraError, Value};
+
+use crate::ansi::hyperlink;
+use crate::color_depth::ColorDepth;
+use crate::context_manager::ContextManager;
+use crate::string_utils::is_visually_empty;
+
+/// Create a link filter closure for Tera
+///
+/// Turns the text into an OSC 8 terminal hyperlink pointing to `url`, or to the `link` value of
+/// the context (`--link`) when no URL is given. The text is left unchanged without a URL, when
+/// it is visually empty or when colors are disabled (plain output).
+///
+/// # Example
+/// ```tera
+/// {{ m | link(url="https://ci.example.com/jobs/42") | color(name=c2) }}
+/// ```
+///
+/// # Returns
+/// A closure that can be used with Tera's `register_filter`
+pub fn create_link_filter() -> impl Fn(&Value, &HashMap<String, Value>) -> Result<Value, TeraError>
+{
+    move |value: &Value, args: &HashMap<String, Value>| {
+        let text = tera::try_get_value!("link", "value", String, value);
+
+        let ctx = ContextManager::get()
+            .read()
+            .map_err(|e| TeraError::msg(e.to_string()))?;
+        let url = match args.get("url") {
+            Some(url) => url
+                .as_str()
+                .ok_or_else(|| TeraError::msg("url must be a string"))?,
+            None => ctx.get("link").unwrap_or_default(),
+        }
+        .trim();
+
+        if url.is_empty()
+            || is_visually_empty(&text)
+            || ColorDepth::from_context(&ctx) == ColorDepth::NoColor
+        {
+            return Ok(Value::String(text));
+        }
+        Ok(Value::String(hyperlink(url, &text)))
+    }
+}
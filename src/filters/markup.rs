@@ -1,4 +1,4 @@
-//! Rich terminal markup for title text (`display` feature).
+//! Rich terminal markup for title text.
 //!
 //! Grammar (processed after CLI `-e` escapes, if any):
 //! - Line starts with `# ` (after leading whitespace): **h1** — bold + underline for the rest of the line.
@@ -6,105 +6,395 @@
 //! - `**text**` — bold.
 //! - `__text__` — underline.
 //! - `//text//` — italic.
-//! - `\` before `#`, `*`, `_`, or `/` removes special meaning for the following character.
+//! - `~~text~~` — strikethrough.
+//! - `` `code` `` — code span, styled with the `markup_code` color value (the theme `string`
+//!   color by default, dim text without a theme). Its content is not parsed.
+//! - `[spec]text[/]` — any color value or spec known to the color manager (`[red]`,
+//!   `[bold fg=#ff8800 bg=navy]`, `[accent]`). `[/]` (or `[/spec]`) closes the innermost tag,
+//!   and brackets not holding a valid spec are kept as is (e.g. `[WIP]`).
+//! - `[label](url)` — OSC 8 hyperlink, the label may hold markup.
+//! - `:name:` — emoji shortcode (e.g. `:rocket:`), unknown names are kept as is.
+//! - `\` before any of `` # * _ / ~ ` [ ] : `` removes special meaning for the following character.
+//!
+//! Styles nest : closing one restores the styles still open around it.
 
-use nu_ansi_term::Style;
 use std::collections::HashMap;
 use tera::{Error as TeraError, Value};
 
-fn find_unescaped_pair(haystack: &str, a: u8, b: u8) -> Option<usize> {
-    let bytes = haystack.as_bytes();
-    let mut i = 0;
-    while i + 1 < bytes.len() {
-        if bytes[i] == b'\\' {
-            i += 2;
-            continue;
-        }
-        if bytes[i] == a && bytes[i + 1] == b {
-            return Some(i);
-        }
-        i += 1;
+use crate::ansi::{hyperlink, strip_escapes};
+use crate::color_depth::ColorDepth;
+use crate::color_manager::{
+    split_spec, ColorManager, StyleFormat, StyleScope, TextAttributes, UnderlineStyle,
+};
+use crate::context::Context;
+use crate::context_manager::ContextManager;
+
+const ESCAPABLE: &[char] = &['#', '*', '_', '/', '~', '`', '[', ']', ':', '\\'];
+
+/// Paired delimiters, see [`delimiter_attributes`].
+const DELIMITERS: [&str; 4] = ["**", "__", "//", "~~"];
+
+fn delimiter_attributes(delimiter: &str) -> TextAttributes {
+    let mut attributes = TextAttributes::default();
+    match delimiter {
+        "**" => attributes.bold = true,
+        "__" => attributes.underline = Some(UnderlineStyle::Single),
+        "//" => attributes.italic = true,
+        _ => attributes.strike = true,
     }
-    None
+    attributes
 }
 
-fn apply_pair_delimited<F>(s: &str, open: u8, close: u8, style_fn: F) -> String
-where
-    F: Fn(&str) -> String,
-{
-    let mut out = String::with_capacity(s.len() + 16);
-    let mut rest = s;
-    while !rest.is_empty() {
-        if let Some(pos) = find_unescaped_pair(rest, open, close) {
-            out.push_str(&rest[..pos]);
-            rest = &rest[pos + 2..];
-            if let Some(end) = find_unescaped_pair(rest, open, close) {
-                let inner = &rest[..end];
-                out.push_str(&style_fn(inner));
-                rest = &rest[end + 2..];
-            } else {
-                out.push(open as char);
-                out.push(close as char);
-                out.push_str(rest);
-                break;
-            }
-        } else {
-            out.push_str(rest);
-            break;
-        }
-    }
-    out
+/// Supported `:name:` shortcodes.
+const EMOJIS: &[(&str, &str)] = &[
+    ("+1", "👍"),
+    ("-1", "👎"),
+    ("arrow_down", "⬇️"),
+    ("arrow_left", "⬅️"),
+    ("arrow_right", "➡️"),
+    ("arrow_up", "⬆️"),
+    ("beer", "🍺"),
+    ("bell", "🔔"),
+    ("boom", "💥"),
+    ("bug", "🐛"),
+    ("bulb", "💡"),
+    ("calendar", "📅"),
+    ("check", "✔️"),
+    ("clock", "🕒"),
+    ("cloud", "☁️"),
+    ("coffee", "☕"),
+    ("computer", "💻"),
+    ("construction", "🚧"),
+    ("crab", "🦀"),
+    ("eyes", "👀"),
+    ("fire", "🔥"),
+    ("folder", "📁"),
+    ("gear", "⚙️"),
+    ("ghost", "👻"),
+    ("globe", "🌐"),
+    ("green_circle", "🟢"),
+    ("hammer", "🔨"),
+    ("heart", "❤️"),
+    ("hourglass", "⌛"),
+    ("house", "🏠"),
+    ("info", "ℹ️"),
+    ("key", "🔑"),
+    ("link", "🔗"),
+    ("lock", "🔒"),
+    ("memo", "📝"),
+    ("no_entry", "⛔"),
+    ("package", "📦"),
+    ("penguin", "🐧"),
+    ("pushpin", "📌"),
+    ("question", "❓"),
+    ("recycle", "♻️"),
+    ("red_circle", "🔴"),
+    ("robot", "🤖"),
+    ("rocket", "🚀"),
+    ("rotating_light", "🚨"),
+    ("skull", "💀"),
+    ("smile", "😄"),
+    ("snake", "🐍"),
+    ("snowflake", "❄️"),
+    ("sparkles", "✨"),
+    ("star", "⭐"),
+    ("sunny", "☀️"),
+    ("tada", "🎉"),
+    ("thinking", "🤔"),
+    ("thumbsdown", "👎"),
+    ("thumbsup", "👍"),
+    ("warning", "⚠️"),
+    ("whale", "🐳"),
+    ("white_check_mark", "✅"),
+    ("wink", "😉"),
+    ("wrench", "🔧"),
+    ("x", "❌"),
+    ("yellow_circle", "🟡"),
+    ("zap", "⚡"),
+];
+
+fn emoji(name: &str) -> Option<&'static str> {
+    EMOJIS.iter().find(|(n, _)| *n == name).map(|(_, e)| *e)
 }
 
-fn style_bold(chunk: &str) -> String {
-    Style::new().bold().paint(chunk).to_string()
+/// What opened a style still in effect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Opener {
+    Heading,
+    Delimiter(&'static str),
+    Tag,
 }
 
-fn style_underline(chunk: &str) -> String {
-    Style::new().underline().paint(chunk).to_string()
+struct Frame {
+    opener: Opener,
+    codes: Vec<String>,
 }
 
-fn style_italic(chunk: &str) -> String {
-    Style::new().italic().paint(chunk).to_string()
+/// Finds the first occurrence of `pattern` not preceded by a `\`.
+fn find_unescaped(haystack: &str, pattern: &str) -> Option<usize> {
+    let mut i = 0;
+    while i < haystack.len() {
+        let rest = &haystack[i..];
+        if let Some(escaped) = rest.strip_prefix('\\') {
+            i += 1 + escaped.chars().next().map_or(0, char::len_utf8);
+        } else if rest.starts_with(pattern) {
+            return Some(i);
+        } else {
+            i += rest.chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    None
 }
 
-fn style_h1(chunk: &str) -> String {
-    Style::new().bold().underline().paint(chunk).to_string()
+/// Turns the `fg=` / `bg=` keys of a tag into a color spec (`bold fg=red bg=blue` becomes
+/// `bold red on blue`).
+fn tag_spec(tag: &str) -> String {
+    split_spec(tag)
+        .into_iter()
+        .map(|token| match token.split_once('=') {
+            Some(("fg", color)) => color.to_string(),
+            Some(("bg", color)) => format!("on {color}"),
+            _ => token.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
 }
 
-fn style_h2(chunk: &str) -> String {
-    Style::new().bold().paint(chunk).to_string()
+/// Splits `[label](url)` at the start of the text into its label, URL and total length.
+fn parse_link(text: &str) -> Option<(&str, &str, usize)> {
+    let label_end = find_unescaped(text, "]")?;
+    let label = &text[1..label_end];
+    let rest = text[label_end + 1..].strip_prefix('(')?;
+    let url_len = rest.find(')')?;
+    let url = &rest[..url_len];
+    if url.is_empty() || url.contains(char::is_whitespace) {
+        return None;
+    }
+    Some((label, url, label_end + 2 + url_len + 1))
 }
 
-fn apply_inline_markup(line: &str) -> String {
-    let s = apply_pair_delimited(line, b'*', b'*', style_bold);
-    let s = apply_pair_delimited(&s, b'_', b'_', style_underline);
-    apply_pair_delimited(&s, b'/', b'/', style_italic)
+struct MarkupRenderer<'a> {
+    ctx: &'a Context,
+    colors: bool,
+    stack: Vec<Frame>,
+    out: String,
 }
 
-/// Applies heading prefixes and inline markup line-by-line (preserves `\n`).
-pub fn apply_rich_markup(input: &str) -> String {
-    let mut out = String::with_capacity(input.len() + 32);
-    for line in input.split_inclusive('\n') {
-        let (body, nl) = line.strip_suffix('\n').map_or((line, ""), |b| (b, "\n"));
-
-        let trimmed = body.trim_start();
-        let prefix_len = body.len() - trimmed.len();
-
-        let styled_body = if let Some(rest) = trimmed.strip_prefix("## ") {
-            let prefix = &body[..prefix_len];
-            format!("{}{}", prefix, style_h2(&apply_inline_markup(rest)))
-        } else if let Some(rest) = trimmed.strip_prefix("# ") {
-            let prefix = &body[..prefix_len];
-            format!("{}{}", prefix, style_h1(&apply_inline_markup(rest)))
-        } else {
-            apply_inline_markup(body)
+impl<'a> MarkupRenderer<'a> {
+    fn new(ctx: &'a Context) -> Self {
+        MarkupRenderer {
+            ctx,
+            colors: ColorDepth::from_context(ctx) != ColorDepth::NoColor,
+            stack: Vec::new(),
+            out: String::new(),
+        }
+    }
+
+    fn codes(&self, spec: Option<String>, attributes: TextAttributes) -> Vec<String> {
+        ColorManager::sgr_codes(
+            self.ctx,
+            StyleFormat {
+                fg_color: spec,
+                bg_color: None,
+                scope: StyleScope::FG,
+                attributes,
+                underline_color: None,
+            },
+        )
+    }
+
+    fn open(&mut self, opener: Opener, codes: Vec<String>) {
+        if !codes.is_empty() {
+            self.out.push_str(&format!("\x1b[{}m", codes.join(";")));
+        }
+        self.stack.push(Frame { opener, codes });
+    }
+
+    /// Closes the innermost style opened by `opener`. Every style opened is closed by its own
+    /// reset, the `AnsiFormatter` restoring the styles around it: the styles opened after this
+    /// one are closed too, then opened again.
+    fn close(&mut self, opener: Opener) -> bool {
+        let Some(index) = self.stack.iter().rposition(|f| f.opener == opener) else {
+            return false;
         };
+        self.reset_from(index);
+        self.stack.remove(index);
+        self.reopen_from(index);
+        true
+    }
+
+    /// Writes a reset for each style opened from the `index` frame on.
+    fn reset_from(&mut self, index: usize) {
+        let open = self.stack[index..]
+            .iter()
+            .filter(|f| !f.codes.is_empty())
+            .count();
+        self.out.push_str(&"\x1b[0m".repeat(open));
+    }
+
+    /// Opens again the styles of the frames from the `index` one on.
+    fn reopen_from(&mut self, index: usize) {
+        for frame in &self.stack[index..] {
+            if !frame.codes.is_empty() {
+                self.out
+                    .push_str(&format!("\x1b[{}m", frame.codes.join(";")));
+            }
+        }
+    }
 
-        out.push_str(&styled_body);
-        out.push_str(nl);
+    fn is_active(&self) -> bool {
+        self.stack.iter().any(|f| !f.codes.is_empty())
     }
-    out
+
+    fn render(mut self, input: &str) -> String {
+        for line in input.split_inclusive('\n') {
+            let (body, nl) = line.strip_suffix('\n').map_or((line, ""), |b| (b, "\n"));
+
+            let trimmed = body.trim_start();
+            let prefix_len = body.len() - trimmed.len();
+            let heading =
+                [("## ", false), ("# ", true)]
+                    .into_iter()
+                    .find_map(|(prefix, underline)| {
+                        trimmed.strip_prefix(prefix).map(|rest| (rest, underline))
+                    });
+
+            if let Some((rest, underline)) = heading {
+                self.out.push_str(&body[..prefix_len]);
+                let attributes = TextAttributes {
+                    bold: true,
+                    underline: underline.then_some(UnderlineStyle::Single),
+                    ..TextAttributes::default()
+                };
+                let codes = self.codes(None, attributes);
+                self.open(Opener::Heading, codes);
+                self.inline(rest);
+                self.close(Opener::Heading);
+            } else {
+                self.inline(body);
+            }
+
+            // Styles spanning several lines are reset before each line break
+            if !nl.is_empty() && self.is_active() {
+                self.reset_from(0);
+                self.out.push('\n');
+                self.reopen_from(0);
+            } else {
+                self.out.push_str(nl);
+            }
+        }
+        self.reset_from(0);
+        self.out
+    }
+
+    fn inline(&mut self, text: &str) {
+        let mut i = 0;
+        while i < text.len() {
+            let rest = &text[i..];
+            let c = rest.chars().next().unwrap_or_default();
+            let consumed = match c {
+                '\\' => match rest[1..].chars().next() {
+                    Some(next) if ESCAPABLE.contains(&next) => {
+                        self.out.push(next);
+                        1 + next.len_utf8()
+                    }
+                    _ => 0,
+                },
+                '`' => self.code_span(rest),
+                '[' => self.bracket(rest),
+                ':' => self.shortcode(rest),
+                _ => self.delimiter(rest),
+            };
+            if consumed == 0 {
+                self.out.push(c);
+                i += c.len_utf8();
+            } else {
+                i += consumed;
+            }
+        }
+    }
+
+    fn delimiter(&mut self, rest: &str) -> usize {
+        let Some(delimiter) = DELIMITERS.into_iter().find(|d| rest.starts_with(d)) else {
+            return 0;
+        };
+        let opener = Opener::Delimiter(delimiter);
+        if self.close(opener) {
+            return delimiter.len();
+        }
+        if find_unescaped(&rest[delimiter.len()..], delimiter).is_none() {
+            return 0;
+        }
+        let codes = self.codes(None, delimiter_attributes(delimiter));
+        self.open(opener, codes);
+        delimiter.len()
+    }
+
+    fn code_span(&mut self, rest: &str) -> usize {
+        let Some(len) = rest[1..].find('`') else {
+            return 0;
+        };
+        let spec = self
+            .ctx
+            .get("markup_code")
+            .or_else(|| self.ctx.contains("theme_string").then_some("theme_string"))
+            .unwrap_or("dim")
+            .to_string();
+        let codes = self.codes(Some(spec), TextAttributes::default());
+        self.open(Opener::Tag, codes);
+        self.out.push_str(&rest[1..=len]);
+        self.close(Opener::Tag);
+        len + 2
+    }
+
+    fn bracket(&mut self, rest: &str) -> usize {
+        if let Some((label, url, len)) = parse_link(rest) {
+            let start = self.out.len();
+            self.inline(label);
+            if self.colors {
+                let label = self.out.split_off(start);
+                self.out.push_str(&hyperlink(url, &label));
+            } else if label != url {
+                self.out.push_str(&format!(" ({url})"));
+            }
+            return len;
+        }
+
+        let Some(end) = rest.find(']') else {
+            return 0;
+        };
+        let tag = rest[1..end].trim();
+        if tag.starts_with('/') {
+            return if self.close(Opener::Tag) { end + 1 } else { 0 };
+        }
+        let spec = tag_spec(tag);
+        if spec.is_empty() || ColorManager::resolve(self.ctx, &spec).is_err() {
+            return 0;
+        }
+        let codes = self.codes(Some(spec), TextAttributes::default());
+        self.open(Opener::Tag, codes);
+        end + 1
+    }
+
+    fn shortcode(&mut self, rest: &str) -> usize {
+        let Some(len) = rest[1..].find(':') else {
+            return 0;
+        };
+        match emoji(&rest[1..=len]) {
+            Some(emoji) => {
+                self.out.push_str(emoji);
+                len + 2
+            }
+            None => 0,
+        }
+    }
+}
+
+/// Applies heading prefixes and inline markup line-by-line (preserves `\n`), resolving tag
+/// colors and the color depth from the given context.
+#[must_use]
+pub fn apply_rich_markup(ctx: &Context, input: &str) -> String {
+    MarkupRenderer::new(ctx).render(input)
 }
 
 /// Create the Tera `markup` filter.
@@ -112,37 +402,130 @@ pub fn create_markup_filter() -> impl Fn(&Value, &HashMap<String, Value>) -> Res
 {
     move |value: &Value, _args: &HashMap<String, Value>| {
         let text = tera::try_get_value!("markup", "value", String, value);
-        Ok(Value::String(apply_rich_markup(&text)))
+        let ctx = ContextManager::get()
+            .read()
+            .map_err(|e| TeraError::msg(e.to_string()))?;
+        // Escape codes already in the text are dropped along with the ones of the markup
+        let text = if ColorDepth::from_context(&ctx) == ColorDepth::NoColor {
+            strip_escapes(&text)
+        } else {
+            text
+        };
+        Ok(Value::String(apply_rich_markup(&ctx, &text)))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::transforms::{AnsiFormatter, Transform};
+
+    fn ctx() -> Context {
+        let mut ctx = Context::new();
+        ctx.insert("color_mode", "truecolor");
+        ctx
+    }
+
+    fn markup(input: &str) -> String {
+        apply_rich_markup(&ctx(), input)
+    }
 
     #[test]
     fn bold_segment() {
-        let s = apply_rich_markup("aa **bb** cc");
-        assert!(s.contains("bb"));
-        assert!(!s.contains("**"));
+        assert_eq!(markup("aa **bb** cc"), "aa \x1b[1mbb\x1b[0m cc");
     }
 
     #[test]
     fn h2_prefix() {
-        let s = apply_rich_markup("## Hello");
-        assert!(!s.contains("## "));
-        assert!(s.contains("Hello"));
+        assert_eq!(markup("  ## Hello"), "  \x1b[1mHello\x1b[0m");
     }
 
     #[test]
     fn escaped_stars() {
-        let s = apply_rich_markup(r"a \**b**");
-        assert!(s.contains("**b**"));
+        assert_eq!(markup(r"a \**b**"), "a **b**");
+        assert_eq!(markup(r"a \*\*b\*\* \[red]"), "a **b** [red]");
+        assert_eq!(markup("a ** b"), "a ** b");
     }
 
     #[test]
     fn newline_preserved() {
-        let s = apply_rich_markup("a\nb");
-        assert_eq!(s.matches('\n').count(), 1);
+        assert_eq!(markup("a\nb"), "a\nb");
+    }
+
+    #[test]
+    fn nested_styles_are_restored() {
+        assert_eq!(
+            markup("[red]a **b ~~c~~** d[/] e"),
+            "\x1b[31ma \x1b[1mb \x1b[9mc\x1b[0m\x1b[0m d\x1b[0m e"
+        );
+        // Closing an outer style closes the inner ones too, and opens them again
+        assert_eq!(
+            markup("**a [red]b** c[/]"),
+            "\x1b[1ma \x1b[31mb\x1b[0m\x1b[0m\x1b[31m c\x1b[0m"
+        );
+        assert_eq!(
+            markup("[bold fg=#ff8800 bg=navy]x[/]"),
+            "\x1b[1;38;2;255;136;0;48;2;0;0;128mx\x1b[0m"
+        );
+        assert_eq!(markup("[red]a\nb"), "\x1b[31ma\x1b[0m\n\x1b[31mb\x1b[0m");
+        assert_eq!(
+            markup("[red][bold]a\nb[/][/]"),
+            "\x1b[31m\x1b[1ma\x1b[0m\x1b[0m\n\x1b[31m\x1b[1mb\x1b[0m\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn nested_styles_through_ansi_formatter() {
+        let format = |input: &str| AnsiFormatter::new().transform(&markup(input)).unwrap();
+        assert_eq!(
+            format("[red]a **b ~~c~~** d[/] e"),
+            "\x1b[31ma \x1b[1mb \x1b[9mc\x1b[0;31m d\x1b[0m e"
+        );
+        assert_eq!(
+            format("**a [red]b** c[/] d"),
+            "\x1b[1ma \x1b[31mb\x1b[22m c\x1b[0m d"
+        );
+        assert_eq!(
+            format("[red][bold]a\nb[/][/] c"),
+            "\x1b[1;31ma\x1b[0m\n\x1b[1;31mb\x1b[0m c"
+        );
+    }
+
+    #[test]
+    fn unknown_tags_are_kept() {
+        assert_eq!(markup("[WIP] fix [ ] [/]"), "[WIP] fix [ ] [/]");
+    }
+
+    #[test]
+    fn code_spans() {
+        let mut ctx = ctx();
+        assert_eq!(
+            apply_rich_markup(&ctx, "run `a **b**`"),
+            "run \x1b[2ma **b**\x1b[0m"
+        );
+        ctx.insert("theme_string", "#00ff00");
+        assert_eq!(apply_rich_markup(&ctx, "`x`"), "\x1b[38;2;0;255;0mx\x1b[0m");
+    }
+
+    #[test]
+    fn links() {
+        assert_eq!(
+            markup("see [**docs**](https://x.io/a_b)"),
+            "see \x1b]8;;https://x.io/a_b\x1b\\\x1b[1mdocs\x1b[0m\x1b]8;;\x1b\\"
+        );
+        let mut ctx = Context::new();
+        ctx.insert("color_mode", "never");
+        assert_eq!(
+            apply_rich_markup(&ctx, "[red]see[/] [docs](https://x.io)"),
+            "see docs (https://x.io)"
+        );
+    }
+
+    #[test]
+    fn shortcodes() {
+        assert_eq!(
+            markup(":rocket: at 12:30:45 :nope:"),
+            "🚀 at 12:30:45 :nope:"
+        );
     }
 }
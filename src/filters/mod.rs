@@ -2,19 +2,54 @@
 //! Currently supports the following filters:
 //! - `color` : Apply a color to the text
 //! - `style` : Apply a style to the text
+//! - `color_by` : Color the text according to the value it maps to
+//! - `color_hash` : Color the text with a color picked from its hash
+//! - `gradient` : Fade the text between several colors
+//! - `banner` : Draw the text as big multi-line text with a FIGlet font
+//! - `frame` : Draw a box around the text, which may span several lines
+//! - `segment` : Draw the text as a powerline-style segment
+//! - `textstyle` : Map the letters and digits to Unicode look-alikes (bold, script, fullwidth, ...)
+//! - `bytes`, `duration`, `number`, `percent`, `ago`, `strftime` : Humanize sizes, durations,
+//!   counts and dates
+//! - `link` : Turn the text into a terminal hyperlink
+//! - `markup` : Render inline markup (emphasis, color tags, code spans, links, emojis)
+//! - `lighten`, `darken`, `mix`, ... : Derive a color from the value
 
 pub mod append;
+pub mod banner;
 pub mod color;
+pub mod color_by;
+pub mod color_function;
+pub mod color_hash;
+pub mod frame;
+pub mod gradient;
 pub mod hide;
-#[cfg(feature = "display")]
+pub mod humanize;
+pub mod link;
 pub mod markup;
 pub mod pad;
+pub mod segment;
 pub mod style;
 pub mod surround;
+pub mod textstyle;
 
 pub use append::create_append_filter;
+pub use banner::create_banner_filter;
 pub use color::create_color_filter;
+pub use color_by::create_color_by_filter;
+pub use color_function::create_color_function_filter;
+pub use color_hash::create_color_hash_filter;
+pub use frame::create_frame_filter;
+pub use gradient::create_gradient_filter;
 pub use hide::create_hide_filter;
+pub use humanize::{
+    create_ago_filter, create_bytes_filter, create_duration_filter, create_number_filter,
+    create_percent_filter, create_strftime_filter,
+};
+pub use link::create_link_filter;
+pub use markup::create_markup_filter;
 pub use pad::create_pad_filter;
+pub use segment::create_segment_filter;
 pub use style::create_style_filter;
 pub use surround::create_surround_filter;
+pub use textstyle::create_textstyle_filter;
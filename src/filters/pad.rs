@@ -1,4 +1,6 @@
-use crate::constants::padding;
+use crate::constants::{encode, padding};
+use crate::context_manager::ContextManager;
+use crate::filler::PatternMode;
 use std::collections::HashMap;
 use tera::{Error as TeraError, Value};
 
@@ -9,18 +11,51 @@ use tera::{Error as TeraError, Value};
 ///
 /// # Arguments
 /// * `value` - The input string to process
-/// * `args` - A `HashMap` containing the filter arguments (not used yet)
+/// * `args` - A `HashMap` containing the filter arguments:
+///   - `pattern_mode`: how the filler is laid out (`edge`, `mirror` and/or `whole`, see
+///     [`PatternMode`]), defaulting to the `pattern_mode` value of the context
+///
+/// # Example
+/// ```tera
+/// {{ f | pad(pattern_mode="edge,mirror") }}
+/// ```
 ///
 /// # Returns
 /// A closure that can be used with Tera's `register_filter`
 pub fn create_pad_filter() -> impl Fn(&Value, &HashMap<String, Value>) -> Result<Value, TeraError> {
-    move |value: &Value, _args: &HashMap<String, Value>| {
+    move |value: &Value, args: &HashMap<String, Value>| {
         let text = tera::try_get_value!("pad", "value", String, value);
 
-        // Surround the text with non-visible markers
+        let mode = match args.get("pattern_mode") {
+            Some(mode) => mode
+                .as_str()
+                .ok_or_else(|| TeraError::msg("pattern_mode must be a string"))?
+                .to_string(),
+            None => {
+                let ctx = ContextManager::get()
+                    .read()
+                    .map_err(|e| TeraError::msg(e.to_string()))?;
+                ctx.get("pattern_mode")
+                    .or_else(|| ctx.get("defaults.pattern_mode"))
+                    .unwrap_or_default()
+                    .to_string()
+            }
+        };
+        let mode = mode
+            .parse::<PatternMode>()
+            .map_err(|e| TeraError::msg(e.to_string()))?;
+
+        // Surround the text with non-visible markers, the pattern mode being stored (zero-width)
+        // right after the start marker
+        let mode = if mode.is_default() {
+            String::new()
+        } else {
+            format!("{}{}", encode(&mode.to_spec()), padding::SEP)
+        };
         Ok(Value::String(format!(
-            "{}{}{}",
+            "{}{}{}{}",
             padding::START,
+            mode,
             text,
             padding::END
         )))
@@ -82,4 +117,43 @@ mod tests {
             format!("{}\x1b[31mhello 🦀\x1b[0m{}", padding::START, padding::END)
         );
     }
+
+    #[test]
+    fn test_pad_filter_with_pattern_mode() {
+        let filter = create_pad_filter();
+        let mut args = HashMap::new();
+        args.insert(
+            "pattern_mode".to_string(),
+            Value::String("mirror, edge".to_string()),
+        );
+        let value = Value::String("<>".to_string());
+
+        let result = filter(&value, &args).unwrap();
+        assert_eq!(
+            result.as_str().unwrap(),
+            format!(
+                "{}{}{}<>{}",
+                padding::START,
+                encode("edge,mirror"),
+                padding::SEP,
+                padding::END
+            )
+        );
+
+        args.insert(
+            "pattern_mode".to_string(),
+            Value::String("none".to_string()),
+        );
+        let result = filter(&value, &args).unwrap();
+        assert_eq!(
+            result.as_str().unwrap(),
+            format!("{}<>{}", padding::START, padding::END)
+        );
+
+        args.insert(
+            "pattern_mode".to_string(),
+            Value::String("left".to_string()),
+        );
+        assert!(filter(&value, &args).is_err());
+    }
 }
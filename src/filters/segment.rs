@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+use tera::{Error as TeraError, Value};
+
+use crate::color_manager::{ColorManager, StyleFormat, StyleScope, TextAttributes};
+use crate::constants::{encode, segment};
+use crate::context::Context;
+use crate::context_manager::ContextManager;
+use crate::segment::{Segment, SegmentStyle};
+
+/// Create a segment filter closure for Tera
+///
+/// Draws the text as a powerline-style segment on the `bg` color, with the `fg` color. Adjacent
+/// segments are joined by a separator drawn in the background color of the previous segment over
+/// the background of the next one. The `separator` (`powerline`, `round`, `slant` or `plain`)
+/// defaults to the `segment_separator` value of the context, then to `powerline`.
+///
+/// # Example
+/// ```tera
+/// {{ user | segment(bg="blue", fg="white") }}{{ f | pad | segment(bg="gray") }}
+/// ```
+///
+/// # Returns
+/// A closure that can be used with Tera's `register_filter`
+pub fn create_segment_filter(
+) -> impl Fn(&Value, &HashMap<String, Value>) -> Result<Value, TeraError> {
+    move |value: &Value, args: &HashMap<String, Value>| {
+        let text = tera::try_get_value!("segment", "value", String, value);
+
+        let str_arg = |name: &str| match args.get(name) {
+            Some(Value::String(s)) => Ok(Some(s.trim_start_matches("raw:").trim_matches('"'))),
+            Some(_) => Err(TeraError::msg(format!("{name} must be a string"))),
+            None => Ok(None),
+        };
+
+        let ctx = ContextManager::get()
+            .read()
+            .map_err(|e| TeraError::msg(e.to_string()))?;
+        let style = match str_arg("separator")? {
+            Some(style) => style.to_string(),
+            None => ctx
+                .get("segment_separator")
+                .or_else(|| ctx.get("defaults.segment_separator"))
+                .unwrap_or(SegmentStyle::default().name())
+                .to_string(),
+        };
+
+        let codes = |color: Option<&str>, is_bg: bool| match color {
+            Some(color) => sgr_params(&ctx, color, is_bg),
+            None => Ok(String::new()),
+        };
+        let bg = str_arg("bg")?;
+        let settings = Segment {
+            style: style
+                .parse()
+                .map_err(|e: crate::error::Error| TeraError::msg(e.to_string()))?,
+            bg: codes(bg, true)?,
+            bg_as_fg: codes(bg, false)?,
+            fg: codes(str_arg("fg")?, false)?,
+        };
+
+        // The segment is drawn by the segment processor, which knows the next segment
+        Ok(Value::String(format!(
+            "{}{}{}{text}{}",
+            segment::START,
+            encode(&settings.to_spec()),
+            segment::SEP,
+            segment::END
+        )))
+    }
+}
+
+/// SGR parameters of the color used as a foreground or a background color.
+fn sgr_params(ctx: &Context, color: &str, is_bg: bool) -> Result<String, TeraError> {
+    let style = StyleFormat {
+        fg_color: (!is_bg).then(|| color.to_string()),
+        bg_color: is_bg.then(|| color.to_string()),
+        scope: if is_bg {
+            StyleScope::BG
+        } else {
+            StyleScope::FG
+        },
+        attributes: TextAttributes::default(),
+        underline_color: None,
+    };
+    ColorManager::try_sgr_codes(ctx, style)
+        .map(|codes| codes.join(";"))
+        .map_err(|e| TeraError::msg(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::decode;
+
+    #[test]
+    fn test_segment_filter() {
+        let filter = create_segment_filter();
+        let mut args = HashMap::new();
+        args.insert("separator".to_string(), Value::String("round".to_string()));
+
+        let result = filter(&Value::String("main".to_string()), &args).unwrap();
+        let result = result.as_str().unwrap();
+        let body = result
+            .strip_prefix(segment::START)
+            .and_then(|r| r.strip_suffix(segment::END))
+            .unwrap();
+        let (spec, content) = body.split_once(segment::SEP).unwrap();
+        assert_eq!(decode(spec), "round|||");
+        assert_eq!(content, "main");
+
+        args.insert("separator".to_string(), Value::String("arrow".to_string()));
+        assert!(filter(&Value::String("main".to_string()), &args).is_err());
+    }
+}
@@ -1,11 +1,15 @@
 use std::collections::HashMap;
 use tera::{Error as TeraError, Value};
 
-use crate::color_manager::{ColorManager, StyleFormat, StyleScope};
+use crate::color_manager::{ColorManager, StyleFormat, StyleScope, TextAttributes, UnderlineStyle};
 use crate::context_manager::ContextManager;
 
 /// Create a style filter closure for Tera
 ///
+/// Besides `fg_color` and `bg_color`, the filter accepts the boolean attributes `bold`,
+/// `italic`, `dim`, `reverse`, `strike` and `blink`, an `underline` that is either a boolean or
+/// one of `single`, `double`, `curly`, `dotted` and `dashed`, and an `underline_color`.
+///
 /// # Returns
 /// A closure that can be used with Tera's `register_filter`
 pub fn create_style_filter() -> impl Fn(&Value, &HashMap<String, Value>) -> Result<Value, TeraError>
@@ -14,32 +18,74 @@ pub fn create_style_filter() -> impl Fn(&Value, &HashMap<String, Value>) -> Resu
         let text = tera::try_get_value!("style", "value", String, value);
 
         // Get the color values and strip the special prefix if present
-        let fg_color = args
-            .get("fg_color")
-            .and_then(|v| v.as_str())
-            .map(|s| s.trim_start_matches("raw:").trim_matches('"').to_string());
-        let bg_color = args
-            .get("bg_color")
-            .and_then(|v| v.as_str())
-            .map(|s| s.trim_start_matches("raw:").trim_matches('"').to_string());
+        let color_arg = |name: &str| {
+            args.get(name)
+                .and_then(|v| v.as_str())
+                .map(|s| s.trim_start_matches("raw:").trim_matches('"').to_string())
+        };
+        let fg_color = color_arg("fg_color");
+        let bg_color = color_arg("bg_color");
+        let underline_color = color_arg("underline_color");
+        let attributes = parse_attributes(args)?;
 
         let scope = match (fg_color.as_ref(), bg_color.as_ref()) {
             (Some(_), Some(_)) => StyleScope::BOTH,
             (Some(_), None) => StyleScope::FG,
             (None, Some(_)) => StyleScope::BG,
-            (None, None) => return Ok(Value::String(text)), // No colors provided, return original text
+            // No colors nor attributes provided, return original text
+            (None, None) if attributes.is_empty() && underline_color.is_none() => {
+                return Ok(Value::String(text))
+            }
+            (None, None) => StyleScope::FG,
         };
 
         let style = StyleFormat {
             fg_color,
             bg_color,
             scope,
+            attributes,
+            underline_color,
         };
 
         let ctx = ContextManager::get()
             .read()
             .map_err(|e| TeraError::msg(e.to_string()))?;
 
-        Ok(Value::String(ColorManager::format(&ctx, &text, style)))
+        ColorManager::try_format(&ctx, &text, style)
+            .map(Value::String)
+            .map_err(|e| TeraError::msg(e.to_string()))
+    }
+}
+
+/// Reads the text attributes from the filter arguments.
+fn parse_attributes(args: &HashMap<String, Value>) -> Result<TextAttributes, TeraError> {
+    let flag = |name: &str| args.get(name).is_some_and(is_truthy);
+
+    let underline = match args.get("underline") {
+        Some(Value::String(s)) if !s.is_empty() && s != "false" => Some(
+            s.parse::<UnderlineStyle>()
+                .map_err(|e| TeraError::msg(e.to_string()))?,
+        ),
+        Some(v @ Value::Bool(_)) if is_truthy(v) => Some(UnderlineStyle::Single),
+        _ => None,
+    };
+
+    Ok(TextAttributes {
+        bold: flag("bold"),
+        dim: flag("dim"),
+        italic: flag("italic"),
+        underline,
+        blink: flag("blink"),
+        reverse: flag("reverse"),
+        strike: flag("strike") || flag("strikethrough"),
+    })
+}
+
+/// Boolean attributes may be given as booleans or as `"true"` strings (e.g. from context vars).
+fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Bool(b) => *b,
+        Value::String(s) => s == "true",
+        _ => false,
     }
 }
@@ -0,0 +1,60 @@
+use std::collections::HashMap;
+use tera::{Error as TeraError, Value};
+
+use crate::textstyle::TextStyle;
+
+/// Create a textstyle filter closure for Tera
+///
+/// Maps the ASCII letters and digits of the text to look-alike Unicode characters: `kind` is
+/// one of `bold`, `italic`, `script`, `fraktur`, `double-struck`, `monospace`, `fullwidth`,
+/// `small-caps`, `circled`, `squared` and `upside-down`. Colors and styles already applied to
+/// the text are kept.
+///
+/// # Example
+/// ```tera
+/// {{ m | textstyle(kind="double-struck") | color(name=c1) }}
+/// ```
+///
+/// # Returns
+/// A closure that can be used with Tera's `register_filter`
+pub fn create_textstyle_filter(
+) -> impl Fn(&Value, &HashMap<String, Value>) -> Result<Value, TeraError> {
+    move |value: &Value, args: &HashMap<String, Value>| {
+        let text = tera::try_get_value!("textstyle", "value", String, value);
+
+        let kind = args
+            .get("kind")
+            .and_then(Value::as_str)
+            .ok_or_else(|| TeraError::msg("Missing kind argument"))?
+            .parse::<TextStyle>()
+            .map_err(|e| TeraError::msg(e.to_string()))?;
+        Ok(Value::String(kind.apply(&text)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_textstyle_filter() {
+        let filter = create_textstyle_filter();
+        let mut args = HashMap::new();
+        args.insert("kind".to_string(), Value::String("small caps".to_string()));
+        let value = Value::String("\x1b[31mtitular\x1b[0m".to_string());
+
+        let result = filter(&value, &args).unwrap();
+        assert_eq!(result.as_str().unwrap(), "\x1b[31mᴛɪᴛᴜʟᴀʀ\x1b[0m");
+    }
+
+    #[test]
+    fn test_textstyle_filter_invalid_kind() {
+        let filter = create_textstyle_filter();
+        let value = Value::String("titular".to_string());
+        assert!(filter(&value, &HashMap::new()).is_err());
+
+        let mut args = HashMap::new();
+        args.insert("kind".to_string(), Value::String("gothic".to_string()));
+        assert!(filter(&value, &args).is_err());
+    }
+}
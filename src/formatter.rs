@@ -1,7 +1,14 @@
 use crate::prelude::*;
 use crate::{
-    config::MainConfig, constants::template::DEFAULT_TEMPLATE_NAME, context::Context, debug,
-    reader::TemplateReader, transforms::TransformManager, writer::TemplateWriter,
+    config::{Appearance, MainConfig, TemplateConfig},
+    constants::template::DEFAULT_TEMPLATE_NAME,
+    context::Context,
+    debug,
+    functions::{exec::ExecCache, git::RepositoryCache},
+    policy::{self, TemplatePolicy},
+    reader::TemplateReader,
+    transforms::TransformManager,
+    writer::TemplateWriter,
 };
 #[cfg(feature = "display")]
 use crate::{
@@ -49,6 +56,15 @@ impl<'a> TemplateFormatter<'a> {
         palette_from_theme(th).insert_into(ctx);
     }
 
+    /// Resolves the active appearance from the context (`--appearance` / `TITULAR_APPEARANCE`)
+    /// falling back to `defaults.appearance` from the main configuration.
+    fn resolve_appearance(ctx: &Context) -> Option<Appearance> {
+        ["appearance", "defaults.appearance"]
+            .iter()
+            .filter_map(|key| ctx.get(key))
+            .find_map(|value| value.parse().ok())
+    }
+
     /// Performs the rendering of the template using the template formatter.
     /// In case it's not present (and is not the default template), it will be downloaded
     /// automatically from the remote repository (if the "fetcher" feature is enabled).
@@ -63,6 +79,16 @@ impl<'a> TemplateFormatter<'a> {
     /// # Errors
     /// Returns an error if the template cannot be loaded, context cannot be updated, or rendering fails.
     pub fn format(&self, context: &Context, template_name: &str) -> Result<bool> {
+        write!(stdout(), "{}", self.render(context, template_name)?)?;
+        Ok(true)
+    }
+
+    /// Renders the template as [`TemplateFormatter::format`] does, returning the rendered text
+    /// instead of printing it.
+    ///
+    /// # Errors
+    /// Returns an error if the template cannot be loaded, context cannot be updated, or rendering fails.
+    pub fn render(&self, context: &Context, template_name: &str) -> Result<String> {
         self.preprocess_template(template_name)?;
 
         let template_payload = TemplateReader::read(self.input_dir, template_name)?;
@@ -71,19 +97,49 @@ impl<'a> TemplateFormatter<'a> {
         // Update the context in a clean way
         crate::context_manager::ContextManager::get().update(|ctx| {
             ctx.append_from(context);
-            ctx.append(&self.config.vars);
-            #[cfg(feature = "display")]
-            Self::inject_theme_palette_vars(ctx);
-            ctx.append(&template_payload.vars);
+            self.append_render_vars(ctx, &template_payload);
+            // Stored as an object, out of reach of the template vars. The commands allowed by
+            // the template itself only count once the user trusted it
+            let template_allowlist: &[String] =
+                if policy::is_trust_granted(self.input_dir, template_name) {
+                    &template_payload.policy.exec_allowlist
+                } else {
+                    &[]
+                };
+            let exec_allowlist = self
+                .config
+                .policy
+                .exec_allowlist
+                .iter()
+                .chain(template_allowlist)
+                .cloned()
+                .collect();
+            ctx.store_object(
+                "policy",
+                TemplatePolicy::new(
+                    self.config.policy.env_allowlist.clone(),
+                    policy::is_trusted(self.input_dir, template_name),
+                )
+                .with_exec_allowlist(exec_allowlist)
+                .with_exec_timeout_max_ms(self.config.policy.exec_timeout_max_ms),
+            );
+            // Command outputs and the git repository are cached for a single render
+            ctx.store_object("exec_cache", ExecCache::default());
+            ctx.store_object("git_repository", RepositoryCache::default());
             ctx.store_object("template_config", template_payload);
         })?;
 
-        write!(
-            stdout(),
-            "{}",
-            TransformManager::get().process(&pattern_data)?
-        )?;
-        Ok(true)
+        TransformManager::get().process(&pattern_data)
+    }
+
+    /// Appends the variables available to a render of the template, in order of precedence:
+    /// the config vars, the theme palette (`display` feature) and the template vars for the
+    /// active appearance.
+    pub(crate) fn append_render_vars(&self, ctx: &mut Context, template: &TemplateConfig) {
+        ctx.append(&self.config.vars);
+        #[cfg(feature = "display")]
+        Self::inject_theme_palette_vars(ctx);
+        ctx.append(&template.vars.resolve(Self::resolve_appearance(ctx)));
     }
 
     /// Performs the preprocessing of the template.
@@ -0,0 +1,452 @@
+//! Box-drawing frames drawn around content by the `frame` filter.
+//!
+//! The filter only stores the frame settings in the rendered text: the text processor draws the
+//! frame once the resolved width is known, laying the content out in the columns left inside the
+//! borders, so that padding groups of the content fill the frame and nested frames fit in it.
+
+use std::str::FromStr;
+
+use crate::ansi::{tokenize, update_layers, visual_width, Token};
+use crate::error::{Error, Result};
+use crate::string_utils::{AnsiTruncateBehavior, Truncate};
+use crate::width::AmbiguousWidth;
+
+/// Set of characters the border is drawn with.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FrameStyle {
+    #[default]
+    Single,
+    Double,
+    Rounded,
+    Heavy,
+    Ascii,
+}
+
+/// Characters of a border: corners (top left, top right, bottom left, bottom right), then the
+/// horizontal and vertical lines.
+struct Borders {
+    top_left: char,
+    top_right: char,
+    bottom_left: char,
+    bottom_right: char,
+    horizontal: char,
+    vertical: char,
+}
+
+impl FrameStyle {
+    /// Name of the style, as accepted by [`FromStr`].
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            FrameStyle::Single => "single",
+            FrameStyle::Double => "double",
+            FrameStyle::Rounded => "rounded",
+            FrameStyle::Heavy => "heavy",
+            FrameStyle::Ascii => "ascii",
+        }
+    }
+
+    fn borders(self) -> Borders {
+        let [top_left, top_right, bottom_left, bottom_right, horizontal, vertical] = match self {
+            FrameStyle::Single => ['┌', '┐', '└', '┘', '─', '│'],
+            FrameStyle::Double => ['╔', '╗', '╚', '╝', '═', '║'],
+            FrameStyle::Rounded => ['╭', '╮', '╰', '╯', '─', '│'],
+            FrameStyle::Heavy => ['┏', '┓', '┗', '┛', '━', '┃'],
+            FrameStyle::Ascii => ['+', '+', '+', '+', '-', '|'],
+        };
+        Borders {
+            top_left,
+            top_right,
+            bottom_left,
+            bottom_right,
+            horizontal,
+            vertical,
+        }
+    }
+}
+
+impl FromStr for FrameStyle {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "single" => Ok(FrameStyle::Single),
+            "double" => Ok(FrameStyle::Double),
+            "rounded" => Ok(FrameStyle::Rounded),
+            "heavy" => Ok(FrameStyle::Heavy),
+            "ascii" => Ok(FrameStyle::Ascii),
+            other => Err(Error::Msg(format!(
+                "invalid frame style \"{other}\" (expected single, double, rounded, heavy or ascii)"
+            ))),
+        }
+    }
+}
+
+/// Horizontal alignment of the title and of the content lines narrower than the frame.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+impl Align {
+    /// Name of the alignment, as accepted by [`FromStr`].
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Align::Left => "left",
+            Align::Center => "center",
+            Align::Right => "right",
+        }
+    }
+
+    /// Splits `free` columns into the columns before and after the aligned text.
+    fn split(self, free: usize) -> (usize, usize) {
+        let before = match self {
+            Align::Left => 0,
+            Align::Center => free / 2,
+            Align::Right => free,
+        };
+        (before, free - before)
+    }
+}
+
+impl FromStr for Align {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "left" => Ok(Align::Left),
+            "center" | "centre" => Ok(Align::Center),
+            "right" => Ok(Align::Right),
+            other => Err(Error::Msg(format!(
+                "invalid alignment \"{other}\" (expected left, center or right)"
+            ))),
+        }
+    }
+}
+
+/// Layout of a frame: its border style, the alignment of its title and content, the columns of
+/// spaces between the border and the content (`padding`) and around the border (`margin`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Frame {
+    pub style: FrameStyle,
+    pub align: Align,
+    pub padding: usize,
+    pub margin: usize,
+}
+
+impl Default for Frame {
+    fn default() -> Self {
+        Self {
+            style: FrameStyle::default(),
+            align: Align::default(),
+            padding: 1,
+            margin: 0,
+        }
+    }
+}
+
+impl FromStr for Frame {
+    type Err = Error;
+
+    /// Parses the spec produced by [`Frame::to_spec`].
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || Error::Msg(format!("invalid frame spec \"{s}\""));
+        let mut fields = s.split_whitespace();
+        let mut next = || fields.next().ok_or_else(invalid);
+        Ok(Self {
+            style: next()?.parse()?,
+            align: next()?.parse()?,
+            padding: next()?.parse().map_err(|_| invalid())?,
+            margin: next()?.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+impl Frame {
+    /// Serializes the frame into a spec stored in the rendered text.
+    ///
+    /// # Examples
+    /// ```
+    /// use titular::frame::{Align, Frame, FrameStyle};
+    ///
+    /// let frame = Frame { style: FrameStyle::Rounded, align: Align::Center, padding: 2, margin: 1 };
+    /// assert_eq!(frame.to_spec(), "rounded center 2 1");
+    /// assert_eq!(frame.to_spec().parse::<Frame>().unwrap(), frame);
+    /// ```
+    #[must_use]
+    pub fn to_spec(&self) -> String {
+        format!(
+            "{} {} {} {}",
+            self.style.name(),
+            self.align.name(),
+            self.padding,
+            self.margin
+        )
+    }
+
+    /// Columns left for the content of a frame drawn in `width` columns.
+    #[must_use]
+    pub fn inner_width(&self, width: usize) -> usize {
+        width.saturating_sub(2 * (self.margin + 1 + self.padding))
+    }
+
+    /// Draws the frame around the lines in `width` columns. Lines narrower than the
+    /// [inner width](Frame::inner_width) are aligned, wider ones are truncated. The title is set
+    /// in the top border, and the border is drawn with the `border` SGR sequence (e.g.
+    /// `"\x1b[34m"`) if not empty, followed by a reset so that the colors of the content are
+    /// left untouched. The lines and the title are measured with the given width of East Asian
+    /// ambiguous characters.
+    ///
+    /// # Examples
+    /// ```
+    /// use titular::frame::{Align, Frame, FrameStyle};
+    /// use titular::width::AmbiguousWidth;
+    ///
+    /// let frame = Frame { style: FrameStyle::Rounded, align: Align::Center, ..Frame::default() };
+    /// let rows = frame.draw(&["hi".to_string()], 10, "T", "", AmbiguousWidth::Narrow);
+    /// assert_eq!(rows, ["╭── T ───╮", "│   hi   │", "╰────────╯"]);
+    /// ```
+    #[must_use]
+    pub fn draw(
+        &self,
+        lines: &[String],
+        width: usize,
+        title: &str,
+        border: &str,
+        ambiguous: AmbiguousWidth,
+    ) -> Vec<String> {
+        let borders = |piece: String| paint(border, piece);
+        let b = self.style.borders();
+        let inner = self.inner_width(width);
+        let run = inner + 2 * self.padding;
+        let margin = " ".repeat(self.margin);
+        let padding = " ".repeat(self.padding);
+        let horizontal = |n: usize| b.horizontal.to_string().repeat(n);
+
+        let mut rows = Vec::with_capacity(lines.len() + 2);
+        rows.push(format!(
+            "{margin}{}{margin}",
+            self.top_border(run, title, &borders, &b, ambiguous)
+        ));
+        let empty = [String::new()];
+        let lines = if lines.is_empty() { &empty[..] } else { lines };
+        // Styles spanning several lines are closed at the end of each line and opened again on
+        // the next one, so that they do not leak into the padding and the borders
+        let mut open = Vec::new();
+        for line in lines {
+            let vertical = borders(b.vertical.to_string());
+            let line = format!("{}{line}", open.concat());
+            rows.push(format!(
+                "{margin}{vertical}{padding}{}{padding}{vertical}{margin}",
+                self.fit(&line, inner, &mut open, ambiguous)
+            ));
+        }
+        rows.push(format!(
+            "{margin}{}{margin}",
+            borders(format!(
+                "{}{}{}",
+                b.bottom_left,
+                horizontal(run),
+                b.bottom_right
+            ))
+        ));
+        rows
+    }
+
+    /// Top border holding the title, surrounded by spaces and at least one line on each side.
+    fn top_border(
+        &self,
+        run: usize,
+        title: &str,
+        borders: &impl Fn(String) -> String,
+        b: &Borders,
+        ambiguous: AmbiguousWidth,
+    ) -> String {
+        let horizontal = |n: usize| b.horizontal.to_string().repeat(n);
+        let mut title = title.to_string();
+        title.truncate_ansi_with(
+            run.saturating_sub(4),
+            AnsiTruncateBehavior::PreserveRemaining,
+            ambiguous,
+        );
+        let title_width = visual_width(&title, ambiguous);
+        close_layers(&mut title);
+        if title_width == 0 {
+            return borders(format!("{}{}{}", b.top_left, horizontal(run), b.top_right));
+        }
+        let title = format!(" {title} ");
+        let (before, after) = self.align.split(run - 4 - title_width);
+        format!(
+            "{}{title}{}",
+            borders(format!("{}{}", b.top_left, horizontal(before + 1))),
+            borders(format!("{}{}", horizontal(after + 1), b.top_right))
+        )
+    }
+
+    /// Aligns or truncates the line to `width` columns, closing the layers of style it leaves
+    /// open, which are stored in `open`.
+    fn fit(
+        &self,
+        line: &str,
+        width: usize,
+        open: &mut Vec<String>,
+        ambiguous: AmbiguousWidth,
+    ) -> String {
+        let mut line = line.to_string();
+        line.truncate_ansi_with(width, AnsiTruncateBehavior::PreserveRemaining, ambiguous);
+        *open = close_layers(&mut line);
+        let (before, after) = self
+            .align
+            .split(width.saturating_sub(visual_width(&line, ambiguous)));
+        format!("{}{line}{}", " ".repeat(before), " ".repeat(after))
+    }
+}
+
+/// Wraps a piece of border in the border style, if any.
+fn paint(border: &str, piece: String) -> String {
+    if border.is_empty() {
+        piece
+    } else {
+        format!("{border}{piece}\x1b[0m")
+    }
+}
+
+/// Closes the layers of style left open by the text, returning them to be opened again.
+fn close_layers(text: &mut String) -> Vec<String> {
+    let mut layers = Vec::new();
+    for token in tokenize(text) {
+        if let Token::Escape(_, seq) = token {
+            update_layers(&mut layers, seq);
+        }
+    }
+    text.push_str(&"\x1b[0m".repeat(layers.len()));
+    layers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NARROW: AmbiguousWidth = AmbiguousWidth::Narrow;
+
+    fn lines(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|l| (*l).to_string()).collect()
+    }
+
+    #[test]
+    fn test_frame_spec() {
+        assert_eq!("ascii left 0 3".parse::<Frame>().unwrap().margin, 3);
+        assert!("ascii left 0".parse::<Frame>().is_err());
+        assert!("square left 0 0".parse::<Frame>().is_err());
+        assert!("ascii top 0 0".parse::<Frame>().is_err());
+        assert_eq!("Heavy".parse::<FrameStyle>().unwrap(), FrameStyle::Heavy);
+        assert_eq!("centre".parse::<Align>().unwrap(), Align::Center);
+    }
+
+    #[test]
+    fn test_draw_styles() {
+        let mut frame = Frame {
+            padding: 0,
+            ..Frame::default()
+        };
+        let content = lines(&["ab"]);
+        assert_eq!(
+            frame.draw(&content, 4, "", "", NARROW),
+            ["┌──┐", "│ab│", "└──┘"]
+        );
+        frame.style = FrameStyle::Double;
+        assert_eq!(
+            frame.draw(&content, 4, "", "", NARROW),
+            ["╔══╗", "║ab║", "╚══╝"]
+        );
+        frame.style = FrameStyle::Heavy;
+        assert_eq!(
+            frame.draw(&content, 4, "", "", NARROW),
+            ["┏━━┓", "┃ab┃", "┗━━┛"]
+        );
+        frame.style = FrameStyle::Ascii;
+        assert_eq!(
+            frame.draw(&content, 4, "", "", NARROW),
+            ["+--+", "|ab|", "+--+"]
+        );
+    }
+
+    #[test]
+    fn test_draw_layout() {
+        let frame = Frame {
+            style: FrameStyle::Ascii,
+            align: Align::Right,
+            padding: 1,
+            margin: 2,
+        };
+        assert_eq!(frame.inner_width(12), 4);
+        assert_eq!(
+            frame.draw(&lines(&["a", "日本", "toolong"]), 12, "Title", "", NARROW),
+            [
+                "  +- Ti -+  ",
+                "  |    a |  ",
+                "  | 日本 |  ",
+                "  | tool |  ",
+                "  +------+  "
+            ]
+        );
+        assert_eq!(
+            frame.draw(&lines(&["a"]), 6, "Title", "", NARROW),
+            ["  +--+  ", "  |  |  ", "  +--+  "]
+        );
+    }
+
+    #[test]
+    fn test_draw_empty_and_colored() {
+        let frame = Frame {
+            style: FrameStyle::Ascii,
+            ..Frame::default()
+        };
+        assert_eq!(frame.draw(&[], 4, "", "", NARROW), ["+--+", "|  |", "+--+"]);
+        assert_eq!(
+            frame.draw(&lines(&["\x1b[31mx\x1b[0m"]), 5, "", "\x1b[34m", NARROW),
+            [
+                "\x1b[34m+---+\x1b[0m",
+                "\x1b[34m|\x1b[0m \x1b[31mx\x1b[0m \x1b[34m|\x1b[0m",
+                "\x1b[34m+---+\x1b[0m"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_draw_resets_open_styles() {
+        let frame = Frame {
+            style: FrameStyle::Ascii,
+            ..Frame::default()
+        };
+        assert_eq!(
+            frame.draw(&lines(&["\x1b[41mx"]), 9, "\x1b[1mT", "", NARROW),
+            [
+                "+- \x1b[1mT\x1b[0m ---+",
+                "| \x1b[41mx\x1b[0m     |",
+                "+-------+"
+            ]
+        );
+        // Every layer is closed, and styles spanning lines are opened again
+        assert_eq!(
+            frame.draw(
+                &lines(&["\x1b[1m\x1b[41mx", "y\x1b[0mz", "w\x1b[0m"]),
+                9,
+                "",
+                "",
+                NARROW
+            ),
+            [
+                "+-------+",
+                "| \x1b[1m\x1b[41mx\x1b[0m\x1b[0m     |",
+                "| \x1b[1m\x1b[41my\x1b[0mz\x1b[0m    |",
+                "| \x1b[1mw\x1b[0m     |",
+                "+-------+"
+            ]
+        );
+    }
+}
@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+use tera::{Error as TeraError, Value};
+
+use crate::color_functions::{check_amount, parse_amount, to_rgb_string, ColorFunction};
+use crate::color_manager::ColorManager;
+use nu_ansi_term::Color;
+
+use crate::color_utils::color_to_rgb;
+use crate::context_manager::ContextManager;
+
+/// Create a Tera function for the given color manipulation
+///
+/// The functions take the color to manipulate as `color` (`bg` for `readable_on`), along with
+/// `other` for `mix`, `candidates` for `readable_on` and an optional `amount` for the others: a
+/// fraction between `-1` and `1` (`0.2`) or a percentage string (`"20%"`).
+///
+/// # Example
+/// ```tera
+/// {% set border = darken(color=accent, amount="20%") %}
+/// {{ m | color(name=readable_on(bg=border)) }}
+/// ```
+///
+/// # Returns
+/// A closure that can be used with Tera's `register_function`
+pub fn create_color_function(
+    function: ColorFunction,
+) -> impl Fn(&HashMap<String, Value>) -> Result<Value, TeraError> {
+    move |args: &HashMap<String, Value>| {
+        let first = if function == ColorFunction::ReadableOn {
+            "bg"
+        } else {
+            "color"
+        };
+        let color = args
+            .get(first)
+            .and_then(Value::as_str)
+            .ok_or_else(|| TeraError::msg(format!("Missing {first} argument")))?;
+        evaluate(function, color, args)
+    }
+}
+
+/// Applies the color function to the given color, reading the remaining arguments (`other`,
+/// `candidates` and `amount`) from `args`.
+///
+/// # Returns
+/// The resulting color as an `RGB(r,g,b)` string, or an empty string if any of the colors
+/// cannot be resolved (an error in strict color mode)
+pub(crate) fn evaluate(
+    function: ColorFunction,
+    color: &str,
+    args: &HashMap<String, Value>,
+) -> Result<Value, TeraError> {
+    let mut names = vec![color.to_string()];
+    match function {
+        ColorFunction::Mix => names.push(
+            args.get("other")
+                .and_then(Value::as_str)
+                .ok_or_else(|| TeraError::msg("Missing other argument"))?
+                .to_string(),
+        ),
+        ColorFunction::ReadableOn => match args.get("candidates") {
+            None => {}
+            Some(Value::Array(candidates)) => {
+                for candidate in candidates {
+                    let name = candidate
+                        .as_str()
+                        .ok_or_else(|| TeraError::msg("candidates must be strings"))?;
+                    names.push(name.to_string());
+                }
+            }
+            Some(_) => return Err(TeraError::msg("candidates must be a list of colors")),
+        },
+        _ => {}
+    }
+
+    let amount = match args.get("amount") {
+        None => None,
+        Some(Value::Number(n)) => Some(n.as_f64().and_then(check_amount).ok_or_else(|| {
+            TeraError::msg(format!(
+                "Invalid amount {n} (expected a fraction between -1 and 1, or a percentage)"
+            ))
+        })?),
+        Some(Value::String(s)) => {
+            Some(parse_amount(s).ok_or_else(|| TeraError::msg(format!("Invalid amount \"{s}\"")))?)
+        }
+        Some(_) => return Err(TeraError::msg("amount must be a number or a percentage")),
+    };
+
+    let ctx = ContextManager::get()
+        .read()
+        .map_err(|e| TeraError::msg(e.to_string()))?;
+
+    let mut colors = Vec::with_capacity(names.len());
+    for name in &names {
+        match ColorManager::resolve(&ctx, name) {
+            Ok(Some(Color::Default)) => {
+                return Err(TeraError::msg(format!(
+                    "Color `{name}` is the terminal default color, which has no RGB value"
+                )))
+            }
+            Ok(Some(color)) => colors.extend(color_to_rgb(color)),
+            Ok(None) => return Ok(Value::String(String::new())),
+            Err(e) if ColorManager::is_strict(&ctx) => return Err(TeraError::msg(e.to_string())),
+            Err(_) => return Ok(Value::String(String::new())),
+        }
+    }
+
+    Ok(Value::String(
+        function
+            .apply(&colors, amount)
+            .map(to_rgb_string)
+            .unwrap_or_default(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_color_functions() {
+        let darken = create_color_function(ColorFunction::Darken);
+        let args = HashMap::from([
+            ("color".to_string(), Value::from("#ff0000")),
+            ("amount".to_string(), Value::from(0.25)),
+        ]);
+        assert_eq!(darken(&args).unwrap(), Value::from("RGB(128,0,0)"));
+        let args = HashMap::from([
+            ("color".to_string(), Value::from("#ff0000")),
+            ("amount".to_string(), Value::from(25)),
+        ]);
+        assert!(darken(&args).is_err());
+
+        let readable_on = create_color_function(ColorFunction::ReadableOn);
+        let args = HashMap::from([
+            ("bg".to_string(), Value::from("RGB(250,250,250)")),
+            (
+                "candidates".to_string(),
+                Value::from(vec!["#ffff00", "NAME(navy)"]),
+            ),
+        ]);
+        assert_eq!(readable_on(&args).unwrap(), Value::from("RGB(0,0,128)"));
+
+        let mix = create_color_function(ColorFunction::Mix);
+        let args = HashMap::from([("color".to_string(), Value::from("#ff0000"))]);
+        assert!(mix(&args).is_err());
+    }
+}
@@ -0,0 +1,301 @@
+//! The `exec` function, rendering the output of a command.
+//!
+//! Commands only run for trusted templates, when their program is in the exec allowlist of the
+//! configuration or of the template (see [`TemplatePolicy::allows_exec`]).
+
+use std::collections::HashMap;
+use std::io::{self, Read};
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tera::{Error as TeraError, Value};
+
+use crate::context_manager::ContextManager;
+use crate::debug;
+use crate::policy::TemplatePolicy;
+
+/// Time a command is given to complete when no timeout is set
+const DEFAULT_TIMEOUT_MS: u64 = 1000;
+
+/// Interval between the checks of a running command
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// The number of bytes of the output of a command that are kept, the rest being discarded
+const MAX_OUTPUT_BYTES: u64 = 64 * 1024;
+
+/// The outputs of the commands run during a render, stored in the context as `exec_cache`, so
+/// that a command used several times by a template runs once. Clones share the same outputs.
+#[derive(Debug, Clone, Default)]
+pub struct ExecCache(Arc<Mutex<HashMap<Vec<String>, Option<String>>>>);
+
+/// Spawns the command in a process group of its own, so that the processes it starts can be
+/// killed along with it.
+fn spawn(program: &str, args: &[String]) -> io::Result<Child> {
+    let mut command = Command::new(program);
+    command
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null());
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut command, 0);
+    command.spawn()
+}
+
+/// Kills the processes left in the process group of the command.
+fn kill_group(child: &Child) {
+    #[cfg(unix)]
+    if let Ok(pgid) = libc::pid_t::try_from(child.id()) {
+        // SAFETY: `kill` has no memory safety requirements, the group is the one of the child
+        unsafe {
+            libc::kill(-pgid, libc::SIGKILL);
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = child;
+}
+
+/// Runs a command, returning its trimmed standard output if it succeeds in time.
+fn run_command(cmd: &[String], timeout: Duration) -> Option<String> {
+    let (program, args) = cmd.split_first()?;
+    let mut child = spawn(program, args).ok()?;
+
+    // Read the output aside, so that a command filling the pipe does not block, keeping its
+    // first bytes only
+    let mut stdout = child.stdout.take()?;
+    let reader = thread::spawn(move || {
+        let mut output = Vec::new();
+        (&mut stdout)
+            .take(MAX_OUTPUT_BYTES)
+            .read_to_end(&mut output)?;
+        io::copy(&mut stdout, &mut io::sink())?;
+        Ok::<_, io::Error>(output)
+    });
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() < deadline => thread::sleep(POLL_INTERVAL),
+            _ => {
+                kill_group(&child);
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+        }
+    };
+    // Processes started by the command may keep the output open past its end
+    kill_group(&child);
+    let output = reader.join().ok()?.ok()?;
+    if !status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output).trim().to_string())
+}
+
+/// Runs the command of the arguments under the policy, using the cache when given.
+fn exec_with(
+    args: &HashMap<String, Value>,
+    policy: Option<&TemplatePolicy>,
+    cache: Option<&ExecCache>,
+) -> Result<Value, TeraError> {
+    let cmd: Vec<String> = match args.get("cmd") {
+        Some(Value::Array(cmd)) if !cmd.is_empty() => cmd
+            .iter()
+            .map(|arg| match arg {
+                Value::String(arg) => Some(arg.clone()),
+                Value::Number(arg) => Some(arg.to_string()),
+                _ => None,
+            })
+            .collect::<Option<_>>()
+            .ok_or_else(|| TeraError::msg("cmd must be an array of strings"))?,
+        Some(_) => return Err(TeraError::msg("cmd must be a non-empty array of strings")),
+        None => return Err(TeraError::msg("Missing cmd argument")),
+    };
+    let timeout = match args.get("timeout_ms") {
+        None => DEFAULT_TIMEOUT_MS,
+        Some(timeout) => timeout
+            .as_u64()
+            .or_else(|| timeout.as_str().and_then(|s| s.trim().parse().ok()))
+            .ok_or_else(|| TeraError::msg("timeout_ms must be a positive integer"))?,
+    };
+    let timeout =
+        Duration::from_millis(timeout.min(policy.map_or(0, TemplatePolicy::exec_timeout_max_ms)));
+    let default = args
+        .get("default")
+        .cloned()
+        .unwrap_or_else(|| Value::String(String::new()));
+
+    if !policy.is_some_and(|policy| policy.allows_exec(&cmd[0])) {
+        debug!("exec: {} is not allowed for this template", cmd[0]);
+        return Ok(default);
+    }
+
+    let output = match cache {
+        Some(cache) => {
+            let cached = cache
+                .0
+                .lock()
+                .map_err(|e| TeraError::msg(e.to_string()))?
+                .get(&cmd)
+                .cloned();
+            cached.unwrap_or_else(|| {
+                let output = run_command(&cmd, timeout);
+                if let Ok(mut cache) = cache.0.lock() {
+                    cache.insert(cmd, output.clone());
+                }
+                output
+            })
+        }
+        None => run_command(&cmd, timeout),
+    };
+    Ok(output.map_or(default, Value::String))
+}
+
+/// Run a command and get its output
+///
+/// # Arguments
+/// * `args` - A `HashMap` containing the function arguments:
+///   - `cmd`: the program and its arguments, e.g. `["cargo", "pkgid"]` (no shell is involved)
+///   - `timeout_ms`: the time the command is given to complete (`1000` by default)
+///   - `default`: the value returned when the command is not allowed, fails or times out (an
+///     empty string by default)
+///
+/// # Returns
+/// A Tera Value containing the trimmed standard output of the command. Commands are disabled
+/// by default: the program must be in the `exec_allowlist` of the `[policy]` section of the
+/// configuration, or of the template once `templates trust` was run for it, and the template must
+/// be trusted (templates downloaded with `templates add` or imported never run commands until
+/// `templates trust` is run). An allowed program runs with any arguments. The timeout is capped
+/// by the `exec_timeout_max_ms` of the configuration, and only the first 64 KiB of the output
+/// are kept. The output of a command is cached for the rest of the render.
+///
+/// # Example
+/// ```tera
+/// {{ exec(cmd=["kubectl", "config", "current-context"], timeout_ms=300, default="-") }}
+/// ```
+///
+/// # Errors
+/// Returns a Tera error if the arguments are invalid.
+#[allow(clippy::implicit_hasher)] // Signature must match Tera's function handler type
+pub fn exec(args: &HashMap<String, Value>) -> Result<Value, TeraError> {
+    // Copied out of the context, so that it is not locked while the command runs
+    let (policy, cache) = {
+        let ctx = ContextManager::get()
+            .read()
+            .map_err(|e| TeraError::msg(e.to_string()))?;
+        (
+            ctx.get_object::<TemplatePolicy>("policy").cloned(),
+            ctx.get_object::<ExecCache>("exec_cache").cloned(),
+        )
+    };
+    exec_with(args, policy.as_ref(), cache.as_ref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(cmd: &[&str]) -> HashMap<String, Value> {
+        let mut args = HashMap::new();
+        args.insert("cmd".to_string(), Value::from(cmd.to_vec()));
+        args.insert("default".to_string(), Value::from("none"));
+        args
+    }
+
+    fn policy(allowlist: &[&str], trusted: bool) -> TemplatePolicy {
+        TemplatePolicy::new(Vec::new(), trusted)
+            .with_exec_allowlist(allowlist.iter().map(|s| (*s).to_string()).collect())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_exec_policy() {
+        let args = args(&["echo", " hello "]);
+        assert_eq!(exec_with(&args, None, None).unwrap(), "none");
+        let untrusted = policy(&["echo"], false);
+        assert_eq!(exec_with(&args, Some(&untrusted), None).unwrap(), "none");
+        let other = policy(&["printf"], true);
+        assert_eq!(exec_with(&args, Some(&other), None).unwrap(), "none");
+        let allowed = policy(&["echo"], true);
+        assert_eq!(exec_with(&args, Some(&allowed), None).unwrap(), "hello");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_exec_failure_and_timeout() {
+        let allowed = policy(&["false", "sleep"], true);
+        assert_eq!(
+            exec_with(&args(&["false"]), Some(&allowed), None).unwrap(),
+            "none"
+        );
+
+        let mut sleep = args(&["sleep", "5"]);
+        sleep.insert("timeout_ms".to_string(), Value::from(50));
+        let start = Instant::now();
+        assert_eq!(exec_with(&sleep, Some(&allowed), None).unwrap(), "none");
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_exec_timeout_capped() {
+        let allowed = policy(&["sleep"], true).with_exec_timeout_max_ms(50);
+        let mut sleep = args(&["sleep", "5"]);
+        sleep.insert("timeout_ms".to_string(), Value::from(u64::MAX));
+        let start = Instant::now();
+        assert_eq!(exec_with(&sleep, Some(&allowed), None).unwrap(), "none");
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_exec_background_processes() {
+        // The output is kept, and the process left running is killed with the command
+        let allowed = policy(&["sh"], true);
+        let mut background = args(&["sh", "-c", "(sleep 5; echo late) & echo early"]);
+        background.insert("timeout_ms".to_string(), Value::from(3000));
+        let start = Instant::now();
+        assert_eq!(
+            exec_with(&background, Some(&allowed), None).unwrap(),
+            "early"
+        );
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_exec_output_capped() {
+        let allowed = policy(&["sh"], true);
+        let large = args(&["sh", "-c", "head -c 200000 /dev/zero | tr '\\0' a"]);
+        let output = exec_with(&large, Some(&allowed), None).unwrap();
+        assert_eq!(output.as_str().unwrap().len(), 64 * 1024);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_exec_cache() {
+        let allowed = policy(&["date"], true);
+        let cache = ExecCache::default();
+        let args = args(&["date", "+%N"]);
+        let first = exec_with(&args, Some(&allowed), Some(&cache)).unwrap();
+        let second = exec_with(&args, Some(&allowed), Some(&cache)).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(cache.0.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_exec_arguments() {
+        let allowed = policy(&["*"], true);
+        assert!(exec_with(&HashMap::new(), Some(&allowed), None).is_err());
+        assert!(exec_with(&args(&[]), Some(&allowed), None).is_err());
+        let mut invalid = args(&["echo"]);
+        invalid.insert("cmd".to_string(), Value::from("echo hello"));
+        assert!(exec_with(&invalid, Some(&allowed), None).is_err());
+        let mut invalid = args(&["echo"]);
+        invalid.insert("timeout_ms".to_string(), Value::from(-1));
+        assert!(exec_with(&invalid, Some(&allowed), None).is_err());
+    }
+}
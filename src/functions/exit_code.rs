@@ -32,6 +32,31 @@ pub fn get_last_exit_code(_args: &HashMap<String, Value>) -> Result<Value, TeraE
     Ok(Value::Number(exit_code.into()))
 }
 
+/// Get the duration of the last command in milliseconds
+///
+/// # Arguments
+/// * `_args` - A `HashMap` containing the function arguments (not used)
+///
+/// # Returns
+/// A Tera Value containing the duration read from `LAST_DURATION_MS`, as exported by the hooks
+/// of `titular init` (defaults to `0`)
+///
+/// # Example
+/// ```tera
+/// {% if get_last_duration() > 5000 %}took {{ get_last_duration() | duration(unit="ms") }}{% endif %}
+/// ```
+///
+/// # Errors
+/// Returns a Tera error if `LAST_DURATION_MS` is set but cannot be parsed as an integer.
+#[allow(clippy::implicit_hasher)] // Signature must match Tera's function handler type
+pub fn get_last_duration(_args: &HashMap<String, Value>) -> Result<Value, TeraError> {
+    let duration = std::env::var("LAST_DURATION_MS")
+        .map_or(Ok(0), |duration| duration.trim().parse::<u64>())
+        .map_err(|_| TeraError::msg("Failed to parse duration as number"))?;
+
+    Ok(Value::Number(duration.into()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -58,4 +83,18 @@ mod tests {
         let result = get_last_exit_code(&args).unwrap();
         assert_eq!(result.as_number().unwrap().as_i64().unwrap(), 0);
     }
+
+    #[test]
+    fn test_get_last_duration() {
+        let args = HashMap::new();
+        temp_env::with_var("LAST_DURATION_MS", Some("1500"), || {
+            assert_eq!(get_last_duration(&args).unwrap(), Value::from(1500));
+        });
+        temp_env::with_var("LAST_DURATION_MS", Some("soon"), || {
+            assert!(get_last_duration(&args).is_err());
+        });
+        temp_env::with_var_unset("LAST_DURATION_MS", || {
+            assert_eq!(get_last_duration(&args).unwrap(), Value::from(0));
+        });
+    }
 }
@@ -0,0 +1,229 @@
+//! Functions reading the git repository of the current directory.
+//!
+//! The repository is read directly, without running `git`, and discovered once per render.
+//! Outside a repository (or when it cannot be read, see [`crate::git`] for the repositories that
+//! are not supported) the functions return empty values rather than failing the render.
+
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+use tera::{Error as TeraError, Map, Value};
+
+use crate::context_manager::ContextManager;
+use crate::git::Repository;
+
+/// Length of the abbreviated commit ids
+const SHORT_COMMIT_LEN: usize = 7;
+
+/// The repository of the current directory, stored in the context as `git_repository` so that
+/// it is discovered once per render. Clones share the same repository.
+#[derive(Clone, Default)]
+pub struct RepositoryCache(Arc<OnceLock<Option<Arc<Repository>>>>);
+
+fn discover() -> Option<Arc<Repository>> {
+    Repository::discover(&std::env::current_dir().ok()?).map(Arc::new)
+}
+
+/// The repository of the current directory, from the cache of the render if any.
+fn current_repository() -> Option<Arc<Repository>> {
+    // Copied out of the context, so that it is not locked while the repository is discovered
+    let cache = ContextManager::get()
+        .read()
+        .ok()?
+        .get_object::<RepositoryCache>("git_repository")
+        .cloned();
+    match cache {
+        Some(cache) => cache.0.get_or_init(discover).clone(),
+        None => discover(),
+    }
+}
+
+/// Get the current branch
+///
+/// # Returns
+/// A Tera Value containing the name of the branch, an empty string outside a repository or
+/// when `HEAD` is detached
+///
+/// # Example
+/// ```tera
+/// {% set branch = git_branch() %}{% if branch %} {{ branch }}{% endif %}
+/// ```
+///
+/// # Errors
+/// Never fails.
+#[allow(clippy::implicit_hasher)] // Signature must match Tera's function handler type
+pub fn git_branch(_args: &HashMap<String, Value>) -> Result<Value, TeraError> {
+    Ok(branch_of(current_repository().as_deref()))
+}
+
+fn branch_of(repo: Option<&Repository>) -> Value {
+    let branch = repo
+        .and_then(|repo| repo.branch().ok().flatten())
+        .unwrap_or_default();
+    Value::String(branch)
+}
+
+/// Get the commit of `HEAD`
+///
+/// # Arguments
+/// * `args` - A `HashMap` containing the function arguments:
+///   - `short`: whether the id is abbreviated to 7 characters (`true` by default)
+///
+/// # Returns
+/// A Tera Value containing the commit id, an empty string outside a repository or before the
+/// first commit
+///
+/// # Errors
+/// Returns a Tera error if `short` is not a boolean.
+#[allow(clippy::implicit_hasher)] // Signature must match Tera's function handler type
+pub fn git_commit(args: &HashMap<String, Value>) -> Result<Value, TeraError> {
+    commit_of(current_repository().as_deref(), args)
+}
+
+fn commit_of(repo: Option<&Repository>, args: &HashMap<String, Value>) -> Result<Value, TeraError> {
+    let short = match args.get("short") {
+        None => true,
+        Some(Value::Bool(short)) => *short,
+        Some(Value::String(s)) => matches!(s.trim(), "true" | "1" | "yes" | "on"),
+        Some(_) => return Err(TeraError::msg("short must be a boolean")),
+    };
+    let mut commit = repo
+        .and_then(|repo| repo.head_commit().ok().flatten())
+        .map(|id| id.to_string())
+        .unwrap_or_default();
+    if short {
+        commit.truncate(SHORT_COMMIT_LEN);
+    }
+    Ok(Value::String(commit))
+}
+
+/// Get whether the tracked files of the repository have uncommitted changes, staged or not
+///
+/// Like `git describe --dirty`, untracked files are not taken into account.
+///
+/// # Returns
+/// A Tera Value containing a boolean, `false` outside a repository
+///
+/// # Example
+/// ```tera
+/// {{ git_branch() }}{% if git_dirty() %}*{% endif %}
+/// ```
+///
+/// # Errors
+/// Never fails.
+#[allow(clippy::implicit_hasher)] // Signature must match Tera's function handler type
+pub fn git_dirty(_args: &HashMap<String, Value>) -> Result<Value, TeraError> {
+    Ok(dirty_of(current_repository().as_deref()))
+}
+
+fn dirty_of(repo: Option<&Repository>) -> Value {
+    Value::Bool(repo.is_some_and(|repo| repo.is_dirty().unwrap_or(false)))
+}
+
+/// Get how far the current branch is from its upstream
+///
+/// # Returns
+/// A Tera Value containing an object with `ahead` and `behind`, the number of commits only on
+/// the branch and only on its upstream, and `upstream`, the name of the upstream (e.g.
+/// `origin/main`). Outside a repository or without an upstream, the counts are 0 and the
+/// upstream is an empty string.
+///
+/// # Example
+/// ```tera
+/// {% set ab = git_ahead_behind() %}{% if ab.ahead %}↑{{ ab.ahead }}{% endif %}{% if ab.behind %}↓{{ ab.behind }}{% endif %}
+/// ```
+///
+/// # Errors
+/// Never fails.
+#[allow(clippy::implicit_hasher)] // Signature must match Tera's function handler type
+pub fn git_ahead_behind(_args: &HashMap<String, Value>) -> Result<Value, TeraError> {
+    Ok(ahead_behind_of(current_repository().as_deref()))
+}
+
+fn ahead_behind_of(repo: Option<&Repository>) -> Value {
+    let ahead_behind = repo
+        .and_then(|repo| repo.ahead_behind().ok().flatten())
+        .unwrap_or_default();
+    let mut map = Map::new();
+    map.insert("ahead".to_string(), Value::from(ahead_behind.ahead));
+    map.insert("behind".to_string(), Value::from(ahead_behind.behind));
+    map.insert("upstream".to_string(), Value::String(ahead_behind.upstream));
+    Value::Object(map)
+}
+
+/// Get the name of the repository: the name of the directory of its main work tree
+///
+/// # Returns
+/// A Tera Value containing the name, an empty string outside a repository
+///
+/// # Errors
+/// Never fails.
+#[allow(clippy::implicit_hasher)] // Signature must match Tera's function handler type
+pub fn git_repo_name(_args: &HashMap<String, Value>) -> Result<Value, TeraError> {
+    let name = current_repository()
+        .map(|repo| repo.name())
+        .unwrap_or_default();
+    Ok(Value::String(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::tests::Fixture;
+
+    /// A repository whose `main` branch is one commit ahead of and one behind `origin/main`.
+    fn fixture() -> Fixture {
+        let fixture = Fixture::new();
+        let tree = fixture.tree(&[]);
+        let base = fixture.commit(tree, &[], 1);
+        let local = fixture.commit(tree, &[base], 2);
+        let remote = fixture.commit(tree, &[base], 3);
+        fixture.git("refs/heads/main", &format!("{local}\n"));
+        fixture.git("refs/remotes/origin/main", &format!("{remote}\n"));
+        fixture.git(
+            "config",
+            "[branch \"main\"]\n\tremote = origin\n\tmerge = refs/heads/main\n",
+        );
+        fixture
+    }
+
+    #[test]
+    fn test_git_branch_and_dirty() {
+        let fixture = fixture();
+        assert_eq!(branch_of(Some(&fixture.repo())), Value::from("main"));
+        assert_eq!(dirty_of(Some(&fixture.repo())), Value::Bool(false));
+        assert_eq!(branch_of(None), Value::from(""));
+        assert_eq!(dirty_of(None), Value::Bool(false));
+    }
+
+    #[test]
+    fn test_git_commit_short() {
+        let fixture = fixture();
+        let head = fixture.repo().head_commit().unwrap().unwrap().to_string();
+
+        let mut args = HashMap::new();
+        let short = commit_of(Some(&fixture.repo()), &args).unwrap();
+        assert_eq!(short, Value::from(&head[..SHORT_COMMIT_LEN]));
+
+        args.insert("short".to_string(), Value::Bool(false));
+        assert_eq!(
+            commit_of(Some(&fixture.repo()), &args).unwrap(),
+            Value::from(head)
+        );
+        assert_eq!(commit_of(None, &args).unwrap(), Value::from(""));
+
+        args.insert("short".to_string(), Value::from(3));
+        assert!(commit_of(Some(&fixture.repo()), &args).is_err());
+    }
+
+    #[test]
+    fn test_git_ahead_behind() {
+        let value = ahead_behind_of(Some(&fixture().repo()));
+        assert_eq!(value["ahead"], Value::from(1));
+        assert_eq!(value["behind"], Value::from(1));
+        assert_eq!(value["upstream"], Value::from("origin/main"));
+
+        let value = ahead_behind_of(None);
+        assert_eq!(value["ahead"], Value::from(0));
+        assert_eq!(value["upstream"], Value::from(""));
+    }
+}
@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+use tera::{Error as TeraError, Value};
+
+use crate::context::Context;
+use crate::context_manager::ContextManager;
+use crate::glyphs::{glyph_support, GlyphSupport};
+use crate::icons::lookup;
+
+/// Get an icon by name
+///
+/// # Arguments
+/// * `args` - A `HashMap` containing the function arguments:
+///   - `name`: the name of the icon (e.g. `git-branch`, `folder`, `rust`)
+///   - `fallback`: the text drawn instead of the Nerd Font glyph when glyphs are disabled (see
+///     the `[icons]` section of the configuration) or when the icon is unknown
+///
+/// # Returns
+/// A Tera Value containing the Nerd Font glyph of the icon or, when glyphs are disabled, the
+/// fallback, then the emoji of the icon (its ASCII form on basic terminals)
+///
+/// # Example
+/// ```tera
+/// {{ icon(name="git-branch", fallback="*") }} {{ branch }}
+/// ```
+///
+/// # Errors
+/// Returns a Tera error if the name is missing, or unknown without a fallback.
+#[allow(clippy::implicit_hasher)] // Signature must match Tera's function handler type
+pub fn icon(args: &HashMap<String, Value>) -> Result<Value, TeraError> {
+    let ctx = ContextManager::get()
+        .read()
+        .map_err(|e| TeraError::msg(e.to_string()))?;
+    icon_with(args, &ctx)
+}
+
+/// Gets an icon as [`icon`] does, with the glyph settings of the given context.
+fn icon_with(args: &HashMap<String, Value>, ctx: &Context) -> Result<Value, TeraError> {
+    let name = args
+        .get("name")
+        .and_then(Value::as_str)
+        .ok_or_else(|| TeraError::msg("Missing name argument"))?;
+    let fallback = args.get("fallback").and_then(Value::as_str);
+
+    let support = glyph_support(ctx);
+    let symbol = match (lookup(name), fallback) {
+        (Some(icon), _) if support == GlyphSupport::NerdFont => icon.glyph,
+        (_, Some(fallback)) => fallback,
+        (Some(icon), None) => icon.render(support),
+        (None, None) => return Err(TeraError::msg(format!("unknown icon \"{name}\""))),
+    };
+    Ok(Value::String(symbol.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(pairs: &[(&str, &str)]) -> HashMap<String, Value> {
+        pairs
+            .iter()
+            .map(|(k, v)| ((*k).to_string(), Value::String((*v).to_string())))
+            .collect()
+    }
+
+    #[test]
+    fn test_icon() {
+        let mut ctx = Context::new();
+        ctx.insert("glyphs", "true");
+        let result = icon_with(&args(&[("name", "git-branch"), ("fallback", "*")]), &ctx).unwrap();
+        assert_eq!(result.as_str().unwrap(), "\u{e725}");
+        let result = icon_with(&args(&[("name", "nope"), ("fallback", "*")]), &ctx).unwrap();
+        assert_eq!(result.as_str().unwrap(), "*");
+        assert!(icon_with(&args(&[("name", "nope")]), &ctx).is_err());
+        assert!(icon_with(&args(&[]), &ctx).is_err());
+
+        ctx.insert("glyphs", "false");
+        let result = icon_with(&args(&[("name", "git-branch"), ("fallback", "*")]), &ctx).unwrap();
+        assert_eq!(result.as_str().unwrap(), "*");
+    }
+}
@@ -1,3 +1,13 @@
+pub mod color;
+pub mod exec;
 pub mod exit_code;
+pub mod git;
+pub mod icon;
+pub mod system;
 
-pub use exit_code::get_last_exit_code;
+pub use color::create_color_function;
+pub use exec::exec;
+pub use exit_code::{get_last_duration, get_last_exit_code};
+pub use git::{git_ahead_behind, git_branch, git_commit, git_dirty, git_repo_name};
+pub use icon::icon;
+pub use system::{cwd, env, hostname, now, os, shell, term_width, username};
@@ -0,0 +1,352 @@
+//! Functions reading the environment of the render: variables, user, host, working directory,
+//! shell, terminal and time.
+
+use chrono::Utc;
+use std::collections::HashMap;
+use std::path::{Component, Path, MAIN_SEPARATOR, MAIN_SEPARATOR_STR};
+use tera::{Error as TeraError, Value};
+
+use crate::constants::template::DEFAULT_TIME_FORMAT;
+use crate::context_manager::ContextManager;
+use crate::filters::humanize::{format_time, parse_timezone};
+use crate::policy::TemplatePolicy;
+
+/// Reads a string argument of a function.
+fn str_arg<'a>(args: &'a HashMap<String, Value>, name: &str) -> Result<Option<&'a str>, TeraError> {
+    args.get(name)
+        .map(|v| {
+            v.as_str()
+                .ok_or_else(|| TeraError::msg(format!("{name} must be a string")))
+        })
+        .transpose()
+}
+
+/// Reads an environment variable if the policy lets the template read it, renders without a
+/// policy being untrusted.
+fn read_env(name: &str, policy: Option<&TemplatePolicy>) -> Option<String> {
+    let allowed = match policy {
+        Some(policy) => policy.allows_env(name),
+        None => TemplatePolicy::untrusted().allows_env(name),
+    };
+    if !allowed {
+        return None;
+    }
+    std::env::var(name).ok()
+}
+
+/// Reads the environment variable of the `name` argument under the policy of the render.
+fn read_env_arg(args: &HashMap<String, Value>) -> Result<(&str, Option<String>), TeraError> {
+    let name = str_arg(args, "name")?.ok_or_else(|| TeraError::msg("Missing name argument"))?;
+    let ctx = ContextManager::get()
+        .read()
+        .map_err(|e| TeraError::msg(e.to_string()))?;
+    Ok((
+        name,
+        read_env(name, ctx.get_object::<TemplatePolicy>("policy")),
+    ))
+}
+
+/// Get the value of an environment variable
+///
+/// # Arguments
+/// * `args` - A `HashMap` containing the function arguments:
+///   - `name`: the name of the variable
+///   - `default`: the value returned when the variable is unset (an empty string by default)
+///
+/// # Returns
+/// A Tera Value containing the value of the variable. Untrusted (downloaded) templates only read
+/// the variables of `env_allowlist` (see the `[policy]` section of the configuration), getting
+/// the default for the others.
+///
+/// # Example
+/// ```tera
+/// {{ env(name="VIRTUAL_ENV", default="system") }}
+/// ```
+///
+/// # Errors
+/// Returns a Tera error if the name is missing.
+#[allow(clippy::implicit_hasher)] // Signature must match Tera's function handler type
+pub fn env(args: &HashMap<String, Value>) -> Result<Value, TeraError> {
+    match read_env_arg(args)?.1 {
+        Some(value) => Ok(Value::String(value)),
+        None => Ok(args
+            .get("default")
+            .cloned()
+            .unwrap_or_else(|| Value::String(String::new()))),
+    }
+}
+
+/// Get the value of an environment variable, replacing the `get_env` function built in Tera
+///
+/// # Arguments
+/// * `args` - A `HashMap` containing the function arguments:
+///   - `name`: the name of the variable
+///   - `default`: the value returned when the variable is unset
+///
+/// # Returns
+/// A Tera Value containing the value of the variable. The variables untrusted templates may not
+/// read (see [`env`]) are considered unset.
+///
+/// # Example
+/// ```tera
+/// {{ get_env(name="HOME", default="~") }}
+/// ```
+///
+/// # Errors
+/// Returns a Tera error if the name is missing, or if the variable is unset and no default is
+/// given.
+#[allow(clippy::implicit_hasher)] // Signature must match Tera's function handler type
+pub fn get_env(args: &HashMap<String, Value>) -> Result<Value, TeraError> {
+    match read_env_arg(args)? {
+        (_, Some(value)) => Ok(Value::String(value)),
+        (name, None) => args.get("default").cloned().ok_or_else(|| {
+            TeraError::msg(format!(
+                "Environment variable `{name}` not found and no default given"
+            ))
+        }),
+    }
+}
+
+/// Get the name of the host
+///
+/// # Example
+/// ```tera
+/// {{ username() }}@{{ hostname() }}
+/// ```
+///
+/// # Errors
+/// Never fails, an unknown host name being returned as `localhost`.
+#[allow(clippy::implicit_hasher)] // Signature must match Tera's function handler type
+pub fn hostname(_args: &HashMap<String, Value>) -> Result<Value, TeraError> {
+    let hostname = whoami::fallible::hostname().unwrap_or_else(|_| "localhost".to_string());
+    Ok(Value::String(hostname))
+}
+
+/// Get the name of the current user
+///
+/// # Errors
+/// Never fails.
+#[allow(clippy::implicit_hasher)] // Signature must match Tera's function handler type
+pub fn username(_args: &HashMap<String, Value>) -> Result<Value, TeraError> {
+    Ok(Value::String(whoami::username()))
+}
+
+/// Shortens a path: the home directory becomes `~` and every directory but the last `keep`
+/// ones is cut to its first letter (its first two for hidden directories).
+fn shorten_path(path: &Path, home: Option<&Path>, keep: usize) -> String {
+    let (prefix, rest) = match home.and_then(|home| path.strip_prefix(home).ok()) {
+        Some(rest) => (format!("~{MAIN_SEPARATOR}"), rest),
+        None => (String::new(), path),
+    };
+    let mut root = prefix;
+    let mut names = Vec::new();
+    for component in rest.components() {
+        match component {
+            Component::Prefix(_) | Component::RootDir => {
+                root.push_str(&component.as_os_str().to_string_lossy());
+            }
+            Component::Normal(name) => names.push(name.to_string_lossy().into_owned()),
+            Component::CurDir | Component::ParentDir => {}
+        }
+    }
+
+    let shortened = names.len().saturating_sub(keep);
+    for name in &mut names[..shortened] {
+        let len = if name.starts_with('.') { 2 } else { 1 };
+        *name = name.chars().take(len).collect();
+    }
+    let path = format!("{root}{}", names.join(MAIN_SEPARATOR_STR));
+    match path.strip_suffix(MAIN_SEPARATOR) {
+        Some("~") => "~".to_string(),
+        _ if path.is_empty() => ".".to_string(),
+        _ => path,
+    }
+}
+
+/// Get the current working directory
+///
+/// # Arguments
+/// * `args` - A `HashMap` containing the function arguments:
+///   - `short`: whether the home directory is shown as `~` and the parent directories are cut
+///     to their first letter (`true` by default)
+///   - `keep`: the number of trailing directories kept whole when shortened (`1` by default)
+///
+/// # Returns
+/// A Tera Value containing the directory, e.g. `~/p/titular`
+///
+/// # Example
+/// ```tera
+/// {{ cwd(keep=2) | color(name=c) }}
+/// ```
+///
+/// # Errors
+/// Returns a Tera error if the current directory cannot be read or the arguments are invalid.
+#[allow(clippy::implicit_hasher)] // Signature must match Tera's function handler type
+pub fn cwd(args: &HashMap<String, Value>) -> Result<Value, TeraError> {
+    let short = match args.get("short") {
+        None => true,
+        Some(Value::Bool(short)) => *short,
+        Some(Value::String(s)) => matches!(s.trim(), "true" | "1" | "yes" | "on"),
+        Some(_) => return Err(TeraError::msg("short must be a boolean")),
+    };
+    let keep = match args.get("keep") {
+        None => 1,
+        Some(keep) => keep
+            .as_u64()
+            .or_else(|| keep.as_str().and_then(|s| s.trim().parse().ok()))
+            .and_then(|keep| usize::try_from(keep).ok())
+            .ok_or_else(|| TeraError::msg("keep must be a positive integer"))?,
+    };
+
+    let cwd = std::env::current_dir().map_err(|e| TeraError::msg(e.to_string()))?;
+    if !short {
+        return Ok(Value::String(cwd.to_string_lossy().into_owned()));
+    }
+    let home = dirs_next::home_dir();
+    Ok(Value::String(shorten_path(&cwd, home.as_deref(), keep)))
+}
+
+/// Get the operating system (e.g. `linux`, `macos` or `windows`)
+///
+/// # Errors
+/// Never fails.
+#[allow(clippy::implicit_hasher)] // Signature must match Tera's function handler type
+pub fn os(_args: &HashMap<String, Value>) -> Result<Value, TeraError> {
+    Ok(Value::String(std::env::consts::OS.to_string()))
+}
+
+/// Get the name of the user shell (e.g. `zsh`), read from `SHELL` (`COMSPEC` on Windows)
+///
+/// # Errors
+/// Never fails, an unknown shell being returned as an empty string.
+#[allow(clippy::implicit_hasher)] // Signature must match Tera's function handler type
+pub fn shell(_args: &HashMap<String, Value>) -> Result<Value, TeraError> {
+    let shell = std::env::var("SHELL")
+        .or_else(|_| std::env::var("COMSPEC"))
+        .ok()
+        .and_then(|path| {
+            Path::new(&path)
+                .file_stem()
+                .map(|name| name.to_string_lossy().into_owned())
+        })
+        .unwrap_or_default();
+    Ok(Value::String(shell))
+}
+
+/// Get the width of the terminal in columns, read from `COLUMNS` when the terminal cannot be
+/// queried (80 when unset)
+///
+/// # Errors
+/// Never fails.
+#[allow(clippy::implicit_hasher)] // Signature must match Tera's function handler type
+pub fn term_width(_args: &HashMap<String, Value>) -> Result<Value, TeraError> {
+    #[cfg(any(feature = "minimal", feature = "display"))]
+    let width = crate::term::TERM_SIZE.get_term_width();
+    #[cfg(not(any(feature = "minimal", feature = "display")))]
+    let width = 0;
+
+    let width = Some(width)
+        .filter(|width| *width > 0)
+        .or_else(|| std::env::var("COLUMNS").ok()?.trim().parse().ok())
+        .unwrap_or(80);
+    Ok(Value::Number(width.into()))
+}
+
+/// Get the current date and time
+///
+/// # Arguments
+/// * `args` - A `HashMap` containing the function arguments:
+///   - `format`: a strftime format, defaulting to `defaults.time_format` of the configuration
+///   - `tz`: the timezone, `local` (the default), `utc` or an offset like `+02:00`
+///
+/// # Example
+/// ```tera
+/// {{ now(format="%a %d %b", tz="utc") }}
+/// ```
+///
+/// # Errors
+/// Returns a Tera error if the format or the timezone is invalid.
+#[allow(clippy::implicit_hasher)] // Signature must match Tera's function handler type
+pub fn now(args: &HashMap<String, Value>) -> Result<Value, TeraError> {
+    let tz = parse_timezone(str_arg(args, "tz")?.unwrap_or("local"))?;
+    let format = match str_arg(args, "format")? {
+        Some(format) => format.to_string(),
+        None => ContextManager::get()
+            .read()
+            .map_err(|e| TeraError::msg(e.to_string()))?
+            .get("defaults.time_format")
+            .unwrap_or(DEFAULT_TIME_FORMAT)
+            .to_string(),
+    };
+    format_time(&Utc::now(), &format, tz).map(Value::String)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_env_with_policy() {
+        temp_env::with_vars([("TITULAR_TEST_SECRET", Some("s3cr3t"))], || {
+            // Renders without a policy are untrusted
+            assert!(read_env("TITULAR_TEST_SECRET", None).is_none());
+            let untrusted = TemplatePolicy::new(vec!["TITULAR_TEST_*".to_string()], false);
+            assert!(read_env("TITULAR_TEST_SECRET", Some(&untrusted)).is_some());
+            let untrusted = TemplatePolicy::new(vec!["USER".to_string()], false);
+            assert!(read_env("TITULAR_TEST_SECRET", Some(&untrusted)).is_none());
+            let trusted = TemplatePolicy::new(Vec::new(), true);
+            assert!(read_env("TITULAR_TEST_SECRET", Some(&trusted)).is_some());
+        });
+    }
+
+    #[test]
+    fn test_env_default() {
+        let mut args = HashMap::new();
+        args.insert("name".to_string(), Value::from("TITULAR_TEST_UNSET"));
+        args.insert("default".to_string(), Value::from("none"));
+        temp_env::with_var_unset("TITULAR_TEST_UNSET", || {
+            assert_eq!(env(&args).unwrap(), Value::from("none"));
+        });
+        assert!(env(&HashMap::new()).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_shorten_path() {
+        let home = Path::new("/home/user");
+        let path = Path::new("/home/user/projects/.config/titular");
+        assert_eq!(shorten_path(path, Some(home), 1), "~/p/.c/titular");
+        assert_eq!(shorten_path(path, Some(home), 2), "~/p/.config/titular");
+        assert_eq!(shorten_path(home, Some(home), 1), "~");
+        assert_eq!(
+            shorten_path(Path::new("/usr/local/lib"), Some(home), 1),
+            "/u/l/lib"
+        );
+        assert_eq!(shorten_path(Path::new("/"), Some(home), 1), "/");
+        assert_eq!(shorten_path(Path::new("/usr/lib"), None, 5), "/usr/lib");
+    }
+
+    #[test]
+    fn test_now() {
+        let mut args = HashMap::new();
+        args.insert("format".to_string(), Value::from("%Y"));
+        args.insert("tz".to_string(), Value::from("utc"));
+        let year = Utc::now().format("%Y").to_string();
+        assert_eq!(now(&args).unwrap(), Value::from(year));
+
+        args.insert("tz".to_string(), Value::from("Mars/Olympus"));
+        assert!(now(&args).is_err());
+    }
+
+    #[test]
+    fn test_shell_and_term_width() {
+        temp_env::with_vars(
+            [("SHELL", Some("/usr/bin/zsh")), ("COLUMNS", Some("123"))],
+            || {
+                assert_eq!(shell(&HashMap::new()).unwrap(), Value::from("zsh"));
+                let width = term_width(&HashMap::new()).unwrap();
+                assert!(width.as_u64().unwrap() > 0);
+            },
+        );
+    }
+}
@@ -0,0 +1,320 @@
+//! The git index: the content of the next commit, with the stat data of the work tree files.
+
+use std::fs;
+use std::path::Path;
+
+use super::object::ObjectId;
+use crate::error::{Error, Result};
+
+/// The mode of a symbolic link
+pub const MODE_SYMLINK: u32 = 0o120_000;
+/// The mode of a submodule
+pub const MODE_GITLINK: u32 = 0o160_000;
+/// The mode of a directory
+pub const MODE_TREE: u32 = 0o040_000;
+
+const FLAG_ASSUME_VALID: u16 = 0x8000;
+const FLAG_EXTENDED: u16 = 0x4000;
+const EXTENDED_SKIP_WORKTREE: u16 = 0x4000;
+const EXTENDED_INTENT_TO_ADD: u16 = 0x2000;
+
+/// An entry of the index.
+#[derive(Debug, Clone)]
+pub struct IndexEntry {
+    pub path: Vec<u8>,
+    pub mode: u32,
+    pub id: ObjectId,
+    pub size: u32,
+    pub mtime: (u32, u32),
+    pub stage: u8,
+    pub assume_valid: bool,
+    pub skip_worktree: bool,
+    pub intent_to_add: bool,
+}
+
+/// A parsed index file.
+#[derive(Debug, Clone)]
+pub struct Index {
+    pub entries: Vec<IndexEntry>,
+    /// The tree of the whole index recorded by the `TREE` extension, when still valid
+    pub root_tree: Option<ObjectId>,
+    /// The modification time of the index file, in seconds and nanoseconds
+    pub mtime: Option<(u32, u32)>,
+}
+
+fn corrupt() -> Error {
+    Error::Msg("corrupt git index".to_string())
+}
+
+/// A cursor over the bytes of the index.
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + len)
+            .ok_or_else(corrupt)?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_be_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    /// Reads up to and past the next NUL byte.
+    fn until_nul(&mut self) -> Result<&'a [u8]> {
+        let len = self.data[self.pos..]
+            .iter()
+            .position(|b| *b == 0)
+            .ok_or_else(corrupt)?;
+        let bytes = self.take(len)?;
+        self.pos += 1;
+        Ok(bytes)
+    }
+
+    /// Reads a variable-length integer as encoded by index version 4.
+    fn varint(&mut self) -> Result<usize> {
+        let mut byte = self.take(1)?[0];
+        let mut value = usize::from(byte & 0x7f);
+        while byte & 0x80 != 0 {
+            byte = self.take(1)?[0];
+            value = (value + 1).checked_shl(7).ok_or_else(corrupt)? | usize::from(byte & 0x7f);
+        }
+        Ok(value)
+    }
+}
+
+impl Index {
+    /// Reads the index file.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or is not an index of version 2 to 4, or is a
+    /// split or sparse index.
+    pub fn open(path: &Path) -> Result<Self> {
+        let data = fs::read(path)?;
+        let mut index = Self::parse(&data)?;
+        index.mtime = fs::metadata(path)
+            .and_then(|meta| meta.modified())
+            .ok()
+            .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+            .and_then(|time| Some((u32::try_from(time.as_secs()).ok()?, time.subsec_nanos())));
+        Ok(index)
+    }
+
+    /// Parses the content of an index file.
+    ///
+    /// # Errors
+    /// Returns an error if the data is not an index of version 2 to 4, or is a split or sparse
+    /// index.
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        // The content ends with its checksum
+        let content = data
+            .get(..data.len().saturating_sub(20))
+            .filter(|content| content.starts_with(b"DIRC"))
+            .ok_or_else(corrupt)?;
+        let mut reader = Reader {
+            data: content,
+            pos: 4,
+        };
+        let version = reader.u32()?;
+        if !(2..=4).contains(&version) {
+            return Err(Error::Msg(format!(
+                "unsupported git index version {version}"
+            )));
+        }
+        let count = reader.u32()?;
+
+        // The count is not trusted further than the smallest entries (62 bytes) the data holds
+        let mut entries = Vec::with_capacity((count as usize).min(content.len() / 62));
+        let mut previous_path: Vec<u8> = Vec::new();
+        for _ in 0..count {
+            let start = reader.pos;
+            reader.take(8)?; // ctime
+            let mtime = (reader.u32()?, reader.u32()?);
+            reader.take(8)?; // dev and ino
+            let mode = reader.u32()?;
+            reader.take(8)?; // uid and gid
+            let size = reader.u32()?;
+            let id = ObjectId::from_bytes(reader.take(20)?).ok_or_else(corrupt)?;
+            let flags = reader.u16()?;
+            let extended = if flags & FLAG_EXTENDED != 0 && version >= 3 {
+                reader.u16()?
+            } else {
+                0
+            };
+
+            let path = if version == 4 {
+                // The path is the previous one, cut by a number of bytes, followed by a suffix
+                let strip = reader.varint()?;
+                let keep = previous_path.len().checked_sub(strip).ok_or_else(corrupt)?;
+                let mut path = previous_path[..keep].to_vec();
+                path.extend_from_slice(reader.until_nul()?);
+                path
+            } else {
+                let path = reader.until_nul()?.to_vec();
+                // Entries are padded with NUL bytes to a multiple of 8 bytes
+                let len = reader.pos - start;
+                reader.take((8 - len % 8) % 8)?;
+                path
+            };
+            previous_path.clone_from(&path);
+
+            entries.push(IndexEntry {
+                path,
+                mode,
+                id,
+                size,
+                mtime,
+                stage: ((flags >> 12) & 3) as u8,
+                assume_valid: flags & FLAG_ASSUME_VALID != 0,
+                skip_worktree: extended & EXTENDED_SKIP_WORKTREE != 0,
+                intent_to_add: extended & EXTENDED_INTENT_TO_ADD != 0,
+            });
+        }
+
+        let mut root_tree = None;
+        while reader.pos + 8 <= content.len() {
+            let signature = reader.take(4)?;
+            let len = reader.u32()? as usize;
+            let extension = reader.take(len)?;
+            match signature {
+                b"TREE" => root_tree = Self::parse_root_tree(extension),
+                // The entries of a split index are partly in a shared index, and those of a
+                // sparse index partly collapsed into directories
+                b"link" => return Err(Error::Msg("split git index not supported".to_string())),
+                b"sdir" => return Err(Error::Msg("sparse git index not supported".to_string())),
+                _ => {}
+            }
+        }
+
+        Ok(Self {
+            entries,
+            root_tree,
+            mtime: None,
+        })
+    }
+
+    /// Reads the tree of the root directory from the `TREE` extension: its first entry, with an
+    /// empty path, whose entry count is -1 once the index no longer matches it.
+    fn parse_root_tree(extension: &[u8]) -> Option<ObjectId> {
+        let mut reader = Reader {
+            data: extension,
+            pos: 0,
+        };
+        if !reader.until_nul().ok()?.is_empty() {
+            return None;
+        }
+        let line_len = extension[reader.pos..].iter().position(|b| *b == b'\n')?;
+        let line = std::str::from_utf8(reader.take(line_len).ok()?).ok()?;
+        reader.pos += 1;
+        let entry_count: i64 = line.split(' ').next()?.parse().ok()?;
+        if entry_count < 0 {
+            return None;
+        }
+        ObjectId::from_bytes(reader.take(20).ok()?)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// An entry to write: path, mode, id, size, mtime and flags.
+    pub(crate) type TestEntry<'a> = (&'a str, u32, ObjectId, u32, (u32, u32), u16);
+
+    /// Builds an index of version 2.
+    pub(crate) fn build_index(entries: &[TestEntry]) -> Vec<u8> {
+        let mut data = b"DIRC".to_vec();
+        data.extend_from_slice(&2u32.to_be_bytes());
+        data.extend_from_slice(&(entries.len() as u32).to_be_bytes());
+        for (path, mode, id, size, mtime, flags) in entries {
+            let start = data.len();
+            data.extend_from_slice(&[0; 8]);
+            data.extend_from_slice(&mtime.0.to_be_bytes());
+            data.extend_from_slice(&mtime.1.to_be_bytes());
+            data.extend_from_slice(&[0; 8]);
+            data.extend_from_slice(&mode.to_be_bytes());
+            data.extend_from_slice(&[0; 8]);
+            data.extend_from_slice(&size.to_be_bytes());
+            data.extend_from_slice(&id.0);
+            let flags = flags | path.len().min(0xfff) as u16;
+            data.extend_from_slice(&flags.to_be_bytes());
+            data.extend_from_slice(path.as_bytes());
+            let len = data.len() - start;
+            data.extend(std::iter::repeat_n(0, 8 - len % 8));
+        }
+        let checksum = sha1_smol::Sha1::from(&data).digest().bytes();
+        data.extend_from_slice(&checksum);
+        data
+    }
+
+    #[test]
+    fn test_parse_index() {
+        let id = ObjectId([7; 20]);
+        let data = build_index(&[
+            ("README.md", 0o100_644, id, 12, (1, 2), 0),
+            ("src/main.rs", 0o100_755, id, 30, (3, 4), 2 << 12),
+        ]);
+        let index = Index::parse(&data).unwrap();
+        assert_eq!(index.entries.len(), 2);
+        assert_eq!(index.entries[0].path, b"README.md");
+        assert_eq!(index.entries[0].size, 12);
+        assert_eq!(index.entries[1].path, b"src/main.rs");
+        assert_eq!(index.entries[1].mode, 0o100_755);
+        assert_eq!(index.entries[1].mtime, (3, 4));
+        assert_eq!(index.entries[1].stage, 2);
+        assert!(index.root_tree.is_none());
+
+        assert!(Index::parse(b"not an index at all, not at all").is_err());
+    }
+
+    #[test]
+    fn test_parse_untrusted_index() {
+        // A huge entry count with no entries behind it
+        let mut data = b"DIRC\0\0\0\x02\xff\xff\xff\xff".to_vec();
+        data.extend_from_slice(&sha1_smol::Sha1::from(&data).digest().bytes());
+        assert!(Index::parse(&data).is_err());
+
+        // Split and sparse indexes
+        for signature in [b"link", b"sdir"] {
+            let mut data = build_index(&[]);
+            data.truncate(data.len() - 20);
+            data.extend_from_slice(signature);
+            data.extend_from_slice(&20u32.to_be_bytes());
+            data.extend_from_slice(&[0; 20]);
+            data.extend_from_slice(&sha1_smol::Sha1::from(&data).digest().bytes());
+            assert!(Index::parse(&data).is_err());
+        }
+    }
+
+    #[test]
+    fn test_parse_root_tree() {
+        let id = ObjectId([9; 20]);
+        let mut extension = b"\x002 1\n".to_vec();
+        extension.extend_from_slice(&id.0);
+        extension.extend_from_slice(b"src\x001 0\n");
+        extension.extend_from_slice(&[1; 20]);
+        assert_eq!(Index::parse_root_tree(&extension), Some(id));
+        assert_eq!(Index::parse_root_tree(b"\x00-1 1\nsrc\x001 0\n"), None);
+    }
+
+    #[test]
+    fn test_varint() {
+        // 0x80 0x00 encodes 128 in the offset encoding of git
+        let mut reader = Reader {
+            data: &[0x80, 0x00, 0x05],
+            pos: 0,
+        };
+        assert_eq!(reader.varint().unwrap(), 128);
+        assert_eq!(reader.varint().unwrap(), 5);
+    }
+}
@@ -0,0 +1,901 @@
+//! Read-only access to git repositories, without running `git`.
+//!
+//! The repository of a directory is found by looking for a `.git` directory (or the `.git` file
+//! of a linked worktree or submodule) in it and its parents. Its branch, commits and state are
+//! then read from `HEAD`, the refs, `packed-refs`, the index and the object database.
+//!
+//! Not supported:
+//! - repositories using SHA-256 object ids (`extensions.objectformat = sha256`), which are
+//!   treated as if there was no repository
+//! - split indexes (`core.splitIndex`) and sparse indexes (`index.sparse`), whose state cannot
+//!   be read: such work trees are never reported as dirty
+
+mod index;
+mod object;
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub use index::{Index, IndexEntry};
+pub use object::{Object, ObjectDb, ObjectId, ObjectKind};
+
+use crate::debug;
+use crate::error::{Error, Result};
+use index::{MODE_GITLINK, MODE_SYMLINK, MODE_TREE};
+
+/// Longest chain of symbolic refs followed
+const MAX_SYMREF_DEPTH: usize = 5;
+
+/// What `HEAD` points to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Head {
+    /// A branch, by its full ref name (e.g. `refs/heads/main`); it may have no commit yet
+    Branch(String),
+    /// A commit
+    Detached(ObjectId),
+}
+
+/// The parts of a commit the repository reads.
+#[derive(Debug, Clone)]
+pub struct Commit {
+    pub tree: ObjectId,
+    pub parents: Vec<ObjectId>,
+    /// The commit time, in seconds since the epoch
+    pub time: i64,
+}
+
+impl Commit {
+    fn parse(data: &[u8]) -> Option<Self> {
+        let mut tree = None;
+        let mut parents = Vec::new();
+        let mut time = 0;
+        for line in data.split(|b| *b == b'\n') {
+            if line.is_empty() {
+                break;
+            }
+            let line = std::str::from_utf8(line).ok()?;
+            match line.split_once(' ') {
+                Some(("tree", id)) => tree = ObjectId::from_hex(id),
+                Some(("parent", id)) => parents.push(ObjectId::from_hex(id)?),
+                Some(("committer", signature)) => {
+                    // "Name <email> 1700000000 +0100"
+                    time = signature.rsplit(' ').nth(1)?.parse().ok()?;
+                }
+                _ => {}
+            }
+        }
+        Some(Self {
+            tree: tree?,
+            parents,
+            time,
+        })
+    }
+}
+
+/// How far a branch is from its upstream.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AheadBehind {
+    /// Commits of the branch missing from the upstream
+    pub ahead: usize,
+    /// Commits of the upstream missing from the branch
+    pub behind: usize,
+    /// The short name of the upstream (e.g. `origin/main`)
+    pub upstream: String,
+}
+
+/// A git repository with a work tree.
+pub struct Repository {
+    git_dir: PathBuf,
+    common_dir: PathBuf,
+    work_dir: PathBuf,
+    objects: ObjectDb,
+}
+
+impl Repository {
+    /// Finds the repository containing the directory.
+    ///
+    /// # Returns
+    /// `None` if the directory is not in a repository, or in a repository using SHA-256 object
+    /// ids.
+    #[must_use]
+    pub fn discover(dir: &Path) -> Option<Self> {
+        dir.ancestors()
+            .find_map(|dir| {
+                let dot_git = dir.join(".git");
+                let git_dir = if dot_git.is_dir() {
+                    dot_git
+                } else if dot_git.is_file() {
+                    // Linked worktrees and submodules point to their git directory
+                    let content = fs::read_to_string(&dot_git).ok()?;
+                    let target = content.trim_end().strip_prefix("gitdir:")?.trim();
+                    dir.join(target)
+                } else {
+                    return None;
+                };
+                let repo = Self::open(git_dir, dir.to_path_buf())?;
+                // Object ids are SHA-1 hashes, the repository is not read further
+                if repo
+                    .config("extensions.objectformat")
+                    .is_some_and(|format| !format.eq_ignore_ascii_case("sha1"))
+                {
+                    debug!(
+                        "git: {} uses SHA-256 object ids, which are not supported",
+                        repo.work_dir.display()
+                    );
+                    return Some(None);
+                }
+                Some(Some(repo))
+            })
+            .flatten()
+    }
+
+    /// Opens the repository of a git directory and its work tree.
+    fn open(git_dir: PathBuf, work_dir: PathBuf) -> Option<Self> {
+        if !git_dir.join("HEAD").is_file() {
+            return None;
+        }
+        // Linked worktrees share the refs and objects of the main repository
+        let common_dir = match fs::read_to_string(git_dir.join("commondir")) {
+            Ok(common_dir) => {
+                let common_dir = git_dir.join(common_dir.trim_end());
+                fs::canonicalize(&common_dir).unwrap_or(common_dir)
+            }
+            Err(_) => git_dir.clone(),
+        };
+        let objects = ObjectDb::new(common_dir.join("objects"));
+        Some(Self {
+            git_dir,
+            common_dir,
+            work_dir,
+            objects,
+        })
+    }
+
+    /// The root of the work tree.
+    #[must_use]
+    pub fn work_dir(&self) -> &Path {
+        &self.work_dir
+    }
+
+    /// The name of the repository: the directory of the main work tree.
+    #[must_use]
+    pub fn name(&self) -> String {
+        let main_dir = match self.common_dir.file_name() {
+            Some(name) if name == ".git" => self.common_dir.parent(),
+            _ => Some(self.work_dir.as_path()),
+        };
+        main_dir
+            .and_then(Path::file_name)
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    /// Reads `HEAD`.
+    ///
+    /// # Errors
+    /// Returns an error if `HEAD` cannot be read or is invalid.
+    pub fn head(&self) -> Result<Head> {
+        let content = fs::read_to_string(self.git_dir.join("HEAD"))?;
+        let content = content.trim_end();
+        if let Some(name) = content.strip_prefix("ref:") {
+            return Ok(Head::Branch(name.trim().to_string()));
+        }
+        ObjectId::from_hex(content)
+            .map(Head::Detached)
+            .ok_or_else(|| Error::Msg("invalid git HEAD".to_string()))
+    }
+
+    /// The short name of the current branch, `None` when `HEAD` is detached.
+    ///
+    /// # Errors
+    /// Returns an error if `HEAD` cannot be read.
+    pub fn branch(&self) -> Result<Option<String>> {
+        Ok(match self.head()? {
+            Head::Branch(name) => Some(
+                name.strip_prefix("refs/heads/")
+                    .unwrap_or(&name)
+                    .to_string(),
+            ),
+            Head::Detached(_) => None,
+        })
+    }
+
+    /// The commit of `HEAD`, `None` on a branch without commits.
+    ///
+    /// # Errors
+    /// Returns an error if `HEAD` or the refs cannot be read.
+    pub fn head_commit(&self) -> Result<Option<ObjectId>> {
+        match self.head()? {
+            Head::Branch(name) => self.resolve_ref(&name),
+            Head::Detached(id) => Ok(Some(id)),
+        }
+    }
+
+    /// Resolves a full ref name (e.g. `refs/heads/main`), following symbolic refs.
+    ///
+    /// # Errors
+    /// Returns an error if the refs cannot be read.
+    pub fn resolve_ref(&self, name: &str) -> Result<Option<ObjectId>> {
+        let mut name = name.to_string();
+        for _ in 0..MAX_SYMREF_DEPTH {
+            match fs::read_to_string(self.common_dir.join(&name)) {
+                Ok(content) => {
+                    let content = content.trim_end();
+                    match content.strip_prefix("ref:") {
+                        Some(target) => name = target.trim().to_string(),
+                        None => return Ok(ObjectId::from_hex(content)),
+                    }
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    return self.packed_ref(&name);
+                }
+                // A directory of refs, e.g. `refs/heads/feature` of `refs/heads/feature/x`
+                Err(_) if self.common_dir.join(&name).is_dir() => return self.packed_ref(&name),
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(None)
+    }
+
+    /// Looks up a ref in `packed-refs`.
+    fn packed_ref(&self, name: &str) -> Result<Option<ObjectId>> {
+        let content = match fs::read_to_string(self.common_dir.join("packed-refs")) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(content
+            .lines()
+            // Skip the header and the peeled tags
+            .filter(|line| !line.starts_with('#') && !line.starts_with('^'))
+            .filter_map(|line| line.split_once(' '))
+            .find(|(_, ref_name)| *ref_name == name)
+            .and_then(|(id, _)| ObjectId::from_hex(id)))
+    }
+
+    /// Reads a commit.
+    ///
+    /// # Errors
+    /// Returns an error if the object is missing or is not a commit.
+    pub fn commit(&self, id: &ObjectId) -> Result<Commit> {
+        self.objects
+            .read(id)?
+            .filter(|object| object.kind == ObjectKind::Commit)
+            .and_then(|object| Commit::parse(&object.data))
+            .ok_or_else(|| Error::Msg(format!("invalid git commit {id}")))
+    }
+
+    /// Reads a git configuration value of the repository, e.g. `branch.main.remote`.
+    ///
+    /// Only the `config` file of the repository is read, not the global configuration.
+    #[must_use]
+    pub fn config(&self, key: &str) -> Option<String> {
+        let content = fs::read_to_string(self.common_dir.join("config")).ok()?;
+        config_value(&content, key)
+    }
+
+    /// Whether tracked files differ from `HEAD`, in the index or the work tree.
+    ///
+    /// Like `git describe --dirty`, untracked files are not taken into account.
+    ///
+    /// # Errors
+    /// Returns an error if the index or the objects cannot be read.
+    pub fn is_dirty(&self) -> Result<bool> {
+        let index = match Index::open(&self.git_dir.join("index")) {
+            Ok(index) => index,
+            Err(Error::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+                Index::parse(&empty_index())?
+            }
+            Err(e) => return Err(e),
+        };
+        let check_mode = self
+            .config("core.filemode")
+            .is_none_or(|value| config_bool(&value));
+        Ok(self.worktree_changed(&index, check_mode) || self.index_changed(&index)?)
+    }
+
+    /// Whether a tracked file of the work tree differs from the index.
+    fn worktree_changed(&self, index: &Index, check_mode: bool) -> bool {
+        index.entries.iter().any(|entry| {
+            if entry.stage > 0 || entry.intent_to_add {
+                // Conflicts and files to be added are changes
+                return true;
+            }
+            if entry.assume_valid || entry.skip_worktree || entry.mode == MODE_GITLINK {
+                return false;
+            }
+            let path = self
+                .work_dir
+                .join(String::from_utf8_lossy(&entry.path).as_ref());
+            !file_matches(&path, entry, index.mtime, check_mode)
+        })
+    }
+
+    /// Whether the index differs from the tree of `HEAD`.
+    fn index_changed(&self, index: &Index) -> Result<bool> {
+        let Some(head) = self.head_commit()? else {
+            return Ok(!index.entries.is_empty());
+        };
+        let tree = self.commit(&head)?.tree;
+        if index.root_tree == Some(tree) {
+            return Ok(false);
+        }
+
+        let mut files = HashMap::new();
+        self.flatten_tree(&tree, &mut Vec::new(), &mut files)?;
+        let staged = index.entries.iter().filter(|entry| entry.stage == 0);
+        if staged.clone().count() != files.len() {
+            return Ok(true);
+        }
+        Ok(staged
+            .into_iter()
+            .any(|entry| files.get(&entry.path) != Some(&(entry.mode, entry.id))))
+    }
+
+    /// Lists the files of a tree, recursively, with their mode and id.
+    fn flatten_tree(
+        &self,
+        id: &ObjectId,
+        prefix: &mut Vec<u8>,
+        files: &mut HashMap<Vec<u8>, (u32, ObjectId)>,
+    ) -> Result<()> {
+        let tree = self
+            .objects
+            .read(id)?
+            .filter(|object| object.kind == ObjectKind::Tree)
+            .ok_or_else(|| Error::Msg(format!("invalid git tree {id}")))?;
+        let invalid = || Error::Msg(format!("invalid git tree {id}"));
+
+        // Entries are "<octal mode> <name>\0<20 bytes id>"
+        let mut rest = tree.data.as_slice();
+        while !rest.is_empty() {
+            let space = rest.iter().position(|b| *b == b' ').ok_or_else(invalid)?;
+            let nul = rest.iter().position(|b| *b == 0).ok_or_else(invalid)?;
+            let mode = std::str::from_utf8(&rest[..space])
+                .ok()
+                .and_then(|mode| u32::from_str_radix(mode, 8).ok())
+                .ok_or_else(invalid)?;
+            let name = rest.get(space + 1..nul).ok_or_else(invalid)?;
+            let entry_id = rest
+                .get(nul + 1..nul + 21)
+                .and_then(ObjectId::from_bytes)
+                .ok_or_else(invalid)?;
+            rest = &rest[nul + 21..];
+
+            let len = prefix.len();
+            prefix.extend_from_slice(name);
+            if mode == MODE_TREE {
+                prefix.push(b'/');
+                self.flatten_tree(&entry_id, prefix, files)?;
+            } else {
+                files.insert(prefix.clone(), (mode, entry_id));
+            }
+            prefix.truncate(len);
+        }
+        Ok(())
+    }
+
+    /// The upstream of the current branch, as its full ref name and its short name.
+    fn upstream(&self) -> Result<Option<(String, String)>> {
+        let Some(branch) = self.branch()? else {
+            return Ok(None);
+        };
+        let (Some(remote), Some(merge)) = (
+            self.config(&format!("branch.{branch}.remote")),
+            self.config(&format!("branch.{branch}.merge")),
+        ) else {
+            return Ok(None);
+        };
+        let merge_branch = merge.strip_prefix("refs/heads/").unwrap_or(&merge);
+        Ok(Some(if remote == "." {
+            (merge.clone(), merge_branch.to_string())
+        } else {
+            (
+                format!("refs/remotes/{remote}/{merge_branch}"),
+                format!("{remote}/{merge_branch}"),
+            )
+        }))
+    }
+
+    /// How far the current branch is from its upstream.
+    ///
+    /// # Returns
+    /// `None` when `HEAD` is detached or the branch has no upstream.
+    ///
+    /// # Errors
+    /// Returns an error if the refs or the commits cannot be read.
+    pub fn ahead_behind(&self) -> Result<Option<AheadBehind>> {
+        let Some((upstream_ref, upstream)) = self.upstream()? else {
+            return Ok(None);
+        };
+        let (Some(local), Some(remote)) = (self.head_commit()?, self.resolve_ref(&upstream_ref)?)
+        else {
+            return Ok(None);
+        };
+        let (ahead, behind) = self.count_exclusive(local, remote)?;
+        Ok(Some(AheadBehind {
+            ahead,
+            behind,
+            upstream,
+        }))
+    }
+
+    /// Counts the commits reachable from only one of two commits.
+    ///
+    /// Commits are walked newest first, marking which side reaches them, until every commit left
+    /// to walk is reached by both: their ancestors are then common to both sides.
+    fn count_exclusive(&self, left: ObjectId, right: ObjectId) -> Result<(usize, usize)> {
+        const LEFT: u8 = 1;
+        const RIGHT: u8 = 2;
+        const BOTH: u8 = LEFT | RIGHT;
+
+        let mut flags: HashMap<ObjectId, u8> = HashMap::new();
+        let mut walked: HashMap<ObjectId, u8> = HashMap::new();
+        let mut commits: HashMap<ObjectId, Commit> = HashMap::new();
+        let mut queue = BinaryHeap::new();
+        for (id, flag) in [(left, LEFT), (right, RIGHT)] {
+            *flags.entry(id).or_default() |= flag;
+            let commit = self.commit(&id)?;
+            queue.push((commit.time, Reverse(id)));
+            commits.insert(id, commit);
+        }
+
+        while queue.iter().any(|(_, Reverse(id))| flags[id] != BOTH) {
+            let Some((_, Reverse(id))) = queue.pop() else {
+                break;
+            };
+            let flag = flags[&id];
+            if walked.insert(id, flag) == Some(flag) {
+                continue;
+            }
+            let parents = commits[&id].parents.clone();
+            for parent in parents {
+                let parent_flags = flags.entry(parent).or_default();
+                if *parent_flags | flag == *parent_flags {
+                    continue;
+                }
+                *parent_flags |= flag;
+                if let std::collections::hash_map::Entry::Vacant(entry) = commits.entry(parent) {
+                    entry.insert(self.commit(&parent)?);
+                }
+                queue.push((commits[&parent].time, Reverse(parent)));
+            }
+        }
+
+        // With clock skew, a commit may have been walked before the other side reached it: mark
+        // the walked ancestors of the commits reached by both sides as reached by both too
+        let mut common: Vec<ObjectId> = queue.into_iter().map(|(_, Reverse(id))| id).collect();
+        while let Some(id) = common.pop() {
+            for parent in commits
+                .get(&id)
+                .map(|commit| &commit.parents)
+                .into_iter()
+                .flatten()
+            {
+                if let Some(flag) = flags.get_mut(parent).filter(|flag| **flag != BOTH) {
+                    *flag = BOTH;
+                    common.push(*parent);
+                }
+            }
+        }
+
+        let count = |side| flags.values().filter(|flag| **flag == side).count();
+        Ok((count(LEFT), count(RIGHT)))
+    }
+}
+
+/// An index without entries, for repositories without an index file.
+fn empty_index() -> Vec<u8> {
+    let mut data = b"DIRC\0\0\0\x02\0\0\0\0".to_vec();
+    data.extend_from_slice(&sha1_smol::Sha1::from(&data).digest().bytes());
+    data
+}
+
+/// Whether a work tree file matches its index entry: by its stat data when unchanged since the
+/// index was written, else by its content.
+fn file_matches(
+    path: &Path,
+    entry: &IndexEntry,
+    index_mtime: Option<(u32, u32)>,
+    check_mode: bool,
+) -> bool {
+    let Ok(meta) = fs::symlink_metadata(path) else {
+        return false;
+    };
+    let is_symlink = entry.mode == MODE_SYMLINK;
+    if meta.file_type().is_symlink() != is_symlink || (!is_symlink && !meta.is_file()) {
+        return false;
+    }
+    #[cfg(unix)]
+    if check_mode && !is_symlink {
+        use std::os::unix::fs::PermissionsExt;
+        let executable = meta.permissions().mode() & 0o111 != 0;
+        if executable != (entry.mode & 0o111 != 0) {
+            return false;
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = check_mode;
+    // The index stores the size truncated to 32 bits
+    #[allow(clippy::cast_possible_truncation)]
+    if meta.len() as u32 != entry.size {
+        return false;
+    }
+
+    let mtime = meta
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+        .and_then(|time| Some((u32::try_from(time.as_secs()).ok()?, time.subsec_nanos())));
+    // A file modified in the same instant the index was written may have changed since
+    let racy = index_mtime.is_none_or(|index_mtime| mtime >= Some(index_mtime));
+    if mtime == Some(entry.mtime) && !racy {
+        return true;
+    }
+
+    let content = if is_symlink {
+        fs::read_link(path).map(|target| target.to_string_lossy().into_owned().into_bytes())
+    } else {
+        fs::read(path)
+    };
+    content.is_ok_and(|content| ObjectId::hash(ObjectKind::Blob, &content) == entry.id)
+}
+
+/// Whether a git configuration value is true.
+fn config_bool(value: &str) -> bool {
+    matches!(
+        value.to_ascii_lowercase().as_str(),
+        "true" | "yes" | "on" | "1" | ""
+    )
+}
+
+/// Reads a value of a git configuration file: `section.key` or `section.subsection.key`, the
+/// section and the key being case-insensitive. The last value set wins.
+fn config_value(content: &str, key: &str) -> Option<String> {
+    let (section, name) = key.rsplit_once('.')?;
+    let (section, subsection) = match section.split_once('.') {
+        Some((section, subsection)) => (section, Some(subsection)),
+        None => (section, None),
+    };
+
+    let mut in_section = false;
+    let mut value = None;
+    for line in content.lines() {
+        let line = line.trim();
+        if let Some(header) = line.strip_prefix('[') {
+            let header = header.split(']').next().unwrap_or_default();
+            let (header_section, header_subsection) = match header.split_once(' ') {
+                Some((header_section, rest)) => {
+                    (header_section, Some(rest.trim().trim_matches('"')))
+                }
+                None => (header, None),
+            };
+            in_section =
+                header_section.eq_ignore_ascii_case(section) && header_subsection == subsection;
+            continue;
+        }
+        if !in_section || line.starts_with(['#', ';']) {
+            continue;
+        }
+        let (line_key, line_value) = match line.split_once('=') {
+            Some((line_key, line_value)) => (line_key.trim(), line_value.trim()),
+            // A key without a value is true
+            None => (line, ""),
+        };
+        if line_key.eq_ignore_ascii_case(name) {
+            value = Some(line_value.trim_matches('"').to_string());
+        }
+    }
+    value
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use miniz_oxide::deflate::compress_to_vec_zlib;
+
+    /// A repository written by hand in a temporary directory.
+    pub(crate) struct Fixture {
+        dir: tempfile::TempDir,
+    }
+
+    impl Fixture {
+        pub(crate) fn new() -> Self {
+            let dir = tempfile::tempdir().unwrap();
+            let git_dir = dir.path().join(".git");
+            fs::create_dir_all(git_dir.join("objects")).unwrap();
+            fs::create_dir_all(git_dir.join("refs/heads")).unwrap();
+            fs::write(git_dir.join("HEAD"), "ref: refs/heads/main\n").unwrap();
+            Self { dir }
+        }
+
+        pub(crate) fn path(&self) -> &Path {
+            self.dir.path()
+        }
+
+        pub(crate) fn git(&self, path: &str, content: &str) {
+            let path = self.path().join(".git").join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+
+        pub(crate) fn object(&self, kind: ObjectKind, data: &[u8]) -> ObjectId {
+            let id = ObjectId::hash(kind, data);
+            let hex = id.to_string();
+            let mut raw = format!("{} {}\0", kind.name(), data.len()).into_bytes();
+            raw.extend_from_slice(data);
+            let dir = self.path().join(".git/objects").join(&hex[..2]);
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join(&hex[2..]), compress_to_vec_zlib(&raw, 6)).unwrap();
+            id
+        }
+
+        pub(crate) fn tree(&self, entries: &[(&str, &str, ObjectId)]) -> ObjectId {
+            let mut data = Vec::new();
+            for (mode, name, id) in entries {
+                data.extend_from_slice(format!("{mode} {name}\0").as_bytes());
+                data.extend_from_slice(&id.0);
+            }
+            self.object(ObjectKind::Tree, &data)
+        }
+
+        pub(crate) fn commit(&self, tree: ObjectId, parents: &[ObjectId], time: i64) -> ObjectId {
+            let mut data = format!("tree {tree}\n");
+            for parent in parents {
+                data.push_str(&format!("parent {parent}\n"));
+            }
+            data.push_str(&format!(
+                "author A <a@example.com> {time} +0000\ncommitter A <a@example.com> {time} +0000\n\nmessage\n"
+            ));
+            self.object(ObjectKind::Commit, data.as_bytes())
+        }
+
+        /// Writes a work tree file and an index tracking the given files.
+        pub(crate) fn stage(&self, files: &[(&str, &str)]) {
+            let mut entries = Vec::new();
+            for (name, content) in files {
+                let path = self.path().join(name);
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(&path, content).unwrap();
+                let id = ObjectId::hash(ObjectKind::Blob, content.as_bytes());
+                entries.push((*name, 0o100_644, id, content.len() as u32, (0, 0), 0));
+            }
+            fs::write(
+                self.path().join(".git/index"),
+                index::tests::build_index(&entries),
+            )
+            .unwrap();
+        }
+
+        pub(crate) fn repo(&self) -> Repository {
+            Repository::discover(self.path()).unwrap()
+        }
+    }
+
+    #[test]
+    fn test_discover() {
+        let fixture = Fixture::new();
+        let sub = fixture.path().join("a/b");
+        fs::create_dir_all(&sub).unwrap();
+        let repo = Repository::discover(&sub).unwrap();
+        assert_eq!(repo.work_dir(), fixture.path());
+        assert_eq!(
+            repo.name(),
+            fixture.path().file_name().unwrap().to_string_lossy()
+        );
+
+        let outside = tempfile::tempdir().unwrap();
+        assert!(Repository::discover(outside.path()).is_none());
+    }
+
+    #[test]
+    fn test_sha256_repository() {
+        let fixture = Fixture::new();
+        fixture.git("config", "[extensions]\n\tobjectFormat = sha256\n");
+        // Not mistaken for a repository around it either
+        let sub = fixture.path().join("sub");
+        fs::create_dir_all(sub.join(".git")).unwrap();
+        assert!(Repository::discover(fixture.path()).is_none());
+        assert!(Repository::discover(&sub).is_none());
+    }
+
+    #[test]
+    fn test_linked_worktree() {
+        let fixture = Fixture::new();
+        let commit = fixture.commit(fixture.tree(&[]), &[], 1);
+        fixture.git("refs/heads/feature", &format!("{commit}\n"));
+        fixture.git("worktrees/wt/HEAD", "ref: refs/heads/feature\n");
+        fixture.git("worktrees/wt/commondir", "../..\n");
+
+        let worktree = tempfile::tempdir().unwrap();
+        let git_dir = fixture.path().join(".git/worktrees/wt");
+        fs::write(
+            worktree.path().join(".git"),
+            format!("gitdir: {}\n", git_dir.display()),
+        )
+        .unwrap();
+        let repo = Repository::discover(worktree.path()).unwrap();
+        assert_eq!(repo.branch().unwrap().as_deref(), Some("feature"));
+        assert_eq!(repo.head_commit().unwrap(), Some(commit));
+        // Named after the main work tree
+        assert_eq!(
+            repo.name(),
+            fixture.path().file_name().unwrap().to_string_lossy()
+        );
+    }
+
+    #[test]
+    fn test_head_and_refs() {
+        let fixture = Fixture::new();
+        let repo = fixture.repo();
+        assert_eq!(repo.branch().unwrap().as_deref(), Some("main"));
+        // Unborn branch
+        assert_eq!(repo.head_commit().unwrap(), None);
+
+        let commit = fixture.commit(fixture.tree(&[]), &[], 1);
+        fixture.git(
+            "packed-refs",
+            &format!("# pack-refs with: peeled fully-peeled sorted\n{commit} refs/heads/main\n^{commit}\n"),
+        );
+        assert_eq!(repo.head_commit().unwrap(), Some(commit));
+        assert_eq!(repo.commit(&commit).unwrap().time, 1);
+
+        // Loose refs take precedence over packed ones
+        let other = fixture.commit(fixture.tree(&[]), &[commit], 2);
+        fixture.git("refs/heads/main", &format!("{other}\n"));
+        assert_eq!(repo.head_commit().unwrap(), Some(other));
+
+        fixture.git("HEAD", &format!("{commit}\n"));
+        assert_eq!(repo.branch().unwrap(), None);
+        assert_eq!(repo.head_commit().unwrap(), Some(commit));
+    }
+
+    #[test]
+    fn test_dirty() {
+        let fixture = Fixture::new();
+        // Nothing committed nor staged
+        assert!(!fixture.repo().is_dirty().unwrap());
+
+        fixture.stage(&[("README.md", "hello\n"), ("src/lib.rs", "// lib\n")]);
+        assert!(fixture.repo().is_dirty().unwrap());
+
+        let readme = fixture.object(ObjectKind::Blob, b"hello\n");
+        let lib = fixture.object(ObjectKind::Blob, b"// lib\n");
+        let src = fixture.tree(&[("100644", "lib.rs", lib)]);
+        let tree = fixture.tree(&[("100644", "README.md", readme), ("40000", "src", src)]);
+        let commit = fixture.commit(tree, &[], 1);
+        fixture.git("refs/heads/main", &format!("{commit}\n"));
+        assert!(!fixture.repo().is_dirty().unwrap());
+
+        // Untracked files are ignored
+        fs::write(fixture.path().join("notes.txt"), "todo").unwrap();
+        assert!(!fixture.repo().is_dirty().unwrap());
+
+        // Same size, different content
+        fs::write(fixture.path().join("README.md"), "HELLO\n").unwrap();
+        assert!(fixture.repo().is_dirty().unwrap());
+        fs::write(fixture.path().join("README.md"), "hello\n").unwrap();
+        assert!(!fixture.repo().is_dirty().unwrap());
+
+        fs::remove_file(fixture.path().join("src/lib.rs")).unwrap();
+        assert!(fixture.repo().is_dirty().unwrap());
+
+        // Staged change, the work tree matching the index
+        fixture.stage(&[("README.md", "hello\n"), ("src/lib.rs", "// lib 2\n")]);
+        assert!(fixture.repo().is_dirty().unwrap());
+    }
+
+    #[test]
+    fn test_ahead_behind() {
+        let fixture = Fixture::new();
+        let tree = fixture.tree(&[]);
+        let base = fixture.commit(tree, &[], 1);
+        let local_1 = fixture.commit(tree, &[base], 2);
+        let local_2 = fixture.commit(tree, &[local_1], 4);
+        let remote = fixture.commit(tree, &[base], 3);
+        fixture.git("refs/heads/main", &format!("{local_2}\n"));
+        assert_eq!(fixture.repo().ahead_behind().unwrap(), None);
+
+        fixture.git(
+            "config",
+            "[core]\n\tbare = false\n[branch \"main\"]\n\tremote = origin\n\tmerge = refs/heads/main\n",
+        );
+        fixture.git("refs/remotes/origin/main", &format!("{remote}\n"));
+        assert_eq!(
+            fixture.repo().ahead_behind().unwrap(),
+            Some(AheadBehind {
+                ahead: 2,
+                behind: 1,
+                upstream: "origin/main".to_string(),
+            })
+        );
+
+        fixture.git("refs/remotes/origin/main", &format!("{local_2}\n"));
+        let ahead_behind = fixture.repo().ahead_behind().unwrap().unwrap();
+        assert_eq!((ahead_behind.ahead, ahead_behind.behind), (0, 0));
+
+        let merge = fixture.commit(tree, &[local_2, remote], 5);
+        fixture.git("refs/heads/main", &format!("{merge}\n"));
+        fixture.git("refs/remotes/origin/main", &format!("{remote}\n"));
+        let ahead_behind = fixture.repo().ahead_behind().unwrap().unwrap();
+        assert_eq!((ahead_behind.ahead, ahead_behind.behind), (3, 0));
+
+        // Commits older than their parents
+        let root = fixture.commit(tree, &[], 5);
+        let upstream = fixture.commit(tree, &[root], 10);
+        let local = fixture.commit(tree, &[fixture.commit(tree, &[upstream], 1)], 2);
+        fixture.git("refs/heads/main", &format!("{local}\n"));
+        fixture.git("refs/remotes/origin/main", &format!("{upstream}\n"));
+        let ahead_behind = fixture.repo().ahead_behind().unwrap().unwrap();
+        assert_eq!((ahead_behind.ahead, ahead_behind.behind), (2, 0));
+    }
+
+    #[test]
+    fn test_config_value() {
+        let config = "[core]\n\tfilemode = false\n[branch \"Main\"]\n\tRemote = origin\n\tmerge = \"refs/heads/main\"\n; comment\n[alias]\n\tst\n";
+        assert_eq!(
+            config_value(config, "core.fileMode").as_deref(),
+            Some("false")
+        );
+        assert_eq!(
+            config_value(config, "branch.Main.remote").as_deref(),
+            Some("origin")
+        );
+        assert_eq!(
+            config_value(config, "branch.Main.merge").as_deref(),
+            Some("refs/heads/main")
+        );
+        assert_eq!(config_value(config, "branch.main.remote"), None);
+        assert_eq!(config_value(config, "alias.st").as_deref(), Some(""));
+    }
+
+    /// Checks the repository against the git command line, packs included.
+    #[test]
+    fn test_against_git() {
+        if !crate::utils::command_exists("git") {
+            return;
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let git = |args: &[&str]| {
+            let output = std::process::Command::new("git")
+                .args(["-c", "user.name=A", "-c", "user.email=a@example.com"])
+                .args(args)
+                .current_dir(dir.path())
+                .output()
+                .unwrap();
+            assert!(output.status.success(), "git {args:?} failed");
+            String::from_utf8(output.stdout).unwrap().trim().to_string()
+        };
+        git(&["init", "-q", "-b", "main"]);
+        for i in 0..5 {
+            fs::write(dir.path().join("file.txt"), "line\n".repeat(100 + i)).unwrap();
+            git(&["add", "file.txt"]);
+            git(&["commit", "-q", "-m", &format!("commit {i}")]);
+        }
+        git(&["gc", "-q", "--aggressive"]);
+
+        let repo = Repository::discover(dir.path()).unwrap();
+        assert!(
+            fs::read_dir(dir.path().join(".git/objects/pack"))
+                .unwrap()
+                .count()
+                > 0
+        );
+        assert_eq!(
+            repo.head_commit().unwrap().unwrap().to_string(),
+            git(&["rev-parse", "HEAD"])
+        );
+        assert!(!repo.is_dirty().unwrap());
+
+        git(&["branch", "-q", "old", "HEAD~3"]);
+        git(&["branch", "-q", "--set-upstream-to=old"]);
+        let ahead_behind = repo.ahead_behind().unwrap().unwrap();
+        assert_eq!((ahead_behind.ahead, ahead_behind.behind), (3, 0));
+        assert_eq!(ahead_behind.upstream, "old");
+
+        fs::write(dir.path().join("file.txt"), "changed\n").unwrap();
+        assert!(repo.is_dirty().unwrap());
+    }
+}
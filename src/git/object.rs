@@ -0,0 +1,485 @@
+//! The git object database: loose objects and packs, deltified objects included.
+
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use miniz_oxide::inflate::{decompress_to_vec_zlib, decompress_to_vec_zlib_with_limit};
+
+use crate::error::{Error, Result};
+use sha1_smol::Sha1;
+
+/// Longest chain of deltas followed to rebuild an object
+const MAX_DELTA_DEPTH: usize = 1000;
+
+/// The name of a git object: the SHA-1 of its content.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ObjectId(pub [u8; 20]);
+
+impl ObjectId {
+    /// Parses a full hexadecimal object id.
+    ///
+    /// # Examples
+    /// ```
+    /// use titular::git::ObjectId;
+    ///
+    /// let id = ObjectId::from_hex("e69de29bb2d1d6434b8b29ae775ad8c2e48c5391").unwrap();
+    /// assert_eq!(id.to_string(), "e69de29bb2d1d6434b8b29ae775ad8c2e48c5391");
+    /// assert!(ObjectId::from_hex("e69de29").is_none());
+    /// ```
+    #[must_use]
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        let hex = hex.as_bytes();
+        if hex.len() != 40 {
+            return None;
+        }
+        let mut id = [0; 20];
+        for (byte, pair) in id.iter_mut().zip(hex.chunks_exact(2)) {
+            let pair = std::str::from_utf8(pair).ok()?;
+            *byte = u8::from_str_radix(pair, 16).ok()?;
+        }
+        Some(Self(id))
+    }
+
+    /// The id of an object of the given kind and content.
+    #[must_use]
+    pub fn hash(kind: ObjectKind, data: &[u8]) -> Self {
+        let mut hasher = Sha1::new();
+        hasher.update(format!("{} {}\0", kind.name(), data.len()).as_bytes());
+        hasher.update(data);
+        Self(hasher.digest().bytes())
+    }
+
+    /// Reads an id from its 20 raw bytes.
+    #[must_use]
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        bytes.try_into().ok().map(Self)
+    }
+}
+
+impl fmt::Display for ObjectId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.iter().try_for_each(|b| write!(f, "{b:02x}"))
+    }
+}
+
+impl fmt::Debug for ObjectId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ObjectId({self})")
+    }
+}
+
+/// The kind of a git object.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectKind {
+    Commit,
+    Tree,
+    Blob,
+    Tag,
+}
+
+impl ObjectKind {
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            ObjectKind::Commit => "commit",
+            ObjectKind::Tree => "tree",
+            ObjectKind::Blob => "blob",
+            ObjectKind::Tag => "tag",
+        }
+    }
+
+    fn from_name(name: &[u8]) -> Option<Self> {
+        match name {
+            b"commit" => Some(ObjectKind::Commit),
+            b"tree" => Some(ObjectKind::Tree),
+            b"blob" => Some(ObjectKind::Blob),
+            b"tag" => Some(ObjectKind::Tag),
+            _ => None,
+        }
+    }
+}
+
+/// A git object.
+#[derive(Debug, Clone)]
+pub struct Object {
+    pub kind: ObjectKind,
+    pub data: Vec<u8>,
+}
+
+fn corrupt(what: impl fmt::Display) -> Error {
+    Error::Msg(format!("corrupt git {what}"))
+}
+
+/// The index of a pack: the sorted ids of its objects and their offsets in the pack.
+struct PackIndex {
+    pack: PathBuf,
+    file: OnceLock<Option<Mutex<File>>>,
+    fanout: Vec<u32>,
+    ids: Vec<u8>,
+    offsets: Vec<u8>,
+    large_offsets: Vec<u8>,
+}
+
+impl PackIndex {
+    /// Reads a version 2 pack index.
+    fn open(path: &Path) -> Result<Self> {
+        let data = fs::read(path)?;
+        if data.len() < 8 + 256 * 4 || data[..8] != [0xff, b't', b'O', b'c', 0, 0, 0, 2] {
+            return Err(corrupt(format!("pack index {}", path.display())));
+        }
+        let fanout: Vec<u32> = data[8..8 + 256 * 4]
+            .chunks_exact(4)
+            .map(|b| u32::from_be_bytes(b.try_into().unwrap()))
+            .collect();
+        // Each fanout entry counts the ids up to its first byte, the last one all of them
+        if fanout.windows(2).any(|pair| pair[0] > pair[1]) {
+            return Err(corrupt(format!("pack index {}", path.display())));
+        }
+        let count = fanout[255] as usize;
+        let ids_start = 8 + 256 * 4;
+        let offsets_start = ids_start + count * 24;
+        let large_start = offsets_start + count * 4;
+        if data.len() < large_start {
+            return Err(corrupt(format!("pack index {}", path.display())));
+        }
+        Ok(Self {
+            pack: path.with_extension("pack"),
+            file: OnceLock::new(),
+            fanout,
+            ids: data[ids_start..ids_start + count * 20].to_vec(),
+            offsets: data[offsets_start..large_start].to_vec(),
+            large_offsets: data[large_start..].to_vec(),
+        })
+    }
+
+    /// The offset of the object in the pack, if it is in it.
+    fn find(&self, id: &ObjectId) -> Option<u64> {
+        let first = id.0[0] as usize;
+        let start = if first == 0 {
+            0
+        } else {
+            self.fanout[first - 1] as usize
+        };
+        let end = self.fanout[first] as usize;
+        let (mut low, mut high) = (start, end);
+        while low < high {
+            let mid = (low + high) / 2;
+            match self.ids[mid * 20..mid * 20 + 20].cmp(&id.0) {
+                std::cmp::Ordering::Less => low = mid + 1,
+                std::cmp::Ordering::Greater => high = mid,
+                std::cmp::Ordering::Equal => return self.offset(mid),
+            }
+        }
+        None
+    }
+
+    fn offset(&self, index: usize) -> Option<u64> {
+        let offset = u32::from_be_bytes(
+            self.offsets
+                .get(index * 4..index * 4 + 4)?
+                .try_into()
+                .ok()?,
+        );
+        if offset & 0x8000_0000 == 0 {
+            return Some(u64::from(offset));
+        }
+        let large = (offset & 0x7fff_ffff) as usize * 8;
+        Some(u64::from_be_bytes(
+            self.large_offsets.get(large..large + 8)?.try_into().ok()?,
+        ))
+    }
+
+    /// Reads up to `len` bytes of the pack at the offset.
+    fn read_at(&self, offset: u64, len: usize) -> Result<Vec<u8>> {
+        let file = self
+            .file
+            .get_or_init(|| File::open(&self.pack).ok().map(Mutex::new))
+            .as_ref()
+            .ok_or_else(|| corrupt(format!("pack {}", self.pack.display())))?;
+        let mut file = file
+            .lock()
+            .map_err(|_| corrupt(format!("pack {}", self.pack.display())))?;
+        file.seek(SeekFrom::Start(offset))?;
+        let mut buffer = Vec::with_capacity(len);
+        file.by_ref().take(len as u64).read_to_end(&mut buffer)?;
+        Ok(buffer)
+    }
+}
+
+/// How a packed object is stored.
+enum PackedKind {
+    Whole(ObjectKind),
+    OffsetDelta(u64),
+    RefDelta(ObjectId),
+}
+
+/// The objects of a repository, read from the `objects` directory.
+pub struct ObjectDb {
+    objects_dir: PathBuf,
+    packs: OnceLock<Vec<PackIndex>>,
+}
+
+impl ObjectDb {
+    #[must_use]
+    pub fn new(objects_dir: PathBuf) -> Self {
+        Self {
+            objects_dir,
+            packs: OnceLock::new(),
+        }
+    }
+
+    /// The indexes of the packs, read on first use.
+    fn packs(&self) -> &[PackIndex] {
+        self.packs.get_or_init(|| {
+            let Ok(entries) = fs::read_dir(self.objects_dir.join("pack")) else {
+                return Vec::new();
+            };
+            entries
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "idx"))
+                .filter_map(|path| PackIndex::open(&path).ok())
+                .collect()
+        })
+    }
+
+    /// Reads an object.
+    ///
+    /// # Returns
+    /// `Ok(None)` if the object is not in the repository.
+    ///
+    /// # Errors
+    /// Returns an error if the object cannot be read or decoded.
+    pub fn read(&self, id: &ObjectId) -> Result<Option<Object>> {
+        self.read_at_depth(id, 0)
+    }
+
+    fn read_at_depth(&self, id: &ObjectId, depth: usize) -> Result<Option<Object>> {
+        if let Some(object) = self.read_loose(id)? {
+            return Ok(Some(object));
+        }
+        for pack in self.packs() {
+            if let Some(offset) = pack.find(id) {
+                return self.read_packed(pack, offset, depth).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    fn read_loose(&self, id: &ObjectId) -> Result<Option<Object>> {
+        let hex = id.to_string();
+        let path = self.objects_dir.join(&hex[..2]).join(&hex[2..]);
+        let compressed = match fs::read(&path) {
+            Ok(compressed) => compressed,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let raw =
+            decompress_to_vec_zlib(&compressed).map_err(|_| corrupt(format!("object {id}")))?;
+        let nul = raw
+            .iter()
+            .position(|b| *b == 0)
+            .ok_or_else(|| corrupt(format!("object {id}")))?;
+        let kind = raw[..nul]
+            .split(|b| *b == b' ')
+            .next()
+            .and_then(ObjectKind::from_name)
+            .ok_or_else(|| corrupt(format!("object {id}")))?;
+        Ok(Some(Object {
+            kind,
+            data: raw[nul + 1..].to_vec(),
+        }))
+    }
+
+    fn read_packed(&self, pack: &PackIndex, offset: u64, depth: usize) -> Result<Object> {
+        if depth > MAX_DELTA_DEPTH {
+            return Err(corrupt("pack: delta chain too long"));
+        }
+        let header = pack.read_at(offset, 32)?;
+        let (kind, size, header_len) = Self::parse_entry_header(&header, offset)
+            .ok_or_else(|| corrupt(format!("pack {}", pack.pack.display())))?;
+
+        // Compressed data is at most slightly larger than the data itself
+        let window = size + size / 64 + 1024;
+        let compressed = pack.read_at(offset + header_len as u64, window)?;
+        let data = decompress_to_vec_zlib_with_limit(&compressed, size.max(1))
+            .map_err(|_| corrupt(format!("pack {}", pack.pack.display())))?;
+
+        match kind {
+            PackedKind::Whole(kind) => Ok(Object { kind, data }),
+            PackedKind::OffsetDelta(base_offset) => {
+                let base = self.read_packed(pack, base_offset, depth + 1)?;
+                Ok(Object {
+                    kind: base.kind,
+                    data: apply_delta(&base.data, &data)?,
+                })
+            }
+            PackedKind::RefDelta(base_id) => {
+                let base = self
+                    .read_at_depth(&base_id, depth + 1)?
+                    .ok_or_else(|| corrupt(format!("pack: missing delta base {base_id}")))?;
+                Ok(Object {
+                    kind: base.kind,
+                    data: apply_delta(&base.data, &data)?,
+                })
+            }
+        }
+    }
+
+    /// Parses the header of a pack entry: its kind, the size of its (delta) data and the length
+    /// of the header.
+    fn parse_entry_header(header: &[u8], offset: u64) -> Option<(PackedKind, usize, usize)> {
+        let mut bytes = header.iter().copied();
+        let mut pos = 1;
+        let mut byte = bytes.next()?;
+        let kind = (byte >> 4) & 7;
+        let mut size = usize::from(byte & 0x0f);
+        let mut shift = 4;
+        while byte & 0x80 != 0 {
+            byte = bytes.next()?;
+            pos += 1;
+            size |= usize::from(byte & 0x7f).checked_shl(shift)?;
+            shift += 7;
+        }
+
+        let kind = match kind {
+            1 => PackedKind::Whole(ObjectKind::Commit),
+            2 => PackedKind::Whole(ObjectKind::Tree),
+            3 => PackedKind::Whole(ObjectKind::Blob),
+            4 => PackedKind::Whole(ObjectKind::Tag),
+            6 => {
+                byte = bytes.next()?;
+                pos += 1;
+                let mut distance = u64::from(byte & 0x7f);
+                while byte & 0x80 != 0 {
+                    byte = bytes.next()?;
+                    pos += 1;
+                    distance = ((distance + 1) << 7) | u64::from(byte & 0x7f);
+                }
+                PackedKind::OffsetDelta(offset.checked_sub(distance)?)
+            }
+            7 => {
+                let id = ObjectId::from_bytes(header.get(pos..pos + 20)?)?;
+                pos += 20;
+                PackedKind::RefDelta(id)
+            }
+            _ => return None,
+        };
+        Some((kind, size, pos))
+    }
+}
+
+/// Rebuilds an object from its base and a delta.
+fn apply_delta(base: &[u8], delta: &[u8]) -> Result<Vec<u8>> {
+    let invalid = || corrupt("delta");
+    let mut pos = 0;
+    let mut varint = || {
+        let mut value = 0usize;
+        let mut shift = 0;
+        loop {
+            let byte = *delta.get(pos).ok_or_else(invalid)?;
+            pos += 1;
+            value |= usize::from(byte & 0x7f)
+                .checked_shl(shift)
+                .ok_or_else(invalid)?;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                return Ok::<usize, Error>(value);
+            }
+        }
+    };
+    let base_size = varint()?;
+    let result_size = varint()?;
+    if base_size != base.len() {
+        return Err(invalid());
+    }
+
+    let mut result = Vec::with_capacity(result_size);
+    while pos < delta.len() {
+        let op = delta[pos];
+        pos += 1;
+        if op & 0x80 != 0 {
+            // Copy a range of the base: the bits of the op tell which offset and size bytes follow
+            let mut read = |bits: std::ops::Range<u8>| -> Result<usize> {
+                let mut value = 0usize;
+                for (i, bit) in bits.enumerate() {
+                    if op & (1 << bit) != 0 {
+                        value |= usize::from(*delta.get(pos).ok_or_else(invalid)?) << (8 * i);
+                        pos += 1;
+                    }
+                }
+                Ok(value)
+            };
+            let offset = read(0..4)?;
+            let size = match read(4..7)? {
+                0 => 0x10000,
+                size => size,
+            };
+            let range = base.get(offset..offset + size).ok_or_else(invalid)?;
+            result.extend_from_slice(range);
+        } else if op != 0 {
+            let range = delta.get(pos..pos + usize::from(op)).ok_or_else(invalid)?;
+            result.extend_from_slice(range);
+            pos += usize::from(op);
+        } else {
+            return Err(invalid());
+        }
+    }
+    if result.len() != result_size {
+        return Err(invalid());
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_delta() {
+        let base = b"hello world";
+        // Sizes, copy 6 bytes at 0, insert "there"
+        let delta = [11, 11, 0x90, 6, 5, b't', b'h', b'e', b'r', b'e'];
+        assert_eq!(apply_delta(base, &delta).unwrap(), b"hello there");
+        assert!(apply_delta(b"short", &delta).is_err());
+        // Copy beyond the base
+        assert!(apply_delta(base, &[11, 20, 0x91, 8, 20]).is_err());
+    }
+
+    #[test]
+    fn test_object_hash() {
+        assert_eq!(
+            ObjectId::hash(ObjectKind::Blob, b"hello\n").to_string(),
+            "ce013625030ba8dba906f756967f9e9ca394464a"
+        );
+    }
+
+    #[test]
+    fn test_pack_index_fanout() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pack.idx");
+        let mut data = vec![0xff, b't', b'O', b'c', 0, 0, 0, 2];
+        // A first byte counting more ids than all of them
+        let mut fanout = [0u32; 256];
+        fanout[0] = 1000;
+        for count in fanout {
+            data.extend_from_slice(&count.to_be_bytes());
+        }
+        fs::write(&path, &data).unwrap();
+        assert!(PackIndex::open(&path).is_err());
+    }
+
+    #[test]
+    fn test_parse_entry_header() {
+        // Blob of 300 bytes
+        let (kind, size, len) = ObjectDb::parse_entry_header(&[0xbc, 0x12], 0).unwrap();
+        assert!(matches!(kind, PackedKind::Whole(ObjectKind::Blob)));
+        assert_eq!((size, len), (300, 2));
+        // Offset delta 200 bytes back
+        let (kind, _, len) = ObjectDb::parse_entry_header(&[0x65, 0x80, 0x48], 1000).unwrap();
+        assert!(matches!(kind, PackedKind::OffsetDelta(800)));
+        assert_eq!(len, 3);
+    }
+}
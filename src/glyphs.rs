@@ -0,0 +1,75 @@
+//! Which symbols the terminal can draw: Nerd Font and powerline glyphs, emojis or plain ASCII.
+//!
+//! Set `enabled = true` or `false` in the `[icons]` section of the configuration (or pass
+//! `-s glyphs=...`) to decide explicitly, Nerd Font glyphs giving way to emojis when disabled.
+//! The default, `auto`, assumes a patched font except on the Linux console and dumb terminals,
+//! which only draw ASCII. The former `glyphs` setting of `[defaults]` is still honoured when
+//! `enabled` is left to `auto`.
+
+use crate::context::Context;
+
+/// The richest kind of symbols the terminal can draw.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GlyphSupport {
+    /// Nerd Font and powerline glyphs
+    NerdFont,
+    /// Emojis, but no private use glyphs
+    Emoji,
+    /// Nothing but ASCII
+    Ascii,
+}
+
+/// Reads the glyph support from the context (`glyphs`, then `icons.enabled`), guessing it from
+/// `TERM` when unset or `auto`.
+#[must_use]
+pub fn glyph_support(ctx: &Context) -> GlyphSupport {
+    let basic_terminal = matches!(std::env::var("TERM").as_deref(), Ok("linux" | "dumb"));
+    let setting = ctx
+        .get("glyphs")
+        .or_else(|| ctx.get("icons.enabled"))
+        .map(|s| s.trim().to_lowercase());
+    match setting.as_deref() {
+        Some("true" | "1" | "yes" | "on") => GlyphSupport::NerdFont,
+        _ if basic_terminal => GlyphSupport::Ascii,
+        Some("false" | "0" | "no" | "off") => GlyphSupport::Emoji,
+        _ => GlyphSupport::NerdFont,
+    }
+}
+
+/// Whether Nerd Font / powerline glyphs are enabled, see [`glyph_support`].
+#[must_use]
+pub fn glyphs_enabled(ctx: &Context) -> bool {
+    glyph_support(ctx) == GlyphSupport::NerdFont
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glyphs_enabled() {
+        temp_env::with_var("TERM", Some("xterm-256color"), || {
+            let mut ctx = Context::new();
+            assert!(glyphs_enabled(&ctx));
+            ctx.insert("icons.enabled", "false");
+            assert_eq!(glyph_support(&ctx), GlyphSupport::Emoji);
+            ctx.insert("glyphs", "Yes");
+            assert!(glyphs_enabled(&ctx));
+            ctx.insert("icons.enabled", "true");
+            ctx.insert("glyphs", "off");
+            assert!(!glyphs_enabled(&ctx));
+        });
+    }
+
+    #[test]
+    fn test_glyph_support_basic_terminal() {
+        temp_env::with_var("TERM", Some("linux"), || {
+            let mut ctx = Context::new();
+            assert_eq!(glyph_support(&ctx), GlyphSupport::Ascii);
+            ctx.insert("icons.enabled", "false");
+            assert_eq!(glyph_support(&ctx), GlyphSupport::Ascii);
+            ctx.insert("icons.enabled", "true");
+            assert_eq!(glyph_support(&ctx), GlyphSupport::NerdFont);
+        });
+    }
+}
@@ -0,0 +1,176 @@
+//! Color gradients applied per grapheme by the `gradient` filter.
+//!
+//! The filter resolves the colors of the gradient and stores them, together with the
+//! interpolation mode, in a compact spec (`"hsl;ff0000;0000ff"`) embedded in the rendered text.
+//! The actual coloring happens once padding has been expanded so that gradients stretch across
+//! the final width of fillers.
+
+use std::str::FromStr;
+
+use crate::color_utils::{hsl_to_rgb, rgb_to_hsl};
+use crate::error::{Error, Result};
+
+/// Color space in which the stops of a gradient are interpolated.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum GradientMode {
+    /// Linear interpolation of the RGB channels
+    #[default]
+    Rgb,
+    /// Interpolation of hue (along the shortest arc), saturation and lightness
+    Hsl,
+}
+
+impl FromStr for GradientMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "rgb" => Ok(GradientMode::Rgb),
+            "hsl" => Ok(GradientMode::Hsl),
+            other => Err(Error::Msg(format!(
+                "invalid gradient mode \"{other}\" (expected rgb or hsl)"
+            ))),
+        }
+    }
+}
+
+/// A gradient made of at least two color stops evenly distributed along the text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Gradient {
+    pub mode: GradientMode,
+    pub stops: Vec<(u8, u8, u8)>,
+}
+
+impl Gradient {
+    /// Serializes the gradient into its spec representation.
+    ///
+    /// # Examples
+    /// ```
+    /// use titular::gradient::{Gradient, GradientMode};
+    ///
+    /// let gradient = Gradient { mode: GradientMode::Hsl, stops: vec![(255, 0, 0), (0, 0, 255)] };
+    /// assert_eq!(gradient.to_spec(), "hsl;ff0000;0000ff");
+    /// assert_eq!(Gradient::from_spec(&gradient.to_spec()), Some(gradient));
+    /// ```
+    #[must_use]
+    pub fn to_spec(&self) -> String {
+        let mode = match self.mode {
+            GradientMode::Rgb => "rgb",
+            GradientMode::Hsl => "hsl",
+        };
+        std::iter::once(mode.to_string())
+            .chain(
+                self.stops
+                    .iter()
+                    .map(|(r, g, b)| format!("{r:02x}{g:02x}{b:02x}")),
+            )
+            .collect::<Vec<_>>()
+            .join(";")
+    }
+
+    /// Parses a spec produced by [`Gradient::to_spec`], returning `None` if it is malformed.
+    #[must_use]
+    pub fn from_spec(spec: &str) -> Option<Gradient> {
+        let mut parts = spec.split(';');
+        let mode = parts.next()?.parse().ok()?;
+        let stops = parts
+            .map(|hex| {
+                let value = u32::from_str_radix(hex, 16).ok()?;
+                let [_, r, g, b] = value.to_be_bytes();
+                Some((r, g, b))
+            })
+            .collect::<Option<Vec<_>>>()?;
+        (!stops.is_empty()).then_some(Gradient { mode, stops })
+    }
+
+    /// Returns the color at the given position of the gradient (`0.0` being the first stop and
+    /// `1.0` the last one).
+    ///
+    /// # Examples
+    /// ```
+    /// use titular::gradient::{Gradient, GradientMode};
+    ///
+    /// let gradient = Gradient { mode: GradientMode::Rgb, stops: vec![(0, 0, 0), (200, 100, 0)] };
+    /// assert_eq!(gradient.color_at(0.5), (100, 50, 0));
+    /// ```
+    #[must_use]
+    #[allow(
+        clippy::cast_precision_loss,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss
+    )]
+    pub fn color_at(&self, t: f64) -> (u8, u8, u8) {
+        let segments = self.stops.len().saturating_sub(1);
+        if segments == 0 {
+            return self.stops.first().copied().unwrap_or_default();
+        }
+        let scaled = t.clamp(0.0, 1.0) * segments as f64;
+        let index = (scaled.floor() as usize).min(segments - 1);
+        let local = scaled - index as f64;
+        let (from, to) = (self.stops[index], self.stops[index + 1]);
+
+        match self.mode {
+            GradientMode::Rgb => (
+                lerp(from.0, to.0, local),
+                lerp(from.1, to.1, local),
+                lerp(from.2, to.2, local),
+            ),
+            GradientMode::Hsl => {
+                let (h1, s1, l1) = rgb_to_hsl(from);
+                let (h2, s2, l2) = rgb_to_hsl(to);
+                let mut delta = h2 - h1;
+                if delta > 180.0 {
+                    delta -= 360.0;
+                } else if delta < -180.0 {
+                    delta += 360.0;
+                }
+                hsl_to_rgb(
+                    h1 + delta * local,
+                    s1 + (s2 - s1) * local,
+                    l1 + (l2 - l1) * local,
+                )
+            }
+        }
+    }
+}
+
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn lerp(a: u8, b: u8, t: f64) -> u8 {
+    (f64::from(a) + (f64::from(b) - f64::from(a)) * t)
+        .round()
+        .clamp(0.0, 255.0) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_color_at_multiple_stops() {
+        let gradient = Gradient {
+            mode: GradientMode::Rgb,
+            stops: vec![(255, 0, 0), (0, 255, 0), (0, 0, 255)],
+        };
+        assert_eq!(gradient.color_at(0.0), (255, 0, 0));
+        assert_eq!(gradient.color_at(0.5), (0, 255, 0));
+        assert_eq!(gradient.color_at(0.75), (0, 128, 128));
+        assert_eq!(gradient.color_at(1.0), (0, 0, 255));
+    }
+
+    #[test]
+    fn test_hsl_takes_shortest_hue_arc() {
+        let gradient = Gradient {
+            mode: GradientMode::Hsl,
+            stops: vec![(255, 0, 64), (255, 64, 0)],
+        };
+        // Going through red (hue 0) rather than across the whole wheel
+        assert_eq!(gradient.color_at(0.5), (255, 0, 0));
+    }
+
+    #[test]
+    fn test_malformed_specs() {
+        assert_eq!(Gradient::from_spec("rgb"), None);
+        assert_eq!(Gradient::from_spec("lab;ff0000"), None);
+        assert_eq!(Gradient::from_spec("rgb;zz0000"), None);
+    }
+}
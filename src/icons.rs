@@ -0,0 +1,157 @@
+//! Named icons: a Nerd Font glyph with an emoji and an ASCII fallback, picked according to what
+//! the terminal can draw (see [`GlyphSupport`]).
+
+use crate::glyphs::GlyphSupport;
+
+/// An icon and its fallbacks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Icon {
+    pub name: &'static str,
+    /// The Nerd Font glyph
+    pub glyph: &'static str,
+    /// The emoji drawn without Nerd Font glyphs
+    pub emoji: &'static str,
+    /// The ASCII text drawn on basic terminals
+    pub ascii: &'static str,
+}
+
+impl Icon {
+    /// The symbol to draw with the given glyph support.
+    ///
+    /// # Examples
+    /// ```
+    /// use titular::glyphs::GlyphSupport;
+    /// use titular::icons::lookup;
+    ///
+    /// let icon = lookup("rust").unwrap();
+    /// assert_eq!(icon.render(GlyphSupport::NerdFont), "\u{e7a8}");
+    /// assert_eq!(icon.render(GlyphSupport::Emoji), "🦀");
+    /// assert_eq!(icon.render(GlyphSupport::Ascii), "rs");
+    /// ```
+    #[must_use]
+    pub fn render(&self, support: GlyphSupport) -> &'static str {
+        match support {
+            GlyphSupport::NerdFont => self.glyph,
+            GlyphSupport::Emoji => self.emoji,
+            GlyphSupport::Ascii => self.ascii,
+        }
+    }
+}
+
+macro_rules! icons {
+    ($($name:literal => $glyph:literal, $emoji:literal, $ascii:literal;)*) => {
+        &[$(Icon { name: $name, glyph: $glyph, emoji: $emoji, ascii: $ascii }),*]
+    };
+}
+
+/// The embedded icons, sorted by name
+pub const ICONS: &[Icon] = icons! {
+    "apple" => "\u{f179}", "🍎", "mac";
+    "arrow-down" => "\u{f063}", "⬇️", "v";
+    "arrow-left" => "\u{f060}", "⬅️", "<-";
+    "arrow-right" => "\u{f061}", "➡️", "->";
+    "arrow-up" => "\u{f062}", "⬆️", "^";
+    "battery" => "\u{f240}", "🔋", "bat";
+    "bell" => "\u{f0f3}", "🔔", "!";
+    "bolt" => "\u{f0e7}", "⚡", "!";
+    "bug" => "\u{f188}", "🐛", "bug";
+    "calendar" => "\u{f073}", "📅", "date";
+    "check" => "\u{f00c}", "✅", "ok";
+    "clock" => "\u{f017}", "🕒", "time";
+    "cloud" => "\u{f0c2}", "☁️", "cloud";
+    "cpu" => "\u{f4bc}", "🧮", "cpu";
+    "cross" => "\u{f00d}", "❌", "x";
+    "docker" => "\u{f308}", "🐳", "docker";
+    "error" => "\u{f057}", "⛔", "E";
+    "file" => "\u{f15b}", "📄", "-";
+    "file-outline" => "\u{ea7b}", "📄", "-";
+    "fire" => "\u{f06d}", "🔥", "*";
+    "folder" => "\u{f07b}", "📁", "/";
+    "folder-open" => "\u{f07c}", "📂", "/";
+    "folder-open-outline" => "\u{f115}", "📂", "/";
+    "git" => "\u{e702}", "🔧", "git";
+    "git-branch" => "\u{e725}", "🌿", "br";
+    "git-commit" => "\u{f417}", "🔘", "@";
+    "git-merge" => "\u{f419}", "🔀", "Y";
+    "github" => "\u{f408}", "🐙", "gh";
+    "go" => "\u{e626}", "🐹", "go";
+    "heart" => "\u{f004}", "❤️", "<3";
+    "home" => "\u{f015}", "🏠", "~";
+    "info" => "\u{f05a}", "ℹ️", "i";
+    "key" => "\u{f084}", "🔑", "key";
+    "linux" => "\u{f17c}", "🐧", "linux";
+    "lock" => "\u{f023}", "🔒", "#";
+    "node" => "\u{e718}", "🟢", "js";
+    "package" => "\u{f487}", "📦", "pkg";
+    "palette" => "\u{e22b}", "🎨", "*";
+    "python" => "\u{e73c}", "🐍", "py";
+    "rocket" => "\u{f135}", "🚀", ">>";
+    "rust" => "\u{e7a8}", "🦀", "rs";
+    "search" => "\u{f002}", "🔍", "?";
+    "server" => "\u{f233}", "🖥️", "host";
+    "star" => "\u{f005}", "⭐", "*";
+    "tag" => "\u{f02b}", "🏷️", "#";
+    "terminal" => "\u{f489}", "💻", ">_";
+    "theme" => "\u{f08b5}", "🖌️", "-";
+    "user" => "\u{f007}", "👤", "@";
+    "warning" => "\u{f071}", "⚠️", "!";
+    "windows" => "\u{f17a}", "🪟", "win";
+};
+
+/// Finds an icon by name, ignoring case and treating `_` as `-`.
+///
+/// # Examples
+/// ```
+/// use titular::icons::lookup;
+///
+/// assert_eq!(lookup("Git_Branch").unwrap().name, "git-branch");
+/// assert!(lookup("unknown").is_none());
+/// ```
+#[must_use]
+pub fn lookup(name: &str) -> Option<&'static Icon> {
+    let name = name.trim().to_lowercase().replace('_', "-");
+    ICONS
+        .binary_search_by(|icon| icon.name.cmp(name.as_str()))
+        .ok()
+        .map(|i| &ICONS[i])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::is_marker;
+    use crate::width::{text_width, AmbiguousWidth};
+
+    #[test]
+    fn test_icons_sorted_and_unique() {
+        assert!(ICONS.windows(2).all(|w| w[0].name < w[1].name));
+        assert!(ICONS.iter().all(|icon| lookup(icon.name) == Some(icon)));
+    }
+
+    #[test]
+    fn test_icon_fallbacks() {
+        for icon in ICONS {
+            // Private use glyphs must not collide with the internal markers
+            assert!(
+                !icon.glyph.is_ascii() && !icon.glyph.chars().any(is_marker),
+                "{}",
+                icon.name
+            );
+            assert!(
+                !icon.emoji.is_empty() && !icon.emoji.is_ascii(),
+                "{}",
+                icon.name
+            );
+            assert!(
+                !icon.ascii.is_empty() && icon.ascii.is_ascii(),
+                "{}",
+                icon.name
+            );
+            assert!(
+                text_width(icon.emoji, AmbiguousWidth::Narrow) <= 2,
+                "{}",
+                icon.name
+            );
+        }
+    }
+}
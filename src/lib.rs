@@ -25,11 +25,17 @@
 //! //    .unwrap();
 //! ```
 
+pub mod ansi;
+pub mod color_depth;
+pub mod color_functions;
 pub mod color_manager;
+pub mod color_names;
+pub mod color_utils;
 pub mod config;
 pub mod constants;
 pub mod context;
 pub mod context_manager;
+pub mod contrast;
 pub mod controller;
 #[cfg(feature = "fetcher")]
 pub mod dispatcher;
@@ -37,24 +43,36 @@ pub mod display;
 pub mod error;
 #[cfg(feature = "fetcher")]
 pub mod fetcher;
+pub mod figlet;
+pub mod filler;
 pub mod filters;
 pub mod formatter;
+pub mod frame;
 pub mod functions;
+pub mod git;
 #[cfg(feature = "fetcher")]
 pub mod github;
+pub mod glyphs;
+pub mod gradient;
+pub mod icons;
 pub mod log;
+pub mod policy;
 pub mod reader;
+pub mod segment;
+pub mod shell;
 pub mod string_utils;
 #[cfg(feature = "bundler")]
 pub mod template_bundle;
 #[cfg(any(feature = "minimal", feature = "display"))]
 pub mod term;
+pub mod textstyle;
 #[cfg(feature = "display")]
 pub mod theme;
 #[cfg(feature = "display")]
 pub mod theme_palette;
 pub mod transforms;
 pub mod utils;
+pub mod width;
 pub mod writer;
 
 /// The titular prelude
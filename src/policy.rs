@@ -0,0 +1,336 @@
+//! Template-safety policy: what a template may read from the system while it renders.
+//!
+//! Trust is an allowlist: a template is trusted only if titular created it (`templates create`
+//! or `templates edit`), which records it in the `.local` file of the templates directory, or
+//! once `templates trust <name>` was run for it, which records it in the `.trusted` file (one
+//! name per line). Any other template — downloaded with `templates add`, imported from a
+//! bundle, copied into the templates directory by hand or installed by an older version — is
+//! untrusted, and only reads the environment variables of the `env_allowlist` of the `[policy]`
+//! section of the configuration.
+//!
+//! Running commands with `exec()` is disabled by default: the commands must be listed in the
+//! `exec_allowlist` of the configuration, and the template must be trusted. The `exec_allowlist`
+//! of the `[policy]` section of a template only counts once `templates trust <name>` was run for
+//! it. An entry of an `exec_allowlist` names a program and allows it with any arguments: allowing
+//! `git` lets the template run any `git` command. A command is given at most
+//! `exec_timeout_max_ms` to complete, whatever the template asks for.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+use crate::constants::template::DEFAULT_TEMPLATE_EXT;
+use crate::error::Result;
+
+/// The file of the templates directory listing the templates created by titular
+pub const LOCAL_FILE: &str = ".local";
+
+/// The file of the templates directory listing the templates trusted with `templates trust`
+pub const TRUSTED_FILE: &str = ".trusted";
+
+/// Environment variables untrusted templates read when no allowlist is configured
+pub const DEFAULT_ENV_ALLOWLIST: &[&str] = &[
+    "USER",
+    "LOGNAME",
+    "HOME",
+    "SHELL",
+    "TERM",
+    "TERM_PROGRAM",
+    "COLORTERM",
+    "LANG",
+    "PWD",
+    "HOSTNAME",
+    "VIRTUAL_ENV",
+    "CONDA_DEFAULT_ENV",
+];
+
+/// The longest time, in milliseconds, a command run with `exec()` is given when none is configured
+pub const DEFAULT_EXEC_TIMEOUT_MAX_MS: u64 = 5000;
+
+/// The policy applied to the template being rendered, stored in the context as `policy`.
+#[derive(Debug, Clone)]
+pub struct TemplatePolicy {
+    env_allowlist: Vec<String>,
+    exec_allowlist: Vec<String>,
+    exec_timeout_max_ms: u64,
+    trusted: bool,
+}
+
+/// Whether a name matches an allowlist pattern, a trailing `*` matching any suffix.
+fn matches_pattern(pattern: &str, name: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => pattern == name,
+    }
+}
+
+impl TemplatePolicy {
+    #[must_use]
+    pub fn new(env_allowlist: Vec<String>, trusted: bool) -> Self {
+        Self {
+            env_allowlist,
+            exec_allowlist: Vec::new(),
+            exec_timeout_max_ms: DEFAULT_EXEC_TIMEOUT_MAX_MS,
+            trusted,
+        }
+    }
+
+    /// The policy of the renders made without one: untrusted, reading the default allowlist.
+    #[must_use]
+    pub fn untrusted() -> Self {
+        Self::new(
+            DEFAULT_ENV_ALLOWLIST
+                .iter()
+                .map(|s| (*s).to_string())
+                .collect(),
+            false,
+        )
+    }
+
+    /// Sets the commands the template may run with `exec()`.
+    #[must_use]
+    pub fn with_exec_allowlist(mut self, exec_allowlist: Vec<String>) -> Self {
+        self.exec_allowlist = exec_allowlist;
+        self
+    }
+
+    /// Sets the longest time, in milliseconds, a command run with `exec()` is given.
+    #[must_use]
+    pub fn with_exec_timeout_max_ms(mut self, exec_timeout_max_ms: u64) -> Self {
+        self.exec_timeout_max_ms = exec_timeout_max_ms;
+        self
+    }
+
+    /// The longest time, in milliseconds, a command run with `exec()` is given.
+    #[must_use]
+    pub fn exec_timeout_max_ms(&self) -> u64 {
+        self.exec_timeout_max_ms
+    }
+
+    /// Whether the template is trusted.
+    #[must_use]
+    pub fn is_trusted(&self) -> bool {
+        self.trusted
+    }
+
+    /// Whether the template may read the given environment variable: trusted templates read
+    /// any of them, untrusted ones those of the allowlist (a trailing `*` matching any suffix).
+    ///
+    /// # Examples
+    /// ```
+    /// use titular::policy::TemplatePolicy;
+    ///
+    /// let policy = TemplatePolicy::new(vec!["USER".into(), "GIT_*".into()], false);
+    /// assert!(policy.allows_env("USER"));
+    /// assert!(policy.allows_env("GIT_BRANCH"));
+    /// assert!(!policy.allows_env("AWS_SECRET_ACCESS_KEY"));
+    /// ```
+    #[must_use]
+    pub fn allows_env(&self, name: &str) -> bool {
+        self.trusted
+            || self
+                .env_allowlist
+                .iter()
+                .any(|pattern| matches_pattern(pattern, name))
+    }
+
+    /// Whether the template may run the given program: only trusted templates run commands,
+    /// those of the exec allowlist (a trailing `*` matching any suffix). The arguments are not
+    /// checked: an allowed program runs with any of them.
+    ///
+    /// # Examples
+    /// ```
+    /// use titular::policy::TemplatePolicy;
+    ///
+    /// let policy = TemplatePolicy::new(Vec::new(), true).with_exec_allowlist(vec!["kubectl".into()]);
+    /// assert!(policy.allows_exec("kubectl"));
+    /// assert!(!policy.allows_exec("rm"));
+    /// assert!(!TemplatePolicy::new(Vec::new(), true).allows_exec("kubectl"));
+    /// ```
+    #[must_use]
+    pub fn allows_exec(&self, program: &str) -> bool {
+        self.trusted
+            && self
+                .exec_allowlist
+                .iter()
+                .any(|pattern| matches_pattern(pattern, program))
+    }
+}
+
+/// The name a template is recorded with, without the template extension.
+fn template_key(name: &str) -> &str {
+    name.trim()
+        .strip_suffix(DEFAULT_TEMPLATE_EXT)
+        .unwrap_or(name.trim())
+}
+
+/// Reads the names of the templates listed in a file of the templates directory.
+fn read_names(templates_dir: &Path, file: &str) -> BTreeSet<String> {
+    fs::read_to_string(templates_dir.join(file))
+        .map(|content| {
+            content
+                .lines()
+                .map(template_key)
+                .filter(|name| !name.is_empty())
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Adds or removes a template of a file of the templates directory, removing the file once empty.
+///
+/// # Returns
+/// `true` if the file changed.
+fn update_names(templates_dir: &Path, file: &str, key: &str, listed: bool) -> Result<bool> {
+    let mut names = read_names(templates_dir, file);
+    let changed = if listed {
+        names.insert(key.to_string())
+    } else {
+        names.remove(key)
+    };
+    if !changed {
+        return Ok(false);
+    }
+
+    let path = templates_dir.join(file);
+    if names.is_empty() {
+        fs::remove_file(path)?;
+    } else {
+        let content: String = names.iter().map(|name| format!("{name}\n")).collect();
+        fs::write(path, content)?;
+    }
+    Ok(true)
+}
+
+/// Whether the template of the templates directory is trusted: created by titular or trusted
+/// with `templates trust`.
+#[must_use]
+pub fn is_trusted(templates_dir: &Path, name: &str) -> bool {
+    let key = template_key(name);
+    read_names(templates_dir, LOCAL_FILE).contains(key) || is_trust_granted(templates_dir, key)
+}
+
+/// Whether the template of the templates directory was trusted with `templates trust`, letting
+/// the `[policy]` section of the template extend what it may do.
+#[must_use]
+pub fn is_trust_granted(templates_dir: &Path, name: &str) -> bool {
+    read_names(templates_dir, TRUSTED_FILE).contains(template_key(name))
+}
+
+/// Records the template of the templates directory as created by titular, trusting it.
+///
+/// # Errors
+/// Returns an error if the `.local` file cannot be written.
+pub fn mark_local(templates_dir: &Path, name: &str) -> Result<()> {
+    update_names(templates_dir, LOCAL_FILE, template_key(name), true)?;
+    Ok(())
+}
+
+/// Marks the template of the templates directory as trusted or untrusted. Untrusting a template
+/// also forgets that titular created it.
+///
+/// # Returns
+/// `true` if the trust of the template changed.
+///
+/// # Errors
+/// Returns an error if the `.local` or `.trusted` file cannot be written.
+pub fn set_trusted(templates_dir: &Path, name: &str, trusted: bool) -> Result<bool> {
+    let key = template_key(name);
+    if trusted {
+        update_names(templates_dir, TRUSTED_FILE, key, true)
+    } else {
+        let local_changed = update_names(templates_dir, LOCAL_FILE, key, false)?;
+        let trusted_changed = update_names(templates_dir, TRUSTED_FILE, key, false)?;
+        Ok(local_changed || trusted_changed)
+    }
+}
+
+/// Forgets the trust of a removed template of the templates directory.
+///
+/// # Errors
+/// Returns an error if the `.local` or `.trusted` file cannot be written.
+pub fn forget(templates_dir: &Path, name: &str) -> Result<()> {
+    set_trusted(templates_dir, name, false)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_template_trust() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(!is_trusted(dir.path(), "basic"));
+
+        mark_local(dir.path(), "created.tl").unwrap();
+        assert!(is_trusted(dir.path(), "created"));
+        assert!(!is_trust_granted(dir.path(), "created"));
+
+        assert!(set_trusted(dir.path(), "downloaded.tl", true).unwrap());
+        assert!(!set_trusted(dir.path(), "downloaded", true).unwrap());
+        assert!(set_trusted(dir.path(), "other", true).unwrap());
+        assert!(is_trusted(dir.path(), "downloaded"));
+        assert_eq!(
+            fs::read_to_string(dir.path().join(TRUSTED_FILE)).unwrap(),
+            "downloaded\nother\n"
+        );
+
+        assert!(set_trusted(dir.path(), "downloaded", false).unwrap());
+        assert!(set_trusted(dir.path(), "other.tl", false).unwrap());
+        assert!(set_trusted(dir.path(), "created", false).unwrap());
+        assert!(!is_trusted(dir.path(), "downloaded"));
+        assert!(!is_trusted(dir.path(), "created"));
+        assert!(!dir.path().join(TRUSTED_FILE).exists());
+        assert!(!dir.path().join(LOCAL_FILE).exists());
+        assert!(!set_trusted(dir.path(), "other", false).unwrap());
+    }
+
+    #[test]
+    fn test_hand_copied_template_untrusted() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("copied.tl"), "[pattern]\ndata = \"x\"\n").unwrap();
+        assert!(!is_trusted(dir.path(), "copied"));
+        assert!(!is_trust_granted(dir.path(), "copied"));
+    }
+
+    #[test]
+    fn test_trust_granted() {
+        let dir = tempfile::tempdir().unwrap();
+        // Local templates are trusted without their policy counting
+        mark_local(dir.path(), "local").unwrap();
+        assert!(!is_trust_granted(dir.path(), "local"));
+        assert!(set_trusted(dir.path(), "local", true).unwrap());
+        assert!(is_trust_granted(dir.path(), "local.tl"));
+
+        assert!(set_trusted(dir.path(), "local", false).unwrap());
+        assert!(!is_trust_granted(dir.path(), "local"));
+        assert!(!dir.path().join(TRUSTED_FILE).exists());
+
+        set_trusted(dir.path(), "local", true).unwrap();
+        forget(dir.path(), "local").unwrap();
+        assert!(!is_trust_granted(dir.path(), "local"));
+        assert!(!dir.path().join(TRUSTED_FILE).exists());
+    }
+
+    #[test]
+    fn test_env_allowlist() {
+        let policy = TemplatePolicy::new(vec!["HOME".to_string()], false);
+        assert!(policy.allows_env("HOME"));
+        assert!(!policy.allows_env("HOMEPATH"));
+        assert!(TemplatePolicy::new(Vec::new(), true).allows_env("HOMEPATH"));
+    }
+
+    #[test]
+    fn test_exec_allowlist() {
+        let allowlist = vec!["cargo".to_string(), "git-*".to_string()];
+        let trusted = TemplatePolicy::new(Vec::new(), true).with_exec_allowlist(allowlist.clone());
+        assert!(trusted.allows_exec("cargo"));
+        assert!(trusted.allows_exec("git-lfs"));
+        assert!(!trusted.allows_exec("/tmp/cargo"));
+        // Untrusted templates never run commands
+        let untrusted = TemplatePolicy::new(Vec::new(), false).with_exec_allowlist(allowlist);
+        assert!(!untrusted.allows_exec("cargo"));
+    }
+}
@@ -0,0 +1,206 @@
+//! Powerline-style segments set by the `segment` filter.
+//!
+//! A segment is some text drawn on a background color and followed by a separator. The filter
+//! only stores the colors of the segment in the rendered text: the segment processor joins
+//! adjacent segments afterwards, drawing each separator in the background color of the segment
+//! it ends over the background color of the next one, so that the colors flow from one segment
+//! into the next.
+
+use std::str::FromStr;
+
+use crate::error::{Error, Result};
+
+/// Shape of the separator ending a segment.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SegmentStyle {
+    /// A solid arrow
+    #[default]
+    Powerline,
+    /// A half circle
+    Round,
+    /// A slanted edge
+    Slant,
+    /// No separator, the backgrounds simply meet
+    Plain,
+}
+
+impl SegmentStyle {
+    /// Name of the style, as accepted by [`FromStr`].
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            SegmentStyle::Powerline => "powerline",
+            SegmentStyle::Round => "round",
+            SegmentStyle::Slant => "slant",
+            SegmentStyle::Plain => "plain",
+        }
+    }
+
+    /// The separator glyph, or its ASCII fallback when Nerd Font glyphs are disabled.
+    ///
+    /// # Examples
+    /// ```
+    /// use titular::segment::SegmentStyle;
+    ///
+    /// assert_eq!(SegmentStyle::Powerline.separator(true), "\u{E0B0}");
+    /// assert_eq!(SegmentStyle::Powerline.separator(false), ">");
+    /// assert_eq!(SegmentStyle::Plain.separator(true), "");
+    /// ```
+    #[must_use]
+    pub fn separator(self, glyphs: bool) -> &'static str {
+        match (self, glyphs) {
+            (SegmentStyle::Powerline, true) => "\u{E0B0}",
+            (SegmentStyle::Powerline, false) => ">",
+            (SegmentStyle::Round, true) => "\u{E0B4}",
+            (SegmentStyle::Round, false) => ")",
+            (SegmentStyle::Slant, true) => "\u{E0BC}",
+            (SegmentStyle::Slant, false) => "/",
+            (SegmentStyle::Plain, _) => "",
+        }
+    }
+}
+
+impl FromStr for SegmentStyle {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "powerline" => Ok(SegmentStyle::Powerline),
+            "round" => Ok(SegmentStyle::Round),
+            "slant" => Ok(SegmentStyle::Slant),
+            "plain" => Ok(SegmentStyle::Plain),
+            other => Err(Error::Msg(format!(
+                "invalid segment separator \"{other}\" (expected powerline, round, slant or plain)"
+            ))),
+        }
+    }
+}
+
+/// Colors of a segment, kept as SGR parameters (e.g. `44` or `48;2;0;0;255`), and the style of
+/// its separator. Parameters are empty when no color applies.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Segment {
+    pub style: SegmentStyle,
+    /// The background color
+    pub bg: String,
+    /// The background color as a foreground color, drawing the separator
+    pub bg_as_fg: String,
+    /// The foreground color
+    pub fg: String,
+}
+
+impl FromStr for Segment {
+    type Err = Error;
+
+    /// Parses the spec produced by [`Segment::to_spec`].
+    fn from_str(s: &str) -> Result<Self> {
+        let fields: Vec<&str> = s.split('|').collect();
+        let [style, bg, bg_as_fg, fg] = fields[..] else {
+            return Err(Error::Msg(format!("invalid segment spec \"{s}\"")));
+        };
+        Ok(Self {
+            style: style.parse()?,
+            bg: bg.to_string(),
+            bg_as_fg: bg_as_fg.to_string(),
+            fg: fg.to_string(),
+        })
+    }
+}
+
+impl Segment {
+    /// Serializes the segment into a spec stored in the rendered text.
+    ///
+    /// # Examples
+    /// ```
+    /// use titular::segment::{Segment, SegmentStyle};
+    ///
+    /// let segment = Segment {
+    ///     style: SegmentStyle::Round,
+    ///     bg: "44".to_string(),
+    ///     bg_as_fg: "34".to_string(),
+    ///     fg: "38;5;15".to_string(),
+    /// };
+    /// assert_eq!(segment.to_spec(), "round|44|34|38;5;15");
+    /// assert_eq!(segment.to_spec().parse::<Segment>().unwrap(), segment);
+    /// ```
+    #[must_use]
+    pub fn to_spec(&self) -> String {
+        format!(
+            "{}|{}|{}|{}",
+            self.style.name(),
+            self.bg,
+            self.bg_as_fg,
+            self.fg
+        )
+    }
+
+    /// Draws the content on the colors of the segment, with a space on each side.
+    #[must_use]
+    pub fn paint(&self, content: &str) -> String {
+        paint(&[&self.bg, &self.fg], &format!(" {content} "))
+    }
+
+    /// Draws the separator ending the segment, over the background of the `next` segment when
+    /// it directly follows.
+    ///
+    /// # Examples
+    /// ```
+    /// use titular::segment::{Segment, SegmentStyle};
+    ///
+    /// let blue = Segment { bg: "44".into(), bg_as_fg: "34".into(), ..Segment::default() };
+    /// let red = Segment { bg: "41".into(), bg_as_fg: "31".into(), ..Segment::default() };
+    /// assert_eq!(blue.separator(Some(&red), false), "\x1b[34;41m>\x1b[0m");
+    /// assert_eq!(red.separator(None, false), "\x1b[31m>\x1b[0m");
+    /// ```
+    #[must_use]
+    pub fn separator(&self, next: Option<&Segment>, glyphs: bool) -> String {
+        let glyph = self.style.separator(glyphs);
+        if glyph.is_empty() {
+            return String::new();
+        }
+        let next_bg = next.map_or("", |next| next.bg.as_str());
+        paint(&[&self.bg_as_fg, next_bg], glyph)
+    }
+}
+
+/// Wraps the text in the SGR parameters that are not empty, followed by a reset.
+fn paint(params: &[&str], text: &str) -> String {
+    let params: Vec<&str> = params.iter().copied().filter(|p| !p.is_empty()).collect();
+    if params.is_empty() {
+        text.to_string()
+    } else {
+        format!("\x1b[{}m{text}\x1b[0m", params.join(";"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_segment_spec() {
+        assert_eq!(
+            "plain|||".parse::<Segment>().unwrap(),
+            Segment {
+                style: SegmentStyle::Plain,
+                ..Segment::default()
+            }
+        );
+        assert!("powerline|44|34".parse::<Segment>().is_err());
+        assert!("arrow|||".parse::<Segment>().is_err());
+    }
+
+    #[test]
+    fn test_segment_paint() {
+        let segment = Segment {
+            style: SegmentStyle::Slant,
+            bg: "44".to_string(),
+            bg_as_fg: "34".to_string(),
+            fg: "97".to_string(),
+        };
+        assert_eq!(segment.paint("x"), "\x1b[44;97m x \x1b[0m");
+        assert_eq!(segment.separator(None, true), "\x1b[34m\u{E0BC}\x1b[0m");
+        assert_eq!(Segment::default().paint("x"), " x ");
+        assert_eq!(Segment::default().separator(None, true), "\u{E0B0}");
+    }
+}
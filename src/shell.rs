@@ -0,0 +1,357 @@
+//! Shell integration: the hooks printed by `titular init <shell>` and the escaping of titles
+//! used in shell prompts.
+//!
+//! The hooks export `LAST_EXIT_CODE` and `LAST_DURATION_MS` (read by `get_last_exit_code()` and
+//! `get_last_duration()`) before each prompt and may render a template after each command,
+//! before each prompt or as the prompt itself.
+
+use std::fmt::Write;
+use std::str::FromStr;
+
+use crate::ansi::{tokenize, Token};
+use crate::error::{Error, Result};
+
+/// A shell titular integrates with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl FromStr for Shell {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "bash" => Ok(Shell::Bash),
+            "zsh" => Ok(Shell::Zsh),
+            "fish" => Ok(Shell::Fish),
+            _ => Err(Error::Msg(format!(
+                "unsupported shell \"{s}\" (expected bash, zsh or fish)"
+            ))),
+        }
+    }
+}
+
+/// When the hooks render the template.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InitMode {
+    /// Before each prompt, as a separator
+    #[default]
+    Separator,
+    /// After each command, skipping empty command lines
+    AfterCommand,
+    /// As the prompt itself
+    Prompt,
+}
+
+impl FromStr for InitMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "separator" => Ok(InitMode::Separator),
+            "after-command" => Ok(InitMode::AfterCommand),
+            "prompt" => Ok(InitMode::Prompt),
+            _ => Err(Error::Msg(format!(
+                "invalid init mode \"{s}\" (expected separator, after-command or prompt)"
+            ))),
+        }
+    }
+}
+
+const BASH_HOOKS: &str = r#"# titular shell integration, load with: eval "$(titular init bash)"
+__titular_start_ms=""
+__titular_ready=""
+
+__titular_now_ms() {
+    if [[ -n "${EPOCHREALTIME:-}" ]]; then
+        local now="${EPOCHREALTIME//[^0-9]/}"
+        __titular_ms=$(( 10#$now / 1000 ))
+    else
+        __titular_ms=$(( SECONDS * 1000 ))
+    fi
+}
+
+__titular_preexec() {
+    __titular_now_ms
+    __titular_start_ms=$__titular_ms
+}
+
+# Without bash-preexec, the DEBUG trap spots the first command run after a prompt
+__titular_debug_trap() {
+    [[ -n "$__titular_ready" && -z "${COMP_LINE:-}" ]] || return
+    [[ "$BASH_COMMAND" == __titular_precmd* || "${FUNCNAME[1]:-}" == __titular_precmd ]] && return
+    __titular_ready=""
+    __titular_preexec
+}
+
+__titular_precmd() {
+    local exit_code=$?
+    export LAST_EXIT_CODE=$exit_code
+    if [[ -n "$__titular_start_ms" ]]; then
+        __titular_now_ms
+        export LAST_DURATION_MS=$(( __titular_ms - __titular_start_ms ))
+    else
+        export LAST_DURATION_MS=0
+    fi
+__RENDER__    __titular_start_ms=""
+    return $exit_code
+}
+
+if [[ -n "${bash_preexec_imported:-}" ]]; then
+    [[ " ${precmd_functions[*]} " == *" __titular_precmd "* ]] \
+        || precmd_functions=(__titular_precmd "${precmd_functions[@]}")
+    [[ " ${preexec_functions[*]} " == *" __titular_preexec "* ]] \
+        || preexec_functions+=(__titular_preexec)
+elif [[ "${PROMPT_COMMAND:-}" != *__titular_precmd* ]]; then
+    [[ -z "$(trap -p DEBUG)" ]] && trap '__titular_debug_trap' DEBUG
+    PROMPT_COMMAND="__titular_precmd${PROMPT_COMMAND:+; $PROMPT_COMMAND}; __titular_ready=1"
+fi
+"#;
+
+const ZSH_HOOKS: &str = r#"# titular shell integration, load with: eval "$(titular init zsh)"
+zmodload zsh/datetime
+autoload -Uz add-zsh-hook
+
+__titular_preexec() {
+    __titular_start=$EPOCHREALTIME
+}
+
+__titular_precmd() {
+    local exit_code=$?
+    export LAST_EXIT_CODE=$exit_code
+    if [[ -n "$__titular_start" ]]; then
+        local -i duration=$(( (EPOCHREALTIME - __titular_start) * 1000 ))
+        export LAST_DURATION_MS=$duration
+    else
+        export LAST_DURATION_MS=0
+    fi
+__RENDER__    unset __titular_start
+    return $exit_code
+}
+
+add-zsh-hook preexec __titular_preexec
+add-zsh-hook precmd __titular_precmd
+"#;
+
+const FISH_HOOKS: &str = r#"# titular shell integration, load with: titular init fish | source
+function __titular_postexec --on-event fish_postexec
+    set -l exit_code $status
+    set -gx LAST_EXIT_CODE $exit_code
+    set -gx LAST_DURATION_MS $CMD_DURATION
+__RENDER_AFTER__end
+"#;
+
+/// Quotes a word for the shell.
+fn quote(shell: Shell, word: &str) -> String {
+    match shell {
+        Shell::Bash | Shell::Zsh => format!("'{}'", word.replace('\'', r"'\''")),
+        Shell::Fish => format!("'{}'", word.replace('\\', r"\\").replace('\'', r"\'")),
+    }
+}
+
+impl Shell {
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Shell::Bash => "bash",
+            Shell::Zsh => "zsh",
+            Shell::Fish => "fish",
+        }
+    }
+
+    /// Escapes a rendered title for the prompt of the shell: the escape sequences are marked as
+    /// taking no room (`\[ \]` for bash, `%{ %}` for zsh) and the characters the shell would
+    /// interpret in the prompt are escaped. Fish prompts are printed as is.
+    ///
+    /// With the `PROMPT_SUBST` option, zsh also expands `$(...)` and backticks in the prompt, which
+    /// no escape prevents both with and without the option: the title must be set through a
+    /// variable, e.g. `title="$(titular ...)"; PROMPT='${title}'`, as the init hooks do.
+    ///
+    /// # Examples
+    /// ```
+    /// use titular::shell::Shell;
+    ///
+    /// let title = "\x1b[1m\x1b[31m100%\x1b[0m";
+    /// assert_eq!(Shell::Zsh.escape_prompt(title), "%{\x1b[1m\x1b[31m%}100%%%{\x1b[0m%}");
+    /// assert_eq!(Shell::Bash.escape_prompt("\x1b[1m$HOME"), "\\[\x1b[1m\\]\\\\$HOME");
+    /// ```
+    #[must_use]
+    pub fn escape_prompt(self, text: &str) -> String {
+        let (start, end) = match self {
+            Shell::Bash => (r"\[", r"\]"),
+            Shell::Zsh => ("%{", "%}"),
+            Shell::Fish => return text.to_string(),
+        };
+        let mut escaped = String::with_capacity(text.len());
+        let mut in_escape = false;
+        for token in tokenize(text) {
+            match token {
+                Token::Escape(_, sequence) => {
+                    if !in_escape {
+                        escaped.push_str(start);
+                        in_escape = true;
+                    }
+                    match self {
+                        // Keeps the string terminator (ESC \) of OSC sequences from escaping \]
+                        Shell::Bash => escaped.push_str(&sequence.replace('\\', r"\\")),
+                        _ => escaped.push_str(sequence),
+                    }
+                }
+                Token::Text(text) => {
+                    if in_escape {
+                        escaped.push_str(end);
+                        in_escape = false;
+                    }
+                    for c in text.chars() {
+                        match (self, c) {
+                            // Bash decodes the prompt then expands it, hence the double escaping
+                            (Shell::Bash, '\\') => escaped.push_str(r"\\\\"),
+                            (Shell::Bash, '$' | '`') => {
+                                escaped.push_str(r"\\");
+                                escaped.push(c);
+                            }
+                            (Shell::Zsh, '%') => escaped.push_str("%%"),
+                            _ => escaped.push(c),
+                        }
+                    }
+                }
+            }
+        }
+        if in_escape {
+            escaped.push_str(end);
+        }
+        escaped
+    }
+
+    /// The hooks integrating titular with the shell, to be evaluated by its startup file.
+    ///
+    /// # Arguments
+    /// * `exe` - The path of the titular executable.
+    /// * `template` - The template to render, if any.
+    /// * `mode` - When the template is rendered.
+    #[must_use]
+    pub fn init_script(self, exe: &str, template: Option<&str>, mode: InitMode) -> String {
+        let command = template.map(|template| {
+            let mut command = format!("{} -t {}", quote(self, exe), quote(self, template));
+            if mode == InitMode::Prompt {
+                let _ = write!(command, " -n --prompt-shell {}", self.name());
+            }
+            command
+        });
+
+        match self {
+            Shell::Bash | Shell::Zsh => {
+                let (hooks, started, prompt_var) = match self {
+                    Shell::Bash => (BASH_HOOKS, "__titular_start_ms", "PS1"),
+                    _ => (ZSH_HOOKS, "__titular_start", "PROMPT"),
+                };
+                let render = match (command, mode) {
+                    (None, _) => String::new(),
+                    (Some(command), InitMode::Separator) => format!("    {command}\n"),
+                    (Some(command), InitMode::AfterCommand) => {
+                        format!("    [[ -n \"${started}\" ]] && {command}\n")
+                    }
+                    // With PROMPT_SUBST, zsh expands the prompt again: the title is only
+                    // expanded once as a variable
+                    (Some(command), InitMode::Prompt) if self == Shell::Zsh => format!(
+                        "    __titular_prompt=\"$({command})\"\n    \
+                         if [[ -o prompt_subst ]]; then {prompt_var}='${{__titular_prompt}}'; \
+                         else {prompt_var}=\"$__titular_prompt\"; fi\n"
+                    ),
+                    (Some(command), InitMode::Prompt) => {
+                        format!("    {prompt_var}=\"$({command})\"\n")
+                    }
+                };
+                hooks.replace("__RENDER__", &render)
+            }
+            Shell::Fish => {
+                let mut script = FISH_HOOKS.replace(
+                    "__RENDER_AFTER__",
+                    &match (&command, mode) {
+                        (Some(command), InitMode::AfterCommand) => format!("    {command}\n"),
+                        _ => String::new(),
+                    },
+                );
+                match (command, mode) {
+                    (Some(command), InitMode::Separator) => {
+                        let _ = write!(
+                            script,
+                            "\nfunction __titular_separator --on-event fish_prompt\n    {command}\nend\n"
+                        );
+                    }
+                    (Some(command), InitMode::Prompt) => {
+                        let _ = write!(script, "\nfunction fish_prompt\n    {command}\nend\n");
+                    }
+                    _ => {}
+                }
+                script
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_prompt() {
+        let title = "\x1b[32m>\x1b[0m `ls` \\ 50% \x1b]8;;https://x.io\x1b\\$x\x1b]8;;\x1b\\";
+        assert_eq!(
+            Shell::Bash.escape_prompt(title),
+            "\\[\x1b[32m\\]>\\[\x1b[0m\\] \\\\`ls\\\\` \\\\\\\\ 50% \
+             \\[\x1b]8;;https://x.io\x1b\\\\\\]\\\\$x\\[\x1b]8;;\x1b\\\\\\]"
+        );
+        assert_eq!(
+            Shell::Zsh.escape_prompt(title),
+            "%{\x1b[32m%}>%{\x1b[0m%} `ls` \\ 50%% \
+             %{\x1b]8;;https://x.io\x1b\\%}$x%{\x1b]8;;\x1b\\%}"
+        );
+        assert_eq!(Shell::Fish.escape_prompt(title), title);
+        assert_eq!(Shell::Bash.escape_prompt("plain"), "plain");
+    }
+
+    #[test]
+    fn test_parse_shell_and_mode() {
+        assert_eq!("Bash".parse::<Shell>().unwrap(), Shell::Bash);
+        assert!("tcsh".parse::<Shell>().is_err());
+        assert_eq!(
+            "after-command".parse::<InitMode>().unwrap(),
+            InitMode::AfterCommand
+        );
+        assert!("never".parse::<InitMode>().is_err());
+    }
+
+    #[test]
+    fn test_init_script() {
+        let script = Shell::Bash.init_script("/usr/bin/titular", None, InitMode::Separator);
+        assert!(script.contains("export LAST_EXIT_CODE"));
+        assert!(!script.contains("__RENDER__"));
+        assert!(!script.contains("/usr/bin/titular"));
+
+        let script = Shell::Bash.init_script("/opt/it's/titular", Some("sep"), InitMode::Prompt);
+        assert!(
+            script.contains("PS1=\"$('/opt/it'\\''s/titular' -t 'sep' -n --prompt-shell bash)\"")
+        );
+
+        let script = Shell::Zsh.init_script("titular", Some("p"), InitMode::Prompt);
+        assert!(script.contains(
+            "__titular_prompt=\"$('titular' -t 'p' -n --prompt-shell zsh)\"\n    \
+             if [[ -o prompt_subst ]]; then PROMPT='${__titular_prompt}'; \
+             else PROMPT=\"$__titular_prompt\"; fi\n"
+        ));
+
+        let script = Shell::Zsh.init_script("titular", Some("sep"), InitMode::AfterCommand);
+        assert!(script.contains("[[ -n \"$__titular_start\" ]] && 'titular' -t 'sep'\n"));
+
+        let script = Shell::Fish.init_script("titular", Some("sep"), InitMode::Separator);
+        assert!(script.contains("--on-event fish_prompt\n    'titular' -t 'sep'\nend"));
+        let script = Shell::Fish.init_script("titular", Some("p"), InitMode::Prompt);
+        assert!(
+            script.contains("function fish_prompt\n    'titular' -t 'p' -n --prompt-shell fish")
+        );
+    }
+}
@@ -1,7 +1,9 @@
-use ansi_parser::{AnsiParser, Output};
-use console::{measure_text_width, strip_ansi_codes};
 use print_positions::print_positions;
 use unicode_general_category::{get_general_category, GeneralCategory};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::ansi::{strip_escapes, tokenize, visual_width, Token};
+use crate::width::{grapheme_width, AmbiguousWidth};
 
 /// Defines how ANSI codes should be handled after truncation
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -14,7 +16,7 @@ pub enum AnsiTruncateBehavior {
     NoModification,
 }
 
-/// Check if a string is visually empty (contains only control characters, ANSI codes, or other non-printable characters)
+/// Check if a string is visually empty (contains only control characters, escape sequences, or other non-printable characters)
 ///
 /// # Arguments
 /// * `s` - The string to check
@@ -29,6 +31,7 @@ pub enum AnsiTruncateBehavior {
 ///
 /// assert!(is_visually_empty("")); // Empty string
 /// assert!(is_visually_empty("\x1b[31m\x1b[0m")); // Only ANSI codes
+/// assert!(is_visually_empty("\x1b]8;;https://x.io\x1b\\\x1b]8;;\x1b\\")); // Empty hyperlink
 /// assert!(is_visually_empty("\u{200B}")); // Zero-width space (format character)
 /// assert!(is_visually_empty("\u{FEFF}")); // Zero-width no-break space (format character)
 /// assert!(!is_visually_empty("   ")); // Spaces are visually present
@@ -38,8 +41,8 @@ pub enum AnsiTruncateBehavior {
 /// ```
 #[must_use]
 pub fn is_visually_empty(s: &str) -> bool {
-    // First strip ANSI codes
-    let stripped = strip_ansi_codes(s);
+    // First strip escape sequences (SGR, OSC hyperlinks, ...)
+    let stripped = strip_escapes(s);
     // Then check if what remains is empty or only contains control/format characters
     stripped.chars().all(|c| {
         // A character is visually empty if it's not whitespace AND it's a control/format character
@@ -97,6 +100,49 @@ pub fn unescape_cli_escapes(input: &str) -> String {
     out
 }
 
+/// Splits the string on the separators found outside quotes, parentheses and brackets, trimming
+/// and skipping empty parts. Used to split color specs and filter arguments such as
+/// `RGB(1,2,3) on blue` or `color(name='RGB(1,2,3)')`.
+///
+/// # Examples
+///
+/// ```
+/// use titular::string_utils::split_top_level;
+///
+/// assert_eq!(
+///     split_top_level(" bold RGB(1, 2, 3)  on blue", char::is_whitespace),
+///     ["bold", "RGB(1, 2, 3)", "on", "blue"]
+/// );
+/// assert_eq!(
+///     split_top_level("a | f(x='1|2', y=[3, 4])", |c| c == '|'),
+///     ["a", "f(x='1|2', y=[3, 4])"]
+/// );
+/// ```
+#[must_use]
+pub fn split_top_level(s: &str, is_separator: impl Fn(char) -> bool) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut quote = None;
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '(' | '[') => depth += 1,
+            (None, ')' | ']') => depth = depth.saturating_sub(1),
+            (None, c) if depth == 0 && is_separator(c) => {
+                parts.push(s[start..i].trim());
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(s[start..].trim());
+    parts.retain(|p| !p.is_empty());
+    parts
+}
+
 /// Prints a string with its raw ANSI codes
 ///
 /// # Arguments
@@ -172,13 +218,15 @@ pub fn expand_to_width(input: &str, target_width: usize) -> String {
 }
 
 /// Expands a string to a target visual width by repeating its content.
-/// The width is calculated based on the actual display width of characters,
-/// taking into account wide characters like emojis.
+/// The width is calculated based on the actual display width of grapheme clusters,
+/// taking into account wide characters like emojis. The last repetition is cut between
+/// clusters, so the result may fall short of the target by a column.
 ///
 /// # Arguments
 ///
 /// * `input` - The input string to expand
 /// * `target_width` - The target width in display units
+/// * `ambiguous` - The width of East Asian ambiguous characters
 ///
 /// # Returns
 ///
@@ -188,24 +236,31 @@ pub fn expand_to_width(input: &str, target_width: usize) -> String {
 ///
 /// ```
 /// use titular::string_utils::expand_to_visual_width;
+/// use titular::width::AmbiguousWidth::{Narrow, Wide};
 ///
-/// assert_eq!(expand_to_visual_width("X", 2), "XX");
-/// assert_eq!(expand_to_visual_width("XY", 3), "XYX");
-/// assert_eq!(expand_to_visual_width("📦", 4), "📦📦"); // Each emoji is 2 units wide
-/// assert_eq!(expand_to_visual_width("📦🌟", 6), "📦🌟📦"); // Each emoji is 2 units wide
+/// assert_eq!(expand_to_visual_width("X", 2, Narrow), "XX");
+/// assert_eq!(expand_to_visual_width("XY", 3, Narrow), "XYX");
+/// assert_eq!(expand_to_visual_width("📦", 4, Narrow), "📦📦"); // Each emoji is 2 units wide
+/// assert_eq!(expand_to_visual_width("📦🌟", 6, Narrow), "📦🌟📦"); // Each emoji is 2 units wide
+/// assert_eq!(expand_to_visual_width("🇫🇷·", 5, Narrow), "🇫🇷·🇫🇷"); // Flags are never split
+/// assert_eq!(expand_to_visual_width("─", 4, Wide), "──");
 /// ```
 #[must_use]
-pub fn expand_to_visual_width(input: &str, target_width: usize) -> String {
+pub fn expand_to_visual_width(
+    input: &str,
+    target_width: usize,
+    ambiguous: AmbiguousWidth,
+) -> String {
     // If input is empty, return as is
     if input.is_empty() {
         return input.to_string();
     }
 
     // Calculate current visual width
-    let current_width = measure_text_width(input);
+    let current_width = visual_width(input, ambiguous);
 
     // If target_width is 0 or less than or equal to current width, return input as-is
-    if target_width == 0 || current_width >= target_width {
+    if target_width == 0 || current_width == 0 || current_width >= target_width {
         return input.to_string();
     }
 
@@ -219,9 +274,9 @@ pub fn expand_to_visual_width(input: &str, target_width: usize) -> String {
     }
 
     // If we've exceeded the target width, truncate
-    if measure_text_width(&result) > target_width {
+    if visual_width(&result, ambiguous) > target_width {
         let mut truncated = result;
-        truncated.truncate_ansi(target_width);
+        truncated.truncate_ansi(target_width, ambiguous);
         truncated
     } else {
         result
@@ -234,8 +289,9 @@ pub trait Truncate {
     ///
     /// # Arguments
     /// * `width` - The maximum width in characters
-    fn truncate_ansi(&mut self, width: usize) {
-        self.truncate_ansi_with(width, AnsiTruncateBehavior::NoModification);
+    /// * `ambiguous` - The width of East Asian ambiguous characters
+    fn truncate_ansi(&mut self, width: usize, ambiguous: AmbiguousWidth) {
+        self.truncate_ansi_with(width, AnsiTruncateBehavior::NoModification, ambiguous);
     }
 
     /// Truncates a string to the specified width with configurable ANSI code handling
@@ -243,7 +299,13 @@ pub trait Truncate {
     /// # Arguments
     /// * `width` - The maximum width in characters
     /// * `behavior` - How to handle ANSI codes after truncation
-    fn truncate_ansi_with(&mut self, width: usize, behavior: AnsiTruncateBehavior);
+    /// * `ambiguous` - The width of East Asian ambiguous characters
+    fn truncate_ansi_with(
+        &mut self,
+        width: usize,
+        behavior: AnsiTruncateBehavior,
+        ambiguous: AmbiguousWidth,
+    );
 }
 
 impl Truncate for String {
@@ -252,20 +314,26 @@ impl Truncate for String {
     /// # Arguments
     /// * `width` - The maximum width in characters
     /// * `behavior` - How to handle ANSI codes after truncation
+    /// * `ambiguous` - The width of East Asian ambiguous characters
     ///
     /// # Examples
     ///
     /// ```
     /// use titular::string_utils::{Truncate, AnsiTruncateBehavior};
+    /// use titular::width::AmbiguousWidth;
     ///
     /// let mut s = String::from("\x1b[31mHello\x1b[0m World");
-    /// s.truncate_ansi_with(5, AnsiTruncateBehavior::PreserveRemaining);
+    /// s.truncate_ansi_with(5, AnsiTruncateBehavior::PreserveRemaining, AmbiguousWidth::Narrow);
     /// assert_eq!(s, "\x1b[31mHello\x1b[0m");
     /// ```
-    fn truncate_ansi_with(&mut self, width: usize, behavior: AnsiTruncateBehavior) {
-        // Get the actual text width without ANSI codes
-        let text_without_ansi = strip_ansi_codes(self);
-        let text_width = measure_text_width(&text_without_ansi);
+    fn truncate_ansi_with(
+        &mut self,
+        width: usize,
+        behavior: AnsiTruncateBehavior,
+        ambiguous: AmbiguousWidth,
+    ) {
+        // Get the actual text width without escape sequences
+        let text_width = visual_width(self, ambiguous);
 
         // If text is already within width limit, return it as is
         if text_width <= width {
@@ -275,33 +343,26 @@ impl Truncate for String {
         // Find the position where we need to cut the text
         let mut current_width = 0;
         let mut result = String::new();
-        let mut current_pos = 0;
 
-        while current_pos < self.len() {
-            // Check if we're in an ANSI sequence
-            if self[current_pos..].starts_with("\x1b[") {
-                if let Some(end) = self[current_pos..].find('m') {
-                    // Only include ANSI codes that come before our truncation point
+        'tokens: for token in tokenize(self) {
+            match token {
+                // Only include escape sequences that come before our truncation point
+                Token::Escape(_, seq) => {
                     if current_width < width {
-                        let ansi_seq = &self[current_pos..=(current_pos + end)];
-                        result.push_str(ansi_seq);
+                        result.push_str(seq);
                     }
-                    current_pos += end + 1;
-                    continue;
                 }
-            }
-
-            // Process regular characters
-            if let Some(c) = self[current_pos..].chars().next() {
-                let char_width = measure_text_width(&c.to_string());
-                if current_width + char_width > width {
-                    break;
+                // Whole grapheme clusters only, never splitting an emoji sequence or an accent
+                Token::Text(text) => {
+                    for grapheme in text.graphemes(true) {
+                        let grapheme_width = grapheme_width(grapheme, ambiguous);
+                        if current_width + grapheme_width > width {
+                            break 'tokens;
+                        }
+                        current_width += grapheme_width;
+                        result.push_str(grapheme);
+                    }
                 }
-                current_width += char_width;
-                result.push(c);
-                current_pos += c.len_utf8();
-            } else {
-                break;
             }
         }
 
@@ -326,12 +387,11 @@ fn process_ansi_escapes(truncated: &str, original: &str, behavior: AnsiTruncateB
             // Get the remaining part by stripping the truncated string from the beginning
             let remaining = original.strip_prefix(truncated).unwrap_or("");
 
-            // Collect remaining ANSI codes using ansi-parser
-            let codes: String = remaining
-                .ansi_parse()
-                .filter_map(|block| match block {
-                    Output::Escape(seq) => Some(seq.to_string()),
-                    Output::TextBlock(_) => None,
+            // Collect the remaining escape sequences (colors, hyperlink ends, ...)
+            let codes: String = tokenize(remaining)
+                .filter_map(|token| match token {
+                    Token::Escape(_, seq) => Some(seq),
+                    Token::Text(_) => None,
                 })
                 .collect();
 
@@ -353,6 +413,8 @@ fn process_ansi_escapes(truncated: &str, original: &str, behavior: AnsiTruncateB
 mod tests {
     use super::*;
 
+    const NARROW: AmbiguousWidth = AmbiguousWidth::Narrow;
+
     #[test]
     fn test_unescape_cli_escapes() {
         assert_eq!(unescape_cli_escapes(""), "");
@@ -428,43 +490,43 @@ mod tests {
     #[test]
     fn test_expand_to_visual_width() {
         // Test basic ASCII characters
-        assert_eq!(expand_to_visual_width("X", 0), "X");
-        assert_eq!(expand_to_visual_width("X", 1), "X");
-        assert_eq!(expand_to_visual_width("X", 2), "XX");
-        assert_eq!(expand_to_visual_width("X", 3), "XXX");
+        assert_eq!(expand_to_visual_width("X", 0, NARROW), "X");
+        assert_eq!(expand_to_visual_width("X", 1, NARROW), "X");
+        assert_eq!(expand_to_visual_width("X", 2, NARROW), "XX");
+        assert_eq!(expand_to_visual_width("X", 3, NARROW), "XXX");
 
         // Test multi-character strings
-        assert_eq!(expand_to_visual_width("XY", 0), "XY");
-        assert_eq!(expand_to_visual_width("XY", 1), "XY");
-        assert_eq!(expand_to_visual_width("XY", 2), "XY");
-        assert_eq!(expand_to_visual_width("XY", 3), "XYX");
-        assert_eq!(expand_to_visual_width("XY", 4), "XYXY");
+        assert_eq!(expand_to_visual_width("XY", 0, NARROW), "XY");
+        assert_eq!(expand_to_visual_width("XY", 1, NARROW), "XY");
+        assert_eq!(expand_to_visual_width("XY", 2, NARROW), "XY");
+        assert_eq!(expand_to_visual_width("XY", 3, NARROW), "XYX");
+        assert_eq!(expand_to_visual_width("XY", 4, NARROW), "XYXY");
 
         // Test emojis (each emoji is 2 units wide)
-        assert_eq!(expand_to_visual_width("📦", 0), "📦");
-        assert_eq!(expand_to_visual_width("📦", 1), "📦");
-        assert_eq!(expand_to_visual_width("📦", 2), "📦");
-        assert_eq!(expand_to_visual_width("📦", 3), "📦");
-        assert_eq!(expand_to_visual_width("📦", 4), "📦📦");
-        assert_eq!(expand_to_visual_width("📦", 5), "📦📦");
-        assert_eq!(expand_to_visual_width("📦", 6), "📦📦📦");
+        assert_eq!(expand_to_visual_width("📦", 0, NARROW), "📦");
+        assert_eq!(expand_to_visual_width("📦", 1, NARROW), "📦");
+        assert_eq!(expand_to_visual_width("📦", 2, NARROW), "📦");
+        assert_eq!(expand_to_visual_width("📦", 3, NARROW), "📦");
+        assert_eq!(expand_to_visual_width("📦", 4, NARROW), "📦📦");
+        assert_eq!(expand_to_visual_width("📦", 5, NARROW), "📦📦");
+        assert_eq!(expand_to_visual_width("📦", 6, NARROW), "📦📦📦");
 
         // Test mixed characters
-        assert_eq!(expand_to_visual_width("📦-", 4), "📦-");
-        assert_eq!(expand_to_visual_width("📦-", 5), "📦-📦"); // Visual width 3, needs to repeat to reach 5
-        assert_eq!(expand_to_visual_width("📦-", 6), "📦-📦-");
+        assert_eq!(expand_to_visual_width("📦-", 4, NARROW), "📦-");
+        assert_eq!(expand_to_visual_width("📦-", 5, NARROW), "📦-📦"); // Visual width 3, needs to repeat to reach 5
+        assert_eq!(expand_to_visual_width("📦-", 6, NARROW), "📦-📦-");
 
         // Test ANSI escape codes
         assert_eq!(
-            expand_to_visual_width("\x1b[31mH\x1b[0m", 0),
+            expand_to_visual_width("\x1b[31mH\x1b[0m", 0, NARROW),
             "\x1b[31mH\x1b[0m"
         );
         assert_eq!(
-            expand_to_visual_width("\x1b[31mH\x1b[0m", 1),
+            expand_to_visual_width("\x1b[31mH\x1b[0m", 1, NARROW),
             "\x1b[31mH\x1b[0m"
         );
         assert_eq!(
-            expand_to_visual_width("\x1b[31mH\x1b[0m", 2),
+            expand_to_visual_width("\x1b[31mH\x1b[0m", 2, NARROW),
             "\x1b[31mH\x1b[0m\x1b[31mH\x1b[0m"
         );
     }
@@ -473,77 +535,135 @@ mod tests {
     fn test_truncate_ansi() {
         // Test basic ASCII truncation
         let mut s = String::from("Hello World");
-        s.truncate_ansi(5);
+        s.truncate_ansi(5, NARROW);
         assert_eq!(s, "Hello");
 
         // Test truncation with ANSI colors
         let mut s = String::from("\x1b[31mHello\x1b[0m World");
-        s.truncate_ansi(5);
+        s.truncate_ansi(5, NARROW);
         assert_eq!(s, "\x1b[31mHello");
 
         // Test truncation with emojis
         let mut s = String::from("Hello 🦀 World");
-        s.truncate_ansi(8);
+        s.truncate_ansi(8, NARROW);
         assert_eq!(s, "Hello 🦀");
         let mut s = String::from("Hello 🦀 World");
-        s.truncate_ansi(7);
+        s.truncate_ansi(7, NARROW);
         assert_eq!(s, "Hello ");
 
         // Test truncation with ANSI and emojis
         let mut s = String::from("\x1b[31mHello 🦀\x1b[0m World");
-        s.truncate_ansi(8);
+        s.truncate_ansi(8, NARROW);
         assert_eq!(s, "\x1b[31mHello 🦀");
         let mut s = String::from("\x1b[31mHello 🦀\x1b[0m World");
-        s.truncate_ansi(7);
+        s.truncate_ansi(7, NARROW);
         assert_eq!(s, "\x1b[31mHello ");
 
         // Test truncation with no change needed
         let mut s = String::from("Hello");
-        s.truncate_ansi(10);
+        s.truncate_ansi(10, NARROW);
         assert_eq!(s, "Hello");
 
         // Test truncation to zero
         let mut s = String::from("Hello");
-        s.truncate_ansi(0);
+        s.truncate_ansi(0, NARROW);
         assert_eq!(s, "");
     }
 
+    #[test]
+    fn test_truncate_ansi_with_hyperlinks() {
+        let link = "\x1b]8;;https://example.com\x1b\\";
+        let end = "\x1b]8;;\x1b\\";
+        let mut s = format!("{link}Hello World{end}");
+        s.truncate_ansi_with(5, AnsiTruncateBehavior::PreserveRemaining, NARROW);
+        assert_eq!(s, format!("{link}Hello{end}"));
+
+        let mut s = format!("{link}\x1b[31mHello{end}\x1b[0m");
+        s.truncate_ansi(10, NARROW);
+        assert_eq!(s, format!("{link}\x1b[31mHello{end}\x1b[0m"));
+    }
+
+    #[test]
+    fn test_expand_to_visual_width_graphemes() {
+        // Emoji ZWJ sequences and flags are repeated whole
+        let family = "👨\u{200D}👩\u{200D}👧";
+        assert_eq!(expand_to_visual_width(family, 5, NARROW), family.repeat(2));
+        assert_eq!(expand_to_visual_width("🇫🇷", 6, NARROW), "🇫🇷🇫🇷🇫🇷");
+        assert_eq!(expand_to_visual_width("-🇫🇷", 4, NARROW), "-🇫🇷-");
+
+        // CJK characters are two columns wide
+        assert_eq!(expand_to_visual_width("日本", 7, NARROW), "日本日");
+        assert_eq!(expand_to_visual_width("=語", 6, NARROW), "=語=語");
+
+        // Combining marks stay with their base character
+        assert_eq!(
+            expand_to_visual_width("e\u{301}", 3, NARROW),
+            "e\u{301}".repeat(3)
+        );
+        assert_eq!(
+            expand_to_visual_width("a\u{300}b\u{301}", 3, NARROW),
+            "a\u{300}b\u{301}a\u{300}"
+        );
+    }
+
+    #[test]
+    fn test_truncate_ansi_graphemes() {
+        let mut s = String::from("ab👨\u{200D}👩\u{200D}👧cd");
+        s.truncate_ansi(4, NARROW);
+        assert_eq!(s, "ab👨\u{200D}👩\u{200D}👧");
+        let mut s = String::from("ab👨\u{200D}👩\u{200D}👧cd");
+        s.truncate_ansi(3, NARROW);
+        assert_eq!(s, "ab");
+
+        let mut s = String::from("\x1b[31m🇫🇷🇯🇵\x1b[0m");
+        s.truncate_ansi_with(3, AnsiTruncateBehavior::PreserveRemaining, NARROW);
+        assert_eq!(s, "\x1b[31m🇫🇷\x1b[0m");
+
+        let mut s = String::from("日本語");
+        s.truncate_ansi(5, NARROW);
+        assert_eq!(s, "日本");
+
+        let mut s = String::from("cafe\u{301}s");
+        s.truncate_ansi(4, NARROW);
+        assert_eq!(s, "cafe\u{301}");
+    }
+
     #[test]
     fn test_truncate_ansi_with_reset_after() {
         // Test basic ASCII truncation
         let mut s = String::from("Hello World");
-        s.truncate_ansi_with(5, AnsiTruncateBehavior::ResetAfter);
+        s.truncate_ansi_with(5, AnsiTruncateBehavior::ResetAfter, NARROW);
         assert_eq!(s, "Hello");
 
         // Test truncation with ANSI colors
         let mut s = String::from("\x1b[31mHello\x1b[0m World");
-        s.truncate_ansi_with(5, AnsiTruncateBehavior::ResetAfter);
+        s.truncate_ansi_with(5, AnsiTruncateBehavior::ResetAfter, NARROW);
         assert_eq!(s, "\x1b[31mHello\x1b[0m");
 
         // Test truncation with emojis
         let mut s = String::from("Hello 🦀 World");
-        s.truncate_ansi_with(8, AnsiTruncateBehavior::ResetAfter);
+        s.truncate_ansi_with(8, AnsiTruncateBehavior::ResetAfter, NARROW);
         assert_eq!(s, "Hello 🦀");
         let mut s = String::from("Hello 🦀 World");
-        s.truncate_ansi_with(7, AnsiTruncateBehavior::ResetAfter);
+        s.truncate_ansi_with(7, AnsiTruncateBehavior::ResetAfter, NARROW);
         assert_eq!(s, "Hello ");
 
         // Test truncation with ANSI and emojis
         let mut s = String::from("\x1b[31mHello 🦀\x1b[0m World");
-        s.truncate_ansi_with(8, AnsiTruncateBehavior::ResetAfter);
+        s.truncate_ansi_with(8, AnsiTruncateBehavior::ResetAfter, NARROW);
         assert_eq!(s, "\x1b[31mHello 🦀\x1b[0m");
         let mut s = String::from("\x1b[31mHello 🦀\x1b[0m World");
-        s.truncate_ansi_with(7, AnsiTruncateBehavior::ResetAfter);
+        s.truncate_ansi_with(7, AnsiTruncateBehavior::ResetAfter, NARROW);
         assert_eq!(s, "\x1b[31mHello \x1b[0m");
 
         // Test truncation with no change needed
         let mut s = String::from("Hello");
-        s.truncate_ansi_with(10, AnsiTruncateBehavior::ResetAfter);
+        s.truncate_ansi_with(10, AnsiTruncateBehavior::ResetAfter, NARROW);
         assert_eq!(s, "Hello");
 
         // Test truncation to zero
         let mut s = String::from("Hello");
-        s.truncate_ansi_with(0, AnsiTruncateBehavior::ResetAfter);
+        s.truncate_ansi_with(0, AnsiTruncateBehavior::ResetAfter, NARROW);
         assert_eq!(s, "");
     }
 
@@ -551,27 +671,27 @@ mod tests {
     fn test_truncate_ansi_with_preserve_remaining() {
         // Test basic ASCII truncation
         let mut s = String::from("Hello World");
-        s.truncate_ansi_with(5, AnsiTruncateBehavior::PreserveRemaining);
+        s.truncate_ansi_with(5, AnsiTruncateBehavior::PreserveRemaining, NARROW);
         assert_eq!(s, "Hello");
 
         // Test truncation with ANSI colors
         let mut s = String::from("\x1b[31mHello\x1b[0m World");
-        s.truncate_ansi_with(5, AnsiTruncateBehavior::PreserveRemaining);
+        s.truncate_ansi_with(5, AnsiTruncateBehavior::PreserveRemaining, NARROW);
         assert_eq!(s, "\x1b[31mHello\x1b[0m");
 
         // Test truncation with emojis
         let mut s = String::from("Hello 🦀 World");
-        s.truncate_ansi_with(8, AnsiTruncateBehavior::PreserveRemaining);
+        s.truncate_ansi_with(8, AnsiTruncateBehavior::PreserveRemaining, NARROW);
         assert_eq!(s, "Hello 🦀");
 
         // Test with multiple ANSI codes and emojis
         let mut s = String::from("\x1b[31mHello 🦀\x1b[32m World\x1b[0m");
-        s.truncate_ansi_with(7, AnsiTruncateBehavior::PreserveRemaining);
+        s.truncate_ansi_with(7, AnsiTruncateBehavior::PreserveRemaining, NARROW);
         assert_eq!(s, "\x1b[31mHello \x1b[32m\x1b[0m");
 
         // Test with nested ANSI codes
         let mut s = String::from("\x1b[1m\x1b[31mBold Red\x1b[32mGreen\x1b[0m");
-        s.truncate_ansi_with(5, AnsiTruncateBehavior::PreserveRemaining);
+        s.truncate_ansi_with(5, AnsiTruncateBehavior::PreserveRemaining, NARROW);
         assert_eq!(s, "\x1b[1m\x1b[31mBold \x1b[32m\x1b[0m");
     }
 
@@ -14,6 +14,7 @@ use zip::{ZipArchive, ZipWriter};
 
 use crate::constants::template::DEFAULT_TEMPLATE_EXT;
 use crate::error::{Error, Result};
+use crate::policy;
 
 fn warn_skip_existing(dest: &Path) {
     let msg = format!(
@@ -134,7 +135,8 @@ fn is_tl_file(path: &Path) -> bool {
         .is_some_and(|e| e.eq_ignore_ascii_case("tl"))
 }
 
-/// Extract `.tl` entries from `archive` into `root`. Unsafe paths are skipped.
+/// Extract `.tl` entries from `archive` into `root`, marking them untrusted. Unsafe paths are
+/// skipped.
 pub fn import_bundle_to_templates_dir(archive: &Path, root: &Path, force: bool) -> Result<()> {
     let data = fs::read(archive).map_err(Error::Io)?;
     let reader = Cursor::new(data);
@@ -174,6 +176,9 @@ pub fn import_bundle_to_templates_dir(archive: &Path, root: &Path, force: bool)
             fs::create_dir_all(parent).map_err(Error::Io)?;
         }
 
+        // Imported templates are untrusted until `templates trust` is run, even if the import
+        // fails once the file is written
+        policy::set_trusted(root, &arc_path_for_zip(&rel), false)?;
         let mut out = File::create(&dest).map_err(Error::Io)?;
         io::copy(&mut file, &mut out).map_err(Error::Io)?;
     }
@@ -213,6 +218,8 @@ mod tests {
             fs::read_to_string(dst.join("nested/b.tl")).unwrap(),
             "[pattern]\ndata=y"
         );
+        assert!(!policy::is_trusted(&dst, "a"));
+        assert!(!policy::is_trusted(&dst, "nested/b"));
     }
 
     #[test]
@@ -0,0 +1,426 @@
+//! Unicode text styles: ASCII letters and digits mapped to look-alike characters (mathematical
+//! alphanumerics, fullwidth forms, small capitals, ...), which look styled on terminals that do
+//! not render bold or italic.
+//!
+//! Escape sequences and markers are left untouched, and characters without a counterpart in the
+//! style are kept as they are.
+
+use std::str::FromStr;
+
+use crate::ansi::{tokenize, update_layers, Token};
+use crate::constants::is_marker;
+use crate::error::{Error, Result};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// A Unicode text style.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextStyle {
+    /// Mathematical bold: 𝐀𝐚𝟏
+    Bold,
+    /// Mathematical italic: 𝐴𝑎
+    Italic,
+    /// Mathematical script: 𝒜𝒶
+    Script,
+    /// Mathematical fraktur: 𝔄𝔞
+    Fraktur,
+    /// Mathematical double-struck: 𝔸𝕒𝟙
+    DoubleStruck,
+    /// Mathematical monospace: 𝙰𝚊𝟷
+    Monospace,
+    /// Fullwidth forms, two columns each: Ａａ１
+    Fullwidth,
+    /// Small capitals: ᴀʙᴄ
+    SmallCaps,
+    /// Circled letters and digits: Ⓐⓐ①
+    Circled,
+    /// Squared capitals: 🄰
+    Squared,
+    /// Upside-down text, read backwards: ɐqɔ
+    UpsideDown,
+}
+
+impl FromStr for TextStyle {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let name: String = s
+            .chars()
+            .filter(|c| !matches!(c, '-' | '_' | ' '))
+            .collect::<String>()
+            .to_lowercase();
+        match name.as_str() {
+            "bold" => Ok(TextStyle::Bold),
+            "italic" => Ok(TextStyle::Italic),
+            "script" => Ok(TextStyle::Script),
+            "fraktur" => Ok(TextStyle::Fraktur),
+            "doublestruck" => Ok(TextStyle::DoubleStruck),
+            "monospace" => Ok(TextStyle::Monospace),
+            "fullwidth" => Ok(TextStyle::Fullwidth),
+            "smallcaps" => Ok(TextStyle::SmallCaps),
+            "circled" => Ok(TextStyle::Circled),
+            "squared" => Ok(TextStyle::Squared),
+            "upsidedown" | "inverted" => Ok(TextStyle::UpsideDown),
+            _ => Err(Error::Msg(format!(
+                "invalid text style \"{s}\" (expected bold, italic, script, fraktur, \
+                 double-struck, monospace, fullwidth, small-caps, circled, squared or upside-down)"
+            ))),
+        }
+    }
+}
+
+/// First code points of the capitals, small letters and digits of the mathematical styles
+const BOLD: (u32, u32, Option<u32>) = (0x1D400, 0x1D41A, Some(0x1D7CE));
+const ITALIC: (u32, u32, Option<u32>) = (0x1D434, 0x1D44E, None);
+const SCRIPT: (u32, u32, Option<u32>) = (0x1D49C, 0x1D4B6, None);
+const FRAKTUR: (u32, u32, Option<u32>) = (0x1D504, 0x1D51E, None);
+const DOUBLE_STRUCK: (u32, u32, Option<u32>) = (0x1D538, 0x1D552, Some(0x1D7D8));
+const MONOSPACE: (u32, u32, Option<u32>) = (0x1D670, 0x1D68A, Some(0x1D7F6));
+
+/// Letters encoded before the mathematical alphanumerics block, whose code points are reserved
+/// (left as holes) in the block
+const HOLES: &[(TextStyle, char, char)] = &[
+    (TextStyle::Italic, 'h', 'ℎ'),
+    (TextStyle::Script, 'B', 'ℬ'),
+    (TextStyle::Script, 'E', 'ℰ'),
+    (TextStyle::Script, 'F', 'ℱ'),
+    (TextStyle::Script, 'H', 'ℋ'),
+    (TextStyle::Script, 'I', 'ℐ'),
+    (TextStyle::Script, 'L', 'ℒ'),
+    (TextStyle::Script, 'M', 'ℳ'),
+    (TextStyle::Script, 'R', 'ℛ'),
+    (TextStyle::Script, 'e', 'ℯ'),
+    (TextStyle::Script, 'g', 'ℊ'),
+    (TextStyle::Script, 'o', 'ℴ'),
+    (TextStyle::Fraktur, 'C', 'ℭ'),
+    (TextStyle::Fraktur, 'H', 'ℌ'),
+    (TextStyle::Fraktur, 'I', 'ℑ'),
+    (TextStyle::Fraktur, 'R', 'ℜ'),
+    (TextStyle::Fraktur, 'Z', 'ℨ'),
+    (TextStyle::DoubleStruck, 'C', 'ℂ'),
+    (TextStyle::DoubleStruck, 'H', 'ℍ'),
+    (TextStyle::DoubleStruck, 'N', 'ℕ'),
+    (TextStyle::DoubleStruck, 'P', 'ℙ'),
+    (TextStyle::DoubleStruck, 'Q', 'ℚ'),
+    (TextStyle::DoubleStruck, 'R', 'ℝ'),
+    (TextStyle::DoubleStruck, 'Z', 'ℤ'),
+];
+
+const SMALL_CAPS: [char; 26] = [
+    'ᴀ', 'ʙ', 'ᴄ', 'ᴅ', 'ᴇ', 'ꜰ', 'ɢ', 'ʜ', 'ɪ', 'ᴊ', 'ᴋ', 'ʟ', 'ᴍ', 'ɴ', 'ᴏ', 'ᴘ', 'ǫ', 'ʀ', 'ꜱ',
+    'ᴛ', 'ᴜ', 'ᴠ', 'ᴡ', 'x', 'ʏ', 'ᴢ',
+];
+
+/// Characters turned upside down, both ways
+const UPSIDE_DOWN: &[(char, char)] = &[
+    ('a', 'ɐ'),
+    ('b', 'q'),
+    ('c', 'ɔ'),
+    ('d', 'p'),
+    ('e', 'ǝ'),
+    ('f', 'ɟ'),
+    ('g', 'ƃ'),
+    ('h', 'ɥ'),
+    ('i', 'ᴉ'),
+    ('j', 'ɾ'),
+    ('k', 'ʞ'),
+    ('m', 'ɯ'),
+    ('n', 'u'),
+    ('r', 'ɹ'),
+    ('t', 'ʇ'),
+    ('v', 'ʌ'),
+    ('w', 'ʍ'),
+    ('y', 'ʎ'),
+    ('A', '∀'),
+    ('C', 'Ɔ'),
+    ('E', 'Ǝ'),
+    ('F', 'Ⅎ'),
+    ('G', '⅁'),
+    ('J', 'ſ'),
+    ('L', '˥'),
+    ('M', 'W'),
+    ('P', 'Ԁ'),
+    ('T', '⊥'),
+    ('U', '∩'),
+    ('V', 'Λ'),
+    ('Y', '⅄'),
+    ('1', 'Ɩ'),
+    ('2', 'ᘔ'),
+    ('3', 'Ɛ'),
+    ('4', 'ߤ'),
+    ('5', 'ϛ'),
+    ('6', '9'),
+    ('7', 'Ɫ'),
+    ('.', '˙'),
+    (',', '\''),
+    ('!', '¡'),
+    ('?', '¿'),
+    ('(', ')'),
+    ('[', ']'),
+    ('{', '}'),
+    ('<', '>'),
+    ('_', '‾'),
+    ('&', '⅋'),
+];
+
+impl TextStyle {
+    /// The look-alike of the character in the style, if any.
+    fn map(self, c: char) -> Option<char> {
+        if let Some(&(_, _, to)) = HOLES.iter().find(|(s, from, _)| *s == self && *from == c) {
+            return Some(to);
+        }
+        let offset = |base: u32, first: char| char::from_u32(base + (c as u32 - first as u32));
+        let math = |(upper, lower, digits): (u32, u32, Option<u32>)| match c {
+            'A'..='Z' => offset(upper, 'A'),
+            'a'..='z' => offset(lower, 'a'),
+            '0'..='9' => digits.and_then(|d| offset(d, '0')),
+            _ => None,
+        };
+        match self {
+            TextStyle::Bold => math(BOLD),
+            TextStyle::Italic => math(ITALIC),
+            TextStyle::Script => math(SCRIPT),
+            TextStyle::Fraktur => math(FRAKTUR),
+            TextStyle::DoubleStruck => math(DOUBLE_STRUCK),
+            TextStyle::Monospace => math(MONOSPACE),
+            TextStyle::Fullwidth => match c {
+                ' ' => Some('\u{3000}'),
+                '!'..='~' => offset(0xFF01, '!'),
+                _ => None,
+            },
+            TextStyle::SmallCaps => c
+                .is_ascii_lowercase()
+                .then(|| SMALL_CAPS[(c as u8 - b'a') as usize]),
+            TextStyle::Circled => match c {
+                'A'..='Z' => offset(0x24B6, 'A'),
+                'a'..='z' => offset(0x24D0, 'a'),
+                '0' => Some('⓪'),
+                '1'..='9' => offset(0x2460, '1'),
+                _ => None,
+            },
+            TextStyle::Squared => match c {
+                'A'..='Z' => offset(0x1F130, 'A'),
+                'a'..='z' => offset(0x1F130, 'a'),
+                _ => None,
+            },
+            TextStyle::UpsideDown => UPSIDE_DOWN.iter().find_map(|&(a, b)| {
+                if c == a {
+                    Some(b)
+                } else if c == b {
+                    Some(a)
+                } else {
+                    None
+                }
+            }),
+        }
+    }
+
+    /// Styles the text, leaving escape sequences and markers in place. Upside-down text is read
+    /// backwards: the graphemes between two markers are reversed as a whole and the escape
+    /// sequences in effect for each of them are re-applied around it.
+    ///
+    /// # Examples
+    /// ```
+    /// use titular::textstyle::TextStyle;
+    ///
+    /// assert_eq!(TextStyle::Bold.apply("Rust 2024"), "𝐑𝐮𝐬𝐭 𝟐𝟎𝟐𝟒");
+    /// assert_eq!(TextStyle::Fullwidth.apply("\x1b[1mhi!\x1b[0m"), "\x1b[1mｈｉ！\x1b[0m");
+    /// assert_eq!(TextStyle::UpsideDown.apply("Hey!"), "¡ʎǝH");
+    /// assert_eq!(
+    ///     TextStyle::UpsideDown.apply("ab\x1b[1mc\x1b[0m"),
+    ///     "\x1b[1mɔ\x1b[0mqɐ"
+    /// );
+    /// ```
+    #[must_use]
+    pub fn apply(self, text: &str) -> String {
+        if self == TextStyle::UpsideDown {
+            return self.apply_reversed(text);
+        }
+        let mut result = String::with_capacity(text.len() * 4);
+        for token in tokenize(text) {
+            match token {
+                Token::Escape(_, seq) => result.push_str(seq),
+                Token::Text(text) => {
+                    result.extend(text.chars().map(|c| self.map(c).unwrap_or(c)));
+                }
+            }
+        }
+        result
+    }
+
+    fn apply_reversed(self, text: &str) -> String {
+        let mut result = String::with_capacity(text.len() * 4);
+        // Styled graphemes since the last marker, with the escapes in effect for each of them.
+        let mut segment: Vec<(String, Escapes)> = Vec::new();
+        let mut current = Escapes::default();
+        let mut emitted = Escapes::default();
+        for token in tokenize(text) {
+            match token {
+                Token::Escape(_, seq) => current.update(seq),
+                Token::Text(text) => {
+                    for grapheme in text.graphemes(true) {
+                        if grapheme.chars().any(is_marker) {
+                            flush_reversed(&mut segment, &mut emitted, &current, &mut result);
+                            result.push_str(grapheme);
+                        } else {
+                            let styled = grapheme.chars().map(|c| self.map(c).unwrap_or(c));
+                            segment.push((styled.collect(), current.clone()));
+                        }
+                    }
+                }
+            }
+        }
+        flush_reversed(&mut segment, &mut emitted, &current, &mut result);
+        result
+    }
+}
+
+/// The escape sequences in effect at some point of a text: the layers of style opened by SGR
+/// sequences, each closed by its own reset as the `AnsiFormatter` expects, and the opening
+/// sequence of the current hyperlink. Sequences only turning attributes off, and the other
+/// escape sequences, amend the innermost layer.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Escapes {
+    layers: Vec<String>,
+    link: String,
+}
+
+impl Escapes {
+    fn update(&mut self, seq: &str) {
+        if update_layers(&mut self.layers, seq) {
+            return;
+        }
+        if let Some(rest) = seq.strip_prefix("\x1b]8;") {
+            let url = rest.split_once(';').map_or("", |(_, url)| url);
+            if url.trim_end_matches(['\x07', '\x1b', '\\']).is_empty() {
+                self.link.clear();
+            } else {
+                self.link = seq.to_string();
+            }
+        } else if let Some(layer) = self.layers.last_mut() {
+            layer.push_str(seq);
+        }
+    }
+
+    /// Writes the escape sequences turning the `self` state into the `to` one: a reset for each
+    /// layer to close, then the layers to open.
+    fn switch_to(&self, to: &Escapes, result: &mut String) {
+        if !self.link.is_empty() && self.link != to.link {
+            result.push_str(LINK_END);
+        }
+        let common = self
+            .layers
+            .iter()
+            .zip(&to.layers)
+            .take_while(|(from, to)| from == to)
+            .count();
+        result.push_str(&SGR_RESET.repeat(self.layers.len() - common));
+        for layer in &to.layers[common..] {
+            result.push_str(layer);
+        }
+        if !to.link.is_empty() && self.link != to.link {
+            result.push_str(&to.link);
+        }
+    }
+}
+
+const SGR_RESET: &str = "\x1b[0m";
+const LINK_END: &str = "\x1b]8;;\x1b\\";
+
+/// Writes the graphemes of the segment backwards, each with its escapes, then restores the
+/// escapes in effect at the end of the segment.
+fn flush_reversed(
+    segment: &mut Vec<(String, Escapes)>,
+    emitted: &mut Escapes,
+    current: &Escapes,
+    result: &mut String,
+) {
+    for (grapheme, escapes) in segment.drain(..).rev() {
+        emitted.switch_to(&escapes, result);
+        *emitted = escapes;
+        result.push_str(&grapheme);
+    }
+    emitted.switch_to(current, result);
+    emitted.clone_from(current);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transforms::{AnsiFormatter, Transform};
+    use crate::width::{text_width, AmbiguousWidth};
+
+    #[test]
+    fn test_text_style_parse() {
+        assert_eq!(
+            "Double-Struck".parse::<TextStyle>().unwrap(),
+            TextStyle::DoubleStruck
+        );
+        assert_eq!(
+            "small_caps".parse::<TextStyle>().unwrap(),
+            TextStyle::SmallCaps
+        );
+        assert_eq!(
+            "inverted".parse::<TextStyle>().unwrap(),
+            TextStyle::UpsideDown
+        );
+        assert!("gothic".parse::<TextStyle>().is_err());
+    }
+
+    #[test]
+    fn test_mathematical_styles() {
+        assert_eq!(TextStyle::Italic.apply("hat 1"), "ℎ𝑎𝑡 1");
+        assert_eq!(TextStyle::Script.apply("Hello"), "ℋℯ𝓁𝓁ℴ");
+        assert_eq!(TextStyle::Fraktur.apply("Zorro"), "ℨ𝔬𝔯𝔯𝔬");
+        assert_eq!(TextStyle::DoubleStruck.apply("NZ 42"), "ℕℤ 𝟜𝟚");
+        assert_eq!(TextStyle::Monospace.apply("ls -a"), "𝚕𝚜 -𝚊");
+        assert_eq!(
+            text_width(&TextStyle::Bold.apply("Status"), AmbiguousWidth::Narrow),
+            6
+        );
+    }
+
+    #[test]
+    fn test_other_styles() {
+        assert_eq!(TextStyle::SmallCaps.apply("Small caps"), "Sᴍᴀʟʟ ᴄᴀᴘꜱ");
+        assert_eq!(TextStyle::Circled.apply("Ab 10"), "Ⓐⓑ ①⓪");
+        assert_eq!(TextStyle::Squared.apply("Ok!"), "🄾🄺!");
+        assert_eq!(TextStyle::Fullwidth.apply("OK 1"), "ＯＫ\u{3000}１");
+        assert_eq!(
+            text_width(&TextStyle::Fullwidth.apply("OK 1"), AmbiguousWidth::Narrow),
+            8
+        );
+        assert_eq!(TextStyle::Fullwidth.apply("é"), "é");
+    }
+
+    #[test]
+    fn test_upside_down_closes_every_layer() {
+        let reversed = TextStyle::UpsideDown.apply("\x1b[1m\x1b[31mabc\x1b[0m\x1b[0m");
+        assert_eq!(reversed, "\x1b[1m\x1b[31mɔqɐ\x1b[0m\x1b[0m");
+        let formatted = AnsiFormatter::new()
+            .transform(&format!("{reversed}tail"))
+            .unwrap();
+        assert_eq!(formatted, "\x1b[1;31mɔqɐ\x1b[0mtail");
+    }
+
+    #[test]
+    fn test_upside_down_keeps_markers() {
+        let text = "\u{F0000}ab\u{F0001}cd\x1b[31me\x1b[0m";
+        assert_eq!(
+            TextStyle::UpsideDown.apply(text),
+            "\u{F0000}qɐ\u{F0001}\x1b[31mǝ\x1b[0mpɔ"
+        );
+        assert_eq!(
+            TextStyle::UpsideDown.apply("\x1b[1mab\x1b[4mc\x1b[0md\x1b[0m"),
+            "\x1b[1mp\x1b[4mɔ\x1b[0mqɐ\x1b[0m"
+        );
+        assert_eq!(
+            TextStyle::UpsideDown.apply("\x1b]8;;u\x1b\\ab\x1b]8;;\x1b\\c"),
+            "ɔ\x1b]8;;u\x1b\\qɐ\x1b]8;;\x1b\\"
+        );
+        assert_eq!(TextStyle::UpsideDown.apply("e\u{301}a"), "ɐǝ\u{301}");
+        assert_eq!(
+            TextStyle::UpsideDown.apply(&TextStyle::UpsideDown.apply("(hello)")),
+            "(hello)"
+        );
+    }
+}
@@ -2,6 +2,7 @@ use strsim::jaro_winkler;
 use syntect::highlighting::{Theme, ThemeSet};
 
 use crate::context::Context;
+use crate::icons;
 use crate::{error::Result, utils};
 use nu_ansi_term::Color::{Green, Yellow};
 use std::io::{self, Write};
@@ -125,12 +126,13 @@ impl ThemeManager {
     pub fn list_themes(&self) -> Result<()> {
         let names = self.theme_names_sorted();
         let themes: Vec<&str> = names.iter().map(String::as_str).collect();
+        let glyph = |name| icons::lookup(name).map_or("", |icon| icon.glyph);
         utils::print_tree_with_prefixes(
             &themes,
             "theme",
             "Available themes",
-            "\u{e22b}",
-            "\u{f08b5}",
+            glyph("palette"),
+            glyph("theme"),
             |s| Yellow.paint(s).to_string(),
             |s| Green.paint(s).to_string(),
         );
@@ -1,10 +1,12 @@
 //! Derive `RGB(...)` strings from a syntect [`Theme`] for template [`color`](crate::filters::color) vars.
 
+use serde::Serialize;
 use syntect::highlighting::{Color, Theme};
 
-/// Semantic colors extracted from a TextMate-style theme (foreground only).
-#[derive(Debug, Clone)]
+/// Semantic colors extracted from a TextMate-style theme.
+#[derive(Debug, Clone, Default)]
 pub struct ThemePalette {
+    pub background: String,
     pub foreground: String,
     pub comment: String,
     pub keyword: String,
@@ -18,8 +20,71 @@ pub fn rgb_string(c: Color) -> String {
     format!("RGB({},{},{})", c.r, c.g, c.b)
 }
 
-fn fallback_rgb(opt: Option<Color>, default: &str) -> String {
-    opt.map(rgb_string).unwrap_or_else(|| default.to_string())
+/// The colors of a [`ThemePalette`], each one inserted as a `theme_<name>` variable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Slot {
+    Background,
+    Foreground,
+    Comment,
+    Keyword,
+    String,
+    Function,
+    Accent,
+}
+
+impl Slot {
+    /// Every slot, in the order of the palette.
+    pub const ALL: [Slot; 7] = [
+        Slot::Background,
+        Slot::Foreground,
+        Slot::Comment,
+        Slot::Keyword,
+        Slot::String,
+        Slot::Function,
+        Slot::Accent,
+    ];
+
+    /// Name of the slot, as in its `theme_<name>` variable.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Slot::Background => "background",
+            Slot::Foreground => "foreground",
+            Slot::Comment => "comment",
+            Slot::Keyword => "keyword",
+            Slot::String => "string",
+            Slot::Function => "function",
+            Slot::Accent => "accent",
+        }
+    }
+}
+
+/// A single palette color along with the theme scope (or setting) it was resolved from.
+#[derive(Debug, Clone, Serialize)]
+pub struct PaletteSlot {
+    #[serde(rename = "name")]
+    pub slot: Slot,
+    pub rgb: String,
+    pub scope: String,
+}
+
+/// Scope reported for slots that fell back to the theme foreground (or a built-in default).
+const FALLBACK_SCOPE: &str = "fallback";
+
+fn setting_slot(slot: Slot, opt: Option<Color>, setting: &str, default: &str) -> PaletteSlot {
+    match opt {
+        Some(c) => PaletteSlot {
+            slot,
+            rgb: rgb_string(c),
+            scope: setting.to_string(),
+        },
+        None => PaletteSlot {
+            slot,
+            rgb: default.to_string(),
+            scope: FALLBACK_SCOPE.to_string(),
+        },
+    }
 }
 
 fn scope_blob(item: &syntect::highlighting::ThemeItem) -> String {
@@ -37,30 +102,65 @@ fn scope_matches_atom(blob: &str, prefix: &str) -> bool {
         .any(|atom| atom == prefix || atom.starts_with(&format!("{prefix}.")))
 }
 
-fn pick(theme: &Theme, prefixes: &[&str], fallback: &str) -> String {
+fn pick(theme: &Theme, slot: Slot, prefixes: &[&str], fallback: &str) -> PaletteSlot {
     for pfx in prefixes {
         for item in &theme.scopes {
             let blob = scope_blob(item);
             if scope_matches_atom(&blob, pfx) {
                 if let Some(c) = item.style.foreground {
-                    return rgb_string(c);
+                    let scope = blob
+                        .split_whitespace()
+                        .find(|atom| scope_matches_atom(atom, pfx))
+                        .unwrap_or(pfx)
+                        .to_string();
+                    return PaletteSlot {
+                        slot,
+                        rgb: rgb_string(c),
+                        scope,
+                    };
                 }
             }
         }
     }
-    fallback.to_string()
+    PaletteSlot {
+        slot,
+        rgb: fallback.to_string(),
+        scope: FALLBACK_SCOPE.to_string(),
+    }
 }
 
+/// Resolves every palette slot of `theme`, keeping track of the scope each color came from.
+///
+/// The order matches the `theme_*` variables inserted by [`ThemePalette::insert_into`].
 #[must_use]
-pub fn palette_from_theme(theme: &Theme) -> ThemePalette {
-    let fg = fallback_rgb(theme.settings.foreground, "RGB(200,200,200)");
-    ThemePalette {
-        foreground: fg.clone(),
-        comment: pick(theme, &["comment", "comment.line"], &fg),
-        keyword: pick(theme, &["keyword", "keyword.control", "storage.type"], &fg),
-        string: pick(theme, &["string"], &fg),
-        function: pick(
+pub fn palette_slots_from_theme(theme: &Theme) -> Vec<PaletteSlot> {
+    let background = setting_slot(
+        Slot::Background,
+        theme.settings.background,
+        "settings.background",
+        "RGB(30,30,30)",
+    );
+    let foreground = setting_slot(
+        Slot::Foreground,
+        theme.settings.foreground,
+        "settings.foreground",
+        "RGB(200,200,200)",
+    );
+    let fg = foreground.rgb.clone();
+    vec![
+        background,
+        foreground,
+        pick(theme, Slot::Comment, &["comment", "comment.line"], &fg),
+        pick(
             theme,
+            Slot::Keyword,
+            &["keyword", "keyword.control", "storage.type"],
+            &fg,
+        ),
+        pick(theme, Slot::String, &["string"], &fg),
+        pick(
+            theme,
+            Slot::Function,
             &[
                 "entity.name.function",
                 "support.function",
@@ -68,17 +168,38 @@ pub fn palette_from_theme(theme: &Theme) -> ThemePalette {
             ],
             &fg,
         ),
-        accent: pick(
+        pick(
             theme,
+            Slot::Accent,
             &["markup.heading", "entity.name.class", "variable.function"],
             &fg,
         ),
+    ]
+}
+
+/// Builds the palette of `theme`, each color being taken from the slot of the same name.
+#[must_use]
+pub fn palette_from_theme(theme: &Theme) -> ThemePalette {
+    let mut palette = ThemePalette::default();
+    for slot in palette_slots_from_theme(theme) {
+        let color = match slot.slot {
+            Slot::Background => &mut palette.background,
+            Slot::Foreground => &mut palette.foreground,
+            Slot::Comment => &mut palette.comment,
+            Slot::Keyword => &mut palette.keyword,
+            Slot::String => &mut palette.string,
+            Slot::Function => &mut palette.function,
+            Slot::Accent => &mut palette.accent,
+        };
+        *color = slot.rgb;
     }
+    palette
 }
 
 impl ThemePalette {
     /// Inserts `theme_*` keys for use with `color(name=...)`.
     pub fn insert_into(&self, ctx: &mut crate::context::Context) {
+        ctx.insert("theme_background", self.background.as_str());
         ctx.insert("theme_foreground", self.foreground.as_str());
         ctx.insert("theme_comment", self.comment.as_str());
         ctx.insert("theme_keyword", self.keyword.as_str());
@@ -87,3 +208,52 @@ impl ThemePalette {
         ctx.insert("theme_accent", self.accent.as_str());
     }
 }
+
+#[cfg(all(test, feature = "display"))]
+mod tests {
+    use super::*;
+    use crate::theme::ThemeManager;
+
+    #[test]
+    fn test_slots_match_palette() {
+        let mgr = ThemeManager::init().unwrap();
+        let theme = mgr.resolve_theme("Monokai").unwrap();
+        let slots = palette_slots_from_theme(theme);
+        let palette = palette_from_theme(theme);
+
+        let names: Vec<_> = slots.iter().map(|s| s.slot.name()).collect();
+        assert_eq!(
+            names,
+            [
+                "background",
+                "foreground",
+                "comment",
+                "keyword",
+                "string",
+                "function",
+                "accent"
+            ]
+        );
+        assert_eq!(slots.iter().map(|s| s.slot).collect::<Vec<_>>(), Slot::ALL);
+        // Every slot is inserted as its variable
+        let mut ctx = crate::context::Context::new();
+        palette.insert_into(&mut ctx);
+        for slot in &slots {
+            let var = format!("theme_{}", slot.slot.name());
+            assert_eq!(ctx.get(&var), Some(slot.rgb.as_str()), "{var}");
+        }
+        assert_eq!(
+            serde_json::to_value(&slots[0]).unwrap()["name"],
+            "background"
+        );
+        assert_eq!(slots[0].rgb, palette.background);
+        assert_eq!(slots[1].rgb, palette.foreground);
+        assert_eq!(slots[2].rgb, palette.comment);
+        assert_eq!(slots[3].rgb, palette.keyword);
+        assert_eq!(slots[4].rgb, palette.string);
+        assert_eq!(slots[5].rgb, palette.function);
+        assert_eq!(slots[6].rgb, palette.accent);
+        assert_eq!(slots[0].scope, "settings.background");
+        assert!(slots[3].scope.starts_with("keyword") || slots[3].scope.starts_with("storage"));
+    }
+}
@@ -1,9 +1,172 @@
+use crate::ansi::{is_partial_reset, tokenize, Token};
 use crate::prelude::*;
-use ansi_parser::{AnsiParser, Output};
-use std::collections::VecDeque;
 
-const RESET_CODE: &str = "\x1b[0m";
+/// The graphic rendition state of the terminal : the attributes and colors in effect.
+///
+/// Colors are kept as their SGR parameters (e.g. `31`, `38;5;208` or `38;2;255;0;0`), so that
+/// they are emitted exactly as they were received.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct SgrState {
+    bold: bool,
+    dim: bool,
+    italic: bool,
+    /// The underline parameter (`4`, `21` or a styled `4:3`)
+    underline: Option<String>,
+    blink: bool,
+    reverse: bool,
+    hidden: bool,
+    strike: bool,
+    overline: bool,
+    fg: Option<String>,
+    bg: Option<String>,
+    underline_color: Option<String>,
+    /// Parameters without a known effect, which only a full reset can turn off
+    other: Vec<String>,
+}
+
+impl SgrState {
+    /// Applies the parameters of an SGR sequence (`1;38;5;208`).
+    fn apply(&mut self, params: &str) {
+        let params: Vec<&str> = params.split(';').collect();
+        let mut i = 0;
+        while i < params.len() {
+            let param = params[i];
+            i += 1;
+
+            // Sub-parameters (`4:3`, `38:2::255:0:0`)
+            if let Some((head, _)) = param.split_once(':') {
+                match head {
+                    "4" => self.underline = (param != "4:0").then(|| param.to_string()),
+                    "38" => self.fg = Some(param.to_string()),
+                    "48" => self.bg = Some(param.to_string()),
+                    "58" => self.underline_color = Some(param.to_string()),
+                    _ => self.push_other(param),
+                }
+                continue;
+            }
+
+            match param.parse::<u8>() {
+                Err(_) if param.is_empty() => *self = SgrState::default(),
+                Ok(0) => *self = SgrState::default(),
+                Ok(1) => self.bold = true,
+                Ok(2) => self.dim = true,
+                Ok(3) => self.italic = true,
+                Ok(4 | 21) => self.underline = Some(param.to_string()),
+                Ok(5 | 6) => self.blink = true,
+                Ok(7) => self.reverse = true,
+                Ok(8) => self.hidden = true,
+                Ok(9) => self.strike = true,
+                Ok(22) => (self.bold, self.dim) = (false, false),
+                Ok(23) => self.italic = false,
+                Ok(24) => self.underline = None,
+                Ok(25) => self.blink = false,
+                Ok(27) => self.reverse = false,
+                Ok(28) => self.hidden = false,
+                Ok(29) => self.strike = false,
+                Ok(30..=37 | 90..=97) => self.fg = Some(param.to_string()),
+                Ok(39) => self.fg = None,
+                Ok(40..=47 | 100..=107) => self.bg = Some(param.to_string()),
+                Ok(49) => self.bg = None,
+                Ok(53) => self.overline = true,
+                Ok(55) => self.overline = false,
+                Ok(59) => self.underline_color = None,
+                Ok(38 | 48 | 58) => {
+                    // Extended colors : `5;n` or `2;r;g;b`
+                    let len = match params.get(i) {
+                        Some(&"5") => 2,
+                        Some(&"2") => 4,
+                        _ => 0,
+                    };
+                    let end = (i + len).min(params.len());
+                    let color = Some(params[i - 1..end].join(";"));
+                    i = end;
+                    match param {
+                        "38" => self.fg = color,
+                        "48" => self.bg = color,
+                        _ => self.underline_color = color,
+                    }
+                }
+                _ => self.push_other(param),
+            }
+        }
+    }
+
+    fn push_other(&mut self, param: &str) {
+        if !self.other.iter().any(|p| p == param) {
+            self.other.push(param.to_string());
+        }
+    }
+
+    /// The parameters turning the `self` state into the `to` state without a full reset, or
+    /// `None` if some parameter cannot be turned off individually.
+    fn diff(&self, to: &SgrState) -> Option<Vec<String>> {
+        if self.other.iter().any(|p| !to.other.contains(p)) {
+            return None;
+        }
+        let mut codes: Vec<String> = Vec::new();
+        let mut toggle = |from: bool, to: bool, on: &str, off: &str| {
+            if from != to {
+                codes.push(if to { on } else { off }.to_string());
+            }
+        };
 
+        // Bold and dim share the same reset
+        if (self.bold && !to.bold) || (self.dim && !to.dim) {
+            toggle(true, false, "", "22");
+            toggle(false, to.bold, "1", "");
+            toggle(false, to.dim, "2", "");
+        } else {
+            toggle(self.bold, to.bold, "1", "");
+            toggle(self.dim, to.dim, "2", "");
+        }
+        toggle(self.italic, to.italic, "3", "23");
+        toggle(self.blink, to.blink, "5", "25");
+        toggle(self.reverse, to.reverse, "7", "27");
+        toggle(self.hidden, to.hidden, "8", "28");
+        toggle(self.strike, to.strike, "9", "29");
+        toggle(self.overline, to.overline, "53", "55");
+
+        let slots = [
+            (&self.underline, &to.underline, "24"),
+            (&self.fg, &to.fg, "39"),
+            (&self.bg, &to.bg, "49"),
+            (&self.underline_color, &to.underline_color, "59"),
+        ];
+        for (from, to, off) in slots {
+            if from != to {
+                codes.push(to.clone().unwrap_or_else(|| off.to_string()));
+            }
+        }
+        codes.extend(to.other.iter().filter(|p| !self.other.contains(p)).cloned());
+        Some(codes)
+    }
+
+    /// The shortest escape sequence turning the `self` state into the `to` state, either a diff
+    /// or a full reset followed by the whole `to` state.
+    fn transition(&self, to: &SgrState) -> String {
+        if self == to {
+            return String::new();
+        }
+        let mut reset = vec!["0".to_string()];
+        reset.extend(SgrState::default().diff(to).unwrap_or_default());
+        let codes = match self.diff(to) {
+            Some(diff) if diff.join(";").len() < reset.join(";").len() => diff,
+            _ => reset,
+        };
+        format!("\x1b[{}m", codes.join(";"))
+    }
+}
+
+/// `AnsiFormatter` is the last transform of the chain. It tracks the SGR state of the text so
+/// that styles nest properly :
+/// - every SGR sequence opens a layer of style, closed by the next full reset (`\x1b[0m`), which
+///   restores the style of the enclosing layers
+/// - sequences only turning attributes off (`22`, `39`, ...) amend the innermost layer
+/// - the sequences actually written are the minimal ones moving the terminal from one state to
+///   the next, right before the text they apply to
+/// - the style is reset before every line break and at the end of the text
+///
+/// Other escape sequences (hyperlinks, cursor movements, ...) are written as is.
 pub struct AnsiFormatter;
 
 impl Default for AnsiFormatter {
@@ -19,40 +182,48 @@ impl AnsiFormatter {
     }
 
     fn process_ansi_codes(input: &str) -> String {
-        let mut result = String::new();
-        let mut stack = VecDeque::<String>::new();
+        let mut result = String::with_capacity(input.len());
+        let mut layers: Vec<Vec<String>> = Vec::new();
+        let mut emitted = SgrState::default();
+        let plain = SgrState::default();
 
-        for output in input.ansi_parse() {
-            match output {
-                Output::TextBlock(text) => {
-                    result.push_str(text);
-                }
-                Output::Escape(escape) => {
-                    let escape_str = escape.to_string();
-                    // Always push the escape string to the result
-                    result.push_str(&escape_str);
-
-                    if escape_str == RESET_CODE {
-                        // Full reset - pop last element
-                        if stack.pop_back().is_some() {
-                            // After reset, reapply remaining stack in order
-                            for code in &stack {
-                                result.push_str(code);
-                            }
+        for token in tokenize(input) {
+            match token {
+                Token::Escape(_, seq) if token.is_sgr() => {
+                    let params = &seq[2..seq.len() - 1];
+                    if params.is_empty() || params == "0" {
+                        layers.pop();
+                    } else if is_partial_reset(params) {
+                        if let Some(layer) = layers.last_mut() {
+                            layer.push(params.to_string());
                         }
                     } else {
-                        // Non-reset code - move into stack
-                        stack.push_back(escape_str);
+                        layers.push(vec![params.to_string()]);
+                    }
+                }
+                Token::Escape(_, seq) => result.push_str(seq),
+                Token::Text(text) => {
+                    let mut target = SgrState::default();
+                    for params in layers.iter().flatten() {
+                        target.apply(params);
+                    }
+                    for (i, line) in text.split('\n').enumerate() {
+                        if i > 0 {
+                            result.push_str(&emitted.transition(&plain));
+                            emitted = plain.clone();
+                            result.push('\n');
+                        }
+                        if !line.is_empty() {
+                            result.push_str(&emitted.transition(&target));
+                            emitted = target.clone();
+                            result.push_str(line);
+                        }
                     }
                 }
             }
         }
 
-        // At the end, apply any remaining ANSI codes in reverse insertion order
-        while let Some(code) = stack.pop_back() {
-            result.push_str(&code);
-        }
-
+        result.push_str(&emitted.transition(&plain));
         result
     }
 }
@@ -67,41 +238,90 @@ impl Transform for AnsiFormatter {
 mod tests {
     use super::*;
 
+    fn format(input: &str) -> String {
+        AnsiFormatter::new().transform(input).unwrap()
+    }
+
     #[test]
     fn test_nested_colors() {
-        let formatter = AnsiFormatter::new();
-        let input = "\x1b[31mRed\x1b[32mGreen\x1b[0mBack to Red";
-        let result = formatter.transform(input).unwrap();
         assert_eq!(
-            result,
-            "\x1b[31mRed\x1b[32mGreen\x1b[0m\x1b[31mBack to Red\x1b[31m"
+            format("\x1b[31mRed\x1b[32mGreen\x1b[0mBack to Red"),
+            "\x1b[31mRed\x1b[32mGreen\x1b[31mBack to Red\x1b[0m"
+        );
+        assert_eq!(
+            format("\x1b[31mRed\x1b[32mGreen\x1b[31mBack to Red\x1b[0m"),
+            "\x1b[31mRed\x1b[32mGreen\x1b[31mBack to Red\x1b[0m"
         );
     }
 
     #[test]
     fn test_multiple_resets() {
-        let formatter = AnsiFormatter::new();
-        let input = "\x1b[31mRed\x1b[32mGreen\x1b[0mBack to Red\x1b[0mNormal";
-        let result = formatter.transform(input).unwrap();
         assert_eq!(
-            result,
-            "\x1b[31mRed\x1b[32mGreen\x1b[0m\x1b[31mBack to Red\x1b[0mNormal"
+            format("\x1b[31mRed\x1b[32mGreen\x1b[0mBack to Red\x1b[0mNormal"),
+            "\x1b[31mRed\x1b[32mGreen\x1b[31mBack to Red\x1b[0mNormal"
         );
     }
 
     #[test]
     fn test_no_ansi_codes() {
-        let formatter = AnsiFormatter::new();
-        let input = "Normal text";
-        let result = formatter.transform(input).unwrap();
-        assert_eq!(result, "Normal text");
+        assert_eq!(format("Normal text"), "Normal text");
     }
 
     #[test]
     fn test_remaining_codes() {
-        let formatter = AnsiFormatter::new();
-        let input = "\x1b[31mRed\x1b[32mGreen";
-        let result = formatter.transform(input).unwrap();
-        assert_eq!(result, "\x1b[31mRed\x1b[32mGreen\x1b[32m\x1b[31m");
+        assert_eq!(
+            format("\x1b[31mRed\x1b[32mGreen"),
+            "\x1b[31mRed\x1b[32mGreen\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_enclosing_attributes_are_restored() {
+        // A color inside a bold style keeps the text bold after its reset
+        assert_eq!(
+            format("\x1b[1;44m[\x1b[38;5;208mx\x1b[0m]\x1b[0m"),
+            "\x1b[1;44m[\x1b[38;5;208mx\x1b[39m]\x1b[0m"
+        );
+        // Turning bold off keeps the dim attribute, through the shortest sequence
+        assert_eq!(
+            format("\x1b[1;2ma\x1b[22;2mb\x1b[0m"),
+            "\x1b[1;2ma\x1b[0;2mb\x1b[0m"
+        );
+        // Partial resets only last until the enclosing layer is closed
+        assert_eq!(
+            format("\x1b[31m\x1b[4ma\x1b[24mb\x1b[0mc\x1b[0md"),
+            "\x1b[4;31ma\x1b[24mbc\x1b[0md"
+        );
+        assert_eq!(
+            format("\x1b[38;2;1;2;3;1mx\x1b[0m"),
+            "\x1b[1;38;2;1;2;3mx\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_minimal_sequences() {
+        // Styles without text are not written
+        assert_eq!(format("a\x1b[31m\x1b[0mb"), "ab");
+        assert_eq!(
+            format("\x1b[31ma\x1b[0m\x1b[31mb\x1b[0m"),
+            "\x1b[31mab\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_reset_before_line_breaks() {
+        assert_eq!(
+            format("\x1b[1ma\nb\x1b[0m\n"),
+            "\x1b[1ma\x1b[0m\n\x1b[1mb\x1b[0m\n"
+        );
+    }
+
+    #[test]
+    fn test_hyperlinks_are_not_stacked() {
+        let input = "\x1b]8;;https://x.io\x1b\\\x1b[31mx\x1b[0m\x1b]8;;\x1b\\";
+        assert_eq!(
+            format(input),
+            "\x1b]8;;https://x.io\x1b\\\x1b[31mx\x1b]8;;\x1b\\\x1b[0m"
+        );
     }
 }
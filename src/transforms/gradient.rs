@@ -0,0 +1,200 @@
+use crate::ansi::{tokenize, Token};
+use crate::color_depth::ColorDepth;
+use crate::constants::{gradient, is_marker};
+use crate::gradient::Gradient;
+use crate::prelude::*;
+use nu_ansi_term::Color;
+use print_positions::print_positions;
+use regex::Regex;
+
+// Regex to match the innermost gradient groups, capturing their encoded spec and content
+static GRADIENT_PATTERN: std::sync::LazyLock<Regex> = std::sync::LazyLock::new(|| {
+    Regex::new(&format!(
+        r"{start}([^{start}{sep}{end}]*){sep}([^{start}{end}]*){end}",
+        start = regex::escape(&gradient::START.to_string()),
+        sep = regex::escape(&gradient::SEP.to_string()),
+        end = regex::escape(&gradient::END.to_string())
+    ))
+    .unwrap()
+});
+
+/// `GradientProcessor` is a transform that colors the content of gradient groups (see the
+/// `gradient` filter) one grapheme at a time. It runs after the `TextProcessor` so that padding
+/// fillers have their final width when the gradient is spread over them.
+pub struct GradientProcessor;
+
+impl Default for GradientProcessor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GradientProcessor {
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Replaces every gradient group of the text with its colored content. Nested groups are
+    /// resolved from the inside out.
+    ///
+    /// # Examples
+    /// ```
+    /// use titular::color_depth::ColorDepth;
+    /// use titular::constants::gradient;
+    /// use titular::transforms::GradientProcessor;
+    ///
+    /// let input = format!(
+    ///     "{}{}{}ab{}",
+    ///     gradient::START,
+    ///     gradient::encode("rgb;ff0000;0000ff"),
+    ///     gradient::SEP,
+    ///     gradient::END
+    /// );
+    /// assert_eq!(
+    ///     GradientProcessor::apply_gradients(&input, ColorDepth::TrueColor),
+    ///     "\x1b[38;2;255;0;0ma\x1b[0m\x1b[38;2;0;0;255mb\x1b[0m"
+    /// );
+    /// assert_eq!(GradientProcessor::apply_gradients(&input, ColorDepth::NoColor), "ab");
+    /// ```
+    #[must_use]
+    pub fn apply_gradients(text: &str, depth: ColorDepth) -> String {
+        let mut result = text.to_string();
+        while GRADIENT_PATTERN.is_match(&result) {
+            result = GRADIENT_PATTERN
+                .replace_all(&result, |caps: &regex::Captures| {
+                    let spec = gradient::decode(&caps[1]);
+                    match Gradient::from_spec(&spec) {
+                        Some(g) if depth != ColorDepth::NoColor => {
+                            Self::colorize(&caps[2], &g, depth)
+                        }
+                        _ => caps[2].to_string(),
+                    }
+                })
+                .into_owned();
+        }
+        result
+    }
+
+    /// Colors every grapheme of the content with its own color of the gradient, leaving any
+    /// escape sequence in place. Each grapheme gets a balanced color/reset pair so that the
+    /// styles of the enclosing text are restored by the `AnsiFormatter`.
+    #[allow(clippy::cast_precision_loss)]
+    fn colorize(content: &str, gradient: &Gradient, depth: ColorDepth) -> String {
+        let is_visible = |g: &str| !g.chars().all(|c| is_marker(c) || c.is_whitespace());
+        let tokens: Vec<Token> = tokenize(content).collect();
+        let total = tokens
+            .iter()
+            .map(|token| match token {
+                Token::Text(text) => print_positions(text).count(),
+                Token::Escape(..) => 0,
+            })
+            .sum::<usize>();
+        let steps = total.saturating_sub(1).max(1) as f64;
+
+        let mut result = String::with_capacity(content.len() * 4);
+        let mut position = 0;
+        for token in tokens {
+            match token {
+                Token::Escape(_, escape) => result.push_str(escape),
+                Token::Text(text) => {
+                    for (start, end) in print_positions(text) {
+                        let grapheme = &text[start..end];
+                        let (r, g, b) = gradient.color_at(position as f64 / steps);
+                        position += 1;
+                        match depth.downsample(Color::Rgb(r, g, b)) {
+                            Some(color) if is_visible(grapheme) => {
+                                result.push_str(&color.paint(grapheme).to_string());
+                            }
+                            _ => result.push_str(grapheme),
+                        }
+                    }
+                }
+            }
+        }
+        result
+    }
+}
+
+impl Transform for GradientProcessor {
+    fn transform(&self, text: &str) -> Result<String> {
+        if !text.contains(gradient::START) {
+            return Ok(text.to_string());
+        }
+        let ctx = ContextManager::get().read()?;
+        Ok(Self::apply_gradients(text, ColorDepth::from_context(&ctx)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transforms::TextProcessor;
+    use crate::width::AmbiguousWidth;
+
+    fn group(spec: &str, content: &str) -> String {
+        format!(
+            "{}{}{}{}{}",
+            gradient::START,
+            gradient::encode(spec),
+            gradient::SEP,
+            content,
+            gradient::END
+        )
+    }
+
+    #[test]
+    fn test_gradient_keeps_escapes_and_spaces() {
+        let input = group("rgb;000000;0000ff", "\x1b[1ma b\x1b[0m");
+        assert_eq!(
+            GradientProcessor::apply_gradients(&input, ColorDepth::TrueColor),
+            "\x1b[1m\x1b[38;2;0;0;0ma\x1b[0m \x1b[38;2;0;0;255mb\x1b[0m\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_gradient_downsamples() {
+        let input = group("rgb;ff0000;ff0000", "x");
+        assert_eq!(
+            GradientProcessor::apply_gradients(&input, ColorDepth::Ansi256),
+            "\x1b[38;5;196mx\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_gradient_spans_expanded_padding() {
+        let processor = TextProcessor::new(Box::new(|| 10));
+        let input = format!(
+            "ab{}",
+            group(
+                "rgb;000000;0000ff",
+                &format!("{}-{}", padding::START, padding::END)
+            )
+        );
+        let padded = processor.process_padding(&input, AmbiguousWidth::Narrow);
+        let result = GradientProcessor::apply_gradients(&padded, ColorDepth::TrueColor);
+
+        assert!(result.starts_with("ab\x1b[38;2;0;0;0m-\x1b[0m"));
+        assert!(result.ends_with("\x1b[38;2;0;0;255m-\x1b[0m"));
+        assert_eq!(console::measure_text_width(&result), 10);
+    }
+
+    #[test]
+    fn test_gradient_inside_padding() {
+        let processor = TextProcessor::new(Box::new(|| 6));
+        let input = format!(
+            "ab{}{}{}",
+            padding::START,
+            group("rgb;000000;0000ff", "-"),
+            padding::END
+        );
+        let padded = processor.process_padding(&input, AmbiguousWidth::Narrow);
+        let result = GradientProcessor::apply_gradients(&padded, ColorDepth::TrueColor);
+        assert_eq!(
+            console::strip_ansi_codes(&result),
+            "ab----",
+            "the filler should be expanded inside the gradient group"
+        );
+        assert!(result.ends_with("\x1b[38;2;0;0;255m-\x1b[0m"));
+    }
+}
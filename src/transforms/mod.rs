@@ -1,13 +1,19 @@
 mod ansi_formatter;
+mod gradient;
 mod line_handler;
 mod processor;
+mod prompt_escaper;
 mod registry;
 mod renderer;
+mod segment;
 mod transform;
 
 pub use ansi_formatter::AnsiFormatter;
+pub use gradient::GradientProcessor;
 pub use line_handler::LineHandler;
 pub use processor::TextProcessor;
+pub use prompt_escaper::PromptEscaper;
 pub use registry::{TransformManager, TransformRegistry};
 pub use renderer::TemplateRenderer;
+pub use segment::SegmentProcessor;
 pub use transform::Transform;
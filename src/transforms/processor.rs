@@ -1,16 +1,21 @@
+use crate::ansi::{strip_escapes, tokenize, Token};
+use crate::constants::{block, decode, frame, is_marker};
 use crate::context::Context;
+use crate::filler::{fill, PatternMode, Side};
+use crate::frame::Frame;
 use crate::prelude::*;
-use crate::string_utils::expand_to_visual_width;
 use crate::term::TERM_SIZE;
-use console::{measure_text_width, strip_ansi_codes};
+use crate::width::{expand_tabs, grapheme_width, text_width, AmbiguousWidth, TAB_WIDTH};
 use regex::Regex;
 use std::sync::{Arc, Mutex};
+use unicode_segmentation::UnicodeSegmentation;
 
 /// Represents a matched padding group with its position and width information
 struct MatchedGroup {
     content: String,
     start: usize,
     end: usize,
+    side: Side,
 }
 
 // Regex to match content between our non-visible markers
@@ -84,16 +89,24 @@ impl TextProcessor {
     ///
     /// # Arguments
     /// * `content` - The content to process, can be multiline
+    /// * `ambiguous` - The width of East Asian ambiguous characters
     ///
     /// # Returns
     /// A string with the processed content
     #[must_use]
-    pub fn process_padding(&self, content: &str) -> String {
-        content
+    pub fn process_padding(&self, content: &str, ambiguous: AmbiguousWidth) -> String {
+        let max_width = self.get_width.lock().unwrap()();
+        Self::layout(content, max_width, ambiguous).join("\n")
+    }
+
+    /// Lays the content out in `max_width` columns: frames are drawn, blocks are spread over
+    /// several lines and the padding groups of every line are expanded.
+    fn layout(content: &str, max_width: usize, ambiguous: AmbiguousWidth) -> Vec<String> {
+        Self::expand_frames(content, max_width, ambiguous)
             .lines()
-            .map(|line| self.process_padding_line(line))
-            .collect::<Vec<String>>()
-            .join("\n")
+            .flat_map(|line| Self::expand_blocks(line, ambiguous))
+            .map(|line| Self::process_padding_line(&line, max_width, ambiguous))
+            .collect()
     }
 
     /// Process a single line of content with padding and line wrapping.
@@ -101,6 +114,8 @@ impl TextProcessor {
     ///
     /// # Arguments
     /// * `content` - The content to process, which may contain `pad()` calls
+    /// * `max_width` - The width of the line
+    /// * `ambiguous` - The width of East Asian ambiguous characters
     ///
     /// # Returns
     /// A string with all `pad()` calls processed:
@@ -129,21 +144,180 @@ impl TextProcessor {
     /// assert!(result.ends_with("World"));
     /// assert!(result.len() > "Hello → World".len());
     /// ```
-    fn process_padding_line(&self, content: &str) -> String {
+    fn process_padding_line(content: &str, max_width: usize, ambiguous: AmbiguousWidth) -> String {
         let mut result = content.to_string();
 
         // First remove all empty padding groups from the string
         Self::remove_empty_pads(&mut result);
 
         // Extract and process padding groups
-        let (groups, text_without_pads) = Self::extract_padding_groups(&result);
+        let (groups, text_without_pads) = Self::extract_padding_groups(&result, ambiguous);
         if !groups.is_empty() {
-            self.process_padding_groups(&mut result, &groups, text_without_pads);
+            Self::process_padding_groups(
+                &mut result,
+                &groups,
+                text_without_pads,
+                max_width,
+                ambiguous,
+            );
         }
 
         result
     }
 
+    /// Replaces the frames of the content with blocks holding their rows. A frame takes the
+    /// columns that the text around it on its line (padding groups aside) leaves out of
+    /// `max_width`, and its content is laid out in the columns left inside its borders, which
+    /// also sizes the frames it holds. Unterminated frames are left as is.
+    fn expand_frames(content: &str, max_width: usize, ambiguous: AmbiguousWidth) -> String {
+        let mut result = String::with_capacity(content.len());
+        let mut offset = 0;
+        while let Some(start) = content[offset..].find(frame::START).map(|i| i + offset) {
+            let body_start = start + frame::START.len_utf8();
+            let Some(end) = Self::frame_end(&content[body_start..]).map(|i| i + body_start) else {
+                break;
+            };
+            let after = end + frame::END.len_utf8();
+            let line_start = content[..start].rfind('\n').map_or(0, |i| i + 1);
+            let line_end = content[after..]
+                .find('\n')
+                .map_or(content.len(), |i| i + after);
+            let around = format!(
+                "{}{}",
+                &content[line_start..start],
+                &content[after..line_end]
+            );
+            let width = max_width.saturating_sub(text_width(
+                &Self::visible_text(&PAD_PATTERN.replace_all(&around, "")),
+                ambiguous,
+            ));
+
+            result.push_str(&content[offset..start]);
+            result.push_str(&Self::draw_frame(
+                &content[body_start..end],
+                width,
+                ambiguous,
+            ));
+            offset = after;
+        }
+        result.push_str(&content[offset..]);
+        result
+    }
+
+    /// Position of the end marker matching the start of a frame, skipping nested frames.
+    fn frame_end(body: &str) -> Option<usize> {
+        let mut depth = 0usize;
+        for (i, c) in body.char_indices() {
+            match c {
+                frame::START => depth += 1,
+                frame::END if depth == 0 => return Some(i),
+                frame::END => depth -= 1,
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// Draws a frame from its body: the spec, border style and title stored by the filter,
+    /// followed by the content.
+    fn draw_frame(body: &str, max_width: usize, ambiguous: AmbiguousWidth) -> String {
+        let mut sections = body.splitn(4, frame::SEP);
+        let (Some(spec), Some(border), Some(title), Some(content)) = (
+            sections.next(),
+            sections.next(),
+            sections.next(),
+            sections.next(),
+        ) else {
+            return body.to_string();
+        };
+        let settings = decode(spec).parse::<Frame>().unwrap_or_default();
+        let lines = Self::layout(content, settings.inner_width(max_width), ambiguous);
+        let rows = settings.draw(&lines, max_width, title, border, ambiguous);
+        format!(
+            "{}{}{}",
+            block::START,
+            rows.join(&block::ROW.to_string()),
+            block::END
+        )
+    }
+
+    /// Lays the blocks of the line (e.g. banners) out on as many lines as they have rows. The
+    /// text around the blocks stays on the middle line, the other lines getting spaces of the
+    /// same width instead (escape sequences and markers kept), so that padding groups align every
+    /// row the same way.
+    fn expand_blocks(line: &str, ambiguous: AmbiguousWidth) -> Vec<String> {
+        enum Segment<'a> {
+            Text(&'a str),
+            Block(Vec<&'a str>),
+        }
+
+        if !line.contains(block::START) {
+            return vec![line.to_string()];
+        }
+        let mut segments = Vec::new();
+        let mut rest = line;
+        while let Some(start) = rest.find(block::START) {
+            segments.push(Segment::Text(&rest[..start]));
+            let body = &rest[start + block::START.len_utf8()..];
+            let (rows, after) = body.split_once(block::END).unwrap_or((body, ""));
+            segments.push(Segment::Block(rows.split(block::ROW).collect()));
+            rest = after;
+        }
+        segments.push(Segment::Text(rest));
+
+        let height = segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Block(rows) => rows.len(),
+                Segment::Text(_) => 1,
+            })
+            .max()
+            .unwrap_or(1);
+        let middle = (height - 1) / 2;
+        (0..height)
+            .map(|i| {
+                segments
+                    .iter()
+                    .map(|segment| match segment {
+                        Segment::Text(text) if i == middle => (*text).to_string(),
+                        Segment::Text(text) => Self::blank(text, ambiguous),
+                        Segment::Block(rows) => rows.get(i).map_or_else(
+                            || Self::blank(rows[0], ambiguous),
+                            |row| (*row).to_string(),
+                        ),
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Replaces the visible text with spaces of the same width, keeping escape sequences and
+    /// markers.
+    fn blank(text: &str, ambiguous: AmbiguousWidth) -> String {
+        tokenize(text)
+            .map(|token| match token {
+                Token::Escape(_, seq) => seq.to_string(),
+                Token::Text(text) => text
+                    .graphemes(true)
+                    .map(|g| match grapheme_width(g, ambiguous) {
+                        0 => g.to_string(),
+                        width => " ".repeat(width),
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+
+    /// Returns the text to be measured, without escape sequences (colors, hyperlinks, ...) nor the
+    /// markers of groups handled by later stages (e.g. gradients). Padding markers are kept, unmatched ones being left as is
+    /// in the output.
+    fn visible_text(content: &str) -> String {
+        strip_escapes(content)
+            .chars()
+            .filter(|c| !is_marker(*c) || [padding::START, padding::END].contains(c))
+            .collect()
+    }
+
     /// Removes all empty padding groups from the given string.
     /// This method modifies the string in place by removing any padding groups
     /// that have no content between the markers.
@@ -163,8 +337,8 @@ impl TextProcessor {
                 let matched = cap.get(0)?;
                 let content = cap.get(1)?;
 
-                // If the content is empty after stripping ANSI codes, mark for removal
-                if strip_ansi_codes(content.as_str()).is_empty() {
+                // If the content is empty after stripping ANSI codes and markers, mark for removal
+                if Self::visible_text(content.as_str()).is_empty() {
                     Some((matched.start(), matched.end()))
                 } else {
                     None
@@ -182,16 +356,20 @@ impl TextProcessor {
     ///
     /// # Arguments
     /// * `content` - The content to process
+    /// * `ambiguous` - The width of East Asian ambiguous characters
     ///
     /// # Returns
     /// A tuple containing:
     /// - Vector of padding group information
     /// - Total occupied space (outside text + padding content)
-    fn extract_padding_groups(content: &str) -> (Vec<MatchedGroup>, usize) {
-        let stripped_content = strip_ansi_codes(content);
-        let stripped_width = measure_text_width(&stripped_content);
-
-        let (groups, total_group_length) = PAD_PATTERN
+    fn extract_padding_groups(
+        content: &str,
+        ambiguous: AmbiguousWidth,
+    ) -> (Vec<MatchedGroup>, usize) {
+        let stripped_content = Self::visible_text(content);
+        let stripped_width = text_width(&stripped_content, ambiguous);
+
+        let (mut groups, group_lengths): (Vec<_>, Vec<_>) = PAD_PATTERN
             .captures_iter(content) // Use original content for matching
             .filter_map(|cap| {
                 cap.get(0).map(|matched| {
@@ -199,8 +377,9 @@ impl TextProcessor {
                     let pad_content = cap.get(1).map_or("", |m| m.as_str()).to_string();
 
                     // Get the stripped version of the matched group for width calculation
-                    let stripped_group = strip_ansi_codes(&content[matched.start()..matched.end()]);
-                    let group_length = measure_text_width(&stripped_group);
+                    let stripped_group =
+                        Self::visible_text(&content[matched.start()..matched.end()]);
+                    let group_length = text_width(&stripped_group, ambiguous);
 
                     // Include all groups, empty or not
                     (
@@ -208,22 +387,24 @@ impl TextProcessor {
                             content: pad_content,
                             start: matched.start(),
                             end: matched.end(),
+                            side: Side::Trailing,
                         },
                         group_length,
                     )
                 })
             })
-            .fold(
-                (Vec::new(), 0),
-                |(mut groups, total_group_length), (group, group_len)| {
-                    // Add all groups to the list
-                    groups.push(group);
-                    (groups, total_group_length + group_len)
-                },
-            );
+            .unzip();
+
+        // A group followed by some text (outside of the next groups) leads that text
+        for (i, group) in groups.iter_mut().enumerate() {
+            let after = text_width(&Self::visible_text(&content[group.end..]), ambiguous);
+            if after > group_lengths[i + 1..].iter().sum::<usize>() {
+                group.side = Side::Leading;
+            }
+        }
 
         // Calculate the width of the text without padding groups
-        let text_without_pads = stripped_width - total_group_length;
+        let text_without_pads = stripped_width - group_lengths.iter().sum::<usize>();
 
         (groups, text_without_pads)
     }
@@ -238,6 +419,8 @@ impl TextProcessor {
     /// * `groups` - A vector of matched padding groups found in the string
     /// * `text_without_pads` - The width of the text excluding padding groups,
     ///   used to calculate available space for padding
+    /// * `max_width` - The width of the line
+    /// * `ambiguous` - The width of East Asian ambiguous characters
     ///
     /// # Note
     /// The available space is distributed evenly among all padding groups,
@@ -266,15 +449,16 @@ impl TextProcessor {
     /// assert!(result.len() > "Hello → ← World".len());
     /// ```
     fn process_padding_groups(
-        &self,
         result: &mut String,
         groups: &[MatchedGroup],
         text_without_pads: usize,
+        max_width: usize,
+        ambiguous: AmbiguousWidth,
     ) {
         // Filter out empty padding groups
         let non_empty_groups: Vec<_> = groups
             .iter()
-            .filter(|g| !strip_ansi_codes(&g.content).is_empty())
+            .filter(|g| !Self::visible_text(&g.content).is_empty())
             .collect();
 
         if non_empty_groups.is_empty() {
@@ -282,7 +466,6 @@ impl TextProcessor {
         }
 
         // Calculate total padding needed and remainder
-        let max_width = self.get_width.lock().unwrap()();
         let total_padding_needed = max_width.saturating_sub(text_without_pads);
         let base_padding = total_padding_needed / non_empty_groups.len();
         let remainder = total_padding_needed % non_empty_groups.len();
@@ -297,6 +480,7 @@ impl TextProcessor {
                 } else {
                     base_padding
                 },
+                ambiguous,
             );
         }
     }
@@ -304,13 +488,16 @@ impl TextProcessor {
     /// Expands a single padding group with the given width.
     /// This method handles the actual expansion of a `pad()` call's content,
     /// preserving any ANSI codes while expanding the content to fill the
-    /// specified width.
+    /// specified width. The content is laid out according to the pattern mode of the group
+    /// (see [`fill`]), columns left over by a wide filler (e.g. an emoji in an odd width)
+    /// being filled with spaces, so the line always reaches its width.
     ///
     /// # Arguments
     /// * `result` - A mutable reference to the string containing the `pad()` call
     /// * `group` - The padding group to expand, containing the content and its
     ///   position in the string
     /// * `padding_width` - The target width to expand the content to
+    /// * `ambiguous` - The width of East Asian ambiguous characters
     ///
     /// # Note
     /// The method preserves any ANSI codes in the content by:
@@ -336,21 +523,35 @@ impl TextProcessor {
     /// assert!(result.ends_with("\x1b[0m"));
     /// assert!(result.len() > "\x1b[31m→\x1b[0m".len());
     /// ```
-    fn expand_padding_group(result: &mut String, group: &MatchedGroup, padding_width: usize) {
+    fn expand_padding_group(
+        result: &mut String,
+        group: &MatchedGroup,
+        padding_width: usize,
+        ambiguous: AmbiguousWidth,
+    ) {
+        // Split the pattern mode stored by the filter from the content
+        let (mode, content) = match group.content.split_once(padding::SEP) {
+            Some((data, content)) => (decode(data).parse().unwrap_or_default(), content),
+            None => (PatternMode::default(), group.content.as_str()),
+        };
+
         // Expand the stripped content
-        let stripped_content = strip_ansi_codes(&group.content);
-        let expanded_content = expand_to_visual_width(&stripped_content, padding_width);
+        let stripped_content = Self::visible_text(content);
+        let expanded_content = fill(
+            &stripped_content,
+            padding_width,
+            mode,
+            group.side,
+            ambiguous,
+        );
 
         // Find the actual content position in the original string
-        let content_start = group
-            .content
-            .find(&stripped_content.to_string())
-            .unwrap_or(0);
+        let content_start = content.find(&stripped_content).unwrap_or(0);
         let content_end = content_start + stripped_content.len();
 
-        // Extract ANSI codes before and after the content
-        let prefix = &group.content[..content_start];
-        let suffix = &group.content[content_end..];
+        // Extract ANSI codes (and markers of enclosed groups) before and after the content
+        let prefix = &content[..content_start];
+        let suffix = &content[content_end..];
 
         // Combine the ANSI codes with the expanded content
         let final_content = format!("{prefix}{expanded_content}{suffix}");
@@ -369,13 +570,17 @@ impl Transform for TextProcessor {
                 (term_width * width as usize) / 100
             });
         }
-        Ok(self.process_padding(text))
+        let ambiguous = AmbiguousWidth::from_context(&ctx);
+        Ok(self.process_padding(&expand_tabs(text, TAB_WIDTH, ambiguous), ambiguous))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use console::measure_text_width;
+
+    const NARROW: AmbiguousWidth = AmbiguousWidth::Narrow;
 
     #[test]
     fn test_extract_padding_groups_basic() {
@@ -387,7 +592,7 @@ mod tests {
             padding::START,
             padding::END
         );
-        let (groups, text_width) = TextProcessor::extract_padding_groups(&input);
+        let (groups, text_width) = TextProcessor::extract_padding_groups(&input, NARROW);
         assert_eq!(groups.len(), 2);
         assert_eq!(groups[0].content, "hello");
         assert_eq!(groups[1].content, "foo");
@@ -398,7 +603,7 @@ mod tests {
     fn test_extract_padding_groups_empty() {
         let _processor = TextProcessor::default();
         let input = format!("{}{}", padding::START, padding::END);
-        let (groups, text_width) = TextProcessor::extract_padding_groups(&input);
+        let (groups, text_width) = TextProcessor::extract_padding_groups(&input, NARROW);
         assert_eq!(
             groups.len(),
             1,
@@ -424,7 +629,7 @@ mod tests {
             padding::START,
             padding::END
         );
-        let (groups, text_width) = TextProcessor::extract_padding_groups(&input);
+        let (groups, text_width) = TextProcessor::extract_padding_groups(&input, NARROW);
         assert_eq!(groups.len(), 2);
         assert_eq!(groups[0].content, "\x1b[31mhello\x1b[0m");
         assert_eq!(groups[1].content, "foo");
@@ -441,7 +646,7 @@ mod tests {
             padding::START,
             padding::END
         );
-        let (groups, text_width) = TextProcessor::extract_padding_groups(&input);
+        let (groups, text_width) = TextProcessor::extract_padding_groups(&input, NARROW);
         assert_eq!(groups.len(), 2);
         assert_eq!(groups[0].content, "hello 🦀");
         assert_eq!(groups[1].content, "foo");
@@ -458,7 +663,7 @@ mod tests {
             padding::START,
             padding::END
         );
-        let (groups, text_width) = TextProcessor::extract_padding_groups(&input);
+        let (groups, text_width) = TextProcessor::extract_padding_groups(&input, NARROW);
         assert_eq!(groups.len(), 2);
         assert_eq!(groups[0].content, "\x1b[31mhello 🦀\x1b[0m");
         assert_eq!(groups[1].content, "foo");
@@ -469,7 +674,7 @@ mod tests {
     fn test_extract_padding_groups_no_markers() {
         let _processor = TextProcessor::default();
         let input = "hello world";
-        let (groups, text_width) = TextProcessor::extract_padding_groups(input);
+        let (groups, text_width) = TextProcessor::extract_padding_groups(input, NARROW);
         assert_eq!(groups.len(), 0);
         assert_eq!(text_width, measure_text_width(input));
     }
@@ -478,25 +683,25 @@ mod tests {
     fn test_extract_padding_groups_unmatched_start() {
         let _processor = TextProcessor::default();
         let input = format!("{}hello world", padding::START);
-        let (groups, text_width) = TextProcessor::extract_padding_groups(&input);
+        let (groups, text_width) = TextProcessor::extract_padding_groups(&input, NARROW);
         assert_eq!(groups.len(), 0);
-        assert_eq!(text_width, measure_text_width(&input));
+        // Markers have no width
+        assert_eq!(text_width, measure_text_width("hello world"));
     }
 
     #[test]
     fn test_extract_padding_groups_unmatched_end() {
         let _processor = TextProcessor::default();
         let input = format!("hello world{}", padding::END);
-        let (groups, text_width) = TextProcessor::extract_padding_groups(&input);
+        let (groups, text_width) = TextProcessor::extract_padding_groups(&input, NARROW);
         assert_eq!(groups.len(), 0);
-        assert_eq!(text_width, measure_text_width(&input));
+        assert_eq!(text_width, measure_text_width("hello world"));
     }
 
     #[test]
     fn test_process_padding_line() {
-        let processor = TextProcessor::new(Box::new(|| 20));
         let input = format!("Hello {}→{} World", padding::START, padding::END);
-        let result = processor.process_padding_line(&input);
+        let result = TextProcessor::process_padding_line(&input, 20, NARROW);
         assert!(result.starts_with("Hello →"));
         assert!(result.ends_with("World"));
         assert!(result.len() > "Hello → World".len());
@@ -504,18 +709,193 @@ mod tests {
 
     #[test]
     fn test_process_padding_line_with_ansi() {
-        let processor = TextProcessor::new(Box::new(|| 20));
         let input = format!(
             "Hello {}\x1b[31m→\x1b[0m{} World",
             padding::START,
             padding::END
         );
-        let result = processor.process_padding_line(&input);
+        let result = TextProcessor::process_padding_line(&input, 20, NARROW);
         assert!(result.starts_with("Hello \x1b[31m→"));
         assert!(result.ends_with("\x1b[0m World"));
         assert!(result.len() > "Hello \x1b[31m→\x1b[0m World".len());
     }
 
+    #[test]
+    fn test_process_padding_line_with_hyperlink() {
+        let link = crate::ansi::hyperlink("https://ci.example.com/jobs/42", "Build");
+        let input = format!("{}-{}{link}", padding::START, padding::END);
+        let result = TextProcessor::process_padding_line(&input, 20, NARROW);
+        assert_eq!(result, format!("{}{link}", "-".repeat(15)));
+    }
+
+    #[test]
+    fn test_process_padding_line_with_wide_fillers() {
+        let family = "👨\u{200D}👩\u{200D}👧";
+        let input = format!("{}{family}{}[x]", padding::START, padding::END);
+        let result = TextProcessor::process_padding_line(&input, 12, NARROW);
+        assert_eq!(result, format!("{} [x]", family.repeat(4)));
+        assert_eq!(text_width(&result, NARROW), 12);
+
+        let input = format!("日本{}🇫🇷{}e\u{301}", padding::START, padding::END);
+        let result = TextProcessor::process_padding_line(&input, 12, NARROW);
+        assert_eq!(result, "日本🇫🇷🇫🇷🇫🇷 e\u{301}");
+        assert_eq!(text_width(&result, NARROW), 12);
+
+        // Ambiguous characters, in the filler and the text, two columns wide
+        let input = format!("±{}─{}…", padding::START, padding::END);
+        let result = TextProcessor::process_padding_line(&input, 10, AmbiguousWidth::Wide);
+        assert_eq!(result, "±───…");
+    }
+
+    #[test]
+    fn test_process_padding_line_with_pattern_mode() {
+        let group = |mode: &str, filler: &str| {
+            format!(
+                "{}{}{}{filler}{}",
+                padding::START,
+                crate::constants::encode(mode),
+                padding::SEP,
+                padding::END
+            )
+        };
+
+        let input = format!("{} msg {}", group("mirror", ">"), group("mirror", ">"));
+        let result = TextProcessor::process_padding_line(&input, 15, NARROW);
+        assert_eq!(result, ">>>>> msg <<<<<");
+
+        let input = format!("{} msg {}", group("edge", "-="), group("edge", "-="));
+        let result = TextProcessor::process_padding_line(&input, 15, NARROW);
+        assert_eq!(result, "=-=-= msg -=-=-");
+
+        let input = format!(
+            "{} msg {}",
+            group("edge,mirror,whole", "<->"),
+            group("edge,mirror,whole", "\x1b[31m->\x1b[0m")
+        );
+        let result = TextProcessor::process_padding_line(&input, 15, NARROW);
+        assert_eq!(result, "  <-> msg \x1b[31m<-<- \x1b[0m");
+    }
+
+    #[test]
+    fn test_expand_blocks() {
+        let banner = format!(
+            "{}ab{}cd{}ef{}",
+            block::START,
+            block::ROW,
+            block::ROW,
+            block::END
+        );
+        let line = format!("[\x1b[31m{banner}\x1b[0m]");
+        assert_eq!(
+            TextProcessor::expand_blocks(&line, NARROW),
+            [
+                " \x1b[31mab\x1b[0m ",
+                "[\x1b[31mcd\x1b[0m]",
+                " \x1b[31mef\x1b[0m "
+            ]
+        );
+
+        let short = format!("{}1{}2{}", block::START, block::ROW, block::END);
+        assert_eq!(
+            TextProcessor::expand_blocks(&format!("{banner}{short}"), NARROW),
+            ["ab1", "cd2", "ef "]
+        );
+        assert_eq!(
+            TextProcessor::expand_blocks("no block", NARROW),
+            ["no block"]
+        );
+    }
+
+    #[test]
+    fn test_process_padding_with_blocks() {
+        let processor = TextProcessor::new(Box::new(|| 9));
+        let banner = format!("{}_{}|{}", block::START, block::ROW, block::END);
+        let input = format!(
+            "{}*{}<{banner}>{}*{}",
+            padding::START,
+            padding::END,
+            padding::START,
+            padding::END
+        );
+        assert_eq!(
+            processor.process_padding(&input, NARROW),
+            "***<_>***\n    |    "
+        );
+    }
+
+    #[test]
+    fn test_process_padding_with_frames() {
+        let framed = |spec: &str, border: &str, title: &str, content: &str| {
+            format!(
+                "{}{}{sep}{border}{sep}{title}{sep}{content}{}",
+                frame::START,
+                crate::constants::encode(spec),
+                frame::END,
+                sep = frame::SEP
+            )
+        };
+        let processor = TextProcessor::new(Box::new(|| 12));
+
+        let content = format!("a\n{}-{}b", padding::START, padding::END);
+        let input = framed("ascii left 1 0", "", "T", &content);
+        assert_eq!(
+            processor.process_padding(&input, NARROW),
+            "+- T ------+\n| a        |\n| -------b |\n+----------+"
+        );
+
+        let inner = framed("single center 0 0", "", "", "x");
+        let input = format!("<{}>", framed("ascii right 0 1", "", "", &inner));
+        assert_eq!(
+            processor.process_padding(&input, NARROW),
+            [
+                "  +------+  ",
+                "  |┌────┐|  ",
+                "< |│ x  │| >",
+                "  |└────┘|  ",
+                "  +------+  "
+            ]
+            .join("\n")
+        );
+
+        let unterminated = format!("{}x", frame::START);
+        assert_eq!(
+            processor.process_padding(&unterminated, NARROW),
+            unterminated
+        );
+    }
+
+    #[test]
+    fn test_process_padding_with_text_styles() {
+        use crate::textstyle::TextStyle;
+
+        let processor = TextProcessor::new(Box::new(|| 11));
+        let pad = format!("{}-{}", padding::START, padding::END);
+        let input = format!("{}{pad}", TextStyle::Fullwidth.apply("OK"));
+        assert_eq!(processor.process_padding(&input, NARROW), "ＯＫ-------");
+
+        let input = format!(
+            "{pad}{}{pad}",
+            TextStyle::Bold.apply(&format!("{}b{}", padding::START, padding::END))
+        );
+        let result = processor.process_padding(&input, NARROW);
+        assert_eq!(result, "---𝐛𝐛𝐛-----");
+        assert_eq!(measure_text_width(&result), 11);
+    }
+
+    #[test]
+    fn test_process_padding_with_tabs() {
+        let processor = TextProcessor::new(Box::new(|| 12));
+        let input = format!(
+            "a\tb{}-{}\n\tc{}-{}",
+            padding::START,
+            padding::END,
+            padding::START,
+            padding::END
+        );
+        let result = processor.process_padding(&expand_tabs(&input, TAB_WIDTH, NARROW), NARROW);
+        assert_eq!(result, "a       b---\n        c---");
+    }
+
     #[test]
     fn test_remove_empty_pads() {
         let _processor = TextProcessor::default();
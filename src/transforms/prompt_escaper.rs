@@ -0,0 +1,33 @@
+use std::str::FromStr;
+
+use crate::prelude::*;
+use crate::shell::Shell;
+
+/// Escapes the rendered title for a shell prompt (`--prompt-shell`)
+pub struct PromptEscaper;
+
+impl Default for PromptEscaper {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PromptEscaper {
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// `PromptEscaper` is a transform that makes the title usable in the prompt of the shell set in
+/// `prompt_shell`: escape sequences are wrapped in the markers of zero-width text of the shell
+/// and the characters it would interpret are escaped. Without a prompt shell, the text is kept.
+impl Transform for PromptEscaper {
+    fn transform(&self, text: &str) -> Result<String> {
+        let ctx = ContextManager::get().read()?;
+        match ctx.get("prompt_shell").filter(|shell| !shell.is_empty()) {
+            Some(shell) => Ok(Shell::from_str(shell)?.escape_prompt(text)),
+            None => Ok(text.to_string()),
+        }
+    }
+}
@@ -25,14 +25,20 @@ impl TransformRegistry {
 
     /// Initializes the transform registry with the default transforms
     /// - `TemplateRenderer`: Renders the template using the Tera engine
+    /// - `SegmentProcessor`: Draws the segments and their separators
     /// - `TextProcessor`: Processes the text handling padding and line wrapping
+    /// - `GradientProcessor`: Colors gradient groups once padding has been expanded
     /// - `LineHandler`: Handles line endings based on context flags
     /// - `AnsiFormatter`: Handles ANSI escape sequences and nested colors
+    /// - `PromptEscaper`: Escapes the title for a shell prompt
     pub fn init(&mut self) {
         self.register("template_renderer", super::TemplateRenderer::new());
+        self.register("segment_processor", super::SegmentProcessor::new());
         self.register("text_processor", super::TextProcessor::default());
+        self.register("gradient_processor", super::GradientProcessor::new());
         self.register("line_handler", super::LineHandler::new());
         self.register("ansi_formatter", super::AnsiFormatter::new());
+        self.register("prompt_escaper", super::PromptEscaper::new());
     }
 
     pub fn register<T: Transform + 'static>(&mut self, name: &str, transform: T) {
@@ -4,11 +4,15 @@ use std::error::Error as StdError;
 use std::sync::Mutex;
 use tera::Tera;
 
+use crate::color_functions::ColorFunction;
 use crate::config::TemplateConfig;
 use crate::constants::template::DEFAULT_TIME_FORMAT;
 use crate::error::{Error, Result};
-use crate::filters::{append, color, hide, pad, style, surround};
-use crate::functions::exit_code;
+use crate::filters::{
+    append, banner, color, color_by, color_hash, create_color_function_filter, frame, gradient,
+    hide, humanize, link, markup, pad, segment, style, surround, textstyle,
+};
+use crate::functions::{create_color_function, exec, exit_code, git, icon, system};
 use crate::prelude::*;
 use crate::utils::safe_time_format;
 
@@ -18,14 +22,49 @@ static TERA_VAR_REGEX: std::sync::LazyLock<Regex> =
 static TERA: std::sync::LazyLock<Mutex<Tera>> = std::sync::LazyLock::new(|| {
     let mut tera = Tera::default();
     tera.register_filter("color", color::create_color_filter());
+    tera.register_filter("color_by", color_by::create_color_by_filter());
+    tera.register_filter("color_hash", color_hash::create_color_hash_filter());
     tera.register_filter("style", style::create_style_filter());
+    tera.register_filter("gradient", gradient::create_gradient_filter());
     tera.register_filter("surround", surround::create_surround_filter());
     tera.register_filter("append", append::create_append_filter());
     tera.register_filter("pad", pad::create_pad_filter());
     tera.register_filter("hide", hide::create_hide_filter());
-    #[cfg(feature = "display")]
-    tera.register_filter("markup", crate::filters::markup::create_markup_filter());
+    tera.register_filter("link", link::create_link_filter());
+    tera.register_filter("markup", markup::create_markup_filter());
+    tera.register_filter("banner", banner::create_banner_filter());
+    tera.register_filter("frame", frame::create_frame_filter());
+    tera.register_filter("segment", segment::create_segment_filter());
+    tera.register_filter("textstyle", textstyle::create_textstyle_filter());
+    tera.register_filter("bytes", humanize::create_bytes_filter());
+    tera.register_filter("duration", humanize::create_duration_filter());
+    tera.register_filter("number", humanize::create_number_filter());
+    tera.register_filter("percent", humanize::create_percent_filter());
+    tera.register_filter("ago", humanize::create_ago_filter());
+    tera.register_filter("strftime", humanize::create_strftime_filter());
     tera.register_function("get_last_exit_code", exit_code::get_last_exit_code);
+    tera.register_function("get_last_duration", exit_code::get_last_duration);
+    tera.register_function("icon", icon::icon);
+    tera.register_function("env", system::env);
+    // Replaces the built-in function, which would read any variable
+    tera.register_function("get_env", system::get_env);
+    tera.register_function("hostname", system::hostname);
+    tera.register_function("username", system::username);
+    tera.register_function("cwd", system::cwd);
+    tera.register_function("os", system::os);
+    tera.register_function("shell", system::shell);
+    tera.register_function("term_width", system::term_width);
+    tera.register_function("now", system::now);
+    tera.register_function("git_branch", git::git_branch);
+    tera.register_function("git_commit", git::git_commit);
+    tera.register_function("git_dirty", git::git_dirty);
+    tera.register_function("git_ahead_behind", git::git_ahead_behind);
+    tera.register_function("git_repo_name", git::git_repo_name);
+    tera.register_function("exec", exec::exec);
+    for function in ColorFunction::ALL {
+        tera.register_function(function.name(), create_color_function(function));
+        tera.register_filter(function.name(), create_color_function_filter(function));
+    }
     Mutex::new(tera)
 });
 
@@ -198,9 +237,11 @@ impl TemplateRenderer {
             tera.render(&template_name, ctx.get_data())
                 .map_err(|e: tera::Error| {
                     let mut error_msg = e.to_string();
-                    if let Some(source) = e.source() {
+                    let mut current = e.source();
+                    while let Some(source) = current {
                         error_msg.push_str("\nCaused by: ");
                         error_msg.push_str(&source.to_string());
+                        current = source.source();
                     }
                     Error::TemplateRenderError(error_msg)
                 })?
@@ -215,3 +256,33 @@ impl Transform for TemplateRenderer {
         self.render(text)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::policy::TemplatePolicy;
+
+    #[test]
+    fn test_untrusted_template_get_env() {
+        let template: TemplateConfig =
+            toml::from_str("[details]\nname = \"untrusted env\"\n[pattern]\ndata = \"\"").unwrap();
+        ContextManager::get()
+            .update(|ctx| {
+                ctx.store_object("policy", TemplatePolicy::new(Vec::new(), false));
+                ctx.store_object("template_config", template);
+            })
+            .unwrap();
+        temp_env::with_var("TITULAR_TEST_RENDER_SECRET", Some("hunter2"), || {
+            let rendered = TemplateRenderer::new()
+                .render(
+                    "{{ get_env(name=\"TITULAR_TEST_RENDER_SECRET\", default=\"none\") }}|\
+                     {{ env(name=\"TITULAR_TEST_RENDER_SECRET\", default=\"none\") }}",
+                )
+                .unwrap();
+            assert_eq!(rendered, "none|none");
+            assert!(TemplateRenderer::new()
+                .render("{{ get_env(name=\"TITULAR_TEST_RENDER_SECRET\") }}")
+                .is_err());
+        });
+    }
+}
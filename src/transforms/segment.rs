@@ -0,0 +1,160 @@
+use crate::ansi::strip_escapes;
+use crate::constants::{decode, is_marker, segment};
+use crate::glyphs::glyphs_enabled;
+use crate::prelude::*;
+use crate::segment::Segment;
+use regex::Regex;
+
+// Regex to match the segment groups, capturing their encoded spec and content
+static SEGMENT_PATTERN: std::sync::LazyLock<Regex> = std::sync::LazyLock::new(|| {
+    Regex::new(&format!(
+        r"{start}([^{start}{sep}{end}]*){sep}([^{start}{end}]*){end}",
+        start = regex::escape(&segment::START.to_string()),
+        sep = regex::escape(&segment::SEP.to_string()),
+        end = regex::escape(&segment::END.to_string())
+    ))
+    .unwrap()
+});
+
+/// `SegmentProcessor` is a transform that draws the segments of the text (see the `segment`
+/// filter) with their separators. It runs before the `TextProcessor`, so that the separators
+/// are measured with the rest of the line and padding groups inside segments stretch them.
+pub struct SegmentProcessor;
+
+impl Default for SegmentProcessor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SegmentProcessor {
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Replaces every segment of the text with its painted content and separator. A segment
+    /// directly followed by another one on the same line (with nothing visible in between) gets
+    /// a separator drawn over the background of the next segment.
+    ///
+    /// # Examples
+    /// ```
+    /// use titular::constants::{encode, segment};
+    /// use titular::transforms::SegmentProcessor;
+    ///
+    /// let group = |spec: &str, text: &str| {
+    ///     format!("{}{}{}{text}{}", segment::START, encode(spec), segment::SEP, segment::END)
+    /// };
+    /// let input = format!("{}{}", group("powerline|44|34|97", "a"), group("powerline|41|31|", "b"));
+    /// assert_eq!(
+    ///     SegmentProcessor::join_segments(&input, false),
+    ///     "\x1b[44;97m a \x1b[0m\x1b[34;41m>\x1b[0m\x1b[41m b \x1b[0m\x1b[31m>\x1b[0m"
+    /// );
+    /// ```
+    #[must_use]
+    pub fn join_segments(text: &str, glyphs: bool) -> String {
+        let groups: Vec<_> = SEGMENT_PATTERN
+            .captures_iter(text)
+            .map(|caps| {
+                let matched = caps.get(0).unwrap();
+                let settings = decode(&caps[1]).parse::<Segment>().unwrap_or_default();
+                (matched.range(), settings, caps.get(2).unwrap().as_str())
+            })
+            .collect();
+
+        let mut result = String::with_capacity(text.len());
+        let mut last = 0;
+        for (i, (range, settings, content)) in groups.iter().enumerate() {
+            result.push_str(&text[last..range.start]);
+            result.push_str(&settings.paint(content));
+            let next = groups
+                .get(i + 1)
+                .filter(|(next, _, _)| Self::is_gap(&text[range.end..next.start]))
+                .map(|(_, next, _)| next);
+            result.push_str(&settings.separator(next, glyphs));
+            last = range.end;
+        }
+        result.push_str(&text[last..]);
+        result
+    }
+
+    /// Whether the text between two segments lets them touch: nothing visible and no line break.
+    fn is_gap(between: &str) -> bool {
+        strip_escapes(between).chars().all(is_marker)
+    }
+}
+
+impl Transform for SegmentProcessor {
+    fn transform(&self, text: &str) -> Result<String> {
+        if !text.contains(segment::START) {
+            return Ok(text.to_string());
+        }
+        let ctx = ContextManager::get().read()?;
+        Ok(Self::join_segments(text, glyphs_enabled(&ctx)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::encode;
+    use crate::transforms::TextProcessor;
+    use crate::width::AmbiguousWidth;
+
+    fn group(spec: &str, content: &str) -> String {
+        format!(
+            "{}{}{}{content}{}",
+            segment::START,
+            encode(spec),
+            segment::SEP,
+            segment::END
+        )
+    }
+
+    #[test]
+    fn test_join_segments_with_gaps() {
+        let input = format!(
+            "{}\n{} | {}",
+            group("round|44|34|", "a"),
+            group("slant|41|31|", "b"),
+            group("plain|42|32|", "c")
+        );
+        assert_eq!(
+            SegmentProcessor::join_segments(&input, true),
+            "\x1b[44m a \x1b[0m\x1b[34m\u{E0B4}\x1b[0m\n\
+             \x1b[41m b \x1b[0m\x1b[31m\u{E0BC}\x1b[0m | \x1b[42m c \x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_join_segments_through_escapes() {
+        let input = format!(
+            "{}\x1b[1m{}\x1b[0m",
+            group("powerline|44|34|", "a"),
+            group("powerline|||", "b")
+        );
+        assert_eq!(
+            SegmentProcessor::join_segments(&input, true),
+            "\x1b[44m a \x1b[0m\x1b[34m\u{E0B0}\x1b[0m\x1b[1m b \u{E0B0}\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_segment_stretches_with_padding() {
+        let input = format!(
+            "{}{}",
+            group("powerline|44|34|", "a"),
+            group(
+                "powerline|41|31|",
+                &format!("{}-{}", padding::START, padding::END)
+            )
+        );
+        let joined = SegmentProcessor::join_segments(&input, false);
+        let result =
+            TextProcessor::new(Box::new(|| 12)).process_padding(&joined, AmbiguousWidth::Narrow);
+        assert_eq!(
+            result,
+            "\x1b[44m a \x1b[0m\x1b[34;41m>\x1b[0m\x1b[41m ----- \x1b[0m\x1b[31m>\x1b[0m"
+        );
+    }
+}
@@ -1,6 +1,6 @@
 use crate::constants::template::DEFAULT_TIME_FORMAT;
 use crate::error::Result;
-use chrono::{DateTime, Local};
+use chrono::{DateTime, Local, TimeZone};
 use nu_ansi_term::Color::{Blue, Yellow};
 use num;
 use std::path::{Path, PathBuf};
@@ -22,25 +22,54 @@ pub const ELEMENT_PREFIX: &str = "\u{ea7b}";
 /// A string containing the formatted time
 #[must_use]
 pub fn safe_time_format(dt: &DateTime<Local>, format: &str) -> String {
-    // Parse the format string first to validate it
-    let items: Vec<_> = chrono::format::strftime::StrftimeItems::new(format).collect();
-
-    // If any item is an error, use default format
-    if items
-        .iter()
-        .any(|item| matches!(item, chrono::format::Item::Error))
-    {
+    try_time_format(dt, format).unwrap_or_else(|| {
         eprintln!(
             "{}",
             Yellow.paint(format!(
                 "WARNING: Invalid time format specified \"{format}\""
             ))
         );
-        return dt.format(DEFAULT_TIME_FORMAT).to_string();
+        dt.format(DEFAULT_TIME_FORMAT).to_string()
+    })
+}
+
+/// Formats a `DateTime` of any timezone using the provided format string.
+///
+/// # Arguments
+///
+/// * `dt` - The `DateTime` to format
+/// * `format` - The format string to use for time formatting
+///
+/// # Returns
+///
+/// The formatted time, or `None` if the format string is invalid
+#[must_use]
+pub fn try_time_format<Tz>(dt: &DateTime<Tz>, format: &str) -> Option<String>
+where
+    Tz: TimeZone,
+    Tz::Offset: std::fmt::Display,
+{
+    // Parse the format string first to validate it
+    let items: Vec<_> = chrono::format::strftime::StrftimeItems::new(format).collect();
+    if items
+        .iter()
+        .any(|item| matches!(item, chrono::format::Item::Error))
+    {
+        return None;
     }
 
     // Use the validated format items
-    dt.format_with_items(items.into_iter()).to_string()
+    Some(dt.format_with_items(items.into_iter()).to_string())
+}
+
+/// The units sizes are formatted with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ByteUnits {
+    /// Powers of 1024 (IEC), shown as KB, MB, GB...
+    #[default]
+    Binary,
+    /// Powers of 1000 (SI), shown as kB, MB, GB...
+    Si,
 }
 
 /// Formats bytes into a human-readable string (KB, MB, etc.)
@@ -105,6 +134,60 @@ pub fn format_bytes(bytes: u64) -> String {
     }
 }
 
+/// Formats a (possibly negative) number of bytes with the largest unit it reaches.
+///
+/// # Arguments
+///
+/// * `bytes` - The number of bytes to format
+/// * `units` - Whether units are powers of 1024 or of 1000
+/// * `precision` - The number of fractional digits, or `None` for up to three digits with
+///   trailing zeros dropped
+///
+/// # Returns
+///
+/// The formatted size, from `B` up to `PB`. Byte counts are always whole numbers.
+///
+/// # Examples
+///
+/// ```
+/// use titular::utils::{format_bytes_with, ByteUnits};
+///
+/// assert_eq!(format_bytes_with(1_468_006.0, ByteUnits::Binary, Some(1)), "1.4 MB");
+/// assert_eq!(format_bytes_with(1500.0, ByteUnits::Si, Some(2)), "1.50 kB");
+/// assert_eq!(format_bytes_with(-2048.0, ByteUnits::Binary, None), "-2.0 KB");
+/// ```
+#[must_use]
+pub fn format_bytes_with(bytes: f64, units: ByteUnits, precision: Option<usize>) -> String {
+    let (base, suffixes) = match units {
+        ByteUnits::Binary => (1024.0, ["B", "KB", "MB", "GB", "TB", "PB"]),
+        ByteUnits::Si => (1000.0, ["B", "kB", "MB", "GB", "TB", "PB"]),
+    };
+    let sign = if bytes <= -0.5 { "-" } else { "" };
+    let mut size = bytes.abs();
+    let mut unit = 0;
+    while size >= base && unit < suffixes.len() - 1 {
+        size /= base;
+        unit += 1;
+    }
+    let suffix = suffixes[unit];
+    if unit == 0 {
+        return format!("{sign}{size:.0} {suffix}");
+    }
+    match precision {
+        Some(precision) => format!("{sign}{size:.precision$} {suffix}"),
+        None => {
+            let size = format!("{size:.3}");
+            let size = size.trim_end_matches('0');
+            let size = if size.ends_with('.') {
+                format!("{size}0")
+            } else {
+                size.to_string()
+            };
+            format!("{sign}{size} {suffix}")
+        }
+    }
+}
+
 /// Performs cleanup operations when the program is interrupted
 ///
 /// Currently the following operations are performed:
@@ -419,6 +502,23 @@ mod tests {
         assert_eq!(format_bytes(1024 * 1024 * 1024 * 1024 * 16), "16384.0 GB");
     }
 
+    #[test]
+    fn test_format_bytes_with() {
+        assert_eq!(format_bytes_with(0.0, ByteUnits::Si, Some(2)), "0 B");
+        assert_eq!(format_bytes_with(999.4, ByteUnits::Si, Some(2)), "999 B");
+        assert_eq!(format_bytes_with(1500.0, ByteUnits::Si, None), "1.5 kB");
+        assert_eq!(
+            format_bytes_with(1536.0, ByteUnits::Binary, Some(0)),
+            "2 KB"
+        );
+        assert_eq!(
+            format_bytes_with(-1536.0, ByteUnits::Binary, Some(1)),
+            "-1.5 KB"
+        );
+        assert_eq!(format_bytes_with(-0.2, ByteUnits::Binary, None), "0 B");
+        assert_eq!(format_bytes_with(2e15, ByteUnits::Si, Some(1)), "2.0 PB");
+    }
+
     #[test]
     fn test_backup_operations() -> Result<()> {
         // Create a temporary file with some content
@@ -0,0 +1,209 @@
+//! Display width of text, measured by grapheme clusters.
+//!
+//! A terminal draws a grapheme cluster (`e` followed by a combining accent, a ZWJ emoji sequence,
+//! a flag, ...) in one or two cells, so widths are summed over clusters rather than `char`s.
+//! East Asian ambiguous characters (`±`, `…`, box drawing, ...) take one column unless
+//! `ambiguous_width = 2` is set, as most terminals do in CJK locales. Each measure takes the
+//! setting as an [`AmbiguousWidth`], read from the context of the render.
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::ansi::{tokenize, Token};
+use crate::constants::is_marker;
+use crate::context::Context;
+
+/// Distance between two tab stops
+pub const TAB_WIDTH: usize = 8;
+
+/// Width of the East Asian ambiguous characters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AmbiguousWidth {
+    /// One column
+    #[default]
+    Narrow,
+    /// Two columns, as most terminals draw them in CJK locales
+    Wide,
+}
+
+impl AmbiguousWidth {
+    /// Reads a setting value: `2` is wide, anything else narrow.
+    #[must_use]
+    pub fn parse(value: &str) -> Self {
+        match value.trim() {
+            "2" => AmbiguousWidth::Wide,
+            _ => AmbiguousWidth::Narrow,
+        }
+    }
+
+    /// Reads the ambiguous width from the context (`ambiguous_width`, then
+    /// `defaults.ambiguous_width`), narrow by default.
+    #[must_use]
+    pub fn from_context(ctx: &Context) -> Self {
+        ctx.get("ambiguous_width")
+            .or_else(|| ctx.get("defaults.ambiguous_width"))
+            .map_or_else(Self::default, Self::parse)
+    }
+}
+
+/// Display width of a single grapheme cluster. Control characters and markers have no width.
+///
+/// # Examples
+/// ```
+/// use titular::width::{grapheme_width, AmbiguousWidth};
+///
+/// assert_eq!(grapheme_width("👨\u{200D}👩\u{200D}👧", AmbiguousWidth::Narrow), 2);
+/// assert_eq!(grapheme_width("🇫🇷", AmbiguousWidth::Narrow), 2);
+/// assert_eq!(grapheme_width("e\u{301}", AmbiguousWidth::Narrow), 1);
+/// assert_eq!(grapheme_width("\x07", AmbiguousWidth::Narrow), 0);
+/// assert_eq!(grapheme_width("…", AmbiguousWidth::Wide), 2);
+/// ```
+#[must_use]
+pub fn grapheme_width(grapheme: &str, ambiguous: AmbiguousWidth) -> usize {
+    let hidden = |c: char| c.is_control() || is_marker(c);
+    if grapheme.chars().any(hidden) {
+        let visible: String = grapheme.chars().filter(|c| !hidden(*c)).collect();
+        return raw_width(&visible, ambiguous);
+    }
+    raw_width(grapheme, ambiguous)
+}
+
+fn raw_width(s: &str, ambiguous: AmbiguousWidth) -> usize {
+    match ambiguous {
+        AmbiguousWidth::Narrow => s.width(),
+        AmbiguousWidth::Wide => s.width_cjk(),
+    }
+}
+
+/// Display width of a text free of escape sequences.
+///
+/// # Examples
+/// ```
+/// use titular::width::{text_width, AmbiguousWidth};
+///
+/// assert_eq!(text_width("こんにちは", AmbiguousWidth::Narrow), 10);
+/// assert_eq!(text_width("cafe\u{301} 🇫🇷", AmbiguousWidth::Narrow), 7);
+/// assert_eq!(text_width("±1…", AmbiguousWidth::Wide), 5);
+/// ```
+#[must_use]
+pub fn text_width(text: &str, ambiguous: AmbiguousWidth) -> usize {
+    text.graphemes(true)
+        .map(|grapheme| grapheme_width(grapheme, ambiguous))
+        .sum()
+}
+
+/// Replaces the tabs with spaces up to the next tab stop. Escape sequences and markers have no
+/// width, and columns restart after each line break.
+///
+/// # Examples
+/// ```
+/// use titular::width::{expand_tabs, AmbiguousWidth};
+///
+/// let narrow = AmbiguousWidth::Narrow;
+/// assert_eq!(expand_tabs("a\tb", 4, narrow), "a   b");
+/// assert_eq!(expand_tabs("\x1b[31mab\x1b[0m\tc\n\td", 4, narrow), "\x1b[31mab\x1b[0m  c\n    d");
+/// ```
+#[must_use]
+pub fn expand_tabs(s: &str, tab_width: usize, ambiguous: AmbiguousWidth) -> String {
+    if !s.contains('\t') || tab_width == 0 {
+        return s.to_string();
+    }
+    let mut result = String::with_capacity(s.len());
+    let mut column = 0;
+    for token in tokenize(s) {
+        match token {
+            Token::Escape(_, seq) => result.push_str(seq),
+            Token::Text(text) => {
+                for grapheme in text.graphemes(true) {
+                    match grapheme {
+                        "\t" => {
+                            let spaces = tab_width - column % tab_width;
+                            result.extend(std::iter::repeat_n(' ', spaces));
+                            column += spaces;
+                        }
+                        "\n" | "\r\n" => {
+                            result.push_str(grapheme);
+                            column = 0;
+                        }
+                        _ => {
+                            result.push_str(grapheme);
+                            column += grapheme_width(grapheme, ambiguous);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NARROW: AmbiguousWidth = AmbiguousWidth::Narrow;
+
+    #[test]
+    fn test_text_width_emoji() {
+        assert_eq!(text_width("🦀", NARROW), 2);
+        assert_eq!(text_width("👨\u{200D}👩\u{200D}👧", NARROW), 2);
+        assert_eq!(text_width("🏳\u{FE0F}\u{200D}🌈", NARROW), 2);
+        assert_eq!(text_width("🇫🇷🇯🇵", NARROW), 4);
+        assert_eq!(text_width("❤\u{FE0F}", NARROW), 2);
+        assert_eq!(text_width("👍🏽", NARROW), 2);
+    }
+
+    #[test]
+    fn test_text_width_cjk() {
+        assert_eq!(text_width("日本語", NARROW), 6);
+        assert_eq!(text_width("안녕", NARROW), 4);
+        assert_eq!(text_width("ｆｕｌｌ", NARROW), 8);
+        assert_eq!(text_width("ｶﾀｶﾅ", NARROW), 4);
+    }
+
+    #[test]
+    fn test_text_width_combining_marks() {
+        assert_eq!(text_width("e\u{301}", NARROW), 1);
+        assert_eq!(text_width("a\u{300}\u{301}\u{302}", NARROW), 1);
+        assert_eq!(text_width("\u{1100}\u{1161}\u{11A8}", NARROW), 2);
+    }
+
+    #[test]
+    fn test_text_width_zero_width() {
+        assert_eq!(text_width("", NARROW), 0);
+        assert_eq!(text_width("\u{200B}", NARROW), 0);
+        assert_eq!(text_width("\x07\r", NARROW), 0);
+        assert_eq!(text_width("\u{F0000}ab\u{F0001}", NARROW), 2);
+    }
+
+    #[test]
+    fn test_ambiguous_width() {
+        let mut ctx = Context::new();
+        assert_eq!(AmbiguousWidth::from_context(&ctx), NARROW);
+        ctx.insert("defaults.ambiguous_width", "2");
+        assert_eq!(AmbiguousWidth::from_context(&ctx), AmbiguousWidth::Wide);
+        ctx.insert("ambiguous_width", "1");
+        assert_eq!(AmbiguousWidth::from_context(&ctx), NARROW);
+        ctx.insert("ambiguous_width", "3");
+        assert_eq!(AmbiguousWidth::from_context(&ctx), NARROW);
+
+        assert_eq!(text_width("±…─", NARROW), 3);
+        assert_eq!(text_width("±…─", AmbiguousWidth::Wide), 6);
+        assert_eq!(text_width("日本", AmbiguousWidth::Wide), 4);
+        assert_eq!(expand_tabs("…\tx", 4, AmbiguousWidth::Wide), "…  x");
+    }
+
+    #[test]
+    fn test_expand_tabs() {
+        assert_eq!(expand_tabs("no tabs", 8, NARROW), "no tabs");
+        assert_eq!(expand_tabs("\t", 8, NARROW), " ".repeat(8));
+        assert_eq!(expand_tabs("abcd\te", 4, NARROW), "abcd    e");
+        assert_eq!(expand_tabs("日本\tx", 8, NARROW), "日本    x");
+        assert_eq!(expand_tabs("e\u{301}\tx", 4, NARROW), "e\u{301}   x");
+        assert_eq!(
+            expand_tabs("\u{F0000}a\u{F0001}\tb", 4, NARROW),
+            "\u{F0000}a\u{F0001}   b"
+        );
+        assert_eq!(expand_tabs("a\tb", 0, NARROW), "a\tb");
+    }
+}
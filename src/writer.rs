@@ -10,6 +10,7 @@ use crate::{
     config::MainConfig,
     constants::template::{DEFAULT_TEMPLATE, DEFAULT_TEMPLATE_EXT},
     error::{Error, Result},
+    policy,
 };
 
 pub struct TemplateWriter {}
@@ -116,6 +117,7 @@ impl TemplateWriter {
                 }
             }
             TemplateWriter::write_new(&template, config)?;
+            policy::mark_local(input_dir, &path)?;
             template_created = true;
         }
         Ok((path, template, template_created))